@@ -0,0 +1,8 @@
+//! Benchmarks of the physics systems, see `bomberman::bench` for what they measure.
+
+use criterion::{criterion_group, criterion_main};
+
+use bomberman::bench::{blasts, collisions};
+
+criterion_group!(benches, collisions, blasts);
+criterion_main!(benches);
@@ -0,0 +1,269 @@
+//! Aids for players who can't tell some colors apart, only there to be seen.
+//!
+//! Players are told apart by their color, and so are teams. When a color vision deficiency is
+//! picked from the options screen, those colors are swapped for a palette keeping them apart
+//! under it, wherever they're shown: on players, in panels and on banners. On top of that,
+//! patterns can be laid over players, each of them drawn with stripes or dots of its own, over
+//! the bombs they place and along fires, so that nothing hangs on colors at all.
+//! Patterns are laid anew every frame, following the match without being part of it.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fire_reach, net::COLORS, settings::Settings, Active, Bomb, Fire, Player, PlayerIndex,
+    OPPONENT_COLOR, TEAMS, TEAM_COLORS,
+};
+
+// patterns are drawn a bit above what they're laid over
+const PATTERN_LIFT: f32 = 0.01;
+// players are light enough for dark patterns to stand out, bombs and fires aren't
+const DARK_PATTERN_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.7);
+const LIGHT_PATTERN_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.8);
+// width of the stripe running along fires, relative to theirs
+const FIRE_STRIPE: f32 = 0.2;
+
+// shapes of the patterns of players, as the offsets and sizes of their pieces, relative to the
+// size of what they're laid over, one pattern for every player going back to the first
+const PATTERNS: [&[(Vec2, Vec2)]; 8] = [
+    // a dot in the middle
+    &[(Vec2::ZERO, Vec2::new(0.3, 0.3))],
+    // two horizontal stripes
+    &[
+        (Vec2::new(0.0, 0.25), Vec2::new(0.8, 0.12)),
+        (Vec2::new(0.0, -0.25), Vec2::new(0.8, 0.12)),
+    ],
+    // two vertical stripes
+    &[
+        (Vec2::new(-0.25, 0.0), Vec2::new(0.12, 0.8)),
+        (Vec2::new(0.25, 0.0), Vec2::new(0.12, 0.8)),
+    ],
+    // two dots across
+    &[
+        (Vec2::new(-0.25, 0.25), Vec2::new(0.2, 0.2)),
+        (Vec2::new(0.25, -0.25), Vec2::new(0.2, 0.2)),
+    ],
+    // a dot in every corner
+    &[
+        (Vec2::new(-0.25, 0.25), Vec2::new(0.2, 0.2)),
+        (Vec2::new(0.25, 0.25), Vec2::new(0.2, 0.2)),
+        (Vec2::new(-0.25, -0.25), Vec2::new(0.2, 0.2)),
+        (Vec2::new(0.25, -0.25), Vec2::new(0.2, 0.2)),
+    ],
+    // a horizontal stripe in the middle
+    &[(Vec2::ZERO, Vec2::new(0.8, 0.15))],
+    // a vertical stripe in the middle
+    &[(Vec2::ZERO, Vec2::new(0.15, 0.8))],
+    // three dots in a row
+    &[
+        (Vec2::new(-0.3, 0.0), Vec2::new(0.18, 0.18)),
+        (Vec2::ZERO, Vec2::new(0.18, 0.18)),
+        (Vec2::new(0.3, 0.0), Vec2::new(0.18, 0.18)),
+    ],
+];
+
+/// Color vision deficiencies colors can be adjusted for
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorVision {
+    /// Colors are shown as they are
+    #[default]
+    Normal,
+    /// Reds and greens can't be told apart, as with protanopia and deuteranopia
+    RedGreen,
+    /// Blues and yellows can't be told apart, as with tritanopia
+    BlueYellow,
+}
+
+/// Colors standing in for those players and teams are told apart by
+struct Palette {
+    /// In the order of the colors humans pick from
+    players: [Color; COLORS.len()],
+    opponent: Color,
+    teams: [Color; TEAMS],
+}
+
+// colors apart from one another under both protanopia and deuteranopia, from the Okabe-Ito set
+const RED_GREEN: Palette = Palette {
+    players: [
+        Color::rgb(0.0, 0.45, 0.7),
+        Color::rgb(0.9, 0.62, 0.0),
+        Color::rgb(0.34, 0.71, 0.91),
+        Color::rgb(0.94, 0.89, 0.26),
+        Color::rgb(0.84, 0.37, 0.0),
+        Color::rgb(0.8, 0.47, 0.65),
+        Color::rgb(0.0, 0.62, 0.45),
+        Color::rgb(0.9, 0.9, 0.9),
+    ],
+    opponent: Color::rgb(0.5, 0.5, 0.5),
+    teams: [Color::rgb(0.9, 0.62, 0.0), Color::rgb(0.0, 0.45, 0.7)],
+};
+
+// colors apart from one another under tritanopia, told apart by redness and lightness
+const BLUE_YELLOW: Palette = Palette {
+    players: [
+        Color::rgb(0.8, 0.1, 0.1),
+        Color::rgb(0.0, 0.55, 0.55),
+        Color::rgb(0.95, 0.6, 0.7),
+        Color::rgb(0.15, 0.15, 0.15),
+        Color::rgb(0.6, 0.0, 0.3),
+        Color::rgb(0.5, 0.85, 0.9),
+        Color::rgb(1.0, 0.45, 0.3),
+        Color::rgb(0.9, 0.9, 0.9),
+    ],
+    opponent: Color::rgb(0.55, 0.55, 0.55),
+    teams: [Color::rgb(0.8, 0.1, 0.1), Color::rgb(0.0, 0.55, 0.55)],
+};
+
+impl ColorVision {
+    pub const ALL: [ColorVision; 3] = [
+        ColorVision::Normal,
+        ColorVision::RedGreen,
+        ColorVision::BlueYellow,
+    ];
+
+    /// The deficiency after this one, going back to the first after the last
+    pub fn next(&self) -> Self {
+        let index = ColorVision::ALL
+            .iter()
+            .position(|other| other == self)
+            .unwrap_or_default();
+        ColorVision::ALL[(index + 1) % ColorVision::ALL.len()]
+    }
+
+    /// Key of the name of the deficiency among the strings of locales
+    pub fn key(&self) -> &'static str {
+        match self {
+            ColorVision::Normal => "color_vision.normal",
+            ColorVision::RedGreen => "color_vision.red_green",
+            ColorVision::BlueYellow => "color_vision.blue_yellow",
+        }
+    }
+
+    fn palette(&self) -> Option<&'static Palette> {
+        match self {
+            ColorVision::Normal => None,
+            ColorVision::RedGreen => Some(&RED_GREEN),
+            ColorVision::BlueYellow => Some(&BLUE_YELLOW),
+        }
+    }
+
+    /// The color shown in place of the given one, kept unless players are told apart by it
+    pub fn adjust(&self, color: Color) -> Color {
+        let palette = match self.palette() {
+            Some(palette) => palette,
+            None => return color,
+        };
+        if let Some(index) = COLORS.iter().position(|other| *other == color) {
+            palette.players[index]
+        } else if let Some(team) = TEAM_COLORS.iter().position(|other| *other == color) {
+            palette.teams[team]
+        } else if color == OPPONENT_COLOR {
+            palette.opponent
+        } else {
+            color
+        }
+    }
+}
+
+/// A piece of a pattern
+#[derive(Component)]
+pub struct Pattern;
+
+/// The pattern of the player with the given index, among those of the match or among humans
+fn pattern(index: Option<&PlayerIndex>, active: Option<&Active>) -> &'static [(Vec2, Vec2)] {
+    let index = index
+        .map(|index| index.0)
+        .or_else(|| active.map(|active| active.0))
+        .unwrap_or_default();
+    PATTERNS[index % PATTERNS.len()]
+}
+
+/// Lays the given pattern over what's at the given place
+fn spawn_pattern(
+    commands: &mut Commands,
+    transform: &Transform,
+    pieces: &[(Vec2, Vec2)],
+    color: Color,
+) {
+    let size = transform.scale.truncate();
+    for (offset, piece) in pieces {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite { color, ..default() },
+                transform: Transform {
+                    translation: transform.translation + (*offset * size).extend(PATTERN_LIFT),
+                    scale: (*piece * size).extend(1.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(Pattern);
+    }
+}
+
+// Lay patterns over players, the bombs they placed and fires, when asked to
+#[allow(clippy::type_complexity)]
+pub fn mark(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    pattern_query: Query<Entity, With<Pattern>>,
+    player_query: Query<
+        (
+            &Transform,
+            &Visibility,
+            Option<&PlayerIndex>,
+            Option<&Active>,
+        ),
+        With<Player>,
+    >,
+    bomb_query: Query<(&Bomb, &Transform, &Visibility)>,
+    fire_query: Query<(&Transform, &Visibility), With<Fire>>,
+) {
+    for entity in &pattern_query {
+        commands.entity(entity).despawn();
+    }
+    if !settings.patterns {
+        return;
+    }
+
+    for (transform, visibility, index, active) in &player_query {
+        if visibility.is_visible {
+            spawn_pattern(
+                &mut commands,
+                transform,
+                pattern(index, active),
+                DARK_PATTERN_COLOR,
+            );
+        }
+    }
+    // bombs hidden by mutators stay hidden
+    for (bomb, transform, visibility) in &bomb_query {
+        if !visibility.is_visible {
+            continue;
+        }
+        if let Some((_, _, index, active)) =
+            bomb.player.and_then(|player| player_query.get(player).ok())
+        {
+            spawn_pattern(
+                &mut commands,
+                transform,
+                pattern(index, active),
+                LIGHT_PATTERN_COLOR,
+            );
+        }
+    }
+    // fires get a stripe running along them, setting them apart from the arena whatever its colors
+    for (transform, visibility) in &fire_query {
+        if visibility.is_visible {
+            let (axis, _) = fire_reach(transform.scale);
+            let stripe = axis + (Vec2::ONE - axis) * FIRE_STRIPE;
+            spawn_pattern(
+                &mut commands,
+                transform,
+                &[(Vec2::ZERO, stripe)],
+                LIGHT_PATTERN_COLOR,
+            );
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Achievements, unlocked by local humans as they play and kept on disk.
+//!
+//! Achievements follow the events of the match rather than the systems sending them: kills,
+//! bricks destroyed by every bomb and bombs set off by others are checked at every physics step,
+//! matches won once their last round ends. Only humans playing on this instance unlock them.
+//! Achievements unlocked for the first time are saved to [`ACHIEVEMENTS_PATH`] right away and
+//! shown on a toast in a corner of the screen for a moment, stacked when several come at once.
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use std::{collections::BTreeSet, io};
+
+use crate::{
+    bot::Roster, locale::Locale, storage, Active, Bomb, BrickDestroyedEvent, ChainEvent,
+    DeathEvent, RoundEndedEvent, Rounds, Rules, Team, SCORE_COLOR,
+};
+
+/// Where unlocked achievements are stored
+pub const ACHIEVEMENTS_PATH: &str = "achievements.toml";
+
+/// Bricks a single bomb destroys to unlock [`Achievement::Demolition`]
+pub const DEMOLITION_BRICKS: usize = 10;
+/// Bombs going off one after the other to unlock [`Achievement::ChainReaction`]
+pub const CHAIN_BOMBS: usize = 5;
+
+const TOAST_FONT_SIZE: f32 = 24.0;
+// seconds toasts are shown for, the last of which fading them out
+const TOAST_SECS: f32 = 4.0;
+// room taken by every toast and its two lines, stacked from the bottom of the screen
+const TOAST_HEIGHT: f32 = 64.0;
+
+/// Something to achieve while playing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Achievement {
+    /// Killing another player
+    FirstKill,
+    /// Destroying [`DEMOLITION_BRICKS`] bricks with a single bomb
+    Demolition,
+    /// Winning a match without dying once
+    Untouchable,
+    /// Setting off a chain of [`CHAIN_BOMBS`] bombs
+    ChainReaction,
+}
+
+impl Achievement {
+    fn text(&self, locale: &Locale) -> String {
+        match self {
+            Achievement::FirstKill => locale.text("achievement.first_kill").to_string(),
+            Achievement::Demolition => {
+                locale.format("achievement.demolition", &[("bricks", &DEMOLITION_BRICKS)])
+            }
+            Achievement::Untouchable => locale.text("achievement.untouchable").to_string(),
+            Achievement::ChainReaction => {
+                locale.format("achievement.chain_reaction", &[("bombs", &CHAIN_BOMBS)])
+            }
+        }
+    }
+}
+
+/// An achievement unlocked for the first time
+pub struct AchievementEvent(pub Achievement);
+
+/// This resource holds the achievements unlocked so far
+#[derive(Default, Serialize, Deserialize)]
+pub struct Unlocked {
+    achievements: BTreeSet<Achievement>,
+    // whether they were read from disk, and are written back there when more are unlocked
+    #[serde(skip)]
+    persistent: bool,
+}
+
+impl Unlocked {
+    /// Reads the achievements unlocked from disk, starting with none if there are none
+    pub fn load() -> Self {
+        let unlocked = match storage::read(ACHIEVEMENTS_PATH) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                warn!("Invalid achievements in {ACHIEVEMENTS_PATH}: {e}");
+                Unlocked::default()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Unlocked::default(),
+            Err(e) => {
+                warn!("Can't read achievements from {ACHIEVEMENTS_PATH}: {e}");
+                Unlocked::default()
+            }
+        };
+        Unlocked {
+            persistent: true,
+            ..unlocked
+        }
+    }
+
+    /// Writes the achievements unlocked to disk
+    fn save(&self) {
+        let result = toml::to_string(self)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                storage::write(ACHIEVEMENTS_PATH, &content).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            warn!("Can't save achievements to {ACHIEVEMENTS_PATH}: {e}");
+        }
+    }
+
+    /// Unlocks the given achievement, telling about it if it wasn't already
+    fn unlock(&mut self, achievement: Achievement, writer: &mut EventWriter<AchievementEvent>) {
+        if self.achievements.insert(achievement) {
+            info!("Achievement unlocked: {achievement:?}");
+            if self.persistent {
+                self.save();
+            }
+            writer.send(AchievementEvent(achievement));
+        }
+    }
+}
+
+/// This resource tracks what achievements span over during the match being played
+#[derive(Default)]
+pub struct Progress {
+    // humans killed since the match started
+    killed: Vec<usize>,
+    // for bombs set off by others, who set off the first bomb of the chain and its length so far
+    chains: HashMap<Entity, (Option<Entity>, usize)>,
+}
+
+/// Whether the given human plays on this instance
+fn local(roster: &Roster, index: usize) -> bool {
+    index < roster.humans
+}
+
+// Check kills, bricks and chains of this step for achievements
+#[allow(clippy::too_many_arguments)]
+pub fn track(
+    roster: Res<Roster>,
+    mut unlocked: ResMut<Unlocked>,
+    mut progress: ResMut<Progress>,
+    mut death_reader: EventReader<DeathEvent>,
+    mut brick_reader: EventReader<BrickDestroyedEvent>,
+    mut chain_reader: EventReader<ChainEvent>,
+    mut achievement_writer: EventWriter<AchievementEvent>,
+    active_query: Query<&Active>,
+    bomb_query: Query<&Bomb>,
+) {
+    // killed players and bombs are still around until the step is over
+    let human = |entity: Option<Entity>| {
+        entity
+            .and_then(|entity| active_query.get(entity).ok())
+            .is_some_and(|active| local(&roster, active.0))
+    };
+    let owner = |bomb: Entity| bomb_query.get(bomb).ok().and_then(|bomb| bomb.player);
+
+    for DeathEvent { player, killer } in death_reader.iter() {
+        if let Ok(active) = active_query.get(*player) {
+            if !progress.killed.contains(&active.0) {
+                progress.killed.push(active.0);
+            }
+        }
+        if *killer != Some(*player) && human(*killer) {
+            unlocked.unlock(Achievement::FirstKill, &mut achievement_writer);
+        }
+    }
+
+    let mut bricks: Vec<(Entity, usize)> = Vec::new();
+    for BrickDestroyedEvent { bomb, .. } in brick_reader.iter() {
+        match bricks.iter_mut().find(|(other, _)| other == bomb) {
+            Some((_, count)) => *count += 1,
+            None => bricks.push((*bomb, 1)),
+        }
+    }
+    if bricks
+        .iter()
+        .any(|(bomb, count)| *count >= DEMOLITION_BRICKS && human(owner(*bomb)))
+    {
+        unlocked.unlock(Achievement::Demolition, &mut achievement_writer);
+    }
+
+    for ChainEvent { bomb, by } in chain_reader.iter() {
+        let (first, length) = progress
+            .chains
+            .get(by)
+            .copied()
+            .unwrap_or_else(|| (owner(*by), 1));
+        progress.chains.insert(*bomb, (first, length + 1));
+        if length + 1 >= CHAIN_BOMBS && human(first) {
+            unlocked.unlock(Achievement::ChainReaction, &mut achievement_writer);
+        }
+    }
+    // bombs gone off are done chaining
+    progress
+        .chains
+        .retain(|bomb, _| bomb_query.get(*bomb).is_ok());
+}
+
+// Check the winners of matches for achievements, once their last round ends
+pub fn win(
+    rules: Res<Rules>,
+    rounds: Res<Rounds>,
+    roster: Res<Roster>,
+    progress: Res<Progress>,
+    mut unlocked: ResMut<Unlocked>,
+    mut round_reader: EventReader<RoundEndedEvent>,
+    mut achievement_writer: EventWriter<AchievementEvent>,
+) {
+    for RoundEndedEvent { winner, team } in round_reader.iter() {
+        if rounds.played < rules.rounds {
+            continue;
+        }
+        // every human in the winning team shares the win
+        let flawless = (0..roster.total_humans())
+            .filter(|index| {
+                *winner == Some(*index) || team.is_some_and(|team| Team::of(*index).0 == team)
+            })
+            .any(|index| local(&roster, index) && !progress.killed.contains(&index));
+        if flawless {
+            unlocked.unlock(Achievement::Untouchable, &mut achievement_writer);
+        }
+    }
+}
+
+/// A toast telling about an achievement just unlocked, until its timer is over
+#[derive(Component)]
+pub struct Toast(Timer);
+
+// Show achievements just unlocked on toasts, fading toasts away once their time is over
+pub fn toast(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+    mut event_reader: EventReader<AchievementEvent>,
+    mut toast_query: Query<(Entity, &mut Toast, &mut Text)>,
+) {
+    // toasts stack up over those still shown
+    let stacked = toast_query.iter().count()..;
+    for (shown, AchievementEvent(achievement)) in stacked.zip(event_reader.iter()) {
+        commands
+            .spawn_bundle(
+                TextBundle::from_section(
+                    locale.format(
+                        "achievement.unlocked",
+                        &[("achievement", &achievement.text(&locale))],
+                    ),
+                    TextStyle {
+                        font: asset_server.load(locale.font()),
+                        font_size: TOAST_FONT_SIZE,
+                        color: SCORE_COLOR,
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        right: Val::Px(10.0),
+                        bottom: Val::Px(10.0 + TOAST_HEIGHT * shown as f32),
+                        ..default()
+                    },
+                    ..default()
+                }),
+            )
+            .insert(Toast(Timer::from_seconds(TOAST_SECS, false)));
+    }
+
+    for (entity, mut toast, mut text) in &mut toast_query {
+        toast.0.tick(time.delta());
+        if toast.0.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let fade = (TOAST_SECS - toast.0.elapsed_secs()).clamp(0., 1.);
+        text.sections[0].style.color.set_a(fade);
+    }
+}
@@ -0,0 +1,342 @@
+//! Opponents' decision making.
+//!
+//! The default [`BotController`] is a [`Brain`], a small state machine evaluated once per
+//! physics step: the bot first looks at the arena, then picks the [`BrainState`] that fits
+//! the situation best, and finally acts according to it.
+//! Outside of danger the state follows from the bot's current [`Goal`], chosen by scoring
+//! every candidate target by value and distance, and kept until reached or replanned.
+//! New behaviors only need a new state, its transition rule in [`Brain::transition`]
+//! and its action in [`Brain::act`], the movement systems stay untouched.
+
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, RngCore};
+
+use crate::{
+    bot::{BotAction, BotController, Snapshot},
+    in_blast, Direction, BRICK_SIZE,
+};
+
+// how close (in tiles) another player must be to be chased
+const ATTACK_RANGE: f32 = 4.;
+// how many physics steps a bot keeps the same direction while roaming or escaping a dead end
+const WANDER_STEPS: u32 = 20;
+// how many physics steps a goal is pursued before looking for a better one
+const REPLAN_STEPS: u32 = 60;
+
+// how much each kind of goal is worth, before accounting for its distance
+const POWER_UP_VALUE: f32 = 3.;
+const HUMAN_VALUE: f32 = 2.;
+const BOT_VALUE: f32 = 1.;
+const BRICK_VALUE: f32 = 1.;
+
+/// What a bot is currently up to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrainState {
+    /// Roam around randomly
+    Wander,
+    /// Reach a power-up, or a breakable brick and blow it up
+    Collect,
+    /// Ambush another player and blow it up
+    Attack,
+    /// Get out of the way of a bomb about to explode
+    Flee,
+}
+
+/// Something worth reaching
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Goal {
+    /// Pick up the power-up at the given position
+    PowerUp(Vec2),
+    /// Blow up the brick at the given position
+    Brick(Vec2),
+    /// Get aligned with the given player and blow it up
+    Ambush(Entity),
+}
+
+impl Goal {
+    /// Checks if the goal can still be reached
+    fn is_valid(&self, snapshot: &Snapshot) -> bool {
+        match self {
+            Goal::PowerUp(position) => snapshot
+                .power_ups
+                .iter()
+                .any(|power_up| power_up.position == *position),
+            Goal::Brick(position) => snapshot
+                .bricks
+                .iter()
+                .any(|brick| brick.breakable && brick.position == *position),
+            Goal::Ambush(entity) => snapshot.others.iter().any(|other| {
+                other.entity == *entity
+                    && tiles(other.position - snapshot.me.position) <= ATTACK_RANGE
+            }),
+        }
+    }
+
+    /// Scores every reachable goal and returns the best one
+    fn best(snapshot: &Snapshot) -> Option<Goal> {
+        let position = snapshot.me.position;
+        let score = |value: f32, target: Vec2| value / (1. + tiles(target - position));
+
+        let power_ups = snapshot.power_ups.iter().map(|power_up| {
+            (
+                Goal::PowerUp(power_up.position),
+                score(POWER_UP_VALUE, power_up.position),
+            )
+        });
+        let bricks = snapshot
+            .bricks
+            .iter()
+            .filter(|brick| brick.breakable)
+            .map(|brick| {
+                (
+                    Goal::Brick(brick.position),
+                    score(BRICK_VALUE, brick.position),
+                )
+            });
+        let players = snapshot
+            .others
+            .iter()
+            .filter(|other| tiles(other.position - position) <= ATTACK_RANGE)
+            .map(|other| {
+                let value = if other.human { HUMAN_VALUE } else { BOT_VALUE };
+                (Goal::Ambush(other.entity), score(value, other.position))
+            });
+
+        power_ups
+            .chain(bricks)
+            .chain(players)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(goal, _)| goal)
+    }
+}
+
+/// A bot's state machine
+pub struct Brain {
+    state: BrainState,
+    // goal currently pursued, if any
+    goal: Option<Goal>,
+    // steps since the current goal has been chosen
+    goal_age: u32,
+    // direction followed while wandering, fleeing or escaping a dead end
+    direction: Direction,
+    // remaining steps to keep following `direction`
+    steps: u32,
+    // position at the previous step, used to detect when the bot is stuck
+    last_position: Vec2,
+}
+
+impl Default for Brain {
+    fn default() -> Self {
+        Brain {
+            state: BrainState::Wander,
+            goal: None,
+            goal_age: 0,
+            direction: Direction::Down,
+            steps: 0,
+            last_position: Vec2::ZERO,
+        }
+    }
+}
+
+/// What a bot knows about itself at a given step
+struct Senses {
+    position: Vec2,
+    // whether the bot moved since the previous step
+    stuck: bool,
+    // whether the bot has bombs left to place
+    can_bomb: bool,
+    // reach of the bot's bombs, in tiles
+    power: f32,
+    // bombs whose blast would reach the bot
+    threats: Vec<Vec2>,
+}
+
+impl Brain {
+    fn transition(&mut self, senses: &Senses, snapshot: &Snapshot) {
+        self.goal_age += 1;
+        if self.goal_age >= REPLAN_STEPS || !self.goal.is_some_and(|goal| goal.is_valid(snapshot)) {
+            self.goal = Goal::best(snapshot);
+            self.goal_age = 0;
+        }
+
+        let next = if !senses.threats.is_empty() {
+            BrainState::Flee
+        } else {
+            match self.goal {
+                Some(Goal::PowerUp(_) | Goal::Brick(_)) => BrainState::Collect,
+                Some(Goal::Ambush(_)) => BrainState::Attack,
+                None => BrainState::Wander,
+            }
+        };
+
+        if next != self.state {
+            self.state = next;
+            self.steps = 0;
+            if next == BrainState::Flee {
+                self.direction = flee_direction(senses.position, &senses.threats);
+                self.steps = WANDER_STEPS;
+            }
+        }
+    }
+
+    fn act(&mut self, senses: &Senses, snapshot: &Snapshot, rng: &mut dyn RngCore) -> BotAction {
+        // when stuck, follow a random direction for a while to get around the obstacle
+        if senses.stuck && self.steps == 0 {
+            self.direction = random_direction(rng, Some(self.direction));
+            self.steps = WANDER_STEPS;
+        }
+
+        // where to go, and what to blow up (and from how far) once there
+        let target = match (self.state, self.goal) {
+            (BrainState::Wander | BrainState::Flee, _) | (_, None) => None,
+            (_, Some(Goal::PowerUp(position))) => Some((position, None)),
+            (_, Some(Goal::Brick(position))) => Some((position, Some((position, 1.5)))),
+            (_, Some(Goal::Ambush(entity))) => snapshot
+                .others
+                .iter()
+                .find(|other| other.entity == entity)
+                .map(|other| {
+                    (
+                        ambush_spot(senses.position, other.position),
+                        Some((other.position, senses.power)),
+                    )
+                }),
+        };
+
+        if let Some((target, victim)) = target {
+            if let Some((victim, range)) = victim {
+                let distance = victim - senses.position;
+                if is_aligned(distance) && tiles(distance) <= range {
+                    // wait in place for a bomb to be available
+                    return if senses.can_bomb {
+                        BotAction::Bomb
+                    } else {
+                        BotAction::Idle
+                    };
+                }
+            }
+            if self.steps == 0 {
+                return BotAction::Move(towards(target - senses.position));
+            }
+        } else if self.steps == 0 {
+            // wandering, or the threat moved away from the initial escape route
+            self.direction = random_direction(rng, None);
+            self.steps = WANDER_STEPS;
+        }
+
+        self.steps = self.steps.saturating_sub(1);
+        BotAction::Move(self.direction)
+    }
+}
+
+impl BotController for Brain {
+    fn decide(&mut self, snapshot: &Snapshot, rng: &mut dyn RngCore) -> BotAction {
+        let me = &snapshot.me;
+        let senses = Senses {
+            position: me.position,
+            stuck: self.last_position == me.position,
+            can_bomb: me.bombs_left > 0,
+            power: me.bomb_power as f32,
+            threats: snapshot
+                .bombs
+                .iter()
+                .filter(|bomb| {
+                    in_blast(
+                        bomb.position.extend(0.),
+                        bomb.power,
+                        me.position.extend(0.),
+                        me.size,
+                    )
+                })
+                .map(|bomb| bomb.position)
+                .collect(),
+        };
+        self.last_position = me.position;
+
+        self.transition(&senses, snapshot);
+        self.act(&senses, snapshot, rng)
+    }
+
+    #[cfg(feature = "debug-tools")]
+    fn describe(&self) -> Option<String> {
+        let goal = match self.goal {
+            Some(Goal::PowerUp(position)) => format!(" power-up at {position}"),
+            Some(Goal::Brick(position)) => format!(" brick at {position}"),
+            Some(Goal::Ambush(entity)) => format!(" player {}", entity.id()),
+            None => String::new(),
+        };
+        Some(format!("{:?}{goal}", self.state))
+    }
+}
+
+/// Distance expressed in tiles
+fn tiles(distance: Vec2) -> f32 {
+    (distance / BRICK_SIZE).length()
+}
+
+/// Whether the distance lays on a single row or column
+fn is_aligned(distance: Vec2) -> bool {
+    distance.x.abs() < BRICK_SIZE.x / 2. || distance.y.abs() < BRICK_SIZE.y / 2.
+}
+
+fn nearest(position: Vec2, candidates: impl Iterator<Item = Vec2>) -> Option<Vec2> {
+    candidates.min_by(|a, b| {
+        a.distance_squared(position)
+            .total_cmp(&b.distance_squared(position))
+    })
+}
+
+/// The closest spot sharing a row or a column with the victim
+fn ambush_spot(position: Vec2, victim: Vec2) -> Vec2 {
+    let same_column = Vec2::new(victim.x, position.y);
+    let same_row = Vec2::new(position.x, victim.y);
+    if same_column.distance_squared(position) < same_row.distance_squared(position) {
+        same_column
+    } else {
+        same_row
+    }
+}
+
+/// Direction that reduces the given distance the most
+fn towards(distance: Vec2) -> Direction {
+    if distance.x.abs() > distance.y.abs() {
+        if distance.x > 0. {
+            Direction::Right
+        } else {
+            Direction::Left
+        }
+    } else if distance.y > 0. {
+        Direction::Up
+    } else {
+        Direction::Down
+    }
+}
+
+/// Direction that gets out of the blast of the nearest threat
+fn flee_direction(position: Vec2, threats: &[Vec2]) -> Direction {
+    let threat = match nearest(position, threats.iter().copied()) {
+        Some(threat) => threat,
+        None => return Direction::Down,
+    };
+    let distance = position - threat;
+    // blasts are crosses, stepping aside is quicker than outrunning them
+    if distance.y.abs() < BRICK_SIZE.y / 2. {
+        if distance.y >= 0. {
+            Direction::Up
+        } else {
+            Direction::Down
+        }
+    } else if distance.x >= 0. {
+        Direction::Right
+    } else {
+        Direction::Left
+    }
+}
+
+fn random_direction(rng: &mut dyn RngCore, exclude: Option<Direction>) -> Direction {
+    let candidates = Direction::ALL
+        .into_iter()
+        .filter(|direction| Some(*direction) != exclude)
+        .collect::<Vec<_>>();
+    *candidates.choose(rng).unwrap_or(&Direction::Down)
+}
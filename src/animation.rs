@@ -0,0 +1,246 @@
+//! Sprite sheets, and the animations played with them.
+//!
+//! Players, bombs and fires are spawned as flat colored quads, sized by their scale like
+//! everything else in the arena, so that matches play the same with nothing rendered at all.
+//! Once they show up, players and bombs are dressed with a frame of their sprite sheet, keeping
+//! the sprite of their quad so that its color is still known to joined instances, while fires
+//! are covered by flames, one for every cell they reach. Sprites of players are shown in the
+//! palette picked for color vision deficiencies, if any.
+//! Destroyed bricks shrink and fade away for a moment, no longer bricks at all.
+//! Frames follow the match itself: where players face and whether they're walking, how long
+//! bombs have been ticking and fires burning. With flashing reduced from the options screen,
+//! fuses burn down once instead of sparkling, and flames fade away instead of flaring up.
+
+use bevy::prelude::*;
+
+use crate::{
+    fire_reach, settings::Settings, Bomb, Corpse, Direction, Facing, Fire, Player, Tick, Velocity,
+    BRICK_SIZE,
+};
+
+const PLAYER_SHEET: &str = "textures/player.png";
+const BOMB_SHEET: &str = "textures/bomb.png";
+const EXPLOSION_SHEET: &str = "textures/explosion.png";
+// pixels of a frame, the same for every sheet
+const FRAME_SIZE: Vec2 = Vec2::new(16., 16.);
+// frames of a walk, one row of them for every direction
+const WALK_FRAMES: usize = 4;
+// physics steps every frame of a walk is shown for
+const WALK_FRAME_STEPS: u64 = 8;
+// frames of a fuse burning
+const FUSE_FRAMES: usize = 4;
+// seconds every frame of a fuse is shown for
+const FUSE_FRAME_SECS: f32 = 0.1;
+// frames of flames flaring up, shown forth and back while they burn
+const FLAME_FRAMES: usize = 4;
+// rows of the explosion sheet: the middle of a blast, the cells along it and those at its ends,
+// both drawn pointing right and turned towards their direction
+const FLAME_CENTER: usize = 0;
+const FLAME_ARM: usize = 1;
+const FLAME_TIP: usize = 2;
+// seconds destroyed bricks take to crumble away
+const CRUMBLE_SECS: f32 = 0.4;
+
+/// This resource holds the sprite sheets of the game
+pub struct Sheets {
+    player: Handle<TextureAtlas>,
+    bomb: Handle<TextureAtlas>,
+    explosion: Handle<TextureAtlas>,
+}
+
+/// A cell of a fire, drawn over it while it burns
+#[derive(Component)]
+pub struct Flame {
+    fire: Entity,
+    // row of the explosion sheet
+    row: usize,
+}
+
+/// A destroyed brick crumbling away, only there to be seen
+#[derive(Component)]
+pub struct Crumble(Timer);
+
+impl Default for Crumble {
+    fn default() -> Self {
+        Crumble(Timer::from_seconds(CRUMBLE_SECS, false))
+    }
+}
+
+// Load the sprite sheets
+pub fn load(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let mut sheet = |path: &str, columns: usize, rows: usize| {
+        texture_atlases.add(TextureAtlas::from_grid(
+            asset_server.load(path),
+            FRAME_SIZE,
+            columns,
+            rows,
+        ))
+    };
+    commands.insert_resource(Sheets {
+        player: sheet(PLAYER_SHEET, WALK_FRAMES, Direction::ALL.len()),
+        bomb: sheet(BOMB_SHEET, FUSE_FRAMES, 1),
+        explosion: sheet(EXPLOSION_SHEET, FLAME_FRAMES, 3),
+    });
+}
+
+// Draw new players, their corpses and bombs out of their sprite sheets instead of their quads
+#[allow(clippy::type_complexity)]
+pub fn dress(
+    mut commands: Commands,
+    sheets: Res<Sheets>,
+    settings: Res<Settings>,
+    player_query: Query<(Entity, &Sprite), Or<(Added<Player>, Added<Corpse>)>>,
+    bomb_query: Query<Entity, Added<Bomb>>,
+) {
+    for (entity, sprite) in &player_query {
+        commands
+            .entity(entity)
+            .remove::<Handle<Image>>()
+            .insert(TextureAtlasSprite {
+                // players are told apart by their color, in a palette they can be told apart in
+                color: settings.color_vision.adjust(sprite.color),
+                // as large as the quad it replaces
+                custom_size: Some(Vec2::ONE),
+                ..default()
+            })
+            .insert(sheets.player.clone());
+    }
+    for entity in &bomb_query {
+        commands
+            .entity(entity)
+            .remove::<Handle<Image>>()
+            .insert(TextureAtlasSprite {
+                custom_size: Some(Vec2::ONE),
+                ..default()
+            })
+            .insert(sheets.bomb.clone());
+    }
+}
+
+// Show players walking towards where they face, or standing still
+pub fn walk(
+    tick: Res<Tick>,
+    mut query: Query<(&Facing, &Velocity, &mut TextureAtlasSprite), With<Player>>,
+) {
+    for (facing, velocity, mut sprite) in &mut query {
+        let row = match facing.0 {
+            Direction::Down => 0,
+            Direction::Left => 1,
+            Direction::Right => 2,
+            Direction::Up => 3,
+        };
+        // following the steps, walks go on from the same frame after a rollback
+        let frame = if velocity.0 == Vec2::ZERO {
+            0
+        } else {
+            (tick.0 / WALK_FRAME_STEPS) as usize % WALK_FRAMES
+        };
+        sprite.index = row * WALK_FRAMES + frame;
+    }
+}
+
+// Make the fuses of bombs sparkle, or burn down when flashing is reduced
+pub fn fuse(settings: Res<Settings>, mut query: Query<(&Bomb, &mut TextureAtlasSprite)>) {
+    for (bomb, mut sprite) in &mut query {
+        sprite.index = if settings.reduced_flashing {
+            // once over the whole fuse
+            ((bomb.timer.percent() * FUSE_FRAMES as f32) as usize).min(FUSE_FRAMES - 1)
+        } else {
+            (bomb.timer.elapsed_secs() / FUSE_FRAME_SECS) as usize % FUSE_FRAMES
+        };
+    }
+}
+
+// Cover new fires with flames, from the middle of the blast to its ends
+pub fn ignite(
+    mut commands: Commands,
+    sheets: Res<Sheets>,
+    query: Query<(Entity, &Transform), Added<Fire>>,
+    flame_query: Query<(Entity, &Flame)>,
+) {
+    for (fire, transform) in &query {
+        commands.entity(fire).remove::<Handle<Image>>();
+        // fires are lit again once burnt out, maybe before their old flames were put out
+        for (flame, _) in flame_query.iter().filter(|(_, flame)| flame.fire == fire) {
+            commands.entity(flame).despawn();
+        }
+        let (axis, reach) = fire_reach(transform.scale);
+        for cell in -reach..=reach {
+            let row = match cell.abs() {
+                0 => FLAME_CENTER,
+                distance if distance == reach => FLAME_TIP,
+                _ => FLAME_ARM,
+            };
+            // pointing away from the middle
+            let direction = axis * if cell < 0 { -1. } else { 1. };
+            let offset = axis * BRICK_SIZE * cell as f32;
+            commands
+                .spawn_bundle(SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        index: row * FLAME_FRAMES,
+                        custom_size: Some(BRICK_SIZE),
+                        ..default()
+                    },
+                    texture_atlas: sheets.explosion.clone(),
+                    transform: Transform {
+                        translation: transform.translation + offset.extend(0.),
+                        rotation: Quat::from_rotation_z(direction.y.atan2(direction.x)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(Flame { fire, row });
+        }
+    }
+}
+
+// Make flames flare up and die down along with their fire, putting them out with it
+pub fn burn(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    fire_query: Query<&Fire>,
+    mut flame_query: Query<(Entity, &Flame, &mut TextureAtlasSprite)>,
+) {
+    for (entity, flame, mut sprite) in &mut flame_query {
+        let fire = match fire_query.get(flame.fire) {
+            Ok(fire) => fire,
+            Err(_) => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        if settings.reduced_flashing {
+            // the largest frame all along, fading away as the fire burns out
+            sprite.index = flame.row * FLAME_FRAMES + FLAME_FRAMES - 1;
+            sprite.color.set_a(fire.0.percent_left());
+            continue;
+        }
+        // up to the largest frame halfway through, then back
+        let flare = 1. - (2. * fire.0.percent() - 1.).abs();
+        let frame = ((flare * FLAME_FRAMES as f32) as usize).min(FLAME_FRAMES - 1);
+        sprite.index = flame.row * FLAME_FRAMES + frame;
+        sprite.color.set_a(1.);
+    }
+}
+
+// Shrink and fade destroyed bricks, despawning them once gone
+pub fn crumble(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Crumble, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut crumble, mut transform, mut sprite) in &mut query {
+        crumble.0.tick(time.delta());
+        if crumble.0.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let left = crumble.0.percent_left();
+        transform.scale = (BRICK_SIZE * left).extend(1.0);
+        sprite.color.set_a(left);
+    }
+}
@@ -0,0 +1,214 @@
+//! The announcer, calling out what happens during matches.
+//!
+//! Announcements are sent as [`AnnouncementEvent`]s, either by the match itself or by the
+//! announcer watching it: the first kill of every round, the time of a round running out, and
+//! the winners of every round once it ends. Every announcement is shown on a banner at the top of the screen for
+//! a moment, replacing the previous one, and called out with a clip of its own unless the
+//! announcer's voice is turned off from the options screen.
+
+use bevy::prelude::*;
+
+use crate::{
+    bot::Roster, locale::Locale, mods::Mods, settings::Settings, sound::play_effect, DeathEvent,
+    Match, RoundEndedEvent, Rounds, Rules, Tick, TEAM_COLORS, TEXT_COLOR, TIME_STEP,
+};
+
+const FIRST_BLOOD_CLIP: &str = "sounds/announcer/first_blood.wav";
+const HURRY_UP_CLIP: &str = "sounds/announcer/hurry_up.wav";
+const WINS_CLIP: &str = "sounds/announcer/wins.wav";
+const BANNER_FONT_SIZE: f32 = 60.0;
+// seconds banners are shown for, the last of which fading them out
+const BANNER_SECS: f32 = 2.0;
+// seconds left in rounds with a time limit when they're called out as running out
+const HURRY_UP_SECS: f32 = 10.0;
+
+/// What the announcer calls out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Announcement {
+    /// The first kill of the round
+    FirstBlood,
+    /// The time of the round is running out
+    HurryUp,
+    /// The given human won
+    Wins(usize),
+    /// The given team won
+    TeamWins(usize),
+}
+
+impl Announcement {
+    fn text(&self, locale: &Locale) -> String {
+        match self {
+            Announcement::FirstBlood => locale.text("announcer.first_blood").to_string(),
+            Announcement::HurryUp => locale.text("announcer.hurry_up").to_string(),
+            Announcement::Wins(index) => {
+                locale.format("announcer.wins", &[("player", &(index + 1))])
+            }
+            Announcement::TeamWins(team) => {
+                locale.format("announcer.team_wins", &[("team", &(team + 1))])
+            }
+        }
+    }
+}
+
+/// Something for the announcer to call out
+pub struct AnnouncementEvent(pub Announcement);
+
+/// This resource holds the clips of the announcer
+pub struct Clips {
+    first_blood: Handle<AudioSource>,
+    hurry_up: Handle<AudioSource>,
+    wins: Handle<AudioSource>,
+}
+
+impl Clips {
+    /// The clips of the announcer, as replaced by content packs
+    pub fn load(asset_server: &AssetServer, mods: &Mods) -> Self {
+        Clips {
+            first_blood: asset_server.load(mods.asset(FIRST_BLOOD_CLIP).as_str()),
+            hurry_up: asset_server.load(mods.asset(HURRY_UP_CLIP).as_str()),
+            wins: asset_server.load(mods.asset(WINS_CLIP).as_str()),
+        }
+    }
+}
+
+/// The banner showing the latest announcement, until its timer is over
+#[derive(Component)]
+pub struct Banner(Timer);
+
+// Load the clips of the announcer
+pub fn load(mut commands: Commands, asset_server: Res<AssetServer>, mods: Res<Mods>) {
+    commands.insert_resource(Clips::load(&asset_server, &mods));
+}
+
+// Call out the first player of every round killed by someone else
+pub fn first_blood(
+    rounds: Res<Rounds>,
+    // start of the latest round whose first blood was called out
+    mut announced: Local<Option<u64>>,
+    mut death_reader: EventReader<DeathEvent>,
+    mut announcement_writer: EventWriter<AnnouncementEvent>,
+) {
+    for DeathEvent { player, killer } in death_reader.iter() {
+        if killer.is_some_and(|killer| killer != *player) && *announced != Some(rounds.start) {
+            *announced = Some(rounds.start);
+            announcement_writer.send(AnnouncementEvent(Announcement::FirstBlood));
+        }
+    }
+}
+
+// Call out rounds running out of time
+pub fn hurry_up(
+    rules: Res<Rules>,
+    rounds: Res<Rounds>,
+    tick: Res<Tick>,
+    // start of the latest round called out as running out of time
+    mut announced: Local<Option<u64>>,
+    mut announcement_writer: EventWriter<AnnouncementEvent>,
+) {
+    let limit = match rules.time_limit {
+        // rounds too short to hurry through aren't called out at all
+        Some(limit) if !rounds.over && limit as f32 > HURRY_UP_SECS => limit,
+        _ => return,
+    };
+    let left = limit as f32 - (tick.0 - rounds.start) as f32 * TIME_STEP;
+    if left <= HURRY_UP_SECS && *announced != Some(rounds.start) {
+        *announced = Some(rounds.start);
+        announcement_writer.send(AnnouncementEvent(Announcement::HurryUp));
+    }
+}
+
+// Call out the winners of every round once it ends
+pub fn winners(
+    mut round_reader: EventReader<RoundEndedEvent>,
+    mut announcement_writer: EventWriter<AnnouncementEvent>,
+) {
+    for RoundEndedEvent { winner, team } in round_reader.iter() {
+        let announcement = match (winner, team) {
+            (_, Some(team)) => Announcement::TeamWins(*team),
+            (Some(index), None) => Announcement::Wins(*index),
+            (None, None) => continue,
+        };
+        announcement_writer.send(AnnouncementEvent(announcement));
+    }
+}
+
+// Show announcements on a banner and call them out, fading banners away once their time is over
+#[allow(clippy::too_many_arguments)]
+pub fn announce(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    settings: Res<Settings>,
+    locale: Res<Locale>,
+    clips: Res<Clips>,
+    roster: Res<Roster>,
+    mut event_reader: EventReader<AnnouncementEvent>,
+    mut banner_query: Query<(Entity, &mut Banner, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    // only the latest announcement is shown
+    if let Some(AnnouncementEvent(announcement)) = event_reader.iter().last() {
+        for (entity, ..) in &banner_query {
+            commands.entity(entity).despawn_recursive();
+        }
+        let (clip, color) = match announcement {
+            Announcement::FirstBlood => (&clips.first_blood, TEXT_COLOR),
+            Announcement::HurryUp => (&clips.hurry_up, TEXT_COLOR),
+            Announcement::Wins(index) => (
+                &clips.wins,
+                settings.color_vision.adjust(roster.color(*index)),
+            ),
+            Announcement::TeamWins(team) => (
+                &clips.wins,
+                settings.color_vision.adjust(TEAM_COLORS[*team]),
+            ),
+        };
+        if settings.announcer_voice {
+            play_effect(&audio, &settings, clip);
+        }
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    position_type: PositionType::Absolute,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::FlexEnd,
+                    padding: UiRect {
+                        top: Val::Percent(10.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                color: Color::NONE.into(),
+                ..default()
+            })
+            .insert(Banner(Timer::from_seconds(BANNER_SECS, false)))
+            .insert(Match)
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle::from_section(
+                    announcement.text(&locale),
+                    TextStyle {
+                        font: asset_server.load(locale.font()),
+                        font_size: BANNER_FONT_SIZE,
+                        color,
+                    },
+                ));
+            });
+        return;
+    }
+
+    for (entity, mut banner, children) in &mut banner_query {
+        banner.0.tick(time.delta());
+        if banner.0.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let fade = (BANNER_SECS - banner.0.elapsed_secs()).clamp(0., 1.);
+        for child in children {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].style.color.set_a(fade);
+            }
+        }
+    }
+}
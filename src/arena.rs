@@ -0,0 +1,170 @@
+//! Layout of the arena: where pillars and breakable bricks stand.
+//!
+//! Pillars stand on every odd row and column, the classic grid that can't be blown up.
+//! Breakable bricks are laid out at random in the other cells, at the density set by the
+//! [`Rules`](crate::Rules), except around spawn points so that players always have room to
+//! place their first bomb and get away from it.
+//! Random arenas can be played in a smaller area in the middle, walled in by pillars.
+//! Arenas can also be drawn by hand, see [`level`](crate::level).
+//! Every spawn point can reach every other one, at worst blowing up bricks on the way:
+//! when pillars would wall a player in, some of them get turned into breakable bricks.
+
+use rand::Rng;
+
+use std::{collections::VecDeque, ops::Range};
+
+use crate::{Direction, COLS, ROWS};
+
+/// What stands in a cell of the arena
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tile {
+    Empty,
+    /// Can't be blown up
+    Pillar,
+    Breakable,
+    /// Pushes players and bombs standing on it towards a direction
+    Conveyor(Direction),
+    /// Moves players and bombs stepping on it to the other warp of the same pair
+    Warp(u8),
+    /// Tosses players stepping on it two cells ahead
+    Trampoline,
+}
+
+/// Every cell of the arena
+pub struct Layout {
+    // row by row, starting from the bottom one
+    tiles: Vec<Tile>,
+}
+
+impl Layout {
+    /// Lays out breakable bricks at random, each free cell getting one with the given chance,
+    /// filling with pillars the cells outside the playable area of the given size
+    pub fn random(
+        rng: &mut impl Rng,
+        density: f64,
+        size: (usize, usize),
+        spawn_points: &[(usize, usize)],
+    ) -> Self {
+        let (cols, rows) = playable(size);
+        let clearings = spawn_points
+            .iter()
+            .flat_map(|&(col, row)| clearing(col, row))
+            .collect::<Vec<_>>();
+        let density = density.clamp(0., 1.);
+
+        let mut tiles = Vec::with_capacity(ROWS * COLS);
+        for row in 0..ROWS {
+            for col in 0..COLS {
+                let pillar = row % 2 == 1 && col % 2 == 1;
+                let outside = !cols.contains(&col) || !rows.contains(&row);
+                let tile = if pillar || outside {
+                    Tile::Pillar
+                } else if clearings.contains(&(col, row)) {
+                    Tile::Empty
+                } else if rng.gen_bool(density) {
+                    Tile::Breakable
+                } else {
+                    Tile::Empty
+                };
+                tiles.push(tile);
+            }
+        }
+        let mut layout = Layout { tiles };
+        layout.connect(spawn_points);
+        layout
+    }
+
+    /// Lays out the given tiles, row by row from the bottom one, leaving spawn points empty
+    pub fn authored(mut tiles: Vec<Tile>, spawn_points: &[(usize, usize)]) -> Self {
+        for &(col, row) in spawn_points {
+            tiles[Layout::index(col, row)] = Tile::Empty;
+        }
+        let mut layout = Layout { tiles };
+        layout.connect(spawn_points);
+        layout
+    }
+
+    fn index(col: usize, row: usize) -> usize {
+        row * COLS + col
+    }
+
+    /// Cells that can be reached from the given one, blowing up bricks on the way
+    fn reachable(&self, start: (usize, usize)) -> Vec<bool> {
+        let mut reached = vec![false; self.tiles.len()];
+        let mut queue = VecDeque::from([start]);
+        reached[Layout::index(start.0, start.1)] = true;
+        while let Some((col, row)) = queue.pop_front() {
+            for (col, row) in neighbours(col, row) {
+                let index = Layout::index(col, row);
+                if !reached[index] && self.tiles[index] != Tile::Pillar {
+                    reached[index] = true;
+                    queue.push_back((col, row));
+                }
+            }
+        }
+        reached
+    }
+
+    /// Turns into breakable bricks the pillars standing between the first spawn point and
+    /// any other one that can't reach it
+    fn connect(&mut self, spawn_points: &[(usize, usize)]) {
+        let (first, others) = match spawn_points.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        for &(col, row) in others {
+            if self.reachable(*first)[Layout::index(col, row)] {
+                continue;
+            }
+            // walk along the row first, then along the column, like a bomb blast would
+            let (mut col, mut row) = (col, row);
+            while (col, row) != *first {
+                if col != first.0 {
+                    col = if col < first.0 { col + 1 } else { col - 1 };
+                } else {
+                    row = if row < first.1 { row + 1 } else { row - 1 };
+                }
+                let tile = &mut self.tiles[Layout::index(col, row)];
+                if *tile == Tile::Pillar {
+                    *tile = Tile::Breakable;
+                }
+            }
+        }
+    }
+
+    /// Every cell along with its tile, row by row
+    pub fn cells(&self) -> impl Iterator<Item = ((usize, usize), Tile)> + '_ {
+        self.tiles
+            .iter()
+            .enumerate()
+            .map(|(index, tile)| ((index % COLS, index / COLS), *tile))
+    }
+}
+
+/// Columns and rows of the playable area of the given size, centered in the arena.
+/// The area is kept away from the edges by an even number of cells, so that pillars
+/// keep their grid and the edges of the area stay free of them
+pub fn playable(size: (usize, usize)) -> (Range<usize>, Range<usize>) {
+    let (cols, rows) = (size.0.clamp(1, COLS), size.1.clamp(1, ROWS));
+    let col = (COLS - cols) / 2 / 2 * 2;
+    let row = (ROWS - rows) / 2 / 2 * 2;
+    (col..COLS - col, row..ROWS - row)
+}
+
+/// Cells left free around a spawn point: the spawn point itself and its neighbours, which
+/// make the classic L in the corners of the arena
+fn clearing(col: usize, row: usize) -> impl Iterator<Item = (usize, usize)> {
+    std::iter::once((col, row)).chain(neighbours(col, row))
+}
+
+/// Cells next to the given one, inside the arena
+fn neighbours(col: usize, row: usize) -> impl Iterator<Item = (usize, usize)> {
+    [
+        col.checked_sub(1).map(|col| (col, row)),
+        (col + 1 < COLS).then_some((col + 1, row)),
+        row.checked_sub(1).map(|row| (col, row)),
+        (row + 1 < ROWS).then_some((col, row + 1)),
+    ]
+    .into_iter()
+    .flatten()
+}
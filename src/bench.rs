@@ -0,0 +1,179 @@
+//! Benchmarks of the physics systems arenas full of things slow down the most.
+//!
+//! They reach into the game, so they're built in with the `bench` feature and run from
+//! `benches/bomberman.rs` with `cargo bench --features bench`; like with any criterion
+//! benchmark, arguments after `--` pick which ones to run. Each one measures a single system of a physics step, in arenas laid
+//! out by [`spawn_arena`] at every size in [`SIZES`]: [`move_event`] checking the collisions of
+//! every player, and [`explode`] resolving the blasts of a growing number of bombs.
+
+use bevy::{
+    ecs::{
+        event::Events,
+        system::{CommandQueue, System},
+    },
+    prelude::*,
+};
+use criterion::{BatchSize, BenchmarkId, Criterion};
+use rand::seq::SliceRandom;
+
+use crate::{
+    arena,
+    bot::{BotRegistry, Roster, MAX_BOTS},
+    cell_position, explode,
+    headless::build_test_app,
+    input::Bindings,
+    move_event, spawn_arena, spawn_bomb, Bomb, Brick, Direction, ExplosionEvent, MatchRng,
+    MoveEvent, Player, Rules, BOMB_Z, COLS, ROWS,
+};
+
+/// Columns and rows of the arenas benchmarks are run in, from the smallest to the standard one
+const SIZES: [(usize, usize); 3] = [(5, 5), (9, 7), (COLS, ROWS)];
+
+// bombs going off together in blast benchmarks
+const BOMBS: [usize; 3] = [1, 8, 32];
+
+/// An app with no window playing in an arena of the given size, crowded with bots
+fn arena(size: (usize, usize)) -> App {
+    let mut app = build_test_app();
+    let rules = Rules {
+        size,
+        // blasts never leave anything behind, so they all cost the same
+        power_up_chance: 0.,
+        ..default()
+    };
+    let mut roster = Roster {
+        humans: 0,
+        ..default()
+    };
+    roster.resize(MAX_BOTS);
+
+    let mut rng = MatchRng::seeded(0);
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, &app.world);
+    spawn_arena(
+        &mut commands,
+        app.world.resource::<BotRegistry>(),
+        &roster,
+        app.world.resource::<Bindings>(),
+        &rules,
+        &mut rng,
+    );
+    queue.apply(&mut app.world);
+    app.insert_resource(rules).insert_resource(rng);
+    app
+}
+
+/// The given system, ready to run on the world of the given app
+fn system<Params>(
+    app: &mut App,
+    system: impl IntoSystem<(), (), Params>,
+) -> impl System<In = (), Out = ()> {
+    let mut system = IntoSystem::into_system(system);
+    system.initialize(&mut app.world);
+    system
+}
+
+/// Every player steering left and right in turn, bumping into whatever's around
+pub fn collisions(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("move_event");
+    for size in SIZES {
+        let mut app = arena(size);
+        let mut move_event = system(&mut app, move_event);
+        let players = app
+            .world
+            .query_filtered::<Entity, With<Player>>()
+            .iter(&app.world)
+            .collect::<Vec<_>>();
+        let mut left = false;
+        group.bench_function(
+            BenchmarkId::from_parameter(format!("{}x{}", size.0, size.1)),
+            |b| {
+                b.iter(|| {
+                    left = !left;
+                    let direction = if left {
+                        Direction::Left
+                    } else {
+                        Direction::Right
+                    };
+                    {
+                        let mut events = app.world.resource_mut::<Events<MoveEvent>>();
+                        // only this step's events are read, the previous ones are dropped
+                        events.update();
+                        for player in &players {
+                            events.send(MoveEvent {
+                                direction,
+                                player: *player,
+                            });
+                        }
+                    }
+                    move_event.run((), &mut app.world);
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Bombs scattered around free cells going off all at once, each blast checked against every
+/// bomb, brick and player
+pub fn blasts(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("explode");
+    for size in SIZES {
+        for bombs in BOMBS {
+            let id = BenchmarkId::new(format!("{}x{}", size.0, size.1), bombs);
+            group.bench_function(id, |b| {
+                // blasts destroy what they reach, so every run gets an arena of its own
+                b.iter_batched_ref(
+                    || {
+                        let mut app = arena(size);
+                        scatter_bombs(&mut app, size, bombs);
+                        let explode = system(&mut app, explode);
+                        (app, explode)
+                    },
+                    |(app, explode)| explode.run((), &mut app.world),
+                    BatchSize::PerIteration,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Places bombs owned by nobody in free cells picked at random, all set off at once
+fn scatter_bombs(app: &mut App, size: (usize, usize), count: usize) {
+    let taken = app
+        .world
+        .query_filtered::<&Transform, With<Brick>>()
+        .iter(&app.world)
+        .map(|transform| transform.translation.truncate())
+        .collect::<Vec<_>>();
+    let (cols, rows) = arena::playable(size);
+    let mut free = rows
+        .flat_map(|row| cols.clone().map(move |col| cell_position(col, row)))
+        .filter(|position| !taken.contains(position))
+        .collect::<Vec<_>>();
+    free.shuffle(&mut app.world.resource_mut::<MatchRng>().0);
+
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, &app.world);
+    let bombs = free
+        .into_iter()
+        .take(count)
+        .map(|position| {
+            spawn_bomb(
+                &mut commands,
+                Bomb {
+                    player: None,
+                    timer: Timer::from_seconds(0., false),
+                    power: 2,
+                },
+                position.extend(BOMB_Z),
+            )
+        })
+        .collect::<Vec<_>>();
+    queue.apply(&mut app.world);
+    let mut events = app.world.resource_mut::<Events<ExplosionEvent>>();
+    for bomb in bombs {
+        events.send(ExplosionEvent(bomb));
+    }
+}
@@ -0,0 +1,252 @@
+//! The boss waiting at the end of the campaign.
+//!
+//! The boss is a huge enemy hovering over pillars and bricks alike, drifting around the arena
+//! and bouncing off its walls. Now and then it attacks, in turn letting loose some minions, which
+//! are plain [`mob`](crate::mob)s, and charging at the closest human.
+//! Touching it is deadly, and it takes many blasts to bring it down: every blast takes away some
+//! of its health, shown by a bar at the top of the screen, and leaves it unhurt for a while.
+
+use bevy::{prelude::*, sprite::collide_aabb::collide};
+use rand::Rng;
+
+use crate::{
+    cell_center,
+    mob::{spawn_mob, MobKind},
+    Active, Bomb, Brick, Direction, Fire, Match, MatchRng, Scoreboard, BOTTOM_WALL, BRICK_SIZE,
+    LEFT_WALL, RIGHT_WALL, TOP_WALL,
+};
+
+const BOSS_SIZE: Vec2 = Vec2::new(90., 90.);
+const BOSS_COLOR: Color = Color::rgb(0.5, 0.1, 0.4);
+// shown while charging, or unhurt after a blast
+const BOSS_CHARGE_COLOR: Color = Color::rgb(0.9, 0.1, 0.2);
+const BOSS_UNHURT_COLOR: Color = Color::rgb(0.8, 0.6, 0.8);
+const BOSS_BAR_COLOR: Color = Color::rgb(0.2, 0.2, 0.2);
+const BOSS_HEALTH: u32 = 8;
+// points earned by bringing down the boss
+const BOSS_POINTS: usize = 5000;
+// distance the boss drifts by every step, and charges by
+const BOSS_SPEED: f32 = BRICK_SIZE.x / 25.;
+const BOSS_CHARGE_SPEED: f32 = BRICK_SIZE.x / 6.;
+// steps between attacks
+const BOSS_ATTACK_STEPS: u32 = 240;
+// longest charge, in steps
+const BOSS_CHARGE_STEPS: u32 = 60;
+// minions let loose by every attack
+const BOSS_MINIONS: usize = 2;
+// steps the boss can't be hurt for after a blast, outlasting the fire of the blast
+const BOSS_UNHURT_STEPS: u32 = 70;
+
+/// The boss, hovering over the arena
+#[derive(Component)]
+pub struct Boss {
+    health: u32,
+    // where it's drifting, or charging, towards
+    heading: Vec2,
+    // steps before the next attack
+    cooldown: u32,
+    // attacks made so far, to take turns between them
+    attacks: u32,
+    // steps of charge left, while charging
+    charging: u32,
+    // steps left before blasts can hurt it again
+    unhurt: u32,
+}
+
+/// Marks the part of the health bar showing the health left
+#[derive(Component)]
+pub struct HealthBar;
+
+/// Places the boss in the middle of the arena, along with its health bar
+pub fn spawn_boss(commands: &mut Commands) {
+    commands
+        .spawn()
+        .insert(Boss {
+            health: BOSS_HEALTH,
+            heading: Vec2::new(1., 1.).normalize(),
+            cooldown: BOSS_ATTACK_STEPS,
+            attacks: 0,
+            charging: 0,
+            unhurt: 0,
+        })
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: BOSS_COLOR,
+                ..default()
+            },
+            transform: Transform {
+                // above everything in the arena
+                translation: Vec3::new(0., 0., 1.),
+                scale: BOSS_SIZE.extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(10.0),
+                    left: Val::Percent(30.0),
+                    ..default()
+                },
+                size: Size::new(Val::Percent(40.0), Val::Px(20.0)),
+                ..default()
+            },
+            color: BOSS_BAR_COLOR.into(),
+            ..default()
+        })
+        .insert(Match)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                        ..default()
+                    },
+                    color: BOSS_CHARGE_COLOR.into(),
+                    ..default()
+                })
+                .insert(HealthBar);
+        });
+}
+
+// Move the boss around, attacking every now and then
+#[allow(clippy::type_complexity)]
+pub fn act(
+    mut commands: Commands,
+    mut rng: ResMut<MatchRng>,
+    obstacle_query: Query<&Transform, (Or<(With<Brick>, With<Bomb>)>, Without<Boss>)>,
+    human_query: Query<&Transform, (With<Active>, Without<Boss>)>,
+    mut boss_query: Query<(&mut Boss, &mut Transform, &mut Sprite)>,
+) {
+    let rng = &mut rng.0;
+    for (mut boss, mut transform, mut sprite) in &mut boss_query {
+        boss.unhurt = boss.unhurt.saturating_sub(1);
+        let position = transform.translation.truncate();
+
+        if boss.charging > 0 {
+            boss.charging -= 1;
+        } else if boss.cooldown > 0 {
+            boss.cooldown -= 1;
+        } else {
+            boss.cooldown = BOSS_ATTACK_STEPS;
+            boss.attacks += 1;
+            let prey = human_query
+                .iter()
+                .map(|human| human.translation.truncate())
+                .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)));
+            match prey {
+                // charges go straight along rows or columns
+                Some(prey) if boss.attacks % 2 == 0 => {
+                    let offset = prey - position;
+                    boss.heading = if offset.x.abs() > offset.y.abs() {
+                        Vec2::X * offset.x.signum()
+                    } else {
+                        Vec2::Y * offset.y.signum()
+                    };
+                    boss.charging = BOSS_CHARGE_STEPS;
+                }
+                _ => {
+                    // minions only show up where there's room for them
+                    let cell = cell_center(transform.translation);
+                    if !obstacle_query
+                        .iter()
+                        .any(|obstacle| obstacle.translation.truncate().distance(cell) < 1.)
+                    {
+                        for _ in 0..BOSS_MINIONS {
+                            let direction = Direction::ALL[rng.gen_range(0..Direction::ALL.len())];
+                            spawn_mob(&mut commands, MobKind::Balloon, cell, direction);
+                        }
+                    }
+                }
+            }
+        }
+
+        let speed = if boss.charging > 0 {
+            BOSS_CHARGE_SPEED
+        } else {
+            BOSS_SPEED
+        };
+        let mut next = position + boss.heading * speed;
+        // bounce off the walls, which also end charges
+        let min = Vec2::new(LEFT_WALL, BOTTOM_WALL) + BOSS_SIZE / 2.;
+        let max = Vec2::new(RIGHT_WALL, TOP_WALL) - BOSS_SIZE / 2.;
+        if next != next.clamp(min, max) {
+            let heading = boss.heading;
+            boss.heading = if boss.charging > 0 {
+                boss.charging = 0;
+                // back to drifting, towards the middle of the arena
+                (-next.signum()).normalize()
+            } else {
+                Vec2::new(
+                    if next.x < min.x || next.x > max.x {
+                        -heading.x
+                    } else {
+                        heading.x
+                    },
+                    if next.y < min.y || next.y > max.y {
+                        -heading.y
+                    } else {
+                        heading.y
+                    },
+                )
+            };
+            next = next.clamp(min, max);
+        }
+        transform.translation = next.extend(transform.translation.z);
+
+        sprite.color = if boss.charging > 0 {
+            BOSS_CHARGE_COLOR
+        } else if boss.unhurt > 0 {
+            BOSS_UNHURT_COLOR
+        } else {
+            BOSS_COLOR
+        };
+    }
+}
+
+// Take away health from the boss caught in a blast, bringing it down once there's none left
+pub fn hurt(
+    mut commands: Commands,
+    mut scoreboard: ResMut<Scoreboard>,
+    fire_query: Query<&Transform, With<Fire>>,
+    mut boss_query: Query<(Entity, &mut Boss, &Transform)>,
+) {
+    for (entity, mut boss, transform) in &mut boss_query {
+        if boss.unhurt > 0 {
+            continue;
+        }
+        let burnt = fire_query.iter().any(|fire| {
+            collide(
+                fire.translation,
+                fire.scale.truncate(),
+                transform.translation,
+                transform.scale.truncate(),
+            )
+            .is_some()
+        });
+        if !burnt {
+            continue;
+        }
+        boss.health = boss.health.saturating_sub(1);
+        boss.unhurt = BOSS_UNHURT_STEPS;
+        if boss.health == 0 {
+            scoreboard.score += BOSS_POINTS;
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+pub fn update_health_bar(
+    boss_query: Query<&Boss>,
+    mut bar_query: Query<&mut Style, With<HealthBar>>,
+) {
+    let health = boss_query.iter().map(|boss| boss.health).sum::<u32>();
+    for mut style in &mut bar_query {
+        style.size.width = Val::Percent(health as f32 / BOSS_HEALTH as f32 * 100.0);
+    }
+}
@@ -0,0 +1,310 @@
+//! Pluggable bots.
+//!
+//! Opponents are driven by a [`BotController`]: at every physics step the controller receives
+//! a [`Snapshot`] of the arena and answers with a [`BotAction`], which is then translated
+//! into the same events a human player would send.
+//! Controllers are created by name from the [`BotRegistry`], so custom bots only need to be
+//! registered there and listed in the [`Roster`] to join a match.
+//! Bots written as other programs don't need registering: they're reached by name, see
+//! [`external`](crate::external).
+
+use bevy::{prelude::*, utils::HashMap};
+use rand::{
+    distributions::{Distribution, Uniform},
+    RngCore,
+};
+
+use crate::{
+    ai::Brain, Active, Bomb, BombEvent, Breakable, Brick, Direction, MatchRng, MoveEvent, Player,
+    PowerUp, PLAYER_COLORS,
+};
+
+/// Name of the controller used when none is specified
+pub const DEFAULT_BOT: &str = "brain";
+/// Bounds to the number of opponents in a match
+pub const MIN_BOTS: usize = 1;
+pub const MAX_BOTS: usize = 7;
+/// Maximum number of human players in a match
+pub const MAX_HUMANS: usize = 4;
+/// Maximum number of players in a match, one per spawn point
+pub const MAX_PLAYERS: usize = 8;
+
+/// What a bot wants to do at a given step
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BotAction {
+    Idle,
+    Move(Direction),
+    Bomb,
+}
+
+/// A player as seen by a bot
+// not every bot looks at everything it's shown
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+pub struct PlayerInfo {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Whether the player is controlled by a human
+    pub human: bool,
+    pub bombs_left: u8,
+    pub bomb_power: u8,
+}
+
+/// A bomb as seen by a bot
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+pub struct BombInfo {
+    pub position: Vec2,
+    pub power: u8,
+    /// The player who placed it, `None` once that player is dead
+    pub owner: Option<Entity>,
+    /// Seconds left before the explosion
+    pub fuse: f32,
+}
+
+/// A brick as seen by a bot
+#[derive(Clone, Debug)]
+pub struct BrickInfo {
+    pub position: Vec2,
+    pub breakable: bool,
+}
+
+/// A power-up as seen by a bot
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+pub struct PowerUpInfo {
+    pub position: Vec2,
+    pub kind: PowerUp,
+}
+
+/// Everything a bot can observe about the arena at a given step
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    /// The player controlled by the bot
+    pub me: PlayerInfo,
+    /// Every other player still alive
+    pub others: Vec<PlayerInfo>,
+    pub bombs: Vec<BombInfo>,
+    pub bricks: Vec<BrickInfo>,
+    pub power_ups: Vec<PowerUpInfo>,
+}
+
+/// The brain of a bot
+pub trait BotController: Send + Sync {
+    /// Decides the next action given the current state of the arena.
+    /// Random choices must be drawn from `rng`, so that a seed reproduces the whole match
+    fn decide(&mut self, snapshot: &Snapshot, rng: &mut dyn RngCore) -> BotAction;
+
+    /// What the bot is up to, shown by debugging tools, if it can tell
+    #[cfg(feature = "debug-tools")]
+    fn describe(&self) -> Option<String> {
+        None
+    }
+}
+
+type BotFactory = Box<dyn Fn() -> Box<dyn BotController> + Send + Sync>;
+
+/// This resource holds every available bot, by name
+pub struct BotRegistry {
+    factories: HashMap<String, BotFactory>,
+}
+
+impl Default for BotRegistry {
+    fn default() -> Self {
+        let mut registry = BotRegistry {
+            factories: HashMap::default(),
+        };
+        registry.register(DEFAULT_BOT, || Box::new(Brain::default()));
+        registry.register("random", || Box::new(RandomBot));
+        registry
+    }
+}
+
+impl BotRegistry {
+    /// Makes a new bot available under the given name, replacing any previous one
+    pub fn register<F>(&mut self, name: impl Into<String>, factory: F)
+    where
+        F: Fn() -> Box<dyn BotController> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.into(), Box::new(factory));
+    }
+
+    /// Creates a new instance of the named bot, connecting to it if it's an external one
+    pub fn create(&self, name: &str) -> Option<Box<dyn BotController>> {
+        match self.factories.get(name) {
+            Some(factory) => Some(factory()),
+            #[cfg(not(target_arch = "wasm32"))]
+            None => crate::external::connect(name),
+            #[cfg(target_arch = "wasm32")]
+            None => None,
+        }
+    }
+}
+
+/// This resource lists who takes part in the next match
+#[derive(Clone)]
+pub struct Roster {
+    /// Number of local human players, none means the bots play among themselves
+    pub humans: usize,
+    /// Number of human players joined from other instances, playing after the local ones
+    pub remotes: usize,
+    /// Controllers of the bots, one per opponent
+    pub bots: Vec<String>,
+    /// Colors of the human players, remote ones pick theirs in the lobby
+    pub colors: [Color; MAX_HUMANS],
+}
+
+impl Default for Roster {
+    fn default() -> Self {
+        Roster {
+            humans: 1,
+            remotes: 0,
+            bots: vec![DEFAULT_BOT.to_string(); 3],
+            colors: PLAYER_COLORS,
+        }
+    }
+}
+
+impl Roster {
+    /// A bots-only match, played behind the menu
+    pub fn demo() -> Self {
+        Roster {
+            humans: 0,
+            remotes: 0,
+            bots: vec![DEFAULT_BOT.to_string(); 4],
+            colors: PLAYER_COLORS,
+        }
+    }
+
+    /// Number of human players, both local and remote
+    pub fn total_humans(&self) -> usize {
+        self.humans + self.remotes
+    }
+
+    /// Color of the given human player
+    pub fn color(&self, index: usize) -> Color {
+        self.colors[index]
+    }
+
+    /// Total number of players
+    pub fn players(&self) -> usize {
+        self.total_humans() + self.bots.len()
+    }
+
+    /// Changes the number of opponents, new ones use the default controller
+    pub fn resize(&mut self, count: usize) {
+        let max = MAX_BOTS.min(MAX_PLAYERS - self.total_humans());
+        self.bots
+            .resize(count.clamp(MIN_BOTS, max), DEFAULT_BOT.to_string());
+    }
+
+    /// Name of the controller of the given opponent
+    pub fn bot_name(&self, index: usize) -> &str {
+        self.bots.get(index).map_or(DEFAULT_BOT, String::as_str)
+    }
+
+    /// Instantiates the controller of the given opponent, falling back to the default one
+    pub fn controller(&self, registry: &BotRegistry, index: usize) -> Bot {
+        let name = self.bot_name(index);
+        Bot(registry.create(name).unwrap_or_else(|| {
+            warn!("Unknown bot {name:?}, falling back to {DEFAULT_BOT:?}");
+            Box::new(Brain::default())
+        }))
+    }
+}
+
+/// Marks a player driven by a bot
+#[derive(Component)]
+pub struct Bot(pub Box<dyn BotController>);
+
+/// The original opponents' behavior: random moves and bombs at every step
+pub struct RandomBot;
+
+impl BotController for RandomBot {
+    fn decide(&mut self, _snapshot: &Snapshot, rng: &mut dyn RngCore) -> BotAction {
+        let between = Uniform::from(0_u8..5_u8);
+        match between.sample(rng) {
+            0 => BotAction::Move(Direction::Down),
+            1 => BotAction::Move(Direction::Left),
+            2 => BotAction::Move(Direction::Right),
+            3 => BotAction::Move(Direction::Up),
+            _ => BotAction::Bomb,
+        }
+    }
+}
+
+/// Asks every bot what to do and sends the resulting events
+#[allow(clippy::too_many_arguments)]
+pub fn drive(
+    mut bots: Query<(Entity, &mut Bot)>,
+    players: Query<(Entity, &Transform, &Player, Option<&Active>)>,
+    bombs: Query<(&Transform, &Bomb)>,
+    bricks: Query<(&Transform, Option<&Breakable>), With<Brick>>,
+    power_ups: Query<(&Transform, &PowerUp)>,
+    mut move_writer: EventWriter<MoveEvent>,
+    mut bomb_writer: EventWriter<BombEvent>,
+    mut rng: ResMut<MatchRng>,
+) {
+    let infos = players
+        .iter()
+        .map(|(entity, transform, player, active)| PlayerInfo {
+            entity,
+            position: transform.translation.truncate(),
+            size: transform.scale.truncate(),
+            human: active.is_some(),
+            bombs_left: player.max_bombs.saturating_sub(player.active_bombs),
+            bomb_power: player.bomb_power,
+        })
+        .collect::<Vec<_>>();
+    let bombs = bombs
+        .iter()
+        .map(|(transform, bomb)| BombInfo {
+            position: transform.translation.truncate(),
+            power: bomb.power,
+            owner: bomb.player,
+            fuse: bomb.timer.duration().as_secs_f32() - bomb.timer.elapsed_secs(),
+        })
+        .collect::<Vec<_>>();
+    let bricks = bricks
+        .iter()
+        .map(|(transform, breakable)| BrickInfo {
+            position: transform.translation.truncate(),
+            breakable: breakable.is_some(),
+        })
+        .collect::<Vec<_>>();
+    let power_ups = power_ups
+        .iter()
+        .map(|(transform, power_up)| PowerUpInfo {
+            position: transform.translation.truncate(),
+            kind: *power_up,
+        })
+        .collect::<Vec<_>>();
+
+    for (entity, mut bot) in &mut bots {
+        let me = match infos.iter().find(|info| info.entity == entity) {
+            Some(me) => me.clone(),
+            None => continue,
+        };
+        let snapshot = Snapshot {
+            me,
+            others: infos
+                .iter()
+                .filter(|info| info.entity != entity)
+                .cloned()
+                .collect(),
+            bombs: bombs.clone(),
+            bricks: bricks.clone(),
+            power_ups: power_ups.clone(),
+        };
+
+        match bot.0.decide(&snapshot, &mut rng.0) {
+            BotAction::Idle => {}
+            BotAction::Move(direction) => move_writer.send(MoveEvent {
+                direction,
+                player: entity,
+            }),
+            BotAction::Bomb => bomb_writer.send(BombEvent { player: entity }),
+        }
+    }
+}
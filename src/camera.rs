@@ -0,0 +1,128 @@
+//! The camera looking at the arena, and the ways it moves.
+//!
+//! The camera looks at the middle of the arena, as long as the whole of it fits in the window.
+//! Otherwise, unless turned off from the options screen, it follows the first local human: it
+//! only moves once the human walks out of a zone around where it looks, catching up smoothly,
+//! and it never shows what's beyond the walls.
+//! Explosions shake the camera, harder the more powerful they are and the closer they go off to
+//! where it looks, unless screen shake is turned off from the options screen.
+//! Shaking only ever moves the camera around where it would be otherwise.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    settings::Settings, Active, Bomb, ExplosionEvent, Player, BOTTOM_WALL, BRICK_SIZE, LEFT_WALL,
+    MAX_POWER, RIGHT_WALL, TOP_WALL, WALL_THICKNESS,
+};
+
+// pixels the camera moves away at most, when shaking as hard as it can
+const MAX_SHAKE: f32 = 12.;
+// shake lost every second, out of the hardest one
+const SHAKE_DECAY: f32 = 1.5;
+// distance from where the camera looks beyond which explosions don't shake it, in pixels
+const SHAKE_REACH: f32 = BRICK_SIZE.x * 10.;
+// half the size of the zone a followed human moves in without moving the camera, in pixels
+const DEADZONE: Vec2 = Vec2::new(BRICK_SIZE.x * 2., BRICK_SIZE.y * 1.5);
+// how quickly the camera catches up with a followed human, the higher the quicker
+const FOLLOW_RATE: f32 = 5.;
+
+/// How hard the camera is shaking, from zero to one
+#[derive(Component, Default)]
+pub struct Shake {
+    trauma: f32,
+    // how far the camera was moved by the latest shake
+    offset: Vec2,
+}
+
+// Shake cameras whenever bombs go off
+pub fn rattle(
+    settings: Option<Res<Settings>>,
+    mut event_reader: EventReader<ExplosionEvent>,
+    bomb_query: Query<(&Bomb, &Transform)>,
+    mut camera_query: Query<(&mut Shake, &Transform)>,
+) {
+    // matches played with nobody watching have no settings at all
+    let enabled = settings.is_some_and(|settings| settings.screen_shake);
+    for ExplosionEvent(bomb) in event_reader.iter() {
+        let (bomb, bomb_transform) = match bomb_query.get(*bomb) {
+            Ok(bomb) if enabled => bomb,
+            _ => continue,
+        };
+        for (mut shake, camera_transform) in &mut camera_query {
+            let focus = camera_transform.translation.truncate() - shake.offset;
+            let distance = bomb_transform.translation.truncate().distance(focus);
+            let closeness = (1. - distance / SHAKE_REACH).max(0.);
+            let power = bomb.power.min(MAX_POWER) as f32 / MAX_POWER as f32;
+            // even the weakest bombs are felt a bit
+            shake.trauma = (shake.trauma + (0.2 + 0.4 * power) * closeness).min(1.);
+        }
+    }
+}
+
+// Move shaking cameras around, calming them down as time goes by
+pub fn shake(time: Res<Time>, mut query: Query<(&mut Shake, &mut Transform)>) {
+    let rng = &mut rand::thread_rng();
+    for (mut shake, mut transform) in &mut query {
+        transform.translation -= shake.offset.extend(0.);
+        shake.trauma = (shake.trauma - SHAKE_DECAY * time.delta_seconds()).max(0.);
+        // hard shakes feel much harder than light ones
+        let strength = shake.trauma * shake.trauma * MAX_SHAKE;
+        shake.offset = Vec2::new(rng.gen_range(-1. ..1.), rng.gen_range(-1. ..1.)) * strength;
+        transform.translation += shake.offset.extend(0.);
+    }
+}
+
+/// Where the camera looks for the given position of the followed human, if any, starting from
+/// where it looked: as close as the deadzone allows, and as far from the walls as the view needs
+fn focus(looked: Vec2, human: Option<Vec2>, view: Vec2) -> Vec2 {
+    let human = match human {
+        Some(human) => human,
+        None => return Vec2::ZERO,
+    };
+    let focus = looked.clamp(human - DEADZONE, human + DEADZONE);
+    let corner = Vec2::new(RIGHT_WALL, TOP_WALL) + WALL_THICKNESS;
+    let bottom_left = Vec2::new(LEFT_WALL, BOTTOM_WALL) - WALL_THICKNESS;
+    let axis = |focus: f32, low: f32, high: f32, view: f32| {
+        if high - low <= view {
+            // the whole arena fits along this axis
+            (low + high) / 2.
+        } else {
+            focus.clamp(low + view / 2., high - view / 2.)
+        }
+    };
+    Vec2::new(
+        axis(focus.x, bottom_left.x, corner.x, view.x),
+        axis(focus.y, bottom_left.y, corner.y, view.y),
+    )
+}
+
+// Follow the first local human around arenas too large for the window
+pub fn follow(
+    time: Res<Time>,
+    windows: Res<Windows>,
+    settings: Res<Settings>,
+    human_query: Query<(&Active, &Transform), With<Player>>,
+    mut camera_query: Query<(&mut Transform, &Shake), Without<Player>>,
+) {
+    let view = match windows.get_primary() {
+        Some(window) => Vec2::new(window.width(), window.height()),
+        None => return,
+    };
+    let human = human_query
+        .iter()
+        .min_by_key(|(active, _)| active.0)
+        .map(|(_, transform)| transform.translation.truncate())
+        .filter(|_| settings.follow_camera);
+    for (mut transform, shake) in &mut camera_query {
+        let looked = transform.translation.truncate() - shake.offset;
+        let target = focus(looked, human, view);
+        // with nobody to follow the camera goes straight back, as other screens expect it
+        let looking = if human.is_some() {
+            looked.lerp(target, 1. - (-FOLLOW_RATE * time.delta_seconds()).exp())
+        } else {
+            target
+        };
+        transform.translation = (looking + shake.offset).extend(transform.translation.z);
+    }
+}
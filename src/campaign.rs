@@ -0,0 +1,332 @@
+//! Single-player campaign, a sequence of stages each one harder than the previous.
+//!
+//! Stages are played in stages mode: once every opponent is gone, the human leaves through the
+//! exit door and gets a reward screen before the next stage.
+//! Later stages are played in larger arenas, against more and smarter opponents, with less time
+//! to clear them, and the last one against the [`boss`](crate::boss).
+//! The stage reached is saved to [`PROGRESS_PATH`], so that the campaign picks up from there.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::io;
+
+use crate::{
+    bot::Roster, level::Level, locale::Locale, mob::MobKind, storage, AppState, Mode, Rules,
+    Scoreboard, SCORE_COLOR, TEXT_COLOR,
+};
+
+/// Where the progress through the campaign is stored
+pub const PROGRESS_PATH: &str = "campaign.toml";
+
+const REWARD_FONT_SIZE: f32 = 40.0;
+// points awarded for every second left on the clock when leaving a stage
+const TIME_BONUS: usize = 10;
+
+/// How a stage of the campaign is played
+struct StageSpec {
+    /// Columns and rows of the playable area
+    size: (usize, usize),
+    /// Controllers of the opponents
+    bots: &'static [&'static str],
+    /// Roaming enemies, see [`mob`](crate::mob)
+    mobs: &'static [MobKind],
+    /// Seconds to leave the stage in
+    time_limit: u32,
+    brick_density: f64,
+    /// Whether the boss waits in the stage
+    boss: bool,
+}
+
+const STAGES: [StageSpec; 7] = [
+    StageSpec {
+        size: (9, 7),
+        bots: &["random"],
+        mobs: &[MobKind::Balloon, MobKind::Balloon],
+        time_limit: 180,
+        brick_density: 0.5,
+        boss: false,
+    },
+    StageSpec {
+        size: (9, 7),
+        bots: &["random", "random"],
+        mobs: &[MobKind::Balloon, MobKind::Ghost],
+        time_limit: 170,
+        brick_density: 0.6,
+        boss: false,
+    },
+    StageSpec {
+        size: (13, 7),
+        bots: &["random", "brain"],
+        mobs: &[MobKind::Balloon, MobKind::Balloon, MobKind::Ghost],
+        time_limit: 150,
+        brick_density: 0.6,
+        boss: false,
+    },
+    StageSpec {
+        size: (13, 11),
+        bots: &["brain", "brain"],
+        mobs: &[
+            MobKind::Balloon,
+            MobKind::Balloon,
+            MobKind::Ghost,
+            MobKind::Hound,
+        ],
+        time_limit: 140,
+        brick_density: 0.7,
+        boss: false,
+    },
+    StageSpec {
+        size: (13, 11),
+        bots: &["brain", "brain", "random"],
+        mobs: &[
+            MobKind::Balloon,
+            MobKind::Ghost,
+            MobKind::Ghost,
+            MobKind::Hound,
+            MobKind::Hound,
+        ],
+        time_limit: 120,
+        brick_density: 0.8,
+        boss: false,
+    },
+    StageSpec {
+        size: (13, 11),
+        bots: &["brain", "brain", "brain", "brain"],
+        mobs: &[
+            MobKind::Balloon,
+            MobKind::Ghost,
+            MobKind::Ghost,
+            MobKind::Hound,
+            MobKind::Hound,
+            MobKind::Hound,
+        ],
+        time_limit: 90,
+        brick_density: 0.8,
+        boss: false,
+    },
+    StageSpec {
+        size: (13, 11),
+        bots: &[],
+        mobs: &[],
+        time_limit: 180,
+        brick_density: 0.3,
+        boss: true,
+    },
+];
+
+/// Progress through the campaign, kept on disk between runs
+#[derive(Default, Serialize, Deserialize)]
+struct Progress {
+    /// Stage to play next, counting from zero
+    stage: usize,
+}
+
+impl Progress {
+    /// Reads the progress from disk, starting over if there's none
+    fn load() -> Self {
+        match storage::read(PROGRESS_PATH) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                warn!("Invalid campaign progress in {PROGRESS_PATH}: {e}");
+                Progress::default()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Progress::default(),
+            Err(e) => {
+                warn!("Can't read campaign progress from {PROGRESS_PATH}: {e}");
+                Progress::default()
+            }
+        }
+    }
+
+    /// Writes the progress to disk
+    fn save(&self) {
+        let result = toml::to_string(self)
+            .map_err(|e| e.to_string())
+            .and_then(|content| storage::write(PROGRESS_PATH, &content).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Can't save campaign progress to {PROGRESS_PATH}: {e}");
+        }
+    }
+}
+
+/// This resource tracks the campaign being played, if any
+pub struct Campaign {
+    /// Stage being played, counting from zero, past the last one once the campaign is over
+    stage: usize,
+    /// Points earned by leaving the last stage early
+    bonus: usize,
+    // settings of the menu, given back once the campaign is left
+    rules: Rules,
+    roster: Roster,
+}
+
+impl Campaign {
+    /// Picks up the campaign from the stage saved on disk, setting up its rules and players
+    pub fn start(rules: &mut Rules, roster: &mut Roster) -> Self {
+        let campaign = Campaign {
+            stage: Progress::load().stage.min(STAGES.len() - 1),
+            bonus: 0,
+            rules: rules.clone(),
+            roster: roster.clone(),
+        };
+        campaign.apply(rules, roster);
+        campaign
+    }
+
+    /// Stage being played, counting from zero
+    pub fn stage(&self) -> usize {
+        self.stage
+    }
+
+    /// Moves on to the next stage, rewarding the seconds left on the clock.
+    /// Returns whether there's one, or the campaign is over
+    pub fn advance(&mut self, seconds_left: u32, rules: &mut Rules, roster: &mut Roster) -> bool {
+        self.bonus = seconds_left as usize * TIME_BONUS;
+        self.stage += 1;
+        // a campaign played to the end starts over next time
+        let next = self.stage < STAGES.len();
+        Progress {
+            stage: if next { self.stage } else { 0 },
+        }
+        .save();
+        if next {
+            self.apply(rules, roster);
+        }
+        next
+    }
+
+    fn apply(&self, rules: &mut Rules, roster: &mut Roster) {
+        let spec = &STAGES[self.stage];
+        // stages are laid out at random, with none of the rules of the level picked before
+        let mut base = self.rules.clone();
+        Level::leave(&mut base);
+        *rules = Rules {
+            rounds: 1,
+            time_limit: Some(spec.time_limit),
+            brick_density: spec.brick_density,
+            mode: Mode::Stages,
+            mobs: spec.mobs.to_vec(),
+            boss: spec.boss,
+            size: spec.size,
+            ..base
+        };
+        roster.humans = 1;
+        roster.remotes = 0;
+        roster.bots = spec.bots.iter().map(|bot| bot.to_string()).collect();
+    }
+}
+
+/// Marks every entity belonging to the reward screen
+#[derive(Component)]
+pub struct RewardScreen;
+
+pub fn setup_reward(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+    campaign: Res<Campaign>,
+    mut scoreboard: ResMut<Scoreboard>,
+) {
+    scoreboard.score += campaign.bonus;
+
+    let style = TextStyle {
+        font: asset_server.load(locale.font()),
+        font_size: REWARD_FONT_SIZE,
+        color: TEXT_COLOR,
+    };
+    let mut lines = vec![
+        (
+            locale.format("campaign.cleared", &[("stage", &campaign.stage)]),
+            SCORE_COLOR,
+        ),
+        (
+            locale.format("campaign.bonus", &[("bonus", &campaign.bonus)]),
+            TEXT_COLOR,
+        ),
+        (
+            locale.format("campaign.score", &[("score", &scoreboard.score)]),
+            TEXT_COLOR,
+        ),
+    ];
+    match STAGES.get(campaign.stage) {
+        Some(spec) if spec.boss => lines.push((
+            locale.format("campaign.boss", &[("stage", &(campaign.stage + 1))]),
+            SCORE_COLOR,
+        )),
+        Some(spec) => lines.push((
+            locale.format(
+                "campaign.next",
+                &[
+                    ("stage", &(campaign.stage + 1)),
+                    ("cols", &spec.size.0),
+                    ("rows", &spec.size.1),
+                    ("bots", &spec.bots.len()),
+                    ("mobs", &spec.mobs.len()),
+                    ("secs", &spec.time_limit),
+                ],
+            ),
+            TEXT_COLOR,
+        )),
+        None => lines.push((locale.text("campaign.complete").to_string(), SCORE_COLOR)),
+    }
+    lines.push((locale.text("campaign.go_on").to_string(), TEXT_COLOR));
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+            ..default()
+        })
+        .insert(RewardScreen)
+        .with_children(|parent| {
+            for (line, color) in lines {
+                parent.spawn_bundle(TextBundle::from_section(
+                    line,
+                    TextStyle {
+                        color,
+                        ..style.clone()
+                    },
+                ));
+            }
+        });
+}
+
+/// Goes on to the next stage, or back to the menu once the campaign is over
+pub fn resume(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    campaign: Res<Campaign>,
+    mut state: ResMut<State<AppState>>,
+) {
+    let over = campaign.stage >= STAGES.len();
+    // fails only if a transition is already queued
+    if keyboard_input.clear_just_pressed(KeyCode::Escape) {
+        let _ = state.replace(AppState::Menu);
+    } else if keyboard_input.clear_just_pressed(KeyCode::Return) {
+        let _ = if over {
+            state.replace(AppState::Menu)
+        } else {
+            state.pop()
+        };
+    }
+}
+
+/// Gives back the settings of the menu when leaving the campaign
+pub fn leave(
+    mut commands: Commands,
+    campaign: Option<Res<Campaign>>,
+    mut rules: ResMut<Rules>,
+    mut roster: ResMut<Roster>,
+) {
+    if let Some(campaign) = campaign {
+        *rules = campaign.rules.clone();
+        *roster = campaign.roster.clone();
+        commands.remove_resource::<Campaign>();
+    }
+}
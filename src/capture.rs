@@ -0,0 +1,333 @@
+//! Screenshots and clips of what's shown, saved to [`CAPTURES_DIR`].
+//!
+//! F12 saves a screenshot of the next frame, and F9 a GIF of the last few seconds.
+//! Windows can't be read back, so a second camera draws the same view into an image, only on
+//! the frames captured: every time a screenshot is asked for, and a few times a second to keep
+//! the frames of the clip, halved to save memory. Once drawn the image is copied back from the
+//! GPU by the render world, and handed over to the main one to be saved.
+//! Browsers can't wait for the GPU, so captures are only compiled into native builds.
+
+use bevy::{
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_asset::RenderAssets,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        renderer::{RenderDevice, RenderQueue},
+        texture::BevyDefault,
+        RenderApp, RenderStage,
+    },
+};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::{self, FilterType},
+    Delay, RgbaImage,
+};
+
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::camera::Shake;
+
+/// Where screenshots and clips are saved
+pub const CAPTURES_DIR: &str = "captures";
+
+const SCREENSHOT_KEY: KeyCode = KeyCode::F12;
+const CLIP_KEY: KeyCode = KeyCode::F9;
+// how far back clips go, and how many frames they show every second
+const CLIP_SECS: f32 = 5.;
+const CLIP_FPS: u32 = 10;
+// how much smaller frames of clips are than the window
+const CLIP_SHRINK: u32 = 2;
+
+/// Screenshots and clips, for windowed instances
+pub struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        let read = ReadBack::default();
+        app.insert_resource(read.clone())
+            .add_startup_system(setup)
+            .add_system(trigger)
+            .add_system(keep);
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(read)
+                .add_system_to_stage(RenderStage::Extract, extract)
+                .add_system_to_stage(RenderStage::Cleanup, read_back);
+        }
+    }
+}
+
+/// A frame read back from the GPU
+struct Frame {
+    image: RgbaImage,
+    // whether it's saved as a screenshot, on top of being kept for clips
+    screenshot: bool,
+}
+
+/// This resource holds frames read back by the render world, not taken in yet by the main one
+#[derive(Clone, Default)]
+struct ReadBack(Arc<Mutex<Vec<Frame>>>);
+
+/// This resource tracks what's captured
+struct Capture {
+    /// Image the view is drawn into on frames captured
+    image: Handle<Image>,
+    /// Size of the image, in physical pixels
+    size: (u32, u32),
+    /// Whether the frame drawn now is saved as a screenshot
+    screenshot: bool,
+    /// When the next frame of clips is drawn
+    timer: Timer,
+    /// Latest frames, oldest first, to make clips of
+    recent: VecDeque<RgbaImage>,
+}
+
+/// Marks the camera drawing the view into the image
+#[derive(Component)]
+struct CaptureCamera;
+
+/// This resource of the render world tells which image to read back, and how it's used
+struct ExtractedCapture {
+    image: Handle<Image>,
+    screenshot: bool,
+}
+
+/// An image the view can be drawn into and read back from, as large as the given size
+fn target(images: &mut Assets<Image>, (width, height): (u32, u32)) -> Handle<Image> {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::bevy_default(),
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_DST
+        | TextureUsages::COPY_SRC
+        | TextureUsages::RENDER_ATTACHMENT;
+    images.add(image)
+}
+
+/// Where a capture taken now is saved, with the given extension
+fn capture_path(kind: &str, extension: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    PathBuf::from(CAPTURES_DIR).join(format!("{kind}-{millis}.{extension}"))
+}
+
+fn setup(mut commands: Commands, windows: Res<Windows>, mut images: ResMut<Assets<Image>>) {
+    let size = windows.get_primary().map_or((1, 1), |window| {
+        (window.physical_width(), window.physical_height())
+    });
+    let image = target(&mut images, size);
+    commands
+        .spawn_bundle(Camera2dBundle {
+            camera: Camera {
+                target: RenderTarget::Image(image.clone()),
+                // drawn before the window, and only on frames captured
+                priority: -1,
+                is_active: false,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(CaptureCamera);
+    commands.insert_resource(Capture {
+        image,
+        size,
+        screenshot: false,
+        timer: Timer::from_seconds(1. / CLIP_FPS as f32, true),
+        recent: VecDeque::new(),
+    });
+}
+
+// Draw the view into the image on frames captured, the same as it's shown in the window
+fn trigger(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    mut images: ResMut<Assets<Image>>,
+    mut capture: ResMut<Capture>,
+    view_query: Query<&Transform, (With<Shake>, Without<CaptureCamera>)>,
+    mut camera_query: Query<(&mut Camera, &mut Transform), With<CaptureCamera>>,
+) {
+    let (mut camera, mut transform) = match camera_query.get_single_mut() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    capture.timer.tick(time.delta());
+    capture.screenshot = keyboard_input.just_pressed(SCREENSHOT_KEY);
+    camera.is_active = capture.screenshot || capture.timer.just_finished();
+    if !camera.is_active {
+        return;
+    }
+
+    if let Ok(view) = view_query.get_single() {
+        *transform = *view;
+    }
+    // the image follows the size of the window
+    if let Some(window) = windows.get_primary() {
+        let size = (window.physical_width(), window.physical_height());
+        if size != capture.size && size.0 > 0 && size.1 > 0 {
+            capture.image = target(&mut images, size);
+            capture.size = size;
+            camera.target = RenderTarget::Image(capture.image.clone());
+        }
+    }
+}
+
+// Keep the frames read back for clips, saving screenshots, and save a clip when asked to
+fn keep(keyboard_input: Res<Input<KeyCode>>, read: Res<ReadBack>, mut capture: ResMut<Capture>) {
+    let frames = match read.0.lock() {
+        Ok(mut frames) => std::mem::take(&mut *frames),
+        Err(_) => return,
+    };
+    for frame in frames {
+        if frame.screenshot {
+            let path = capture_path("screenshot", "png");
+            let result = fs::create_dir_all(CAPTURES_DIR)
+                .map_err(|e| e.to_string())
+                .and_then(|_| frame.image.save(&path).map_err(|e| e.to_string()));
+            match result {
+                Ok(()) => info!("Screenshot saved to {}", path.display()),
+                Err(e) => warn!("Can't save screenshot to {}: {e}", path.display()),
+            }
+        }
+        let (width, height) = frame.image.dimensions();
+        capture.recent.push_back(imageops::resize(
+            &frame.image,
+            (width / CLIP_SHRINK).max(1),
+            (height / CLIP_SHRINK).max(1),
+            FilterType::Triangle,
+        ));
+        if capture.recent.len() > (CLIP_SECS * CLIP_FPS as f32) as usize {
+            capture.recent.pop_front();
+        }
+    }
+
+    if keyboard_input.just_pressed(CLIP_KEY) && !capture.recent.is_empty() {
+        let frames = capture.recent.iter().cloned().collect::<Vec<_>>();
+        let path = capture_path("clip", "gif");
+        // encoding takes a while, matches go on meanwhile
+        thread::spawn(move || match save_clip(&path, frames) {
+            Ok(()) => info!("Clip saved to {}", path.display()),
+            Err(e) => warn!("Can't save clip to {}: {e}", path.display()),
+        });
+    }
+}
+
+/// Saves the given frames as a GIF looping forever
+fn save_clip(path: &Path, frames: Vec<RgbaImage>) -> image::ImageResult<()> {
+    fs::create_dir_all(CAPTURES_DIR)?;
+    let mut encoder = GifEncoder::new(fs::File::create(path)?);
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder.encode_frames(frames.into_iter().map(|frame| {
+        image::Frame::from_parts(frame, 0, 0, Delay::from_numer_denom_ms(1000, CLIP_FPS))
+    }))
+}
+
+// Tell the render world which image to read back, on frames captured
+fn extract(
+    mut commands: Commands,
+    capture: Option<Res<Capture>>,
+    camera_query: Query<&Camera, With<CaptureCamera>>,
+) {
+    let active = camera_query.iter().any(|camera| camera.is_active);
+    match capture {
+        Some(capture) if active => commands.insert_resource(ExtractedCapture {
+            image: capture.image.clone(),
+            screenshot: capture.screenshot,
+        }),
+        _ => commands.remove_resource::<ExtractedCapture>(),
+    }
+}
+
+// Copy the image drawn back from the GPU, once drawn, waiting for it
+fn read_back(
+    capture: Option<Res<ExtractedCapture>>,
+    read: Res<ReadBack>,
+    images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let capture = match capture {
+        Some(capture) => capture,
+        None => return,
+    };
+    let gpu_image = match images.get(&capture.image) {
+        Some(gpu_image) => gpu_image,
+        None => return,
+    };
+    let (width, height) = (gpu_image.size.x as u32, gpu_image.size.y as u32);
+    // rows are copied aligned, their padding is dropped afterwards
+    let row = width * 4;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_row = row.div_ceil(align) * align;
+
+    let buffer = render_device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("capture"),
+        size: (padded_row * height) as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = render_device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("capture"),
+    });
+    encoder.copy_texture_to_buffer(
+        gpu_image.texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(padded_row),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    render_queue.submit([encoder.finish()]);
+
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |_| ());
+    render_device.wgpu_device().poll(wgpu::Maintain::Wait);
+    let mut pixels = Vec::with_capacity((row * height) as usize);
+    for padded in slice.get_mapped_range().chunks(padded_row as usize) {
+        pixels.extend_from_slice(&padded[..row as usize]);
+    }
+    buffer.unmap();
+
+    // windows may be drawn in BGRA, images are saved in RGBA
+    if matches!(
+        gpu_image.texture_format,
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+    ) {
+        for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    if let (Some(image), Ok(mut frames)) =
+        (RgbaImage::from_raw(width, height, pixels), read.0.lock())
+    {
+        frames.push(Frame {
+            image,
+            screenshot: capture.screenshot,
+        });
+    }
+}
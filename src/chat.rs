@@ -0,0 +1,161 @@
+//! Text chat of online matches.
+//!
+//! Enter opens the chat line and sends what's been typed, Escape drops it.
+//! While typing, keys don't reach the rest of the game, so that writing doesn't move players.
+//! Lines typed here wait in the [`Chat`] outbox until [`net`](crate::net) sends them, and
+//! lines coming from the network are shown for a while before fading out.
+
+use bevy::{input::InputSystem, prelude::*};
+
+use std::collections::VecDeque;
+
+use crate::{locale::Locale, SCOREBOARD_TEXT_PADDING, TEXT_COLOR};
+
+const CHAT_FONT_SIZE: f32 = 24.0;
+// how many lines are shown at most
+const CHAT_LINES: usize = 5;
+// seconds a line is shown for
+const CHAT_FADE_SECS: f32 = 8.0;
+// longest line that can be typed
+const MAX_CHAT_LENGTH: usize = 80;
+
+/// A line of the chat, shown for a while
+#[derive(Clone, Debug)]
+struct ChatLine {
+    text: String,
+    age: Timer,
+}
+
+/// This resource holds the chat lines being shown and typed
+#[derive(Default)]
+pub struct Chat {
+    // whether a line is being typed
+    open: bool,
+    draft: String,
+    lines: VecDeque<ChatLine>,
+    /// Lines typed locally, waiting to be sent
+    pub outbox: Vec<String>,
+}
+
+impl Chat {
+    /// Shows a line, written by the given author
+    pub fn push(&mut self, from: &str, text: &str) {
+        self.lines.push_back(ChatLine {
+            text: format!("{from}: {text}"),
+            age: Timer::from_seconds(CHAT_FADE_SECS, false),
+        });
+        while self.lines.len() > CHAT_LINES {
+            self.lines.pop_front();
+        }
+    }
+}
+
+/// Marks the text showing the chat
+#[derive(Component)]
+struct ChatText;
+
+/// Chat overlay, for instances taking part in online matches
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Chat>()
+            .add_startup_system(setup)
+            // right after the keyboard is read, to hide it from everybody else while typing
+            .add_system_to_stage(CoreStage::PreUpdate, type_line.after(InputSystem))
+            .add_system(show);
+    }
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, locale: Res<Locale>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(locale.mono_font()),
+                    font_size: CHAT_FONT_SIZE,
+                    color: TEXT_COLOR,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(CHAT_FONT_SIZE * 2.),
+                    left: SCOREBOARD_TEXT_PADDING,
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(ChatText);
+}
+
+fn type_line(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut chat: ResMut<Chat>,
+) {
+    if !chat.open {
+        // drop whatever was typed before opening the line
+        characters.clear();
+        if keyboard_input.clear_just_pressed(KeyCode::Return) {
+            chat.open = true;
+        }
+        return;
+    }
+
+    for character in characters.iter() {
+        if !character.char.is_control() && chat.draft.chars().count() < MAX_CHAT_LENGTH {
+            chat.draft.push(character.char);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        chat.draft.pop();
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut chat.draft);
+        if !line.trim().is_empty() {
+            chat.outbox.push(line);
+        }
+        chat.open = false;
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        chat.draft.clear();
+        chat.open = false;
+    }
+
+    // nothing else sees the keys pressed while typing
+    let pressed = keyboard_input.get_pressed().copied().collect::<Vec<_>>();
+    for key in pressed {
+        keyboard_input.reset(key);
+    }
+}
+
+fn show(time: Res<Time>, mut chat: ResMut<Chat>, mut query: Query<&mut Text, With<ChatText>>) {
+    for line in &mut chat.lines {
+        line.age.tick(time.delta());
+    }
+    chat.lines.retain(|line| !line.age.finished());
+
+    let mut text = match query.get_single_mut() {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    let style = text.sections[0].style.clone();
+    let mut sections = vec![TextSection::new("", style.clone())];
+    for line in &chat.lines {
+        let mut color = style.color;
+        color.set_a(line.age.percent_left());
+        sections.push(TextSection::new(
+            format!("{}\n", line.text),
+            TextStyle {
+                color,
+                ..style.clone()
+            },
+        ));
+    }
+    if chat.open {
+        sections.push(TextSection::new(format!("> {}_", chat.draft), style));
+    }
+    text.sections = sections;
+}
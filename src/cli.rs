@@ -0,0 +1,191 @@
+//! Command line of the game, so that matches can be launched from scripts.
+//!
+//! Every flag is optional: with none at all, the game opens on the menu. Flags about the match
+//! itself, like the arena or the mutators, change the rules the menu starts from, or the rules
+//! of headless matches.
+
+use clap::{builder::RangedU64ValueParser, Parser};
+
+use std::path::PathBuf;
+
+use crate::{
+    clock, external,
+    level::Level,
+    mutator::Mutator,
+    net::DEFAULT_PORT,
+    rollback::DEFAULT_INPUT_DELAY,
+    scripting::ModeScript,
+    transport::{self, Address},
+    Rules, COLS, ROWS,
+};
+
+/// Bomberman, against bots and other humans
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Watch the bots play among themselves
+    #[arg(long)]
+    pub spectate: bool,
+    /// Play bots-only matches as fast as possible without a window, printing their statistics
+    #[arg(long)]
+    pub headless: bool,
+    /// Number of headless matches to play
+    #[arg(long, default_value_t = 1)]
+    pub matches: usize,
+    /// Write the outcome of every headless match to the given file, as CSV if it's named
+    /// `.csv`, as JSON lines otherwise
+    #[arg(long, value_name = "PATH", requires = "headless")]
+    pub stats: Option<PathBuf>,
+    /// Host an online match on the given port
+    #[arg(long, value_name = "PORT")]
+    pub host: Option<u16>,
+    /// Join the online match hosted at the given address, like 127.0.0.1:7777
+    #[arg(long, value_name = "ADDRESS", value_parser = address)]
+    pub join: Option<Address>,
+    /// Watch the online match hosted at the given address, like 127.0.0.1:7777
+    #[arg(long, value_name = "ADDRESS", value_parser = address, conflicts_with = "join")]
+    pub watch: Option<Address>,
+    /// Play against the other player at the given address, like 127.0.0.1:7777, with rollback
+    #[arg(long, value_name = "ADDRESS", value_parser = address)]
+    pub rollback: Option<Address>,
+    /// Port to play with rollback from
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    pub port: u16,
+    /// Which of the two players to be, with rollback
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+    pub player: u8,
+    /// Physics steps inputs are delayed by, with rollback
+    #[arg(long, value_name = "STEPS", default_value_t = DEFAULT_INPUT_DELAY)]
+    pub input_delay: u64,
+    /// Seed of the match, so that it can be played again just the same
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Number of opponents
+    #[arg(long, value_name = "N")]
+    pub bots: Option<usize>,
+    /// Opponent driven by another program, taking the place of a bot: exec:COMMAND starts the
+    /// command and talks to it over its standard input and output, ws:PORT talks to a WebSocket
+    /// client connecting to the port. Can be given several times
+    #[arg(long = "external-bot", value_name = "BOT", value_parser = external_bot)]
+    pub external_bots: Vec<String>,
+    /// Rules to play by, written as the `[rules]` of the settings
+    #[arg(long, value_name = "PATH")]
+    pub rules: Option<PathBuf>,
+    /// Arena to play in, drawn by hand
+    #[arg(long, value_name = "PATH")]
+    pub level: Option<PathBuf>,
+    /// Rows of the playable area of random arenas
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..=ROWS as u64))]
+    pub rows: Option<usize>,
+    /// Columns of the playable area of random arenas
+    #[arg(long, value_parser = RangedU64ValueParser::<usize>::new().range(1..=COLS as u64))]
+    pub cols: Option<usize>,
+    /// Mode script to play on top of the mode, see `assets/modes`
+    #[arg(long, value_name = "PATH")]
+    pub mode_script: Option<PathBuf>,
+    /// Twists to play with, separated by commas, like double_speed,no_pickups
+    #[arg(long, value_delimiter = ',', value_parser = mutator)]
+    pub mutators: Vec<Mutator>,
+    /// Physics steps played every second, slowing matches down or speeding them up
+    #[arg(
+        long,
+        value_name = "STEPS",
+        default_value_t = clock::default_tick_rate(),
+        value_parser = tick_rate
+    )]
+    pub tick_rate: f64,
+    /// Play matches fast-forward from the start, F2 toggles it anyway
+    #[arg(long)]
+    pub fast_forward: bool,
+    /// Match to play again, see `replay`
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+    /// Reload levels and the rules as they're changed on disk, see `reload`
+    #[arg(long, conflicts_with = "headless")]
+    pub hot_reload: bool,
+}
+
+impl Cli {
+    /// Changes the given rules as asked, or tells what's wrong
+    pub fn customize(&self, rules: &mut Rules) -> Result<(), String> {
+        if let Some(path) = &self.rules {
+            *rules = Rules::load(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        }
+        if let Some(path) = &self.level {
+            let level = Level::load(path).map_err(|e| format!("{}: {e}", path.display()))?;
+            level.apply(rules);
+        }
+        if let Some(path) = &self.mode_script {
+            let script = ModeScript::load(path).map_err(|e| format!("{}: {e}", path.display()))?;
+            rules.script = Some(script);
+        }
+        self.overrides().apply(rules);
+        Ok(())
+    }
+
+    /// The rules asked for one by one, on top of those of the files given
+    pub fn overrides(&self) -> Overrides {
+        Overrides {
+            cols: self.cols,
+            rows: self.rows,
+            mutators: self.mutators.clone(),
+        }
+    }
+}
+
+/// Rules given one by one on the command line, which win over those of `--rules` and `--level`
+#[derive(Clone, Default)]
+pub struct Overrides {
+    pub cols: Option<usize>,
+    pub rows: Option<usize>,
+    /// Played instead of the mutators of the files, unless empty
+    pub mutators: Vec<Mutator>,
+}
+
+impl Overrides {
+    /// Changes the given rules as asked
+    pub fn apply(&self, rules: &mut Rules) {
+        if let Some(cols) = self.cols {
+            rules.size.0 = cols;
+        }
+        if let Some(rows) = self.rows {
+            rules.size.1 = rows;
+        }
+        if !self.mutators.is_empty() {
+            rules.mutators = self.mutators.clone();
+        }
+    }
+}
+
+fn address(address: &str) -> Result<Address, String> {
+    transport::resolve(address).ok_or_else(|| format!("can't resolve {address:?}"))
+}
+
+fn external_bot(name: &str) -> Result<String, String> {
+    if external::is_external(name) {
+        Ok(name.to_string())
+    } else {
+        Err(format!(
+            "expected {}COMMAND or {}PORT",
+            external::COMMAND_PREFIX,
+            external::WEBSOCKET_PREFIX
+        ))
+    }
+}
+
+fn tick_rate(rate: &str) -> Result<f64, String> {
+    match rate.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0. => Ok(rate),
+        _ => Err("expected a positive number of steps per second".to_string()),
+    }
+}
+
+fn mutator(name: &str) -> Result<Mutator, String> {
+    Mutator::ALL
+        .into_iter()
+        .find(|mutator| mutator.label().replace(' ', "_") == name)
+        .ok_or_else(|| {
+            let names = Mutator::ALL.map(|mutator| mutator.label().replace(' ', "_"));
+            format!("expected one of {}", names.join(", "))
+        })
+}
@@ -0,0 +1,116 @@
+//! How fast matches are played, as opposed to how often frames are drawn.
+//!
+//! Everything affecting a match happens in physics steps, each simulating [`TIME_STEP`] seconds
+//! of play and counted by [`Tick`](crate::Tick); frames only show their outcome. When physics
+//! steps follow the wall clock, the [`Clock`] sets how many of them run every second: the tick
+//! rate slows matches down or speeds them up without changing how they play out, and
+//! fast-forwarding runs them [`FAST_FORWARD`] times faster on top of that.
+
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+
+use crate::TIME_STEP;
+
+/// How many times faster matches are played while fast-forwarding
+pub const FAST_FORWARD: f64 = 4.;
+
+// toggles fast-forwarding
+const FAST_FORWARD_KEY: KeyCode = KeyCode::F2;
+// physics steps owed that are caught up on at most, so that a slow frame doesn't snowball into
+// ever slower ones
+const MAX_STEPS_BEHIND: f64 = 16.;
+// physics steps run at most every frame by instances keeping up with others
+const MAX_STEPS_PER_FRAME: u32 = 16;
+
+/// This resource sets how many physics steps run every second of the wall clock
+pub struct Clock {
+    /// Physics steps run every second, at normal speed
+    pub tick_rate: f64,
+    /// Whether physics steps run [`FAST_FORWARD`] times faster than normal
+    pub fast_forward: bool,
+    // physics steps owed by the wall clock, not run yet
+    owed: f64,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new(default_tick_rate(), false)
+    }
+}
+
+impl Clock {
+    /// A clock running the given physics steps every second, fast-forwarded or not
+    pub fn new(tick_rate: f64, fast_forward: bool) -> Self {
+        Clock {
+            tick_rate,
+            fast_forward,
+            owed: 0.,
+        }
+    }
+
+    /// Physics steps run every second, counting fast-forwarding in
+    pub fn rate(&self) -> f64 {
+        if self.fast_forward {
+            self.tick_rate * FAST_FORWARD
+        } else {
+            self.tick_rate
+        }
+    }
+}
+
+/// Physics steps run every second by default, making matches last as long as they simulate
+pub fn default_tick_rate() -> f64 {
+    (1. / TIME_STEP as f64).round()
+}
+
+/// Runs as many physics steps as the wall clock owes since the previous frame
+pub fn run_steps(time: Res<Time>, mut clock: ResMut<Clock>, mut looping: Local<bool>) -> ShouldRun {
+    // the criterion is checked again after every step, but time only passes once per frame
+    if !*looping {
+        clock.owed = (clock.owed + time.delta_seconds_f64() * clock.rate()).min(MAX_STEPS_BEHIND);
+    }
+    *looping = clock.owed >= 1.;
+    if *looping {
+        clock.owed -= 1.;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        ShouldRun::No
+    }
+}
+
+/// Runs the physics steps the wall clock owes, never dropping any but running no more than
+/// [`MAX_STEPS_PER_FRAME`] every frame: browsers stop drawing frames of pages hidden away, and
+/// instances kept in step with others catch up with them over the next frames, instead of
+/// freezing on the first one
+pub fn catch_up(
+    time: Res<Time>,
+    mut clock: ResMut<Clock>,
+    mut ran: Local<Option<u32>>,
+) -> ShouldRun {
+    // the criterion is checked again after every step, but time only passes once per frame
+    let steps = match *ran {
+        Some(steps) => steps,
+        None => {
+            clock.owed += time.delta_seconds_f64() * clock.rate();
+            0
+        }
+    };
+    if clock.owed >= 1. && steps < MAX_STEPS_PER_FRAME {
+        clock.owed -= 1.;
+        *ran = Some(steps + 1);
+        ShouldRun::YesAndCheckAgain
+    } else {
+        *ran = None;
+        ShouldRun::No
+    }
+}
+
+// Toggle fast-forwarding
+pub fn fast_forward(keyboard_input: Res<Input<KeyCode>>, mut clock: ResMut<Clock>) {
+    if keyboard_input.just_pressed(FAST_FORWARD_KEY) {
+        clock.fast_forward = !clock.fast_forward;
+        info!(
+            "Fast-forward {}",
+            if clock.fast_forward { "on" } else { "off" }
+        );
+    }
+}
@@ -0,0 +1,381 @@
+//! Developer console, compiled in only with the `debug-tools` feature.
+//!
+//! The key left of 1 drops the console down and takes it back up. Lines typed in it are run as
+//! commands: their first word names one of the [`CommandRegistry`], the others are handed to it.
+//! Besides the commands found here, plugins can add their own with
+//! [`RegisterCommand::register_command`], and `help` lists them all.
+//! Like the chat, the console keeps the keys pressed while it's down from the rest of the game.
+
+use bevy::{
+    ecs::{event::Events, system::CommandQueue},
+    input::InputSystem,
+    prelude::*,
+};
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::{
+    cell_position, power_ups::PowerUpRegistry, spawn_bomb, spawn_breakable, Active, Bomb,
+    DeathEvent, MatchRng, Player, Rules, Seed, BOMB_Z, COLS, ROWS, TEXT_COLOR,
+};
+
+const CONSOLE_FONT_SIZE: f32 = 18.0;
+const CONSOLE_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.8);
+// key dropping the console down and taking it back up
+const CONSOLE_KEY: KeyCode = KeyCode::Grave;
+// how many lines of past commands and their output are shown at most
+const CONSOLE_LINES: usize = 12;
+
+/// What a command does with the arguments it's given, telling how it went
+pub type CommandHandler = Box<dyn Fn(&mut World, &[&str]) -> Result<String, String> + Send + Sync>;
+
+struct ConsoleCommand {
+    // how the command is typed, shown by `help` and when it's typed wrong
+    usage: String,
+    handler: CommandHandler,
+}
+
+/// This resource holds every command of the console, by name
+pub struct CommandRegistry {
+    commands: BTreeMap<String, ConsoleCommand>,
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        let mut registry = CommandRegistry {
+            commands: BTreeMap::new(),
+        };
+        registry.register("give", "give <power-up>", give);
+        registry.register("kill", "kill <player>", kill);
+        registry.register("spawn", "spawn brick|bomb <col> <row>", spawn);
+        registry.register("set", "set fuse|power_up_chance <value>", set);
+        registry.register("seed", "seed <seed>", seed);
+        registry
+    }
+}
+
+impl CommandRegistry {
+    /// Makes a new command available under the given name, replacing any previous one
+    pub fn register<F>(&mut self, name: impl Into<String>, usage: impl Into<String>, handler: F)
+    where
+        F: Fn(&mut World, &[&str]) -> Result<String, String> + Send + Sync + 'static,
+    {
+        self.commands.insert(
+            name.into(),
+            ConsoleCommand {
+                usage: usage.into(),
+                handler: Box::new(handler),
+            },
+        );
+    }
+
+    /// Runs the given line, telling how it went
+    fn run(&self, world: &mut World, line: &str) -> Result<String, String> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let (name, args) = match words.split_first() {
+            Some((name, args)) => (*name, args),
+            None => return Ok(String::new()),
+        };
+        if name == "help" {
+            return Ok(self
+                .commands
+                .values()
+                .map(|command| command.usage.as_str())
+                .chain(["help"])
+                .collect::<Vec<_>>()
+                .join("\n"));
+        }
+        let command = self
+            .commands
+            .get(name)
+            .ok_or_else(|| format!("Unknown command {name:?}, try help"))?;
+        (command.handler)(world, args).map_err(|e| format!("{e}\nusage: {}", command.usage))
+    }
+}
+
+/// Lets plugins add commands to the console
+pub trait RegisterCommand {
+    /// Makes a new command available under the given name, see [`CommandRegistry::register`]
+    fn register_command<F>(
+        &mut self,
+        name: impl Into<String>,
+        usage: impl Into<String>,
+        handler: F,
+    ) -> &mut Self
+    where
+        F: Fn(&mut World, &[&str]) -> Result<String, String> + Send + Sync + 'static;
+}
+
+impl RegisterCommand for App {
+    fn register_command<F>(
+        &mut self,
+        name: impl Into<String>,
+        usage: impl Into<String>,
+        handler: F,
+    ) -> &mut Self
+    where
+        F: Fn(&mut World, &[&str]) -> Result<String, String> + Send + Sync + 'static,
+    {
+        self.world
+            .get_resource_or_insert_with(CommandRegistry::default)
+            .register(name, usage, handler);
+        self
+    }
+}
+
+/// This resource holds what the console shows and what's being typed in it
+#[derive(Default)]
+struct Console {
+    open: bool,
+    draft: String,
+    lines: VecDeque<String>,
+    // lines typed, waiting to be run
+    pending: Vec<String>,
+}
+
+impl Console {
+    fn push(&mut self, text: &str) {
+        for line in text.lines() {
+            self.lines.push_back(line.to_string());
+        }
+        while self.lines.len() > CONSOLE_LINES {
+            self.lines.pop_front();
+        }
+    }
+}
+
+/// Marks the panel of the console
+#[derive(Component)]
+struct ConsolePanel;
+
+/// Marks the text of the console
+#[derive(Component)]
+struct ConsoleText;
+
+/// Developer console, for windowed instances
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .init_resource::<CommandRegistry>()
+            .add_startup_system(setup)
+            // right after the keyboard is read, to hide it from everybody else while typing
+            .add_system_to_stage(CoreStage::PreUpdate, type_line.after(InputSystem))
+            .add_system(run.exclusive_system())
+            .add_system(show);
+    }
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(0.),
+                    left: Val::Px(0.),
+                    ..default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                padding: UiRect::all(Val::Px(CONSOLE_FONT_SIZE / 2.)),
+                display: Display::None,
+                ..default()
+            },
+            color: CONSOLE_BACKGROUND.into(),
+            ..default()
+        })
+        .insert(ConsolePanel)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: CONSOLE_FONT_SIZE,
+                        color: TEXT_COLOR,
+                    },
+                ))
+                .insert(ConsoleText);
+        });
+}
+
+fn type_line(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut console: ResMut<Console>,
+) {
+    if keyboard_input.clear_just_pressed(CONSOLE_KEY) {
+        console.open = !console.open;
+        // the key itself isn't typed
+        characters.clear();
+        return;
+    }
+    if !console.open {
+        characters.clear();
+        return;
+    }
+
+    for character in characters.iter() {
+        if !character.char.is_control() {
+            console.draft.push(character.char);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        console.draft.pop();
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut console.draft);
+        if !line.trim().is_empty() {
+            console.pending.push(line);
+        }
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        console.draft.clear();
+        console.open = false;
+    }
+
+    // nothing else sees the keys pressed while typing
+    let pressed = keyboard_input.get_pressed().copied().collect::<Vec<_>>();
+    for key in pressed {
+        keyboard_input.reset(key);
+    }
+}
+
+// Run the lines typed, with the whole world at hand
+fn run(world: &mut World) {
+    let pending = std::mem::take(&mut world.resource_mut::<Console>().pending);
+    if pending.is_empty() {
+        return;
+    }
+    world.resource_scope(|world, registry: Mut<CommandRegistry>| {
+        for line in pending {
+            let output = registry.run(world, &line).unwrap_or_else(|e| e);
+            let mut console = world.resource_mut::<Console>();
+            console.push(&format!("> {line}"));
+            console.push(&output);
+        }
+    });
+}
+
+fn show(
+    console: Res<Console>,
+    mut panel_query: Query<&mut Style, With<ConsolePanel>>,
+    mut text_query: Query<&mut Text, With<ConsoleText>>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+    for mut style in &mut panel_query {
+        style.display = if console.open {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+    for mut text in &mut text_query {
+        let mut lines = console.lines.iter().cloned().collect::<Vec<_>>();
+        lines.push(format!("> {}_", console.draft));
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+/// Parses the given argument, or tells what's wrong with it
+fn parse<T: std::str::FromStr>(args: &[&str], index: usize, what: &str) -> Result<T, String> {
+    let arg = args.get(index).ok_or_else(|| format!("Missing {what}"))?;
+    arg.parse().map_err(|_| format!("Invalid {what} {arg:?}"))
+}
+
+// Give a power-up to the first local human
+fn give(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let id = args.first().copied().ok_or("Missing power-up")?;
+    world.resource_scope(|world, registry: Mut<PowerUpRegistry>| {
+        let power_up = registry.find(id).ok_or_else(|| {
+            let ids = registry.ids().collect::<Vec<_>>();
+            format!(
+                "Unknown power-up {id:?}, expected one of {}",
+                ids.join(", ")
+            )
+        })?;
+        let mut query = world.query::<(&Active, &mut Player)>();
+        let (_, mut player) = query
+            .iter_mut(world)
+            .find(|(active, _)| active.0 == 0)
+            .ok_or("Nobody to give it to")?;
+        registry.apply(power_up, &mut player);
+        Ok(format!("Gave {id} to P1"))
+    })
+}
+
+// Kill a player, counting them from one in the order they were spawned
+fn kill(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let index = parse::<usize>(args, 0, "player")?;
+    let mut players = world
+        .query_filtered::<Entity, With<Player>>()
+        .iter(world)
+        .collect::<Vec<_>>();
+    players.sort();
+    let player = *index
+        .checked_sub(1)
+        .and_then(|index| players.get(index))
+        .ok_or_else(|| format!("There are {} players", players.len()))?;
+    world.resource_mut::<Events<DeathEvent>>().send(DeathEvent {
+        player,
+        killer: None,
+    });
+    Ok(format!("Killed player {index}"))
+}
+
+// Spawn a breakable brick or a bomb in a cell, counting columns from the left and rows from
+// the bottom
+fn spawn(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let col = parse::<usize>(args, 1, "column")?;
+    let row = parse::<usize>(args, 2, "row")?;
+    if col >= COLS || row >= ROWS {
+        return Err(format!("Cells go up to {} {}", COLS - 1, ROWS - 1));
+    }
+    let position = cell_position(col, row);
+    let rules = world.resource::<Rules>();
+    let (brick, fuse) = (rules.theme().palette().brick, rules.fuse);
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, world);
+    match args.first().copied() {
+        Some("brick") => spawn_breakable(&mut commands, position, brick),
+        Some("bomb") => {
+            spawn_bomb(
+                &mut commands,
+                Bomb {
+                    player: None,
+                    timer: Timer::from_seconds(fuse, false),
+                    power: 1,
+                },
+                position.extend(BOMB_Z),
+            );
+        }
+        Some(other) => return Err(format!("Can't spawn {other:?}")),
+        None => return Err("Missing what to spawn".to_string()),
+    }
+    queue.apply(world);
+    Ok(format!("Spawned {} at {col} {row}", args[0]))
+}
+
+// Change a rule of the match being played, and of the next ones
+fn set(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let mut rules = world.resource_mut::<Rules>();
+    match args.first().copied() {
+        Some("fuse") => rules.fuse = parse::<f32>(args, 1, "seconds")?.max(0.),
+        Some("power_up_chance") => {
+            rules.power_up_chance = parse::<f64>(args, 1, "chance")?.clamp(0., 1.)
+        }
+        Some(other) => return Err(format!("Unknown rule {other:?}")),
+        None => return Err("Missing rule".to_string()),
+    }
+    Ok(format!("Set {} to {}", args[0], args[1]))
+}
+
+// Seed the rest of the match being played, and the next ones
+fn seed(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let seed = parse::<u64>(args, 0, "seed")?;
+    world.insert_resource(MatchRng::seeded(seed));
+    world.insert_resource(Seed(Some(seed)));
+    Ok(format!("Seeded with {seed}"))
+}
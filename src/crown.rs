@@ -0,0 +1,95 @@
+//! Crowns mode, where players race to collect crowns instead of fighting to the last one.
+//!
+//! Crowns show up among the debris of destroyed bricks, and players drop one when killed, along
+//! with every crown they were holding.
+//! Crowns lying on the ground are piled up cell by cell, and walking over a pile picks up all of
+//! it: the first player holding as many crowns as the [`Rules`] ask for wins the round.
+
+use bevy::{prelude::*, sprite::collide_aabb::collide};
+
+use crate::{cell_center, Active, DeathEvent, GameOverEvent, Match, Mode, Rounds, Rules, PICKUP_Z};
+
+const CROWN_SIZE: Vec2 = Vec2::new(24., 24.);
+const CROWN_COLOR: Color = Color::rgb(1.0, 0.8, 0.1);
+/// Chance for a destroyed brick to leave a crown behind
+pub const CROWN_CHANCE: f64 = 0.2;
+
+/// A pile of crowns lying on the ground
+#[derive(Component)]
+pub struct Crown(u32);
+
+/// Crowns held by a player
+#[derive(Component, Default)]
+pub struct Crowns(pub u32);
+
+/// Places a pile of crowns in the middle of the cell holding `translation`
+pub fn spawn_crown(commands: &mut Commands, count: u32, translation: Vec3) {
+    commands
+        .spawn()
+        .insert(Crown(count))
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: CROWN_COLOR,
+                ..default()
+            },
+            transform: Transform {
+                // above power-ups sharing the cell
+                translation: cell_center(translation).extend(PICKUP_Z + 0.05),
+                scale: CROWN_SIZE.extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match);
+}
+
+// Drop the crowns of killed players where they died, along with one more
+pub fn drop_on_death(
+    mut commands: Commands,
+    rules: Res<Rules>,
+    mut event_reader: EventReader<DeathEvent>,
+    player_query: Query<(&Crowns, &Transform)>,
+) {
+    for DeathEvent { player, .. } in event_reader.iter() {
+        if rules.mode != Mode::Crowns {
+            continue;
+        }
+        // killed players are only despawned once the step is over
+        if let Ok((crowns, transform)) = player_query.get(*player) {
+            spawn_crown(&mut commands, crowns.0 + 1, transform.translation);
+        }
+    }
+}
+
+// Pick up piles of crowns, ending the round once a player holds enough of them
+pub fn collect(
+    mut commands: Commands,
+    rules: Res<Rules>,
+    rounds: Res<Rounds>,
+    crown_query: Query<(Entity, &Crown, &Transform)>,
+    mut player_query: Query<(&mut Crowns, &Transform, Option<&Active>)>,
+    mut game_over_writer: EventWriter<GameOverEvent>,
+) {
+    for (crown_entity, crown, crown_transform) in &crown_query {
+        for (mut crowns, player_transform, active) in &mut player_query {
+            if collide(
+                crown_transform.translation,
+                crown_transform.scale.truncate(),
+                player_transform.translation,
+                player_transform.scale.truncate(),
+            )
+            .is_some()
+            {
+                crowns.0 += crown.0;
+                commands.entity(crown_entity).despawn();
+                if crowns.0 >= rules.crowns && !rounds.over {
+                    game_over_writer.send(GameOverEvent {
+                        winner: active.map(|active| active.0),
+                        team: None,
+                    });
+                }
+                break;
+            }
+        }
+    }
+}
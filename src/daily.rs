@@ -0,0 +1,196 @@
+//! Daily challenge, the same match for everyone playing on the same day.
+//!
+//! The challenge is a battle against the default opponents in a random arena, with the default
+//! rules whatever the menu says, whose seed is the date of the day in UTC, like `20261016`: the
+//! arena, and whatever its bricks leave behind, are the same for everyone that day, and
+//! `--seed` plays it again any other day. The best score of every day is kept in [`DAILY_PATH`],
+//! to compare attempts with each other, and the score of every attempt is logged along with it.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::{collections::BTreeMap, io};
+
+use crate::{
+    bot::Roster, locale::Locale, storage, RoundEndedEvent, Rounds, Rules, Scoreboard, Seed,
+};
+
+/// Where the best scores of every day are stored
+pub const DAILY_PATH: &str = "daily.toml";
+
+// seconds in a day
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// Best scores of every day, by date, kept on disk between runs
+#[derive(Default, Serialize, Deserialize)]
+struct Bests {
+    scores: BTreeMap<String, usize>,
+}
+
+impl Bests {
+    /// Reads the best scores from disk, starting with none if there are none
+    fn load() -> Self {
+        match storage::read(DAILY_PATH) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                warn!("Invalid daily scores in {DAILY_PATH}: {e}");
+                Bests::default()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Bests::default(),
+            Err(e) => {
+                warn!("Can't read daily scores from {DAILY_PATH}: {e}");
+                Bests::default()
+            }
+        }
+    }
+
+    /// Writes the best scores to disk
+    fn save(&self) {
+        let result = toml::to_string(self)
+            .map_err(|e| e.to_string())
+            .and_then(|content| storage::write(DAILY_PATH, &content).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Can't save daily scores to {DAILY_PATH}: {e}");
+        }
+    }
+}
+
+/// The date the given number of days after the first of January 1970 falls on, as year, month
+/// and day
+pub fn date(days: i64) -> (i64, u32, u32) {
+    // days since the first of March of year zero, split in eras of 400 years
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // months count from March, so that leap days come last
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+    (year, month as u32, day as u32)
+}
+
+/// The seed of the challenge of the given date, the date itself written as digits
+pub fn seed_of((year, month, day): (i64, u32, u32)) -> u64 {
+    year as u64 * 10_000 + month as u64 * 100 + day as u64
+}
+
+/// Today's date in UTC, as year, month and day
+fn today() -> (i64, u32, u32) {
+    date((unix_secs() / DAY_SECS) as i64)
+}
+
+/// Seconds since the Unix epoch, by the clock of the system
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_secs() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Seconds since the Unix epoch, by the clock of the browser, the system one being out of reach
+#[cfg(target_arch = "wasm32")]
+fn unix_secs() -> u64 {
+    (js_sys::Date::now() / 1000.) as u64
+}
+
+/// This resource tracks the daily challenge being played, if any
+pub struct Daily {
+    /// Date of the challenge, like 2026-10-16
+    date: String,
+    /// Best score of the day before this attempt, if any
+    best: Option<usize>,
+    /// Score of this attempt, once over
+    score: Option<usize>,
+    // settings of the menu, given back once the challenge is left
+    rules: Rules,
+    roster: Roster,
+    seed: Option<u64>,
+}
+
+impl Daily {
+    /// Sets up today's challenge, its rules, players and seed
+    pub fn start(rules: &mut Rules, roster: &mut Roster, seed: &mut Seed) -> Self {
+        let today = today();
+        let (year, month, day) = today;
+        let date = format!("{year}-{month:02}-{day:02}");
+        let daily = Daily {
+            best: Bests::load().scores.get(&date).copied(),
+            date,
+            score: None,
+            rules: rules.clone(),
+            roster: roster.clone(),
+            seed: seed.0,
+        };
+        *rules = Rules::default();
+        roster.humans = 1;
+        roster.remotes = 0;
+        roster.bots = Roster::default().bots;
+        seed.0 = Some(seed_of(today));
+        daily
+    }
+
+    /// What the scoreboard shows about the challenge, after the score
+    pub fn label(&self, locale: &Locale) -> String {
+        match (self.score, self.best) {
+            (Some(score), best) if best.is_none_or(|best| score > best) => {
+                locale.format("hud.daily_new_best", &[("date", &self.date)])
+            }
+            (_, Some(best)) => {
+                locale.format("hud.daily_best", &[("date", &self.date), ("best", &best)])
+            }
+            (_, None) => locale.format("hud.daily", &[("date", &self.date)]),
+        }
+    }
+}
+
+// Keep the score of the challenge once over, if it's the best of the day
+pub fn record(
+    rules: Res<Rules>,
+    rounds: Res<Rounds>,
+    scoreboard: Res<Scoreboard>,
+    daily: Option<ResMut<Daily>>,
+    mut round_reader: EventReader<RoundEndedEvent>,
+) {
+    let mut daily = match daily {
+        Some(daily) => daily,
+        None => return,
+    };
+    let over = round_reader.iter().count() > 0 && rounds.played >= rules.rounds;
+    if !over || daily.score.is_some() {
+        return;
+    }
+    let score = scoreboard.score;
+    daily.score = Some(score);
+    info!(
+        "Daily challenge {}: {score} points, best of the day {}",
+        daily.date,
+        daily.best.map_or(score, |best| best.max(score))
+    );
+    if daily.best.is_none_or(|best| score > best) {
+        let mut bests = Bests::load();
+        bests.scores.insert(daily.date.clone(), score);
+        bests.save();
+    }
+}
+
+/// Gives back the settings of the menu when leaving the challenge
+pub fn leave(
+    mut commands: Commands,
+    daily: Option<Res<Daily>>,
+    mut rules: ResMut<Rules>,
+    mut roster: ResMut<Roster>,
+    mut seed: ResMut<Seed>,
+) {
+    if let Some(daily) = daily {
+        *rules = daily.rules.clone();
+        *roster = daily.roster.clone();
+        seed.0 = daily.seed;
+        commands.remove_resource::<Daily>();
+    }
+}
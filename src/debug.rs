@@ -0,0 +1,233 @@
+//! Tools for developers, compiled in only with the `debug-tools` feature.
+//!
+//! F3 shows an overlay with how smoothly the game runs and what's going on in the match: frames
+//! per second, how many bombs, fires and bricks there are, the physics step the match is at, and
+//! what every bot is up to.
+//! F4 draws the arena as collisions see it: the boundaries of cells, the boxes players, bricks
+//! and bombs collide with, and the cells the blast of every bomb reaches. Like hints, these
+//! lines are drawn anew every frame, and left out of the match.
+//! The [`console`](crate::console) comes along with them.
+
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use crate::{
+    blast_cells, bot::Bot, console::ConsolePlugin, Bomb, Breakable, Brick, Fire, Player, Tick,
+    BOTTOM_WALL, BRICK_SIZE, COLS, LEFT_WALL, RIGHT_WALL, ROWS, SCOREBOARD_TEXT_PADDING,
+    TEXT_COLOR, TOP_WALL,
+};
+
+const OVERLAY_FONT_SIZE: f32 = 18.0;
+// key showing and hiding the overlay
+const OVERLAY_KEY: KeyCode = KeyCode::F3;
+// key showing and hiding collision lines
+const WIREFRAME_KEY: KeyCode = KeyCode::F4;
+// above everything in the arena, fire included
+const WIREFRAME_Z: f32 = 0.5;
+// pixels lines are thick
+const LINE_WIDTH: f32 = 1.;
+const GRID_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.3);
+const PLAYER_BOX_COLOR: Color = Color::rgb(0.2, 1.0, 0.2);
+const BRICK_BOX_COLOR: Color = Color::rgb(0.2, 0.6, 1.0);
+const BOMB_BOX_COLOR: Color = Color::rgb(1.0, 0.2, 0.2);
+const BLAST_COLOR: Color = Color::rgb(1.0, 0.6, 0.1);
+// blasted cells are outlined a bit inside them, not to hide the grid
+const BLAST_INSET: Vec2 = Vec2::new(6., 6.);
+
+/// Developer tools, for windowed instances
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(FrameTimeDiagnosticsPlugin)
+            .add_plugin(ConsolePlugin)
+            .init_resource::<Wireframes>()
+            .add_startup_system(setup)
+            .add_system(toggle)
+            .add_system(show)
+            .add_system(wireframe);
+    }
+}
+
+/// Marks the text of the overlay
+#[derive(Component)]
+struct Overlay;
+
+/// This resource tells whether collision lines are drawn
+#[derive(Default)]
+struct Wireframes(bool);
+
+/// A line drawn over the arena
+#[derive(Component)]
+struct Line;
+
+/// Draws a line of the given color between the given points, either horizontal or vertical
+fn spawn_line(commands: &mut Commands, from: Vec2, to: Vec2, color: Color) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite { color, ..default() },
+            transform: Transform {
+                translation: ((from + to) / 2.).extend(WIREFRAME_Z),
+                scale: ((to - from).abs().max(Vec2::splat(LINE_WIDTH))).extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Line);
+}
+
+/// Outlines the box of the given size around the given center
+fn spawn_box(commands: &mut Commands, center: Vec2, size: Vec2, color: Color) {
+    let (low, high) = (center - size / 2., center + size / 2.);
+    spawn_line(commands, low, Vec2::new(high.x, low.y), color);
+    spawn_line(commands, Vec2::new(low.x, high.y), high, color);
+    spawn_line(commands, low, Vec2::new(low.x, high.y), color);
+    spawn_line(commands, Vec2::new(high.x, low.y), high, color);
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: OVERLAY_FONT_SIZE,
+                    color: TEXT_COLOR,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(OVERLAY_FONT_SIZE * 3.),
+                    right: SCOREBOARD_TEXT_PADDING,
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(Overlay)
+        .insert(Visibility { is_visible: false });
+}
+
+// Show and hide the overlay and collision lines
+fn toggle(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut wireframes: ResMut<Wireframes>,
+    mut query: Query<&mut Visibility, With<Overlay>>,
+) {
+    if keyboard_input.just_pressed(OVERLAY_KEY) {
+        for mut visibility in &mut query {
+            visibility.is_visible = !visibility.is_visible;
+        }
+    }
+    if keyboard_input.just_pressed(WIREFRAME_KEY) {
+        wireframes.0 = !wireframes.0;
+    }
+}
+
+// Fill the overlay in, as long as it's shown
+#[allow(clippy::too_many_arguments)]
+fn show(
+    diagnostics: Res<Diagnostics>,
+    tick: Res<Tick>,
+    entities: Query<Entity>,
+    bomb_query: Query<(), With<Bomb>>,
+    fire_query: Query<(), With<Fire>>,
+    brick_query: Query<(), With<Breakable>>,
+    bot_query: Query<(Entity, &Bot)>,
+    mut query: Query<(&mut Text, &Visibility), With<Overlay>>,
+) {
+    let (mut text, visibility) = match query.get_single_mut() {
+        Ok(overlay) => overlay,
+        Err(_) => return,
+    };
+    if !visibility.is_visible {
+        return;
+    }
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.average())
+        .unwrap_or_default();
+    let mut lines = vec![
+        format!("fps {fps:.0}"),
+        format!("tick {}", tick.0),
+        format!(
+            "entities {} bombs {} fires {} bricks {}",
+            entities.iter().count(),
+            bomb_query.iter().count(),
+            fire_query.iter().count(),
+            brick_query.iter().count()
+        ),
+    ];
+    let mut bots = bot_query.iter().collect::<Vec<_>>();
+    bots.sort_by_key(|(entity, _)| *entity);
+    for (entity, bot) in bots {
+        let goal = bot.0.describe().unwrap_or_else(|| "?".to_string());
+        lines.push(format!("bot {}: {goal}", entity.id()));
+    }
+    text.sections[0].value = lines.join("\n");
+}
+
+// Draw cells, collision boxes and blasts, as long as asked to
+fn wireframe(
+    mut commands: Commands,
+    wireframes: Res<Wireframes>,
+    line_query: Query<Entity, With<Line>>,
+    player_query: Query<&Transform, With<Player>>,
+    brick_query: Query<&Transform, With<Brick>>,
+    bomb_query: Query<(&Bomb, &Transform)>,
+) {
+    for entity in &line_query {
+        commands.entity(entity).despawn();
+    }
+    if !wireframes.0 {
+        return;
+    }
+    for col in 0..=COLS {
+        let x = LEFT_WALL + col as f32 * BRICK_SIZE.x;
+        spawn_line(
+            &mut commands,
+            Vec2::new(x, BOTTOM_WALL),
+            Vec2::new(x, TOP_WALL),
+            GRID_COLOR,
+        );
+    }
+    for row in 0..=ROWS {
+        let y = BOTTOM_WALL + row as f32 * BRICK_SIZE.y;
+        spawn_line(
+            &mut commands,
+            Vec2::new(LEFT_WALL, y),
+            Vec2::new(RIGHT_WALL, y),
+            GRID_COLOR,
+        );
+    }
+    let boxes = player_query
+        .iter()
+        .map(|transform| (transform, PLAYER_BOX_COLOR))
+        .chain(
+            brick_query
+                .iter()
+                .map(|transform| (transform, BRICK_BOX_COLOR)),
+        )
+        .chain(
+            bomb_query
+                .iter()
+                .map(|(_, transform)| (transform, BOMB_BOX_COLOR)),
+        );
+    for (transform, color) in boxes {
+        spawn_box(
+            &mut commands,
+            transform.translation.truncate(),
+            transform.scale.truncate(),
+            color,
+        );
+    }
+    for (bomb, transform) in &bomb_query {
+        for cell in blast_cells(transform.translation.truncate(), bomb.power) {
+            spawn_box(&mut commands, cell, BRICK_SIZE - BLAST_INSET, BLAST_COLOR);
+        }
+    }
+}
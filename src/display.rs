@@ -0,0 +1,155 @@
+//! The window the game is shown in, changed while playing.
+//!
+//! Alt+Enter switches between windowed and fullscreen anywhere, while the resolution and vsync
+//! are picked from the options screen. Either way the choice is saved with the settings, so the
+//! window opens the same way on the next launch, and applied to the window right away.
+//! The window is titled after the game, followed by the round being played or whether it's
+//! paused, and native builds give it the icon of the game; pages show theirs instead.
+//! Texts are scaled along with the interface scale picked from the options screen, from the
+//! moment they're shown, and scaled again whenever the scale changes, options included.
+
+use bevy::prelude::*;
+
+use crate::{locale::Locale, settings::Settings, AppState, Rounds, Rules};
+
+/// Title of the window, before what's going on
+pub const TITLE: &str = "Bomberman";
+// icon of the window, read as soon as it opens
+#[cfg(not(target_arch = "wasm32"))]
+const ICON_PATH: &str = "assets/textures/icon.png";
+
+/// Sizes of the window to pick from, in logical pixels
+pub const RESOLUTIONS: [(f32, f32); 5] = [
+    (960., 540.),
+    (1280., 720.),
+    (1600., 900.),
+    (1920., 1080.),
+    (2560., 1440.),
+];
+
+/// Smallest scale of the interface
+pub const MIN_UI_SCALE: f32 = 0.75;
+/// Largest scale of the interface
+pub const MAX_UI_SCALE: f32 = 2.0;
+// how much the scale of the interface changes at once
+const UI_SCALE_STEP: f32 = 0.25;
+
+/// Marks texts with the interface scale their font sizes are scaled by
+#[derive(Component)]
+pub struct Scaled(f32);
+
+/// The interface scale a step up or down from the given one, within the smallest and largest
+pub fn step_ui_scale(scale: f32, up: bool) -> f32 {
+    let step = if up { UI_SCALE_STEP } else { -UI_SCALE_STEP };
+    (scale + step).clamp(MIN_UI_SCALE, MAX_UI_SCALE)
+}
+
+/// Index among the [`RESOLUTIONS`] of the one after the given size, the first one if the size
+/// isn't among them
+pub fn next_resolution(width: f32, height: f32) -> usize {
+    RESOLUTIONS
+        .iter()
+        .position(|resolution| *resolution == (width, height))
+        .map_or(0, |index| (index + 1) % RESOLUTIONS.len())
+}
+
+// Switch between windowed and fullscreen on Alt+Enter, before menus take the Enter for theirs
+pub fn fullscreen(mut keyboard_input: ResMut<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    let alt = keyboard_input.any_pressed([KeyCode::LAlt, KeyCode::RAlt]);
+    if alt && keyboard_input.clear_just_pressed(KeyCode::Return) {
+        settings.window.fullscreen = !settings.window.fullscreen;
+        settings.save();
+    }
+}
+
+// Make the window match the settings whenever they change
+pub fn apply(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+    let window = match windows.get_primary_mut() {
+        Some(window) => window,
+        None => return,
+    };
+    let wanted = &settings.window;
+    if window.mode() != wanted.mode() {
+        window.set_mode(wanted.mode());
+    }
+    if window.present_mode() != wanted.present_mode() {
+        window.set_present_mode(wanted.present_mode());
+    }
+    if (window.requested_width(), window.requested_height()) != (wanted.width, wanted.height) {
+        window.set_resolution(wanted.width, wanted.height);
+    }
+}
+
+// Give the window the icon of the game
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_icon(windows: NonSend<bevy::winit::WinitWindows>) {
+    let window = match windows.get_window(bevy::window::WindowId::primary()) {
+        Some(window) => window,
+        None => return,
+    };
+    let icon = image::open(ICON_PATH)
+        .map_err(|e| e.to_string())
+        .and_then(|image| {
+            let image = image.into_rgba8();
+            let (width, height) = image.dimensions();
+            winit::window::Icon::from_rgba(image.into_raw(), width, height)
+                .map_err(|e| e.to_string())
+        });
+    match icon {
+        Ok(icon) => window.set_window_icon(Some(icon)),
+        Err(e) => warn!("Can't read the window icon from {ICON_PATH}: {e}"),
+    }
+}
+
+// Tell the round being played in the title of the window, or that it's paused
+pub fn title(
+    state: Res<State<AppState>>,
+    rounds: Res<Rounds>,
+    rules: Res<Rules>,
+    locale: Res<Locale>,
+    mut windows: ResMut<Windows>,
+) {
+    let round = (rounds.played + 1).min(rules.rounds);
+    let suffix = match state.current() {
+        AppState::Paused => Some(locale.text("window.paused").to_string()),
+        AppState::InGame if rules.rounds > 1 => Some(locale.format(
+            "window.round",
+            &[("round", &round), ("rounds", &rules.rounds)],
+        )),
+        _ => None,
+    };
+    let title = match suffix {
+        Some(suffix) => format!("{TITLE} - {suffix}"),
+        None => TITLE.to_string(),
+    };
+    if let Some(window) = windows.get_primary_mut() {
+        // setting the same title again still talks to the window
+        if window.title() != title {
+            window.set_title(title);
+        }
+    }
+}
+
+// Scale texts as they show up, and all of them again whenever the interface scale changes
+pub fn scale_text(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut query: Query<(Entity, &mut Text, Option<&Scaled>)>,
+) {
+    // settings edited by hand may be out of bounds
+    let scale = settings.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+    for (entity, mut text, scaled) in &mut query {
+        // texts rebuilding their sections do so out of their first one, already scaled
+        let current = scaled.map_or(1., |scaled| scaled.0);
+        if current == scale {
+            continue;
+        }
+        for section in &mut text.sections {
+            section.style.font_size *= scale / current;
+        }
+        commands.entity(entity).insert(Scaled(scale));
+    }
+}
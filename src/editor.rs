@@ -0,0 +1,310 @@
+//! Level editor, reached from the menu.
+//!
+//! Tiles are painted on the arena with the mouse, the left button using the selected brush and
+//! the right one emptying cells.
+//! Editing starts from a blank arena or from any level on disk, and the result is saved as
+//! the custom level, ready to be played.
+
+use bevy::prelude::*;
+
+use std::path::Path;
+
+use crate::{
+    cell_position,
+    level::{Level, Levels, LEVELS_DIR},
+    locale::Locale,
+    mods::Mods,
+    net::COLORS,
+    AppState, Direction, BRICK_SIZE, COLS, LEFT_WALL, ROWS, SCOREBOARD_TEXT_PADDING, TEXT_COLOR,
+    TOP_WALL,
+};
+
+const EDITOR_FONT_SIZE: f32 = 24.0;
+const CUSTOM_NAME: &str = "Custom";
+const CUSTOM_FILE: &str = "custom.toml";
+// shows through the gaps between cells
+const GRID_COLOR: Color = Color::rgb(0.2, 0.2, 0.2);
+
+/// Marks every entity belonging to the editor
+#[derive(Component)]
+pub struct Editor;
+
+/// A cell of the arena being edited, counting rows from the top
+#[derive(Component)]
+pub struct EditorCell {
+    col: usize,
+    row: usize,
+}
+
+/// Marks the text showing the brush and the level being edited
+#[derive(Component)]
+pub struct EditorStatus;
+
+/// What the left mouse button paints
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Brush {
+    Pillar,
+    Breakable,
+    Conveyor(Direction),
+    Warp,
+    Trampoline,
+    Spawn,
+}
+
+impl Brush {
+    /// Key of the name of the brush among the strings of locales
+    fn key(&self) -> &'static str {
+        match self {
+            Brush::Pillar => "brush.pillar",
+            Brush::Breakable => "brush.brick",
+            Brush::Conveyor(Direction::Up) => "brush.conveyor_up",
+            Brush::Conveyor(Direction::Down) => "brush.conveyor_down",
+            Brush::Conveyor(Direction::Left) => "brush.conveyor_left",
+            Brush::Conveyor(Direction::Right) => "brush.conveyor_right",
+            Brush::Warp => "brush.warp",
+            Brush::Trampoline => "brush.trampoline",
+            Brush::Spawn => "brush.spawn",
+        }
+    }
+}
+
+/// This resource holds the level being edited
+pub struct Draft {
+    /// Level the map was loaded from, whose rules are kept when saving
+    level: Level,
+    // level on disk the draft was loaded from, if any
+    source: Option<usize>,
+    /// Cells of the map, row by row from the top one
+    cells: Vec<char>,
+    brush: Brush,
+    // outcome of the last save
+    status: String,
+}
+
+impl Draft {
+    fn new(level: Level, source: Option<usize>) -> Self {
+        let cells = level.map.iter().flat_map(|line| line.chars()).collect();
+        Draft {
+            level,
+            source,
+            cells,
+            brush: Brush::Pillar,
+            status: String::new(),
+        }
+    }
+
+    fn paint(&mut self, col: usize, row: usize, brush: Option<Brush>) {
+        let index = row * COLS + col;
+        let cell = match brush {
+            Some(Brush::Pillar) => '#',
+            Some(Brush::Breakable) => '*',
+            Some(Brush::Conveyor(Direction::Up)) => '^',
+            Some(Brush::Conveyor(Direction::Down)) => 'v',
+            Some(Brush::Conveyor(Direction::Left)) => '<',
+            Some(Brush::Conveyor(Direction::Right)) => '>',
+            Some(Brush::Warp) if self.cells[index].is_ascii_uppercase() => return,
+            // the letter of a warp still missing its partner, or a new one
+            Some(Brush::Warp) => {
+                let count = |letter| self.cells.iter().filter(|cell| **cell == letter).count();
+                match ('A'..='Z')
+                    .find(|letter| count(*letter) == 1)
+                    .or_else(|| ('A'..='Z').find(|letter| count(*letter) == 0))
+                {
+                    Some(letter) => letter,
+                    None => return,
+                }
+            }
+            Some(Brush::Trampoline) => 'o',
+            Some(Brush::Spawn) if self.cells[index].is_ascii_digit() => return,
+            // the first number not taken yet
+            Some(Brush::Spawn) => match ('1'..='8').find(|digit| !self.cells.contains(digit)) {
+                Some(digit) => digit,
+                None => return,
+            },
+            None => '.',
+        };
+        self.cells[index] = cell;
+    }
+
+    fn save(&mut self, levels: &mut Levels, mods: &Mods, locale: &Locale) {
+        let mut level = self.level.clone();
+        level.name = CUSTOM_NAME.to_string();
+        level.map = self
+            .cells
+            .chunks(COLS)
+            .map(|row| row.iter().collect())
+            .collect();
+        if let Err(e) = level.check() {
+            self.status = locale.format("editor.failed", &[("error", &e)]);
+            return;
+        }
+        self.status = match level.save(&Path::new(LEVELS_DIR).join(CUSTOM_FILE)) {
+            Ok(()) => {
+                // make it available to the menu right away
+                *levels = Levels::load(mods);
+                self.source = levels.0.iter().position(|level| level.name == CUSTOM_NAME);
+                self.level = level;
+                locale.format("editor.saved", &[("name", &CUSTOM_NAME)])
+            }
+            Err(e) => locale.format("editor.failed", &[("error", &e)]),
+        };
+    }
+}
+
+pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>, locale: Res<Locale>) {
+    commands.insert_resource(Draft::new(Level::blank(CUSTOM_NAME), None));
+    commands.insert_resource(ClearColor(GRID_COLOR));
+
+    for row in 0..ROWS {
+        for col in 0..COLS {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    transform: Transform {
+                        translation: cell_position(col, ROWS - 1 - row).extend(0.0),
+                        // leave a thin gap between cells
+                        scale: (BRICK_SIZE - Vec2::ONE * 2.).extend(1.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(EditorCell { col, row })
+                .insert(Editor);
+        }
+    }
+
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(locale.mono_font()),
+                    font_size: EDITOR_FONT_SIZE,
+                    color: TEXT_COLOR,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: SCOREBOARD_TEXT_PADDING,
+                    left: SCOREBOARD_TEXT_PADDING,
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(EditorStatus)
+        .insert(Editor);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn edit(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    locale: Res<Locale>,
+    mut levels: ResMut<Levels>,
+    mods: Res<Mods>,
+    mut draft: ResMut<Draft>,
+    mut state: ResMut<State<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::P) {
+        draft.brush = Brush::Pillar;
+    } else if keyboard_input.just_pressed(KeyCode::B) {
+        draft.brush = Brush::Breakable;
+    } else if keyboard_input.just_pressed(KeyCode::C) {
+        // pressed again, turns the belt
+        draft.brush = match draft.brush {
+            Brush::Conveyor(Direction::Right) => Brush::Conveyor(Direction::Down),
+            Brush::Conveyor(Direction::Down) => Brush::Conveyor(Direction::Left),
+            Brush::Conveyor(Direction::Left) => Brush::Conveyor(Direction::Up),
+            _ => Brush::Conveyor(Direction::Right),
+        };
+    } else if keyboard_input.just_pressed(KeyCode::W) {
+        draft.brush = Brush::Warp;
+    } else if keyboard_input.just_pressed(KeyCode::J) {
+        draft.brush = Brush::Trampoline;
+    } else if keyboard_input.just_pressed(KeyCode::N) {
+        draft.brush = Brush::Spawn;
+    } else if keyboard_input.just_pressed(KeyCode::T) {
+        draft.level.theme = draft.level.theme.next();
+    } else if keyboard_input.just_pressed(KeyCode::S) {
+        draft.save(&mut levels, &mods, &locale);
+    } else if keyboard_input.just_pressed(KeyCode::Left)
+        || keyboard_input.just_pressed(KeyCode::Right)
+    {
+        // a blank arena sits right after the levels on disk
+        let blank = levels.0.len();
+        let current = draft.source.unwrap_or(blank);
+        let next = if keyboard_input.just_pressed(KeyCode::Right) {
+            (current + 1) % (blank + 1)
+        } else {
+            (current + blank) % (blank + 1)
+        };
+        let brush = draft.brush;
+        *draft = match levels.0.get(next) {
+            Some(level) => Draft::new(level.clone(), Some(next)),
+            None => Draft::new(Level::blank(CUSTOM_NAME), None),
+        };
+        draft.brush = brush;
+    } else if keyboard_input.clear_just_pressed(KeyCode::Escape) {
+        // fails only if a transition is already queued
+        let _ = state.set(AppState::Menu);
+    }
+
+    let brush = if mouse_input.pressed(MouseButton::Left) {
+        Some(draft.brush)
+    } else if mouse_input.pressed(MouseButton::Right) {
+        None
+    } else {
+        return;
+    };
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    if let Some(cursor) = window.cursor_position() {
+        // the camera sits in the middle of the window, where the arena is centered too
+        let position = cursor - Vec2::new(window.width(), window.height()) / 2.;
+        let col = ((position.x - LEFT_WALL) / BRICK_SIZE.x).floor();
+        let row = ((TOP_WALL - position.y) / BRICK_SIZE.y).floor();
+        if (0. ..COLS as f32).contains(&col) && (0. ..ROWS as f32).contains(&row) {
+            draft.paint(col as usize, row as usize, brush);
+        }
+    }
+}
+
+pub fn show(
+    draft: Res<Draft>,
+    locale: Res<Locale>,
+    mut cell_query: Query<(&EditorCell, &mut Sprite)>,
+    mut status_query: Query<&mut Text, With<EditorStatus>>,
+) {
+    let palette = draft.level.theme.palette();
+    for (cell, mut sprite) in &mut cell_query {
+        sprite.color = match draft.cells[cell.row * COLS + cell.col] {
+            '#' => palette.wall,
+            '*' => palette.brick,
+            '<' | '>' | '^' | 'v' => palette.conveyor,
+            'A'..='Z' => palette.warp,
+            'o' => palette.trampoline,
+            digit @ '1'..='8' => COLORS[digit as usize - '1' as usize],
+            _ => palette.background,
+        };
+    }
+
+    if let Ok(mut text) = status_query.get_single_mut() {
+        let source = match draft.source {
+            Some(_) => draft.level.name.as_str(),
+            None => locale.text("editor.blank"),
+        };
+        text.sections[0].value = locale.format(
+            "editor.status",
+            &[
+                ("source", &source),
+                ("brush", &locale.text(draft.brush.key())),
+                ("theme", &locale.text(draft.level.theme.key())),
+                ("status", &draft.status),
+            ],
+        );
+    }
+}
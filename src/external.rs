@@ -0,0 +1,435 @@
+//! Bots written as other programs, in whatever language.
+//!
+//! An external bot is listed in the [`Roster`](crate::bot::Roster) under a name telling how to
+//! reach it: `exec:COMMAND` starts the given command and talks to it over its standard input and
+//! output, `ws:PORT` waits for a WebSocket client to connect to the given port and talks to it.
+//! Either way, at every physics step the game sends the bot a snapshot of the arena as a line of
+//! JSON, or a text message, and reads back the action it picked the same way:
+//!
+//! ```text
+//! > {"me":{"id":4,"position":[-96.0,64.0],"size":[30.0,30.0],"human":false,"bombs_left":1,"bomb_power":1},"others":[...],"bombs":[...],"bricks":[...],"power_ups":[...]}
+//! < {"action":"left"}
+//! ```
+//!
+//! Power-ups are sent with the index of their kind in
+//! [`POWER_UPS_PATH`](crate::power_ups::POWER_UPS_PATH).
+//! Actions are `idle`, `bomb`, `up`, `down`, `left` and `right`. The game never waits for bots:
+//! at every step it takes the last action answered since the step before, to an earlier snapshot,
+//! and bots with no answer yet stand still for the step, so that a stuck bot can't hang the match;
+//! since which answers make it in time depends on the machine, matches with external bots can't
+//! be replayed exactly.
+//! Programs are started anew for every match and stopped when their player dies, WebSocket
+//! clients are disconnected then and have to connect again for the next match.
+//! Browsers can't start programs nor listen on ports, so external bots are only compiled into
+//! native builds.
+
+use bevy::prelude::*;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use tungstenite::Message;
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::TcpListener,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    bot::{BombInfo, BotAction, BotController, BrickInfo, PlayerInfo, PowerUpInfo, Snapshot},
+    Direction, PowerUp,
+};
+
+/// Prefix of the names of bots started as commands
+pub const COMMAND_PREFIX: &str = "exec:";
+/// Prefix of the names of bots connecting over WebSocket
+pub const WEBSOCKET_PREFIX: &str = "ws:";
+
+// how long WebSocket bots are waited for, before they're sent the next snapshot
+const ANSWER_TIMEOUT: Duration = Duration::from_millis(100);
+// how often the port is checked for WebSocket clients, until one connects
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(10);
+// what bots are taken to answer until they're there to
+const IDLE_ANSWER: &str = r#"{"action":"idle"}"#;
+
+/// A player, as sent to external bots
+#[derive(Serialize)]
+struct PlayerMessage {
+    /// Identifies the player from one step to the next, and as the owner of bombs
+    id: u32,
+    position: [f32; 2],
+    size: [f32; 2],
+    human: bool,
+    bombs_left: u8,
+    bomb_power: u8,
+}
+
+impl From<&PlayerInfo> for PlayerMessage {
+    fn from(info: &PlayerInfo) -> Self {
+        PlayerMessage {
+            id: info.entity.id(),
+            position: info.position.to_array(),
+            size: info.size.to_array(),
+            human: info.human,
+            bombs_left: info.bombs_left,
+            bomb_power: info.bomb_power,
+        }
+    }
+}
+
+/// A bomb, as sent to external bots
+#[derive(Serialize)]
+struct BombMessage {
+    position: [f32; 2],
+    power: u8,
+    owner: Option<u32>,
+    fuse: f32,
+}
+
+impl From<&BombInfo> for BombMessage {
+    fn from(info: &BombInfo) -> Self {
+        BombMessage {
+            position: info.position.to_array(),
+            power: info.power,
+            owner: info.owner.map(|owner| owner.id()),
+            fuse: info.fuse,
+        }
+    }
+}
+
+/// A brick, as sent to external bots
+#[derive(Serialize)]
+struct BrickMessage {
+    position: [f32; 2],
+    breakable: bool,
+}
+
+impl From<&BrickInfo> for BrickMessage {
+    fn from(info: &BrickInfo) -> Self {
+        BrickMessage {
+            position: info.position.to_array(),
+            breakable: info.breakable,
+        }
+    }
+}
+
+/// A power-up, as sent to external bots
+#[derive(Serialize)]
+struct PowerUpMessage {
+    position: [f32; 2],
+    /// Index of the kind of power-up, as defined
+    kind: PowerUp,
+}
+
+impl From<&PowerUpInfo> for PowerUpMessage {
+    fn from(info: &PowerUpInfo) -> Self {
+        PowerUpMessage {
+            position: info.position.to_array(),
+            kind: info.kind,
+        }
+    }
+}
+
+/// The arena, as sent to external bots at every step
+#[derive(Serialize)]
+struct SnapshotMessage {
+    me: PlayerMessage,
+    others: Vec<PlayerMessage>,
+    bombs: Vec<BombMessage>,
+    bricks: Vec<BrickMessage>,
+    power_ups: Vec<PowerUpMessage>,
+}
+
+impl From<&Snapshot> for SnapshotMessage {
+    fn from(snapshot: &Snapshot) -> Self {
+        SnapshotMessage {
+            me: PlayerMessage::from(&snapshot.me),
+            others: snapshot.others.iter().map(PlayerMessage::from).collect(),
+            bombs: snapshot.bombs.iter().map(BombMessage::from).collect(),
+            bricks: snapshot.bricks.iter().map(BrickMessage::from).collect(),
+            power_ups: snapshot
+                .power_ups
+                .iter()
+                .map(PowerUpMessage::from)
+                .collect(),
+        }
+    }
+}
+
+/// An action, as read back from external bots
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    Idle,
+    Bomb,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Deserialize)]
+struct AnswerMessage {
+    action: Action,
+}
+
+impl From<Action> for BotAction {
+    fn from(action: Action) -> Self {
+        match action {
+            Action::Idle => BotAction::Idle,
+            Action::Bomb => BotAction::Bomb,
+            Action::Up => BotAction::Move(Direction::Up),
+            Action::Down => BotAction::Move(Direction::Down),
+            Action::Left => BotAction::Move(Direction::Left),
+            Action::Right => BotAction::Move(Direction::Right),
+        }
+    }
+}
+
+/// The action of the given answer of a bot
+pub fn parse_answer(answer: &str) -> Result<BotAction, serde_json::Error> {
+    serde_json::from_str::<AnswerMessage>(answer).map(|answer| answer.action.into())
+}
+
+/// Whether the given name is the one of an external bot, and a valid one
+pub fn is_external(name: &str) -> bool {
+    name.strip_prefix(COMMAND_PREFIX)
+        .map(|command| !command.trim().is_empty())
+        .or_else(|| {
+            name.strip_prefix(WEBSOCKET_PREFIX)
+                .map(|port| port.parse::<u16>().is_ok())
+        })
+        .unwrap_or_default()
+}
+
+/// Connects to the external bot of the given name, `None` if it isn't one
+pub fn connect(name: &str) -> Option<Box<dyn BotController>> {
+    let result = if let Some(command) = name.strip_prefix(COMMAND_PREFIX) {
+        ExternalBot::start(command)
+    } else if let Some(port) = name.strip_prefix(WEBSOCKET_PREFIX) {
+        let port = port.parse::<u16>().ok()?;
+        Ok(ExternalBot::listen(port))
+    } else {
+        return None;
+    };
+    match result {
+        Ok(bot) => Some(Box::new(bot)),
+        Err(e) => {
+            warn!("Can't start bot {name:?}: {e}");
+            None
+        }
+    }
+}
+
+/// A bot driven by another program. Its snapshots and answers go through a thread of its own,
+/// so that the game never waits for them
+pub struct ExternalBot {
+    snapshots: Sender<String>,
+    // bots are shared between threads, the lock is never contended
+    answers: Mutex<Receiver<String>>,
+    /// The program driving the bot, if started by the game, stopped along with the bot
+    child: Option<Child>,
+    // whether the bot was already told off, so that logs aren't flooded at every step
+    warned: bool,
+}
+
+impl ExternalBot {
+    /// Starts the given command, made of the program and its arguments
+    fn start(command: &str) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no command"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let (stdin, stdout) = match (child.stdin.take(), child.stdout.take()) {
+            (Some(stdin), Some(stdout)) => (stdin, stdout),
+            _ => return Err(io::Error::other("no standard streams")),
+        };
+
+        let (snapshot_sender, snapshot_receiver) = mpsc::channel();
+        let (answer_sender, answer_receiver) = mpsc::channel();
+        thread::spawn(move || relay_process(snapshot_receiver, answer_sender, stdin, stdout));
+        Ok(ExternalBot {
+            snapshots: snapshot_sender,
+            answers: Mutex::new(answer_receiver),
+            child: Some(child),
+            warned: false,
+        })
+    }
+
+    /// Waits for a WebSocket client on the given port, meanwhile the bot stands still
+    fn listen(port: u16) -> Self {
+        let (snapshot_sender, snapshot_receiver) = mpsc::channel();
+        let (answer_sender, answer_receiver) = mpsc::channel();
+        thread::spawn(move || {
+            if let Err(e) = relay_websocket(port, snapshot_receiver, answer_sender) {
+                warn!("WebSocket bot on port {port} is gone: {e}");
+            }
+        });
+        ExternalBot {
+            snapshots: snapshot_sender,
+            answers: Mutex::new(answer_receiver),
+            child: None,
+            warned: false,
+        }
+    }
+
+    /// Where the answers of the bot arrive
+    fn answers(&mut self) -> &mut Receiver<String> {
+        // nothing can panic while holding the lock
+        self.answers.get_mut().unwrap()
+    }
+
+    fn warn_once(&mut self, message: impl FnOnce() -> String) {
+        if !self.warned {
+            warn!("{}", message());
+            self.warned = true;
+        }
+    }
+}
+
+impl BotController for ExternalBot {
+    fn decide(&mut self, snapshot: &Snapshot, _rng: &mut dyn RngCore) -> BotAction {
+        let message = match serde_json::to_string(&SnapshotMessage::from(snapshot)) {
+            Ok(message) => message,
+            Err(e) => {
+                self.warn_once(|| format!("Can't describe the arena to an external bot: {e}"));
+                return BotAction::Idle;
+            }
+        };
+        if self.snapshots.send(message).is_err() {
+            self.warn_once(|| "External bot stopped answering".to_string());
+            return BotAction::Idle;
+        }
+        // only answers already there are taken, the latest one if the bot caught up on several
+        let mut latest = None;
+        loop {
+            match self.answers().try_recv() {
+                Ok(answer) => latest = Some(answer),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.warn_once(|| "External bot stopped answering".to_string());
+                    break;
+                }
+            }
+        }
+        match latest {
+            Some(answer) => parse_answer(&answer).unwrap_or_else(|e| {
+                self.warn_once(|| format!("Invalid answer from an external bot {answer:?}: {e}"));
+                BotAction::Idle
+            }),
+            None => BotAction::Idle,
+        }
+    }
+}
+
+impl Drop for ExternalBot {
+    fn drop(&mut self) {
+        // the relay thread ends as soon as the program's output does
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Writes snapshots to the standard input of a program, reading back a line of its standard
+/// output after each of them, until either side is gone. Snapshots sent while the program was
+/// thinking are skipped but for the latest
+fn relay_process(
+    snapshots: Receiver<String>,
+    answers: Sender<String>,
+    mut stdin: ChildStdin,
+    stdout: ChildStdout,
+) {
+    let mut lines = BufReader::new(stdout).lines();
+    while let Ok(snapshot) = snapshots.recv() {
+        let snapshot = snapshots.try_iter().last().unwrap_or(snapshot);
+        if writeln!(stdin, "{snapshot}")
+            .and_then(|_| stdin.flush())
+            .is_err()
+        {
+            return;
+        }
+        match lines.next() {
+            Some(Ok(answer)) => {
+                if answers.send(answer).is_err() {
+                    return;
+                }
+            }
+            _ => return,
+        }
+    }
+}
+
+/// Waits for a WebSocket client on the given port, answering for it meanwhile, then sends it
+/// snapshots reading back a message after each of them, until either side is gone. Snapshots
+/// sent while the client was thinking are skipped but for the latest
+fn relay_websocket(
+    port: u16,
+    snapshots: Receiver<String>,
+    answers: Sender<String>,
+) -> Result<(), String> {
+    let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|e| e.to_string())?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    info!("Waiting for a WebSocket bot on port {port}");
+    let stream = loop {
+        match listener.accept() {
+            Ok((stream, _)) => break stream,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => match snapshots.try_recv() {
+                Ok(_) => {
+                    let _ = answers.send(IDLE_ANSWER.to_string());
+                }
+                Err(TryRecvError::Empty) => thread::sleep(ACCEPT_INTERVAL),
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            },
+            Err(e) => return Err(e.to_string()),
+        }
+    };
+    stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+    // slow clients are sent newer snapshots meanwhile, their late answers are still passed on
+    stream
+        .set_read_timeout(Some(ANSWER_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    let mut socket = tungstenite::accept(stream).map_err(|e| e.to_string())?;
+
+    while let Ok(snapshot) = snapshots.recv() {
+        let snapshot = snapshots.try_iter().last().unwrap_or(snapshot);
+        socket
+            .write_message(Message::Text(snapshot))
+            .map_err(|e| e.to_string())?;
+        loop {
+            match socket.read_message() {
+                Ok(Message::Text(answer)) => {
+                    if answers.send(answer).is_err() {
+                        return Ok(());
+                    }
+                    break;
+                }
+                Ok(Message::Close(_)) => return Ok(()),
+                // pings and the like aren't answers
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    break
+                }
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+    let _ = socket.close(None);
+    Ok(())
+}
@@ -0,0 +1,131 @@
+//! Bricks and bombs indexed by the cell they stand in.
+//!
+//! Moving players only ever touch bricks in the cells around them, and blasts only what lies
+//! along their cross, so looking those cells up in the [`Grid`] spares going through every brick
+//! and bomb of the arena for every move and every blast. The grid follows the arena by change
+//! detection: [`index_bricks`] runs at the start of every physics step, before anything looks
+//! bricks up, [`index_bombs`] once conveyors and warps are done moving bombs around, and
+//! [`forget`] once the steps of every frame are over, catching what went away after the last
+//! one. It can't wait for the last stage of the frame, which starts by clearing removals.
+//! Entries may outlive what they point to for a while, so lookups go through a query anyway.
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{Bomb, Brick, BRICK_SIZE};
+
+/// Entities indexed by the cell they stand in
+#[derive(Default)]
+pub struct Layer {
+    entities: HashMap<IVec2, Vec<Entity>>,
+    // where every indexed entity stands, to find it again when it moves or goes away
+    cells: HashMap<Entity, IVec2>,
+}
+
+impl Layer {
+    fn insert(&mut self, entity: Entity, cell: IVec2) {
+        if self.cells.get(&entity) == Some(&cell) {
+            return;
+        }
+        self.remove(entity);
+        self.entities.entry(cell).or_default().push(entity);
+        self.cells.insert(entity, cell);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some(cell) = self.cells.remove(&entity) {
+            if let Some(entities) = self.entities.get_mut(&cell) {
+                entities.retain(|other| *other != entity);
+                if entities.is_empty() {
+                    self.entities.remove(&cell);
+                }
+            }
+        }
+    }
+
+    /// Entities standing in the given cells, cell after cell
+    pub fn in_cells<'a, I>(&'a self, cells: I) -> impl Iterator<Item = Entity> + 'a
+    where
+        I: IntoIterator<Item = IVec2>,
+        I::IntoIter: 'a,
+    {
+        cells
+            .into_iter()
+            .flat_map(|cell| self.entities.get(&cell).into_iter().flatten().copied())
+    }
+
+    /// Entities standing in the given cell or in the eight around it
+    pub fn around(&self, cell: IVec2) -> impl Iterator<Item = Entity> + '_ {
+        self.in_cells((-1..=1).flat_map(move |y| (-1..=1).map(move |x| cell + IVec2::new(x, y))))
+    }
+}
+
+/// This resource indexes the bricks and bombs of the arena by the cell they stand in
+#[derive(Default)]
+pub struct Grid {
+    pub bricks: Layer,
+    pub bombs: Layer,
+}
+
+/// The cell the given point lies in, the one whose center is nearest
+pub fn cell(translation: Vec3) -> IVec2 {
+    (translation.truncate() / BRICK_SIZE).round().as_ivec2()
+}
+
+/// Cells anything caught by a blast of the given power from the given point may stand in:
+/// its cross, one cell wider on every side for things caught between two cells
+pub fn blast_cells(translation: Vec3, power: u8) -> impl Iterator<Item = IVec2> {
+    let center = cell(translation);
+    let reach = power as i32 + 1;
+    let horizontal = (-1..=1).flat_map(move |y| (-reach..=reach).map(move |x| IVec2::new(x, y)));
+    let vertical = (-1..=1).flat_map(move |x| {
+        (-reach..=reach)
+            .filter(|y| y.abs() > 1)
+            .map(move |y| IVec2::new(x, y))
+    });
+    horizontal
+        .chain(vertical)
+        .map(move |offset| center + offset)
+}
+
+// Index the bricks laid since the previous step, and forget about the ones gone
+pub fn index_bricks(
+    mut grid: ResMut<Grid>,
+    query: Query<(Entity, &Transform), Added<Brick>>,
+    removed: RemovedComponents<Brick>,
+) {
+    for entity in removed.iter() {
+        grid.bricks.remove(entity);
+    }
+    for (entity, transform) in &query {
+        grid.bricks.insert(entity, cell(transform.translation));
+    }
+}
+
+// Index the bombs placed or moved since the previous step, and forget about the ones gone
+#[allow(clippy::type_complexity)]
+pub fn index_bombs(
+    mut grid: ResMut<Grid>,
+    query: Query<(Entity, &Transform), (With<Bomb>, Or<(Added<Bomb>, Changed<Transform>)>)>,
+    removed: RemovedComponents<Bomb>,
+) {
+    for entity in removed.iter() {
+        grid.bombs.remove(entity);
+    }
+    for (entity, transform) in &query {
+        grid.bombs.insert(entity, cell(transform.translation));
+    }
+}
+
+// Forget about the bricks and bombs gone during this frame, after its last step
+pub fn forget(
+    mut grid: ResMut<Grid>,
+    removed_bricks: RemovedComponents<Brick>,
+    removed_bombs: RemovedComponents<Bomb>,
+) {
+    for entity in removed_bricks.iter() {
+        grid.bricks.remove(entity);
+    }
+    for entity in removed_bombs.iter() {
+        grid.bombs.remove(entity);
+    }
+}
@@ -0,0 +1,324 @@
+//! Batch simulation of bots-only matches, without rendering.
+//!
+//! Every app update advances the simulation by exactly one physics step, so matches run
+//! as fast as the CPU allows and don't depend on the wall clock. Tests play matches the same
+//! way, see [`build_test_app`].
+//! Along with the statistics printed at the end, the outcome of every match can be written to a
+//! file as it ends, to be looked into with other tools: as CSV, one row for every player of every
+//! match, when the file is named `.csv`, and as JSON lines, one object for every match, otherwise.
+
+use bevy::{ecs::system::CommandQueue, input::InputPlugin, prelude::*, utils::HashMap};
+use serde::Serialize;
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{
+    bot::{BotRegistry, Roster},
+    bury, explode,
+    input::Bindings,
+    spawn_arena, Bomb, BrickDestroyedEvent, DeathEvent, GamePlugin, Match, MatchRng, Player, Rules,
+    Stepping, Tick, TIME_STEP,
+};
+
+// matches lasting longer than this many physics steps (3 minutes) end in a draw
+const MAX_TICKS: u64 = 3 * 60 * 60;
+
+/// Aggregated results of a single bot
+#[derive(Default)]
+struct Stats {
+    wins: usize,
+    survival_ticks: u64,
+}
+
+/// This resource counts what every player did during the match being played, by name
+#[derive(Default)]
+pub struct Tally {
+    pub kills: HashMap<String, usize>,
+    pub bricks: HashMap<String, usize>,
+}
+
+/// Outcome of a single match, as written to statistics files
+#[derive(Serialize)]
+struct MatchRecord {
+    /// Number of the match, from 0
+    index: usize,
+    /// Seed the match can be replayed with
+    seed: u64,
+    winner: Option<String>,
+    /// How long the match lasted, in seconds of play
+    duration: f32,
+    players: Vec<PlayerRecord>,
+}
+
+/// What a single player did during a match
+#[derive(Serialize)]
+struct PlayerRecord {
+    name: String,
+    /// Opponents killed by the player's bombs, not counting the player itself
+    kills: usize,
+    /// Bricks destroyed by the player's bombs
+    bricks: usize,
+    /// How long the player stayed alive, in seconds of play
+    survival: f32,
+}
+
+/// Where the outcomes of matches are written, in the format picked by the name of the file
+enum StatsFile {
+    Csv(BufWriter<File>),
+    JsonLines(BufWriter<File>),
+}
+
+impl StatsFile {
+    /// Creates the file at the given path, writing the header of CSV files
+    fn create(path: &Path) -> io::Result<Self> {
+        let writer = BufWriter::new(File::create(path)?);
+        if path.extension().is_some_and(|extension| extension == "csv") {
+            let mut file = StatsFile::Csv(writer);
+            file.write_line("match,seed,winner,duration,player,kills,bricks,survival")?;
+            Ok(file)
+        } else {
+            Ok(StatsFile::JsonLines(writer))
+        }
+    }
+
+    fn writer(&mut self) -> &mut BufWriter<File> {
+        match self {
+            StatsFile::Csv(writer) | StatsFile::JsonLines(writer) => writer,
+        }
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.writer(), "{line}")
+    }
+
+    /// Writes the outcome of a match, flushing it so that it's there even if later ones never end
+    fn write(&mut self, record: &MatchRecord) -> io::Result<()> {
+        match self {
+            StatsFile::Csv(_) => {
+                for player in &record.players {
+                    let line = format!(
+                        "{},{},{},{:.2},{},{},{},{:.2}",
+                        record.index,
+                        record.seed,
+                        csv_field(record.winner.as_deref().unwrap_or_default()),
+                        record.duration,
+                        csv_field(&player.name),
+                        player.kills,
+                        player.bricks,
+                        player.survival,
+                    );
+                    self.write_line(&line)?;
+                }
+            }
+            StatsFile::JsonLines(_) => {
+                let line = serde_json::to_string(record).map_err(io::Error::from)?;
+                self.write_line(&line)?;
+            }
+        }
+        self.writer().flush()
+    }
+}
+
+/// The given text as a CSV field, quoted when it holds separators or quotes
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// An app simulating matches with no window, one physics step at every update.
+/// Nobody presses keys in it: players are driven by sending their events, like bots do
+pub fn build_test_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(InputPlugin)
+        .add_plugin(GamePlugin {
+            stepping: Stepping::EveryUpdate,
+        });
+    app
+}
+
+/// Plays the given number of matches by the given rules and prints per-bot statistics,
+/// writing the outcome of every match to the given file, if any.
+/// Matches are numbered from the given seed, so that each of them can be replayed on its own
+pub fn run(
+    matches: usize,
+    mut roster: Roster,
+    rules: Rules,
+    seed: Option<u64>,
+    stats_path: Option<&Path>,
+) {
+    // there's nobody to press the keys
+    roster.humans = 0;
+
+    let mut stats_file = stats_path.map(|path| {
+        StatsFile::create(path).unwrap_or_else(|e| {
+            eprintln!("Can't write statistics to {}: {e}", path.display());
+            std::process::exit(1);
+        })
+    });
+
+    let mut app = build_test_app();
+    app.insert_resource(rules)
+        .insert_resource(roster.clone())
+        .init_resource::<Tally>()
+        .add_system(tally.after(explode).before(bury));
+
+    let seed = seed.unwrap_or_else(rand::random);
+    println!("seed {seed}");
+
+    let mut stats = BTreeMap::<String, Stats>::new();
+    let mut draws = 0;
+    for index in 0..matches {
+        let match_seed = seed.wrapping_add(index as u64);
+        app.insert_resource(MatchRng::seeded(match_seed));
+        let (winner, ticks, survivals) = play(&mut app, &roster);
+        match &winner {
+            Some(winner) => stats.entry(winner.clone()).or_default().wins += 1,
+            None => draws += 1,
+        }
+        for (name, ticks) in &survivals {
+            stats.entry(name.clone()).or_default().survival_ticks += ticks;
+        }
+
+        if let Some(file) = &mut stats_file {
+            let tally = app.world.resource::<Tally>();
+            let mut players = survivals
+                .into_iter()
+                .map(|(name, ticks)| PlayerRecord {
+                    kills: tally.kills.get(&name).copied().unwrap_or_default(),
+                    bricks: tally.bricks.get(&name).copied().unwrap_or_default(),
+                    survival: ticks as f32 * TIME_STEP,
+                    name,
+                })
+                .collect::<Vec<_>>();
+            players.sort_by(|a, b| a.name.cmp(&b.name));
+            let record = MatchRecord {
+                index,
+                seed: match_seed,
+                winner,
+                duration: ticks as f32 * TIME_STEP,
+                players,
+            };
+            if let Err(e) = file.write(&record) {
+                eprintln!("Can't write statistics of match {index}: {e}");
+                stats_file = None;
+            }
+        }
+    }
+
+    println!("{matches} matches, {draws} draws");
+    println!(
+        "{:<20} {:>6} {:>9} {:>13}",
+        "bot", "wins", "win rate", "avg survival"
+    );
+    for (name, stats) in stats {
+        println!(
+            "{:<20} {:>6} {:>8.1}% {:>12.1}s",
+            name,
+            stats.wins,
+            100. * stats.wins as f32 / matches.max(1) as f32,
+            stats.survival_ticks as f32 * TIME_STEP / matches.max(1) as f32,
+        );
+    }
+}
+
+// Count kills and destroyed bricks towards the players whose bombs did it
+pub fn tally(
+    mut tally: ResMut<Tally>,
+    mut death_reader: EventReader<DeathEvent>,
+    mut brick_reader: EventReader<BrickDestroyedEvent>,
+    name_query: Query<&Name>,
+    bomb_query: Query<&Bomb>,
+) {
+    // killed players and bombs are still around until the step is over, bombs still have the
+    // owner they'll lose once it's buried
+    let name = |entity: Option<Entity>| {
+        entity
+            .and_then(|entity| name_query.get(entity).ok())
+            .map(|name| name.to_string())
+    };
+
+    for DeathEvent { player, killer } in death_reader.iter() {
+        if *killer != Some(*player) {
+            if let Some(killer) = name(*killer) {
+                *tally.kills.entry(killer).or_default() += 1;
+            }
+        }
+    }
+    for BrickDestroyedEvent { bomb, .. } in brick_reader.iter() {
+        let owner = bomb_query.get(*bomb).ok().and_then(|bomb| bomb.player);
+        if let Some(owner) = name(owner) {
+            *tally.bricks.entry(owner).or_default() += 1;
+        }
+    }
+}
+
+/// Plays a single match, returning the winner, if any, how many steps it lasted and how many
+/// steps each bot survived
+fn play(app: &mut App, roster: &Roster) -> (Option<String>, u64, HashMap<String, u64>) {
+    let leftovers = app
+        .world
+        .query_filtered::<Entity, With<Match>>()
+        .iter(&app.world)
+        .collect::<Vec<_>>();
+    for entity in leftovers {
+        app.world.despawn(entity);
+    }
+
+    // the generator is taken out of the world while commands borrow it
+    let mut rng = app.world.remove_resource::<MatchRng>().unwrap_or_default();
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, &app.world);
+    spawn_arena(
+        &mut commands,
+        app.world.resource::<BotRegistry>(),
+        roster,
+        app.world.resource::<Bindings>(),
+        app.world.resource::<Rules>(),
+        &mut rng,
+    );
+    queue.apply(&mut app.world);
+    app.world.insert_resource(rng);
+    if let Some(mut tally) = app.world.get_resource_mut::<Tally>() {
+        *tally = Tally::default();
+    }
+
+    let start = app.world.resource::<Tick>().0;
+    let mut players = app.world.query_filtered::<&Name, With<Player>>();
+    let mut survivals = players
+        .iter(&app.world)
+        .map(|name| (name.to_string(), 0))
+        .collect::<HashMap<_, _>>();
+
+    loop {
+        app.update();
+
+        let ticks = app.world.resource::<Tick>().0 - start;
+        let alive = players
+            .iter(&app.world)
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        for name in &alive {
+            if let Some(survival) = survivals.get_mut(name) {
+                *survival = ticks;
+            }
+        }
+
+        if alive.len() <= 1 || ticks >= MAX_TICKS {
+            let winner = if alive.len() == 1 {
+                alive.into_iter().next()
+            } else {
+                None
+            };
+            return (winner, ticks, survivals);
+        }
+    }
+}
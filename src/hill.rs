@@ -0,0 +1,116 @@
+//! King of the hill mode, where players fight over a zone of the arena.
+//!
+//! The hill is a highlighted square of cells, starting in the middle of the arena and moving
+//! somewhere else every now and then.
+//! A player standing alone on it holds it, earning a point every step: the first player holding
+//! it as long as the [`Rules`] ask for wins the round, while players crowding it earn nothing.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    arena::playable, cell_center, cell_position, Active, GameOverEvent, Match, MatchRng, Mode,
+    Rounds, Rules, Tick, BRICK_SIZE, MARK_Z, TIME_STEP,
+};
+
+// cells the hill spans from its middle one, along rows and columns
+const HILL_REACH: usize = 1;
+const HILL_COLOR: Color = Color::rgba(1.0, 0.9, 0.4, 0.2);
+// shown while a player holds the hill
+const HILL_HELD_COLOR: Color = Color::rgba(1.0, 0.9, 0.4, 0.5);
+// seconds between moves of the hill
+const HILL_MOVE_SECS: f32 = 20.0;
+
+/// The zone players fight over, placed on the cell in its middle
+#[derive(Component)]
+pub struct Hill;
+
+/// Steps a player held the hill for
+#[derive(Component, Default)]
+pub struct Hold(pub u64);
+
+impl Hold {
+    /// Seconds the hill was held for
+    pub fn secs(&self) -> u32 {
+        (self.0 as f32 * TIME_STEP) as u32
+    }
+}
+
+/// Places the hill in the middle of the playable area
+pub fn spawn_hill(commands: &mut Commands, size: (usize, usize)) {
+    let (cols, rows) = playable(size);
+    let width = (2 * HILL_REACH + 1) as f32;
+    commands
+        .spawn()
+        .insert(Hill)
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: HILL_COLOR,
+                ..default()
+            },
+            transform: Transform {
+                // below everything in the arena
+                translation: cell_position(
+                    (cols.start + cols.end) / 2,
+                    (rows.start + rows.end) / 2,
+                )
+                .extend(MARK_Z),
+                scale: (BRICK_SIZE * width).extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match);
+}
+
+// Move the hill around, and score the player holding it
+pub fn hold(
+    rules: Res<Rules>,
+    tick: Res<Tick>,
+    rounds: Res<Rounds>,
+    mut rng: ResMut<MatchRng>,
+    mut hill_query: Query<(&mut Transform, &mut Sprite), With<Hill>>,
+    mut player_query: Query<(&mut Hold, &Transform, Option<&Active>), Without<Hill>>,
+    mut game_over_writer: EventWriter<GameOverEvent>,
+) {
+    if rules.mode != Mode::Hill || rounds.over {
+        return;
+    }
+    let elapsed = tick.0 - rounds.start;
+    for (mut transform, mut sprite) in &mut hill_query {
+        // the whole hill stays in the playable area, where there's room for it to move
+        let (cols, rows) = playable(rules.size);
+        if elapsed > 0
+            && elapsed.is_multiple_of((HILL_MOVE_SECS / TIME_STEP) as u64)
+            && cols.len() > 2 * HILL_REACH
+            && rows.len() > 2 * HILL_REACH
+        {
+            let col = rng
+                .0
+                .gen_range(cols.start + HILL_REACH..cols.end - HILL_REACH);
+            let row = rng
+                .0
+                .gen_range(rows.start + HILL_REACH..rows.end - HILL_REACH);
+            transform.translation = cell_position(col, row).extend(transform.translation.z);
+        }
+
+        let hill = transform.translation.truncate();
+        let reach = HILL_REACH as f32 * BRICK_SIZE.x + 1.;
+        let mut holders = player_query.iter_mut().filter(|(_, player, _)| {
+            (cell_center(player.translation) - hill).abs().max_element() < reach
+        });
+        match (holders.next(), holders.next()) {
+            (Some((mut hold, _, active)), None) => {
+                sprite.color = HILL_HELD_COLOR;
+                hold.0 += 1;
+                if hold.0 >= (rules.hill as f32 / TIME_STEP) as u64 {
+                    game_over_writer.send(GameOverEvent {
+                        winner: active.map(|active| active.0),
+                        team: None,
+                    });
+                }
+            }
+            _ => sprite.color = HILL_COLOR,
+        }
+    }
+}
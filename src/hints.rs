@@ -0,0 +1,101 @@
+//! Visual aids reading blasts ahead of time, only there to be seen.
+//!
+//! When turned on from the options screen, the cells a bomb placed right now would blast are
+//! marked under the feet of every local human.
+//! Whatever the options, the cells bombs are about to blast are tinted during the last moments
+//! of their fuse, for everyone watching.
+//! Hints are laid anew every frame, following the match without being part of it.
+
+use bevy::prelude::*;
+
+use crate::{
+    blast_cells, bomb_cell, net::Remote, settings::Settings, Active, Bomb, Brick, Flying, Match,
+    Player, Rules, BRICK_SIZE, HINT_Z,
+};
+
+const PREVIEW_COLOR: Color = Color::rgb(1.0, 1.0, 1.0);
+const PREVIEW_ALPHA: f32 = 0.3;
+const DANGER_COLOR: Color = Color::rgb(1.0, 0.2, 0.1);
+// seconds left on the fuse of bombs when their blast starts being telegraphed
+const DANGER_SECS: f32 = 0.3;
+// hints are a bit smaller than cells, so that neighbouring ones can be told apart
+const HINT_SIZE: Vec2 = Vec2::new(BRICK_SIZE.x * 0.9, BRICK_SIZE.y * 0.9);
+
+/// A cell a bomb placed right now would blast
+#[derive(Component)]
+pub struct Preview;
+
+/// A cell a bomb is about to blast
+#[derive(Component)]
+pub struct Danger;
+
+/// Marks the given cell with a hint of the given color and opacity
+fn spawn_hint(commands: &mut Commands, cell: Vec2, mut color: Color, alpha: f32) -> Entity {
+    color.set_a(alpha);
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite { color, ..default() },
+            transform: Transform {
+                translation: cell.extend(HINT_Z),
+                scale: HINT_SIZE.extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .id()
+}
+
+// Mark the cells bombs placed right now by local humans would blast, when asked to
+#[allow(clippy::type_complexity)]
+pub fn preview(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    rules: Res<Rules>,
+    hint_query: Query<Entity, With<Preview>>,
+    brick_query: Query<&Transform, (With<Brick>, Without<Player>)>,
+    player_query: Query<(&Player, &Transform), (With<Active>, Without<Remote>, Without<Flying>)>,
+) {
+    for entity in &hint_query {
+        commands.entity(entity).despawn();
+    }
+    if !settings.blast_preview {
+        return;
+    }
+    for (player, transform) in &player_query {
+        if player.active_bombs >= player.max_bombs {
+            continue;
+        }
+        let cell = match bomb_cell(transform.translation, &brick_query) {
+            Some(cell) => cell,
+            None => continue,
+        };
+        for cell in blast_cells(cell, player.placed_power(&rules)) {
+            let hint = spawn_hint(&mut commands, cell, PREVIEW_COLOR, PREVIEW_ALPHA);
+            commands.entity(hint).insert(Preview);
+        }
+    }
+}
+
+// Tint the cells bombs about to go off will blast, deeper the closer they are to it
+pub fn telegraph(
+    mut commands: Commands,
+    hint_query: Query<Entity, With<Danger>>,
+    bomb_query: Query<(&Bomb, &Transform, &Visibility)>,
+) {
+    for entity in &hint_query {
+        commands.entity(entity).despawn();
+    }
+    for (bomb, transform, visibility) in &bomb_query {
+        let left = bomb.timer.duration().as_secs_f32() - bomb.timer.elapsed_secs();
+        // bombs that can't be seen don't give themselves away
+        if left > DANGER_SECS || !visibility.is_visible {
+            continue;
+        }
+        let alpha = 0.2 + 0.4 * (1. - left / DANGER_SECS);
+        for cell in blast_cells(transform.translation.truncate(), bomb.power) {
+            let hint = spawn_hint(&mut commands, cell, DANGER_COLOR, alpha);
+            // shown to joined instances along with the rest of the match
+            commands.entity(hint).insert(Danger).insert(Match);
+        }
+    }
+}
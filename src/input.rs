@@ -0,0 +1,335 @@
+//! Human players' controls.
+//!
+//! Every human player is driven by its own [`Controls`], a mapping from [`Action`]s to keys.
+//! The mappings of all players are stored in the [`Bindings`] resource, loaded at startup along
+//! with the rest of the [`Settings`](crate::settings::Settings) and saved back with them whenever
+//! they're changed from the options screen.
+//! Humans beyond the keyboard layouts play with a gamepad each, see [`Pad`].
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Active, AppState, BombEvent, DashEvent, DetonateEvent, Direction, MoveEvent, Player};
+
+// how many physics steps a bomb key press is remembered for, when it can't be honored right away
+pub const BOMB_BUFFER_STEPS: u8 = 6;
+// how far a stick must be pushed to move
+const STICK_THRESHOLD: f32 = 0.5;
+
+// gamepad layout, the same for every pad
+const PAD_MOVES: [(GamepadButtonType, Direction); 4] = [
+    (GamepadButtonType::DPadUp, Direction::Up),
+    (GamepadButtonType::DPadDown, Direction::Down),
+    (GamepadButtonType::DPadLeft, Direction::Left),
+    (GamepadButtonType::DPadRight, Direction::Right),
+];
+const PAD_BOMB: GamepadButtonType = GamepadButtonType::South;
+const PAD_DETONATE: GamepadButtonType = GamepadButtonType::East;
+const PAD_DASH: GamepadButtonType = GamepadButtonType::West;
+const PAD_PAUSE: GamepadButtonType = GamepadButtonType::Start;
+
+/// Something a human player can do
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Bomb,
+    Detonate,
+    Dash,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 8] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Bomb,
+        Action::Detonate,
+        Action::Dash,
+        Action::Pause,
+    ];
+
+    /// Key of the name of the action among the strings of locales
+    pub fn key(&self) -> &'static str {
+        match self {
+            Action::Up => "action.up",
+            Action::Down => "action.down",
+            Action::Left => "action.left",
+            Action::Right => "action.right",
+            Action::Bomb => "action.bomb",
+            Action::Detonate => "action.detonate",
+            Action::Dash => "action.dash",
+            Action::Pause => "action.pause",
+        }
+    }
+}
+
+/// Keys driving a human player
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Controls {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub bomb: KeyCode,
+    pub detonate: KeyCode,
+    // bindings saved before dashes existed get the key of the first layout
+    #[serde(default = "default_dash")]
+    pub dash: KeyCode,
+    pub pause: KeyCode,
+}
+
+fn default_dash() -> KeyCode {
+    KeyCode::RControl
+}
+
+impl Controls {
+    /// Key bound to the given action
+    pub fn key(&self, action: Action) -> KeyCode {
+        match action {
+            Action::Up => self.up,
+            Action::Down => self.down,
+            Action::Left => self.left,
+            Action::Right => self.right,
+            Action::Bomb => self.bomb,
+            Action::Detonate => self.detonate,
+            Action::Dash => self.dash,
+            Action::Pause => self.pause,
+        }
+    }
+
+    /// Binds the given action to a new key
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        let slot = match action {
+            Action::Up => &mut self.up,
+            Action::Down => &mut self.down,
+            Action::Left => &mut self.left,
+            Action::Right => &mut self.right,
+            Action::Bomb => &mut self.bomb,
+            Action::Detonate => &mut self.detonate,
+            Action::Dash => &mut self.dash,
+            Action::Pause => &mut self.pause,
+        };
+        *slot = key;
+    }
+}
+
+/// Gamepad driving a human player
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Pad(pub Gamepad);
+
+impl Pad {
+    fn button(&self, button_type: GamepadButtonType) -> GamepadButton {
+        GamepadButton::new(self.0, button_type)
+    }
+
+    fn axis(&self, axis_type: GamepadAxisType) -> GamepadAxis {
+        GamepadAxis::new(self.0, axis_type)
+    }
+}
+
+/// Physics steps a human player still wants to place a bomb for
+#[derive(Component, Default)]
+pub struct BombBuffer(pub u8);
+
+/// This resource holds the controls of every human player
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bindings {
+    pub players: Vec<Controls>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            players: vec![
+                Controls {
+                    up: KeyCode::Up,
+                    down: KeyCode::Down,
+                    left: KeyCode::Left,
+                    right: KeyCode::Right,
+                    bomb: KeyCode::Space,
+                    detonate: KeyCode::RShift,
+                    dash: default_dash(),
+                    pause: KeyCode::P,
+                },
+                Controls {
+                    up: KeyCode::W,
+                    down: KeyCode::S,
+                    left: KeyCode::A,
+                    right: KeyCode::D,
+                    bomb: KeyCode::LControl,
+                    detonate: KeyCode::LShift,
+                    dash: KeyCode::LAlt,
+                    pause: KeyCode::Tab,
+                },
+            ],
+        }
+    }
+}
+
+impl Bindings {
+    /// Gives every keyboard layout its controls, even when the bindings were written for
+    /// fewer or more players
+    pub fn complete(&mut self) {
+        let defaults = Bindings::default();
+        self.players.truncate(defaults.players.len());
+        for controls in defaults.players.into_iter().skip(self.players.len()) {
+            self.players.push(controls);
+        }
+    }
+
+    /// Keyboard controls of the given human player, or `None` if it plays with a gamepad
+    pub fn controls(&self, index: usize) -> Option<Controls> {
+        self.players.get(index).copied()
+    }
+
+    /// Gamepad of the given human player, the first ones being on the keyboard
+    pub fn pad(&self, index: usize) -> Pad {
+        Pad(Gamepad::new(index.saturating_sub(self.players.len())))
+    }
+}
+
+/// Records bomb key presses, on every frame so that short taps between physics steps aren't lost
+#[allow(clippy::type_complexity)]
+pub fn buffer_bomb(
+    keyboard_input: Res<Input<KeyCode>>,
+    pad_input: Res<Input<GamepadButton>>,
+    mut keyboard_query: Query<(&Controls, &mut BombBuffer), (With<Player>, With<Active>)>,
+    mut pad_query: Query<(&Pad, &mut BombBuffer), (With<Player>, With<Active>, Without<Controls>)>,
+) {
+    for (controls, mut buffer) in &mut keyboard_query {
+        if keyboard_input.just_pressed(controls.bomb) {
+            buffer.0 = BOMB_BUFFER_STEPS;
+        }
+    }
+    for (pad, mut buffer) in &mut pad_query {
+        if pad_input.just_pressed(pad.button(PAD_BOMB)) {
+            buffer.0 = BOMB_BUFFER_STEPS;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn move_player(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut move_writer: EventWriter<MoveEvent>,
+    mut bomb_writer: EventWriter<BombEvent>,
+    mut detonate_writer: EventWriter<DetonateEvent>,
+    mut dash_writer: EventWriter<DashEvent>,
+    mut query: Query<(Entity, &Controls, &mut BombBuffer), (With<Player>, With<Active>)>,
+) {
+    for (player, controls, mut buffer) in &mut query {
+        for (key, direction) in [
+            (controls.up, Direction::Up),
+            (controls.down, Direction::Down),
+            (controls.left, Direction::Left),
+            (controls.right, Direction::Right),
+        ] {
+            if keyboard_input.pressed(key) {
+                move_writer.send(MoveEvent { direction, player });
+            }
+        }
+        // holding the key places a single bomb, the buffer is emptied once it's placed
+        if buffer.0 > 0 {
+            buffer.0 -= 1;
+            bomb_writer.send(BombEvent { player });
+        }
+        if keyboard_input.pressed(controls.detonate) {
+            detonate_writer.send(DetonateEvent { player });
+        }
+        if keyboard_input.pressed(controls.dash) {
+            dash_writer.send(DashEvent { player });
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn move_pad(
+    pad_input: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut move_writer: EventWriter<MoveEvent>,
+    mut bomb_writer: EventWriter<BombEvent>,
+    mut detonate_writer: EventWriter<DetonateEvent>,
+    mut dash_writer: EventWriter<DashEvent>,
+    mut query: Query<
+        (Entity, &Pad, &mut BombBuffer),
+        (With<Player>, With<Active>, Without<Controls>),
+    >,
+) {
+    for (player, pad, mut buffer) in &mut query {
+        let x = axes
+            .get(pad.axis(GamepadAxisType::LeftStickX))
+            .unwrap_or(0.);
+        let y = axes
+            .get(pad.axis(GamepadAxisType::LeftStickY))
+            .unwrap_or(0.);
+        for (button, direction) in PAD_MOVES {
+            let tilted = match direction {
+                Direction::Up => y > STICK_THRESHOLD,
+                Direction::Down => y < -STICK_THRESHOLD,
+                Direction::Left => x < -STICK_THRESHOLD,
+                Direction::Right => x > STICK_THRESHOLD,
+            };
+            if tilted || pad_input.pressed(pad.button(button)) {
+                move_writer.send(MoveEvent { direction, player });
+            }
+        }
+        if buffer.0 > 0 {
+            buffer.0 -= 1;
+            bomb_writer.send(BombEvent { player });
+        }
+        if pad_input.pressed(pad.button(PAD_DETONATE)) {
+            detonate_writer.send(DetonateEvent { player });
+        }
+        if pad_input.pressed(pad.button(PAD_DASH)) {
+            dash_writer.send(DashEvent { player });
+        }
+    }
+}
+
+/// Pauses the match when any human asks to
+pub fn pause(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut pad_input: ResMut<Input<GamepadButton>>,
+    mut state: ResMut<State<AppState>>,
+    keyboard_query: Query<&Controls, (With<Player>, With<Active>)>,
+    pad_query: Query<&Pad, (With<Player>, With<Active>)>,
+) {
+    // consume the key press, or the paused screen would see it too and resume right away
+    let pressed = keyboard_query
+        .iter()
+        .any(|controls| keyboard_input.clear_just_pressed(controls.pause))
+        || pad_query
+            .iter()
+            .any(|pad| pad_input.clear_just_pressed(pad.button(PAD_PAUSE)));
+    if pressed {
+        // fails only if a transition is already queued
+        let _ = state.push(AppState::Paused);
+    }
+}
+
+/// Resumes the match when any human asks to
+pub fn resume(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut pad_input: ResMut<Input<GamepadButton>>,
+    mut state: ResMut<State<AppState>>,
+    bindings: Res<Bindings>,
+) {
+    let pad_pause = pad_input
+        .get_just_pressed()
+        .find(|button| button.button_type == PAD_PAUSE)
+        .copied();
+    let pressed = bindings
+        .players
+        .iter()
+        .any(|controls| keyboard_input.clear_just_pressed(controls.pause))
+        || pad_pause.is_some_and(|button| pad_input.clear_just_pressed(button));
+    if pressed {
+        // fails only if a transition is already queued
+        let _ = state.pop();
+    }
+}
@@ -0,0 +1,295 @@
+//! Arenas drawn by hand, shipped as TOML files in `assets/levels/`.
+//!
+//! A level has a name, the arena drawn as ASCII rows from top to bottom, and optionally some
+//! rules overriding the default ones and the [`Theme`] it is drawn with:
+//!
+//! ```toml
+//! name = "Corridors"
+//! theme = "ice"
+//! map = [
+//!     "1.*********.2",
+//!     ".#*#*#*#*#*#.",
+//!     # ...one row for every row of the arena
+//! ]
+//!
+//! [rules]
+//! rounds = 3
+//! power_up_chance = 0.5
+//! mutators = ["giant_blasts", "no_pickups"]
+//! ```
+//!
+//! In the map `#` is a pillar, `*` a breakable brick and `.` an empty cell, while `1` to `8`
+//! mark spawn points, in the order players take them.
+//! `<`, `>`, `^` and `v` are conveyor belts, pushing players and bombs the way they point.
+//! Capital letters are warps, coming in pairs: players and bombs stepping on one of them
+//! show up on the other one with the same letter.
+//! `o` is a trampoline, tossing players over whatever stands two cells ahead of them.
+//! Players exceeding the level's spawn points take the usual ones, and as in random arenas
+//! every spawn point can reach the others.
+//! Content packs add their own `levels`, replacing those with the same name.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    arena::{Layout, Tile},
+    locale::Locale,
+    mods::Mods,
+    mutator::Mutator,
+    spawn_points,
+    theme::Theme,
+    Direction, Rules, COLS, ROWS,
+};
+
+pub const LEVELS_DIR: &str = "assets/levels";
+
+/// An arena drawn by hand
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub name: String,
+    #[serde(default)]
+    pub theme: Theme,
+    /// Rows of the arena, from the top one
+    pub map: Vec<String>,
+    #[serde(default)]
+    rules: Overrides,
+}
+
+/// Rules a level plays by instead of the default ones
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Overrides {
+    rounds: Option<u32>,
+    time_limit: Option<u32>,
+    power_up_chance: Option<f64>,
+    rubble_chance: Option<f64>,
+    mutators: Option<Vec<Mutator>>,
+}
+
+impl Level {
+    /// A level with the classic grid of pillars and nothing else, spawn points in the corners
+    pub fn blank(name: &str) -> Self {
+        let mut map = (0..ROWS)
+            .map(|row| {
+                (0..COLS)
+                    .map(|col| {
+                        if row % 2 == 1 && col % 2 == 1 {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        map[0][0] = '1';
+        map[0][COLS - 1] = '2';
+        map[ROWS - 1][COLS - 1] = '3';
+        map[ROWS - 1][0] = '4';
+        Level {
+            name: name.to_string(),
+            map: map.into_iter().map(String::from_iter).collect(),
+            rules: Overrides::default(),
+            theme: Theme::default(),
+        }
+    }
+
+    /// Reads a level from disk, checking its map
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let level: Level = toml::from_str(&content).map_err(|e| e.to_string())?;
+        level.check()?;
+        Ok(level)
+    }
+
+    /// Whether the map can be played in, or what's wrong with it
+    pub fn check(&self) -> Result<(), String> {
+        self.grid().map(|_| ())
+    }
+
+    /// Writes the level to disk
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = toml::to_string(self).map_err(|e| e.to_string())?;
+        fs::create_dir_all(LEVELS_DIR).map_err(|e| e.to_string())?;
+        fs::write(path, content).map_err(|e| e.to_string())
+    }
+
+    /// Picks the level to play in, along with the rules it sets instead of those played by
+    /// before any level
+    pub fn apply(&self, rules: &mut Rules) {
+        Level::leave(rules);
+        rules.base = Some(Box::new(rules.clone()));
+        if let Some(rounds) = self.rules.rounds {
+            rules.rounds = rounds;
+        }
+        if let Some(time_limit) = self.rules.time_limit {
+            rules.time_limit = Some(time_limit);
+        }
+        if let Some(power_up_chance) = self.rules.power_up_chance {
+            rules.power_up_chance = power_up_chance;
+        }
+        if let Some(rubble_chance) = self.rules.rubble_chance {
+            rules.rubble_chance = rubble_chance;
+        }
+        if let Some(mutators) = &self.rules.mutators {
+            rules.mutators = mutators.clone();
+        }
+        rules.level = Some(self.clone());
+    }
+
+    /// Goes back to random arenas, and to the rules the level played in overrode
+    pub fn leave(rules: &mut Rules) {
+        if let Some(base) = rules.base.take() {
+            rules.rounds = base.rounds;
+            rules.time_limit = base.time_limit;
+            rules.power_up_chance = base.power_up_chance;
+            rules.rubble_chance = base.rubble_chance;
+            rules.mutators = base.mutators;
+        }
+        rules.level = None;
+    }
+
+    /// Tiles of the map, row by row from the bottom one, along with its spawn points
+    #[allow(clippy::type_complexity)]
+    fn grid(&self) -> Result<(Vec<Tile>, Vec<(usize, usize)>), String> {
+        if self.map.len() != ROWS {
+            return Err(format!("{} rows instead of {ROWS}", self.map.len()));
+        }
+        let mut tiles = Vec::with_capacity(ROWS * COLS);
+        let mut spawns = Vec::new();
+        for (row, line) in self.map.iter().rev().enumerate() {
+            if line.chars().count() != COLS {
+                return Err(format!("row {} isn't {COLS} cells long", ROWS - row));
+            }
+            for (col, cell) in line.chars().enumerate() {
+                let tile = match cell {
+                    '#' => Tile::Pillar,
+                    '*' => Tile::Breakable,
+                    '.' => Tile::Empty,
+                    '<' => Tile::Conveyor(Direction::Left),
+                    '>' => Tile::Conveyor(Direction::Right),
+                    '^' => Tile::Conveyor(Direction::Up),
+                    'v' => Tile::Conveyor(Direction::Down),
+                    '1'..='8' => {
+                        spawns.push((cell, (col, row)));
+                        Tile::Empty
+                    }
+                    'A'..='Z' => Tile::Warp(cell as u8 - b'A'),
+                    'o' => Tile::Trampoline,
+                    _ => return Err(format!("unknown cell '{cell}' in row {}", ROWS - row)),
+                };
+                tiles.push(tile);
+            }
+        }
+        for letter in 'A'..='Z' {
+            let count = self
+                .map
+                .iter()
+                .flat_map(|line| line.chars())
+                .filter(|cell| *cell == letter)
+                .count();
+            if count != 0 && count != 2 {
+                return Err(format!("warp {letter} must come in a pair"));
+            }
+        }
+        spawns.sort_unstable();
+        if spawns.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err("spawn points must be numbered once each".to_string());
+        }
+        Ok((tiles, spawns.into_iter().map(|(_, cell)| cell).collect()))
+    }
+
+    /// Lays out the arena for the given number of players, along with their spawn points
+    pub fn arena(&self, count: usize) -> (Vec<(usize, usize)>, Layout) {
+        let (tiles, mut spawns) = self.grid().unwrap_or_else(|e| {
+            warn!("Invalid level {}: {e}", self.name);
+            (vec![Tile::Empty; ROWS * COLS], Vec::new())
+        });
+        for cell in spawn_points(count, (COLS, ROWS)) {
+            if spawns.len() >= count {
+                break;
+            }
+            if !spawns.contains(&cell) {
+                spawns.push(cell);
+            }
+        }
+        spawns.truncate(count);
+        let layout = Layout::authored(tiles, &spawns);
+        (spawns, layout)
+    }
+}
+
+/// This resource holds the levels found on disk, sorted by name
+#[derive(Default)]
+pub struct Levels(pub Vec<Level>);
+
+impl Levels {
+    /// Reads every level in [`LEVELS_DIR`] and in those of the given content packs, skipping
+    /// the invalid ones
+    pub fn load(mods: &Mods) -> Self {
+        let mut levels = Vec::<Level>::new();
+        let dirs = std::iter::once(PathBuf::from(LEVELS_DIR)).chain(mods.dirs("levels"));
+        // packs come later the higher their priority, replacing levels of the same name
+        for level in dirs.flat_map(|dir| read_dir(&dir)) {
+            levels.retain(|other| other.name != level.name);
+            levels.push(level);
+        }
+        levels.sort_by(|a, b| a.name.cmp(&b.name));
+        Levels(levels)
+    }
+
+    /// Moves to the next level to play in, or the previous one, a random arena coming
+    /// before the first level and after the last one
+    pub fn cycle(&self, rules: &mut Rules, forward: bool) {
+        // a random arena sits right after the levels
+        let random = self.0.len();
+        let current = rules
+            .level
+            .as_ref()
+            .and_then(|current| self.0.iter().position(|level| level.name == current.name))
+            .unwrap_or(random);
+        let next = if forward {
+            (current + 1) % (random + 1)
+        } else {
+            (current + random) % (random + 1)
+        };
+        match self.0.get(next) {
+            Some(level) => level.apply(rules),
+            None => Level::leave(rules),
+        }
+    }
+}
+
+/// Every valid level in the given folder
+fn read_dir(dir: &Path) -> Vec<Level> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            if e.kind() != io::ErrorKind::NotFound {
+                warn!("Can't read levels from {}: {e}", dir.display());
+            }
+            return Vec::new();
+        }
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|path| {
+            Level::load(&path)
+                .map_err(|e| warn!("Invalid level {}: {e}", path.display()))
+                .ok()
+        })
+        .collect()
+}
+
+/// Name of the arena played in
+pub fn arena_label(rules: &Rules, locale: &Locale) -> String {
+    match &rules.level {
+        Some(level) => level.name.clone(),
+        None => locale.text("arena.random").to_string(),
+    }
+}
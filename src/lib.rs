@@ -0,0 +1,3536 @@
+//! A simplified implementation of the classic game "Bomberman".
+//!
+//! The game is launched by [`run`], while [`bench`] holds the benchmarks of `benches/`.
+
+use bevy::{
+    ecs::{
+        schedule::ShouldRun,
+        system::{Command, EntityCommands},
+    },
+    input::InputSystem,
+    prelude::*,
+    render::texture::ImageSettings,
+    sprite::collide_aabb::{collide, Collision},
+    utils::HashMap,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use clap::Parser;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use std::time::Duration;
+
+mod accessibility;
+mod achievements;
+mod ai;
+mod animation;
+mod announcer;
+mod arena;
+#[cfg(feature = "bench")]
+pub mod bench;
+mod boss;
+mod bot;
+mod camera;
+mod campaign;
+#[cfg(not(target_arch = "wasm32"))]
+mod capture;
+mod chat;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
+mod clock;
+#[cfg(feature = "debug-tools")]
+pub mod console;
+mod crown;
+mod daily;
+#[cfg(feature = "debug-tools")]
+mod debug;
+mod display;
+mod editor;
+#[cfg(not(target_arch = "wasm32"))]
+mod external;
+mod grid;
+mod headless;
+mod hill;
+mod hints;
+mod input;
+mod level;
+mod lighting;
+mod lobby;
+mod locale;
+mod menu;
+mod mob;
+mod mods;
+mod mutator;
+mod net;
+mod options;
+mod particles;
+mod power_ups;
+mod records;
+#[cfg(not(target_arch = "wasm32"))]
+mod reload;
+#[cfg(not(target_arch = "wasm32"))]
+mod replay;
+mod rollback;
+mod save;
+mod script;
+mod scripting;
+mod settings;
+mod sound;
+mod storage;
+mod survival;
+#[cfg(test)]
+mod tests;
+mod theme;
+mod transport;
+mod tutorial;
+
+// Defines the amount of time that should elapse between each physics step.
+const TIME_STEP: f32 = 1.0 / 60.0;
+
+const WALL_THICKNESS: f32 = 10.0;
+// x coordinates
+const RIGHT_WALL: f32 = BRICK_SIZE.x * (COLS as f32) / 2.;
+const LEFT_WALL: f32 = -RIGHT_WALL;
+// y coordinates
+const TOP_WALL: f32 = BRICK_SIZE.y * (ROWS as f32) / 2.;
+const BOTTOM_WALL: f32 = -TOP_WALL;
+
+const BRICK_SIZE: Vec2 = Vec2::new(50., 50.);
+const BOMB_SIZE: Vec2 = Vec2::new(40., 40.);
+const PLAYER_SIZE: Vec2 = Vec2::new(40., 40.);
+const POWER_UP_SIZE: Vec2 = Vec2::new(30., 30.);
+
+// depth of every layer of the arena, from the floor up, so that overlapping sprites are always
+// drawn in the same order: the tiles of the floor, what's laid on them, marks on the floor,
+// hints, bricks, bombs, pickups, players, the shade of night and the light cast on it, and fire,
+// with the HUD drawn above all of them
+const TILE_Z: f32 = -1.5;
+const FLOOR_Z: f32 = -1.0;
+const MARK_Z: f32 = -0.5;
+const HINT_Z: f32 = -0.25;
+const BRICK_Z: f32 = 0.0;
+const BOMB_Z: f32 = 0.1;
+const PICKUP_Z: f32 = 0.2;
+const PLAYER_Z: f32 = 0.3;
+const NIGHT_Z: f32 = 0.35;
+const GLOW_Z: f32 = 0.37;
+const FIRE_Z: f32 = 0.4;
+
+const MOVE_SPEED_X: f32 = BRICK_SIZE.x / 10.;
+const MOVE_SPEED_Y: f32 = BRICK_SIZE.y / 10.;
+// share of the top speed players gain every step they're steered, and lose every step they
+// aren't
+const ACCELERATION: f32 = 0.5;
+const DECELERATION: f32 = 1.0;
+// steps a dash lasts, covering two cells, and steps before the next one
+const DASH_STEPS: u32 = 10;
+const DASH_SPEED: f32 = BRICK_SIZE.x * 2. / DASH_STEPS as f32;
+const DASH_COOLDOWN_STEPS: u32 = 180;
+// characters of the dash meter in the HUD
+const DASH_METER: u32 = 5;
+// distance conveyor belts push players and bombs by, every step
+const CONVEYOR_SPEED: f32 = BRICK_SIZE.x / 25.;
+// steps before something that went through a warp can do it again
+const WARP_COOLDOWN_STEPS: u32 = 30;
+// cells trampolines toss players by
+const TRAMPOLINE_REACH: f32 = 2.;
+// steps players spend in the air when tossed by a trampoline
+const FLIGHT_STEPS: u32 = 20;
+
+const SCOREBOARD_FONT_SIZE: f32 = 40.0;
+const SCOREBOARD_TEXT_PADDING: Val = Val::Px(5.0);
+const HUD_FONT_SIZE: f32 = 30.0;
+const GAMEOVER_FONT_SIZE: f32 = 400.0;
+const PAUSED_FONT_SIZE: f32 = 100.0;
+const TITLE_FONT_SIZE: f32 = 120.0;
+
+const PLAYER_COLORS: [Color; bot::MAX_HUMANS] = [
+    Color::rgb(0.3, 0.3, 0.7),
+    Color::rgb(0.3, 0.6, 0.3),
+    Color::rgb(0.7, 0.6, 0.2),
+    Color::rgb(0.2, 0.6, 0.7),
+];
+const OPPONENT_COLOR: Color = Color::rgb(0.4, 0.4, 0.6);
+const TEAM_COLORS: [Color; TEAMS] = [Color::rgb(0.8, 0.3, 0.3), Color::rgb(0.3, 0.4, 0.8)];
+const TEXT_COLOR: Color = Color::rgb(0.5, 0.5, 1.0);
+const SCORE_COLOR: Color = Color::rgb(1.0, 0.5, 0.5);
+const BOMB_COLOR: Color = Color::rgb(0.0, 0.0, 0.0);
+const FIRE_COLOR: Color = Color::rgb(1.0, 0.0, 0.0);
+const RUBBLE_COLOR: Color = Color::rgb(0.5, 0.45, 0.4);
+const EXIT_COLOR: Color = Color::rgb(0.1, 0.7, 0.3);
+
+// chance for a destroyed brick to leave a power-up behind, unless the rules say otherwise
+const POWER_UP_CHANCE: f64 = 0.3;
+// chance for a free cell to start with a breakable brick, unless the rules say otherwise
+const BRICK_DENSITY: f64 = 0.8;
+// chance for a destroyed brick to leave rubble behind, unless the rules say otherwise
+const RUBBLE_CHANCE: f64 = 0.0;
+// seconds bombs tick for before going off, unless the rules say otherwise
+const FUSE_SECS: f32 = 1.0;
+// seconds rubble takes to clear
+const RUBBLE_SECS: f32 = 4.0;
+// how much rubble slows down players walking through it
+const RUBBLE_SLOWDOWN: f32 = 0.5;
+// roaming enemies in every stage, unless the rules say otherwise
+const MOBS: [mob::MobKind; 3] = [mob::MobKind::Balloon; 3];
+// crowns to hold to win a crowns match, unless the rules say otherwise
+const CROWNS: u32 = 5;
+// seconds to hold the hill for to win a king of the hill match, unless the rules say otherwise
+const HILL_SECS: u32 = 30;
+// power-ups can't push a player's stats beyond these
+const MAX_BOMBS: u8 = 8;
+const MAX_POWER: u8 = 8;
+// sides of team matches, players taking turns joining them in spawn order
+const TEAMS: usize = 2;
+// seconds the outcome of a round is shown before the next one starts
+const ROUND_BREAK_SECS: f32 = 3.0;
+// seconds killed players take to spin, shrink and fade away
+const DEATH_SECS: f32 = 0.8;
+// turns killed players spin around while fading away
+const DEATH_TURNS: f32 = 2.;
+// seconds the menu is left idle for before a demo match is played behind the title
+const DEMO_IDLE_SECS: f32 = 20.0;
+
+// standard bomberman stage
+const ROWS: usize = 11;
+const COLS: usize = 13;
+
+/// Screens the game can be in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum AppState {
+    Menu,
+    Options,
+    Editor,
+    /// Replaces the menu when hosting an online match
+    Lobby,
+    InGame,
+    /// Pushed on top of `InGame`, so that the match is kept while paused
+    Paused,
+    /// Pushed on top of `InGame` between the stages of the campaign
+    Reward,
+    /// Best times of time attack runs
+    Records,
+    /// Content packs found, to enable and disable
+    Mods,
+}
+
+/// Plays as the command line says
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run() {
+    let cli = cli::Cli::parse();
+    let settings = settings::Settings::load();
+    let mods = mods::Mods::scan(&settings.disabled_mods);
+    mods.paint();
+    let mut roster = bot::Roster::default();
+    let mut rules = settings.rules.clone();
+    let mut seed = cli.seed;
+    let mut scripts = script::Scripts::default();
+    if let Some(path) = &cli.replay {
+        match replay::Replay::load(path) {
+            Ok(mut replay) => {
+                replay.humans.truncate(bot::MAX_HUMANS);
+                roster.humans = replay.humans.len();
+                scripts.0 = replay.humans;
+                roster.bots = replay.bots;
+                rules = replay.rules;
+                seed = Some(replay.seed);
+            }
+            Err(e) => {
+                eprintln!("Can't play {} again: {e}", path.display());
+                std::process::exit(1);
+            }
+        }
+    }
+    if cli.spectate {
+        roster.humans = 0;
+    }
+    if let Some(count) = cli.bots {
+        roster.resize(count);
+    }
+    // external bots take the place of the first opponents, joining them when they're not enough
+    for (index, bot) in cli.external_bots.iter().enumerate() {
+        match roster.bots.get_mut(index) {
+            Some(name) => *name = bot.clone(),
+            None => roster.bots.push(bot.clone()),
+        }
+    }
+    roster
+        .bots
+        .truncate(bot::MAX_BOTS.min(bot::MAX_PLAYERS - roster.total_humans()));
+    if let Err(e) = cli.customize(&mut rules) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+
+    if cli.headless {
+        headless::run(cli.matches, roster, rules, seed, cli.stats.as_deref());
+        return;
+    }
+    if let Some(peer) = cli.rollback {
+        let transport = match transport::Transport::bind(cli.port) {
+            Ok(transport) => transport,
+            Err(e) => {
+                eprintln!("Can't play against {peer}: {e}");
+                std::process::exit(1);
+            }
+        };
+        rollback::run(rollback::Config {
+            transport,
+            peer: Some(peer),
+            seat: Some(cli.player as usize - 1),
+            input_delay: cli.input_delay,
+        });
+        return;
+    }
+    if let Some(address) = cli.join.or(cli.watch) {
+        if let Err(e) = net::join(address, cli.watch.is_some()) {
+            eprintln!("Can't join {address}: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut app = App::new();
+    if let Some(port) = cli.host {
+        match net::Host::bind(port) {
+            Ok(host) => {
+                app.insert_resource(host)
+                    .add_plugin(chat::ChatPlugin)
+                    .add_system(net::host_receive)
+                    .add_system(net::host_watchdog.after(net::host_receive))
+                    .add_system(net::host_chat)
+                    .add_system_set(SystemSet::on_enter(AppState::Lobby).with_system(lobby::setup))
+                    .add_system_set(
+                        SystemSet::on_update(AppState::Lobby)
+                            .with_system(lobby::navigate)
+                            .with_system(net::host_lobby)
+                            .with_system(bevy::window::close_on_esc),
+                    )
+                    .add_system_set(
+                        SystemSet::on_exit(AppState::Lobby)
+                            .with_system(despawn_with::<lobby::Lobby>),
+                    )
+                    .add_system_set(
+                        SystemSet::on_enter(AppState::InGame).with_system(net::host_start),
+                    );
+            }
+            Err(e) => {
+                eprintln!("Can't host on port {port}: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+    for human in 0..bot::MAX_HUMANS {
+        roster.colors[human] = net::COLORS[settings.color(human)];
+    }
+    // sprite sheets are pixel art
+    app.insert_resource(settings.window.descriptor())
+        .insert_resource(ImageSettings::default_nearest())
+        .add_plugins(DefaultPlugins)
+        .add_plugin(GamePlugin {
+            stepping: Stepping::Realtime,
+        })
+        .insert_resource(ClearColor(theme::Theme::default().palette().background))
+        .insert_resource(roster)
+        .insert_resource(Seed(seed))
+        .insert_resource(scripts)
+        .insert_resource(clock::Clock::new(cli.tick_rate, cli.fast_forward))
+        .insert_resource(settings.bindings.clone())
+        .insert_resource(rules)
+        .insert_resource(locale::Locale::load(&settings.language))
+        .insert_resource(settings)
+        .insert_resource(level::Levels::load(&mods))
+        .insert_resource(power_ups::PowerUpRegistry::load(&mods))
+        .insert_resource(mods)
+        .insert_resource(achievements::Unlocked::load())
+        .add_state(if cli.host.is_some() {
+            AppState::Lobby
+        } else {
+            AppState::Menu
+        })
+        .add_startup_system(setup_camera)
+        .add_startup_system(animation::load)
+        .add_system(animation::dress)
+        .add_system(power_ups::dress)
+        .add_system(animation::walk)
+        .add_system(animation::fuse)
+        .add_system(animation::ignite)
+        .add_system(animation::burn)
+        .add_system(animation::crumble)
+        .add_system(accessibility::mark)
+        .init_resource::<particles::ParticlePool>()
+        .add_system(particles::blast)
+        .add_system(particles::sparkle)
+        .add_system(particles::drift)
+        .add_system(camera::shake)
+        .add_system(camera::follow.after(camera::shake))
+        .add_system(hints::preview)
+        .add_system(hints::telegraph)
+        .add_startup_system(lighting::setup)
+        .add_system(lighting::shine)
+        .add_startup_system(sound::load)
+        .add_system(sound::play)
+        .add_system(sound::tune)
+        .add_startup_system(announcer::load)
+        .add_system(announcer::announce)
+        .add_system(achievements::toast)
+        .add_system_to_stage(CoreStage::PreUpdate, display::fullscreen.after(InputSystem))
+        .add_system(display::apply)
+        .add_system(display::scale_text)
+        .add_startup_system(display::set_icon)
+        .add_system(display::title)
+        .add_system(locale::apply)
+        .add_system(clock::fast_forward)
+        .init_resource::<Demo>()
+        .add_system_set(
+            SystemSet::on_enter(AppState::Menu)
+                .with_system(campaign::leave.before(menu::setup))
+                .with_system(daily::leave.before(menu::setup))
+                .with_system(tutorial::leave.before(menu::setup))
+                .with_system(menu::setup)
+                .with_system(reset_demo),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Menu)
+                // the key leaving the demo isn't seen by the menu
+                .with_system(
+                    schedule_demo
+                        .before(menu::navigate)
+                        .before(sound::click)
+                        .before(bevy::window::close_on_esc),
+                )
+                .with_system(menu::navigate)
+                .with_system(sound::click)
+                .with_system(restart_demo.after(schedule_demo))
+                .with_system(bevy::window::close_on_esc),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::Menu)
+                .with_system(despawn_with::<menu::Menu>)
+                .with_system(despawn_with::<Match>),
+        )
+        .add_system_set(SystemSet::on_enter(AppState::Options).with_system(options::setup))
+        .add_system_set(
+            SystemSet::on_update(AppState::Options)
+                .with_system(options::navigate)
+                .with_system(sound::click),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::Options).with_system(despawn_with::<options::Options>),
+        )
+        .add_system_set(SystemSet::on_enter(AppState::Editor).with_system(editor::setup))
+        .add_system_set(
+            SystemSet::on_update(AppState::Editor)
+                .with_system(editor::edit)
+                .with_system(editor::show.after(editor::edit)),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::Editor)
+                .with_system(despawn_with::<editor::Editor>)
+                .with_system(reset_background),
+        )
+        .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(setup))
+        .add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(save::resume)
+                .with_system(time_up.before(game_over))
+                .with_system(announcer::first_blood)
+                .with_system(announcer::hurry_up)
+                .with_system(game_over)
+                .with_system(announcer::winners.after(game_over))
+                .with_system(achievements::win.after(game_over))
+                .with_system(daily::record.after(game_over))
+                .with_system(tutorial::advance)
+                .with_system(tutorial::prompt.after(tutorial::advance))
+                .with_system(tutorial::retry)
+                .with_system(next_round.after(game_over))
+                .with_system(exit_stage)
+                .with_system(carry_over.after(exit_stage))
+                .with_system(input::buffer_bomb)
+                .with_system(input::pause)
+                .with_system(back_to_menu),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .label(Phase::Ui)
+                .after(Phase::Cleanup)
+                .with_system(update_scoreboard)
+                .with_system(update_hud)
+                .with_system(boss::update_health_bar)
+                .with_system(mutator::hide_bombs),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::InGame)
+                .with_system(despawn_with::<Match>)
+                .with_system(reset_background),
+        )
+        .add_system_set(SystemSet::on_enter(AppState::Paused).with_system(setup_paused))
+        .add_system_set(
+            SystemSet::on_update(AppState::Paused)
+                .with_system(input::resume)
+                .with_system(save::save),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::Paused).with_system(despawn_with::<PausedScreen>),
+        )
+        .add_system_set(SystemSet::on_enter(AppState::Reward).with_system(campaign::setup_reward))
+        .add_system_set(SystemSet::on_update(AppState::Reward).with_system(campaign::resume))
+        .add_system_set(
+            SystemSet::on_exit(AppState::Reward)
+                .with_system(despawn_with::<campaign::RewardScreen>),
+        )
+        .add_system_set(SystemSet::on_enter(AppState::Records).with_system(records::setup))
+        .add_system_set(SystemSet::on_update(AppState::Records).with_system(records::navigate))
+        .add_system_set(
+            SystemSet::on_exit(AppState::Records)
+                .with_system(despawn_with::<records::RecordsScreen>),
+        )
+        .add_system_set(SystemSet::on_enter(AppState::Mods).with_system(mods::setup))
+        .add_system_set(
+            SystemSet::on_update(AppState::Mods)
+                .with_system(mods::navigate)
+                .with_system(sound::click),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::Mods)
+                .with_system(despawn_with::<mods::ModsScreen>)
+                .with_system(mods::apply),
+        )
+        .add_plugin(capture::CapturePlugin);
+    if cli.hot_reload {
+        app.add_plugin(reload::HotReloadPlugin {
+            level: cli.level.clone(),
+            rules: cli.rules.clone(),
+            overrides: cli.overrides(),
+        });
+    }
+    #[cfg(feature = "debug-tools")]
+    app.add_plugin(debug::DebugPlugin);
+    app.run();
+}
+
+/// Plays against whoever else joins the same room, as browsers have no command line
+#[cfg(target_arch = "wasm32")]
+pub fn run() {
+    rollback::run(rollback::Config {
+        transport: transport::Transport::open(&transport::room()),
+        peer: None,
+        seat: None,
+        input_delay: rollback::DEFAULT_INPUT_DELAY,
+    });
+}
+
+/// How physics steps are driven
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stepping {
+    /// Physics steps follow the wall clock
+    Realtime,
+    /// A single physics step runs at every update
+    EveryUpdate,
+    /// Physics steps are run by someone else, see [`rollback`]
+    Manual,
+}
+
+/// Resources, events and systems simulating a match, shared by windowed and headless runs
+struct GamePlugin {
+    stepping: Stepping,
+}
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Scoreboard::default())
+            .init_resource::<Tick>()
+            .init_resource::<clock::Clock>()
+            .init_resource::<grid::Grid>()
+            .init_resource::<FirePool>()
+            .init_resource::<MatchRng>()
+            .init_resource::<Seed>()
+            .init_resource::<Rules>()
+            .init_resource::<Rounds>()
+            .init_resource::<Stage>()
+            .init_resource::<survival::Waves>()
+            .init_resource::<records::Run>()
+            .init_resource::<bot::BotRegistry>()
+            .init_resource::<bot::Roster>()
+            .init_resource::<power_ups::PowerUpRegistry>()
+            .init_resource::<script::Scripts>()
+            .init_resource::<achievements::Unlocked>()
+            .init_resource::<achievements::Progress>()
+            .init_resource::<locale::Locale>()
+            .init_resource::<input::Bindings>()
+            .add_event::<ExplosionEvent>()
+            .add_event::<Explosion2Event>()
+            .add_event::<MoveEvent>()
+            .add_event::<BombEvent>()
+            .add_event::<DetonateEvent>()
+            .add_event::<DashEvent>()
+            .add_event::<PickUpEvent>()
+            .add_event::<BrickDestroyedEvent>()
+            .add_event::<ChainEvent>()
+            .add_event::<DeathEvent>()
+            .add_event::<GameOverEvent>()
+            .add_event::<RoundEndedEvent>()
+            .add_event::<announcer::AnnouncementEvent>()
+            .add_event::<achievements::AchievementEvent>()
+            .add_system_to_stage(CoreStage::PostUpdate, grid::forget);
+
+        match self.stepping {
+            Stepping::Realtime => {
+                let [first, rest @ ..] = physics();
+                app.add_system_set(
+                    first.with_run_criteria(clock::run_steps.chain(unpaused).label(PhysicsStep)),
+                );
+                for set in rest {
+                    app.add_system_set(set.with_run_criteria(PhysicsStep));
+                }
+            }
+            Stepping::EveryUpdate => {
+                for set in physics() {
+                    app.add_system_set(set);
+                }
+            }
+            Stepping::Manual => {}
+        }
+    }
+}
+
+/// Phases of a physics step, each one reading what the previous ones wrote, so that every step
+/// plays out the same way given the same inputs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemLabel)]
+enum Phase {
+    /// The step starts: humans, local, remote or scripted, send the events of what they do
+    Input,
+    /// Bots send theirs, and survival waves come in
+    Ai,
+    /// Players, mobs and bombs move around the arena
+    Movement,
+    /// Bombs are placed, and the ones whose time came go off
+    Bombs,
+    /// Blasts hit whatever they reach, and players pick up what they touch
+    Combat,
+    /// Fires burn out, the dead fade away, and the outcome of the step is sent around
+    Cleanup,
+    /// The outcome of the steps of a frame is shown, once they're all over
+    Ui,
+}
+
+/// Run criteria every phase of physics steps follows, so that they all run as many steps
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, RunCriteriaLabel)]
+struct PhysicsStep;
+
+/// Systems making up a physics step, one set per phase
+fn physics() -> [SystemSet; 6] {
+    [
+        SystemSet::new()
+            .label(Phase::Input)
+            .with_system(advance_tick)
+            .with_system(grid::index_bricks)
+            .with_system(input::move_player)
+            .with_system(input::move_pad)
+            .with_system(net::drive_remote)
+            .with_system(script::attach.before(script::drive))
+            .with_system(script::drive),
+        SystemSet::new()
+            .label(Phase::Ai)
+            .after(Phase::Input)
+            .with_system(bot::drive)
+            .with_system(survival::survive),
+        SystemSet::new()
+            .label(Phase::Movement)
+            .after(Phase::Ai)
+            .with_system(move_event)
+            .with_system(dash.after(move_event))
+            .with_system(convey.after(move_event))
+            .with_system(warp.after(convey))
+            .with_system(bounce.after(warp))
+            .with_system(depenetrate.after(dash).after(bounce))
+            .with_system(mob::roam.after(move_event))
+            .with_system(boss::act.after(move_event)),
+        SystemSet::new()
+            .label(Phase::Bombs)
+            .after(Phase::Movement)
+            .with_system(place_bomb)
+            .with_system(detonate)
+            .with_system(grid::index_bombs)
+            .with_system(check_for_explosions.after(grid::index_bombs)),
+        SystemSet::new()
+            .label(Phase::Combat)
+            .after(Phase::Bombs)
+            .with_system(camera::rattle.before(explode))
+            .with_system(explode)
+            .with_system(explode2.after(explode))
+            .with_system(pick_up)
+            .with_system(apply_power_ups.after(pick_up))
+            .with_system(power_ups::wear_off.before(apply_power_ups))
+            .with_system(score_bricks.after(explode))
+            .with_system(crown::collect)
+            .with_system(hill::hold)
+            .with_system(records::clear.after(explode))
+            .with_system(mob::touch)
+            .with_system(mob::burn.after(explode))
+            .with_system(boss::hurt.after(explode))
+            .with_system(bury.after(explode).after(mob::touch))
+            .with_system(crown::drop_on_death.after(explode).after(mob::touch))
+            .with_system(achievements::track.after(explode).after(mob::touch))
+            .with_system(scripting::run_hooks.after(explode).after(mob::touch)),
+        SystemSet::new()
+            .label(Phase::Cleanup)
+            .after(Phase::Combat)
+            .with_system(fire)
+            .with_system(clear_rubble)
+            .with_system(decay)
+            .with_system(net::host_broadcast.after(fire)),
+    ]
+}
+
+#[derive(Component, Clone)]
+struct Player {
+    max_bombs: u8,
+    active_bombs: u8,
+    bomb_power: u8,
+    // whether the player's bombs can be set off on demand
+    detonator: bool,
+    /// Effects of power-ups picked up, until they wear off
+    boosts: Vec<power_ups::Boost>,
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Player {
+            max_bombs: 1,
+            active_bombs: 0,
+            bomb_power: 1,
+            detonator: false,
+            boosts: Vec::new(),
+        }
+    }
+}
+
+impl Player {
+    /// Power of the bombs the player places, as the rules make it
+    fn placed_power(&self, rules: &Rules) -> u8 {
+        if rules.mutated(mutator::Mutator::GiantBlasts) {
+            self.bomb_power + mutator::GIANT_BLAST_POWER
+        } else {
+            self.bomb_power
+        }
+    }
+}
+
+/// Where a player last moved towards, for actions aimed ahead of it
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Deref, DerefMut)]
+struct Facing(Direction);
+
+impl Default for Facing {
+    fn default() -> Self {
+        Facing(Direction::Down)
+    }
+}
+
+/// A player's dash, moving it quickly ahead once in a while
+#[derive(Component, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+struct Dash {
+    /// Steps left of the dash under way
+    steps: u32,
+    /// Steps left before the player can dash again
+    cooldown: u32,
+}
+
+/// Distance a player moves by every step
+#[derive(Component, Clone, Copy, Debug, Default, Deref, DerefMut)]
+struct Velocity(Vec2);
+
+/// Index of a player among those of the match, in the order they were spawned
+#[derive(Component)]
+struct PlayerIndex(usize);
+
+/// Marks a player driven by a human, with its index among humans
+#[derive(Component)]
+struct Active(usize);
+
+#[derive(Component)]
+struct Breakable;
+
+/// A tile of the floor, only there to tell cells apart
+#[derive(Component)]
+struct Floor;
+
+/// Marks every entity belonging to the match being played, to be despawned when it ends
+#[derive(Component)]
+struct Match;
+
+struct ExplosionEvent(Entity);
+
+struct Explosion2Event(Entity);
+
+/// One of the four directions a player can move towards
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    /// One unit towards the direction
+    fn vector(&self) -> Vec2 {
+        match self {
+            Direction::Up => Vec2::Y,
+            Direction::Down => Vec2::NEG_Y,
+            Direction::Left => Vec2::NEG_X,
+            Direction::Right => Vec2::X,
+        }
+    }
+}
+
+struct MoveEvent {
+    direction: Direction,
+    player: Entity,
+}
+
+struct BombEvent {
+    player: Entity,
+}
+
+struct DetonateEvent {
+    player: Entity,
+}
+
+struct DashEvent {
+    player: Entity,
+}
+
+/// A power-up picked up during this step, given to the player by `apply_power_ups`
+struct PickUpEvent {
+    player: Entity,
+    power_up: PowerUp,
+    position: Vec2,
+}
+
+/// A brick destroyed by a blast during this step, crumbling away where it stood
+struct BrickDestroyedEvent {
+    position: Vec2,
+    /// The bomb whose blast destroyed it, first among those reaching it
+    bomb: Entity,
+}
+
+/// A bomb set off by the blast of another during this step, going off at the next one
+struct ChainEvent {
+    bomb: Entity,
+    /// The bomb whose blast set it off
+    by: Entity,
+}
+
+/// A player killed during this step, despawned by `bury` once the step is over
+struct DeathEvent {
+    player: Entity,
+    /// The player whose bomb it was, `None` when killed by the environment: mobs, or bombs
+    /// whose owner was dead already
+    killer: Option<Entity>,
+}
+
+struct GameOverEvent {
+    /// The human left alone in the arena, if any
+    winner: Option<usize>,
+    /// The team left alone in the arena, if any
+    team: Option<usize>,
+}
+
+/// A round over and shown as such, once the dead are done dying
+struct RoundEndedEvent {
+    /// The human who won, or who won the whole match after its last round, if any
+    winner: Option<usize>,
+    /// The team who won, or who won the whole match after its last round, if any
+    team: Option<usize>,
+}
+
+/// The side a player fights on in team matches
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+struct Team(usize);
+
+impl Team {
+    /// Team of the player taking the given spawn point, so that humans, taking the first ones,
+    /// are split among teams too
+    fn of(index: usize) -> Self {
+        Team(index % TEAMS)
+    }
+}
+
+/// Marks the text shown while the match is paused
+#[derive(Component)]
+struct PausedScreen;
+
+#[derive(Component)]
+struct Brick;
+
+/// A conveyor belt, pushing players and bombs standing on it
+#[derive(Component)]
+struct Conveyor(Direction);
+
+/// A warp, moving players and bombs stepping on it to its partner
+#[derive(Component)]
+struct Warp {
+    destination: Vec2,
+}
+
+/// Steps left before a player or a bomb that went through a warp can do it again
+#[derive(Component)]
+struct Warped(u32);
+
+/// Marks a trampoline, tossing players stepping on it
+#[derive(Component)]
+struct Trampoline;
+
+/// A player tossed by a trampoline, flying over everything until landing
+#[derive(Component)]
+struct Flying {
+    from: Vec2,
+    to: Vec2,
+    // steps spent in the air so far
+    step: u32,
+}
+
+#[derive(Component)]
+struct Bomb {
+    /// The player who placed it, `None` once that player is dead
+    player: Option<Entity>,
+    timer: Timer,
+    power: u8,
+}
+
+#[derive(Component)]
+struct Fire(Timer);
+
+/// This resource holds fires burnt out, hidden away to be lit again instead of spawning new ones
+#[derive(Default)]
+struct FirePool(Vec<Entity>);
+
+/// What's left of a killed player for a moment, before being gone for good
+#[derive(Component)]
+struct Corpse {
+    timer: Timer,
+    // points scored for the kill once the corpse is gone
+    points: usize,
+}
+
+/// The door leading to the next stage, hidden under a brick
+#[derive(Component)]
+struct Exit;
+
+/// Left behind by some destroyed bricks, slows down players until it clears
+#[derive(Component)]
+struct Rubble(Timer);
+
+/// A bonus left behind by destroyed bricks, as the index of its kind in the
+/// [`PowerUpRegistry`](power_ups::PowerUpRegistry)
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct PowerUp(u8);
+
+// This resource counts the physics steps since the game started
+#[derive(Default)]
+struct Tick(u64);
+
+/// This resource drives every random outcome of a match, so that instances sharing the seed
+/// agree on them
+#[derive(Clone)]
+struct MatchRng(ChaCha8Rng);
+
+impl MatchRng {
+    fn seeded(seed: u64) -> Self {
+        MatchRng(ChaCha8Rng::seed_from_u64(seed))
+    }
+}
+
+impl Default for MatchRng {
+    fn default() -> Self {
+        MatchRng(ChaCha8Rng::from_entropy())
+    }
+}
+
+/// This resource holds the seed every match starts from, when a reproducible one is asked for
+#[derive(Default)]
+struct Seed(Option<u64>);
+
+/// This resource holds the rules matches are played by, set in the lobby of online matches
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+struct Rules {
+    /// Rounds making up a match
+    rounds: u32,
+    /// Seconds a round lasts before ending with no winner, if limited
+    time_limit: Option<u32>,
+    /// Chance for a destroyed brick to leave a power-up behind
+    power_up_chance: f64,
+    /// Chance for a free cell to start with a breakable brick
+    brick_density: f64,
+    /// Chance for a destroyed brick to leave rubble behind, slowing players down for a while
+    rubble_chance: f64,
+    /// Seconds bombs tick for before going off
+    fuse: f32,
+    /// What the match is about
+    mode: Mode,
+    /// Whether blasts kill teammates of the player who placed the bomb, in team matches
+    friendly_fire: bool,
+    /// Whether players are nudged towards the middle of rows and columns when moving,
+    /// as in the classic games, instead of moving freely
+    classic_movement: bool,
+    /// Whether players block each other's way, instead of walking through each other
+    solid_players: bool,
+    /// Whether players move diagonally when steered along two ways at once, instead of
+    /// only along the way they're already moving
+    diagonals: bool,
+    /// Crowns to hold to win in crowns mode, see [`crown`]
+    crowns: u32,
+    /// Seconds to hold the hill for to win in king of the hill mode, see [`hill`]
+    hill: u32,
+    /// Twists the match is played with, see [`mutator`]
+    mutators: Vec<mutator::Mutator>,
+    /// Roaming enemies spawned in every stage, see [`mob`]
+    mobs: Vec<mob::MobKind>,
+    /// Whether stages are played against the boss, see [`boss`]
+    boss: bool,
+    /// Columns and rows of the playable area of random arenas, walled in by pillars
+    size: (usize, usize),
+    /// Arena drawn by hand to play in, instead of a random one
+    level: Option<level::Level>,
+    /// Rules as they were before the level overrode some of them
+    #[serde(skip)]
+    base: Option<Box<Rules>>,
+    /// Script played on top of the mode, see [`scripting`]
+    script: Option<scripting::ModeScript>,
+}
+
+impl Rules {
+    /// Reads rules from disk, written as the `[rules]` of the settings
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load(path: &std::path::Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        toml::from_str(&content).map_err(|e| e.to_string())
+    }
+
+    /// Colors of the arena
+    fn theme(&self) -> theme::Theme {
+        self.level
+            .as_ref()
+            .map(|level| level.theme)
+            .unwrap_or_default()
+    }
+
+    /// Whether the match is played with the given mutator
+    fn mutated(&self, mutator: mutator::Mutator) -> bool {
+        self.mutators.contains(&mutator)
+    }
+}
+
+/// What a match is about
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Mode {
+    /// The last one standing wins the round
+    Battle,
+    /// The match goes on through stages, each one left through an exit door once every
+    /// opponent is gone
+    Stages,
+    /// Humans last as long as they can against endless waves of mobs, see [`survival`]
+    Survival,
+    /// The last team standing wins the round, see [`Team`]
+    Teams,
+    /// The first player collecting enough crowns wins the round, see [`crown`]
+    Crowns,
+    /// The first player holding the hill long enough wins the round, see [`hill`]
+    Hill,
+    /// Humans clear every breakable brick as fast as they can, see [`records`]
+    TimeAttack,
+}
+
+impl Mode {
+    const ALL: [Mode; 7] = [
+        Mode::Battle,
+        Mode::Stages,
+        Mode::Survival,
+        Mode::Teams,
+        Mode::Crowns,
+        Mode::Hill,
+        Mode::TimeAttack,
+    ];
+
+    /// Key of the name of the mode among the strings of locales
+    fn key(&self) -> &'static str {
+        match self {
+            Mode::Battle => "mode.battle",
+            Mode::Stages => "mode.stages",
+            Mode::Survival => "mode.survival",
+            Mode::Teams => "mode.teams",
+            Mode::Crowns => "mode.crowns",
+            Mode::Hill => "mode.hill",
+            Mode::TimeAttack => "mode.time_attack",
+        }
+    }
+
+    /// The mode after this one, going back to the first after the last
+    fn next(&self) -> Mode {
+        let index = Mode::ALL
+            .iter()
+            .position(|mode| mode == self)
+            .unwrap_or_default();
+        Mode::ALL[(index + 1) % Mode::ALL.len()]
+    }
+
+    /// Whether bots take part in the match
+    fn opponents(&self) -> bool {
+        !matches!(self, Mode::Survival | Mode::TimeAttack)
+    }
+
+    /// Whether the last player standing wins the round
+    fn last_standing(&self) -> bool {
+        matches!(self, Mode::Battle | Mode::Crowns | Mode::Hill)
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            rounds: 1,
+            time_limit: None,
+            power_up_chance: POWER_UP_CHANCE,
+            brick_density: BRICK_DENSITY,
+            rubble_chance: RUBBLE_CHANCE,
+            fuse: FUSE_SECS,
+            mode: Mode::Battle,
+            friendly_fire: true,
+            classic_movement: false,
+            solid_players: false,
+            diagonals: false,
+            crowns: CROWNS,
+            hill: HILL_SECS,
+            mutators: Vec::new(),
+            mobs: MOBS.to_vec(),
+            boss: false,
+            size: (COLS, ROWS),
+            level: None,
+            base: None,
+            script: None,
+        }
+    }
+}
+
+/// This resource tracks the rounds of the match being played
+#[derive(Default)]
+struct Rounds {
+    /// Rounds already over
+    played: u32,
+    /// Rounds won by every human
+    wins: Vec<u32>,
+    /// Rounds won by every team, in team matches
+    team_wins: [u32; TEAMS],
+    /// Physics step the current round started at
+    start: u64,
+    /// Whether the outcome of the current round was shown already
+    over: bool,
+    // winner and team of the current round once known, until the dead are done dying
+    outcome: Option<(Option<usize>, Option<usize>)>,
+    // physics step the next round starts at, once the current one is over
+    next: Option<u64>,
+}
+
+impl Rounds {
+    /// The human who won the most rounds, if nobody ties with them
+    fn leader(&self) -> Option<usize> {
+        best(&self.wins)
+    }
+
+    /// The team who won the most rounds, if no other team ties with it
+    fn team_leader(&self) -> Option<usize> {
+        best(&self.team_wins)
+    }
+}
+
+// Index of the highest number of wins, if it's above zero and unique
+fn best(wins: &[u32]) -> Option<usize> {
+    let best = *wins.iter().max()?;
+    let mut leaders = wins
+        .iter()
+        .enumerate()
+        .filter(|(_, wins)| **wins == best)
+        .map(|(index, _)| index);
+    match (leaders.next(), leaders.next()) {
+        (Some(index), None) if best > 0 => Some(index),
+        _ => None,
+    }
+}
+
+/// Marks the text showing the score
+#[derive(Component)]
+struct ScoreText;
+
+/// Marks the panel showing the stats of the human with the given index
+#[derive(Component)]
+struct HumanPanel(usize);
+
+/// This resource schedules the demo match played behind the title once the menu is left idle
+#[derive(Default)]
+struct Demo {
+    /// Seconds the menu has been left idle for
+    idle: f32,
+    /// Whether the demo match is being played
+    playing: bool,
+}
+
+/// Marks the title shown over the demo match
+#[derive(Component)]
+struct Title;
+
+// This resource tracks the game's score
+#[derive(Default)]
+struct Scoreboard {
+    score: usize,
+}
+
+/// This resource tracks the stages cleared, when the rules ask for stages
+#[derive(Default)]
+struct Stage {
+    cleared: u32,
+    /// Humans as they left the last stage, along with their index, to carry power-ups over
+    carried: Vec<(usize, Player)>,
+}
+
+// This bundle is a collection of the components that define a "wall" in our game
+#[derive(Bundle)]
+struct WallBundle {
+    // You can nest bundles inside of other bundles like this
+    // Allowing you to compose their functionality
+    #[bundle]
+    sprite_bundle: SpriteBundle,
+}
+
+/// Which side of the arena is this wall located on?
+enum WallLocation {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl WallLocation {
+    fn position(&self) -> Vec2 {
+        match self {
+            WallLocation::Left => Vec2::new(LEFT_WALL - WALL_THICKNESS / 2., 0.),
+            WallLocation::Right => Vec2::new(RIGHT_WALL + WALL_THICKNESS / 2., 0.),
+            WallLocation::Bottom => Vec2::new(0., BOTTOM_WALL - WALL_THICKNESS / 2.),
+            WallLocation::Top => Vec2::new(0., TOP_WALL + WALL_THICKNESS / 2.),
+        }
+    }
+
+    fn size(&self) -> Vec2 {
+        let arena_height = TOP_WALL - BOTTOM_WALL;
+        let arena_width = RIGHT_WALL - LEFT_WALL;
+        // Make sure we haven't messed up our constants
+        assert!(arena_height > 0.0);
+        assert!(arena_width > 0.0);
+
+        match self {
+            WallLocation::Left | WallLocation::Right => {
+                Vec2::new(WALL_THICKNESS, arena_height + WALL_THICKNESS)
+            }
+            WallLocation::Bottom | WallLocation::Top => {
+                Vec2::new(arena_width + WALL_THICKNESS, WALL_THICKNESS)
+            }
+        }
+    }
+}
+
+impl WallBundle {
+    // This "builder method" allows us to reuse logic across our wall entities,
+    // making our code easier to read and less prone to bugs when we change the logic
+    fn new(location: WallLocation, color: Color) -> WallBundle {
+        WallBundle {
+            sprite_bundle: SpriteBundle {
+                transform: Transform {
+                    // We need to convert our Vec2 into a Vec3, by giving it a z-coordinate
+                    // This is used to determine the order of our sprites
+                    translation: location.position().extend(BRICK_Z),
+                    // The z-scale of 2D objects must always be 1.0,
+                    // or their ordering will be affected in surprising ways.
+                    // See https://github.com/bevyengine/bevy/issues/4149
+                    scale: location.size().extend(1.0),
+                    ..default()
+                },
+                sprite: Sprite { color, ..default() },
+                ..default()
+            },
+        }
+    }
+}
+
+// Spawn the camera, shared by every screen
+fn setup_camera(mut commands: Commands) {
+    commands
+        .spawn_bundle(Camera2dBundle::default())
+        .insert(camera::Shake::default());
+}
+
+// Add the game's entities to our world
+#[allow(clippy::too_many_arguments)]
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    locale: Res<locale::Locale>,
+    bot_registry: Res<bot::BotRegistry>,
+    roster: Res<bot::Roster>,
+    bindings: Res<input::Bindings>,
+    seed: Res<Seed>,
+    tick: Res<Tick>,
+    rules: Res<Rules>,
+    mut rng: ResMut<MatchRng>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut rounds: ResMut<Rounds>,
+    mut stage: ResMut<Stage>,
+    mut progress: ResMut<achievements::Progress>,
+    settings: Res<settings::Settings>,
+    campaign: Option<Res<campaign::Campaign>>,
+) {
+    *scoreboard = Scoreboard::default();
+    *progress = achievements::Progress::default();
+    // the campaign may pick up from a later stage
+    *stage = Stage {
+        cleared: campaign.map_or(0, |campaign| campaign.stage() as u32),
+        ..default()
+    };
+    *rounds = Rounds {
+        wins: vec![0; roster.total_humans()],
+        start: tick.0,
+        ..default()
+    };
+    if let Some(seed) = seed.0 {
+        *rng = MatchRng::seeded(seed);
+    }
+    commands.insert_resource(ClearColor(rules.theme().palette().background));
+    spawn_arena(
+        &mut commands,
+        &bot_registry,
+        &roster,
+        &bindings,
+        &rules,
+        &mut rng,
+    );
+
+    // Scoreboard
+    commands
+        .spawn_bundle(
+            TextBundle::from_sections([
+                TextSection::new(
+                    locale.text("hud.score"),
+                    TextStyle {
+                        font: asset_server.load(locale.font()),
+                        font_size: SCOREBOARD_FONT_SIZE,
+                        color: TEXT_COLOR,
+                    },
+                ),
+                TextSection::from_style(TextStyle {
+                    font: asset_server.load(locale.mono_font()),
+                    font_size: SCOREBOARD_FONT_SIZE,
+                    color: SCORE_COLOR,
+                }),
+            ])
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: SCOREBOARD_TEXT_PADDING,
+                    left: SCOREBOARD_TEXT_PADDING,
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(ScoreText)
+        .insert(Match);
+
+    // every human gets a panel in the corner of the screen closest to its spawn point
+    for index in 0..roster.total_humans() {
+        commands
+            .spawn_bundle(
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load(locale.mono_font()),
+                        font_size: HUD_FONT_SIZE,
+                        color: settings.color_vision.adjust(roster.color(index)),
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    position: hud_corner(index),
+                    ..default()
+                }),
+            )
+            .insert(HumanPanel(index))
+            .insert(Match);
+    }
+}
+
+/// Screen corner of the panel of the given human, following the order of spawn points
+fn hud_corner(index: usize) -> UiRect<Val> {
+    // the top left corner is shared with the score
+    let below_score = Val::Px(SCOREBOARD_FONT_SIZE + 10.0);
+    match index {
+        0 => UiRect {
+            top: below_score,
+            left: SCOREBOARD_TEXT_PADDING,
+            ..default()
+        },
+        1 => UiRect {
+            top: SCOREBOARD_TEXT_PADDING,
+            right: SCOREBOARD_TEXT_PADDING,
+            ..default()
+        },
+        2 => UiRect {
+            bottom: SCOREBOARD_TEXT_PADDING,
+            right: SCOREBOARD_TEXT_PADDING,
+            ..default()
+        },
+        _ => UiRect {
+            bottom: SCOREBOARD_TEXT_PADDING,
+            left: SCOREBOARD_TEXT_PADDING,
+            ..default()
+        },
+    }
+}
+
+// Wait for the menu to be left idle again before playing the demo match
+fn reset_demo(mut demo: ResMut<Demo>) {
+    *demo = Demo::default();
+}
+
+// Play a bots-only match behind the title once the menu is left idle for a while, going back to
+// the menu as soon as any key or button is pressed
+#[allow(clippy::too_many_arguments)]
+fn schedule_demo(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    locale: Res<locale::Locale>,
+    bot_registry: Res<bot::BotRegistry>,
+    bindings: Res<input::Bindings>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut gamepad_input: ResMut<Input<GamepadButton>>,
+    mut rng: ResMut<MatchRng>,
+    mut demo: ResMut<Demo>,
+    mut menu_query: Query<(&mut Visibility, Option<&Children>), With<menu::Menu>>,
+    mut visibility_query: Query<&mut Visibility, Without<menu::Menu>>,
+    match_query: Query<Entity, With<Match>>,
+) {
+    let pressed = keyboard_input.get_just_pressed().next().is_some()
+        || gamepad_input.get_just_pressed().next().is_some();
+    if pressed {
+        demo.idle = 0.;
+    } else {
+        demo.idle += time.delta_seconds();
+    }
+    let playing = if demo.playing {
+        !pressed
+    } else {
+        demo.idle >= DEMO_IDLE_SECS
+    };
+    if playing == demo.playing {
+        return;
+    }
+    demo.playing = playing;
+
+    // the menu isn't there while the demo is, UI nodes don't pass their visibility on
+    for (mut visibility, children) in &mut menu_query {
+        visibility.is_visible = !playing;
+        for child in children.into_iter().flatten() {
+            if let Ok(mut visibility) = visibility_query.get_mut(*child) {
+                visibility.is_visible = !playing;
+            }
+        }
+    }
+    if !playing {
+        keyboard_input.clear();
+        gamepad_input.clear();
+        for entity in &match_query {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    spawn_arena(
+        &mut commands,
+        &bot_registry,
+        &bot::Roster::demo(),
+        &bindings,
+        &Rules::default(),
+        &mut rng,
+    );
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                locale.text("title.name"),
+                TextStyle {
+                    font: asset_server.load(locale.font()),
+                    font_size: TITLE_FONT_SIZE,
+                    color: SCORE_COLOR,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(25.0),
+                    top: Val::Percent(10.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(Title)
+        .insert(Match);
+}
+
+// Start over the demo match once there's a winner
+#[allow(clippy::too_many_arguments)]
+fn restart_demo(
+    mut commands: Commands,
+    demo: Res<Demo>,
+    bot_registry: Res<bot::BotRegistry>,
+    bindings: Res<input::Bindings>,
+    mut rng: ResMut<MatchRng>,
+    title_query: Query<(), With<Title>>,
+    player_query: Query<(), With<Player>>,
+    match_query: Query<Entity, (With<Match>, Without<Title>)>,
+) {
+    // the title is there once the demo match is
+    if !demo.playing || title_query.is_empty() || player_query.iter().count() > 1 {
+        return;
+    }
+
+    for entity in &match_query {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_arena(
+        &mut commands,
+        &bot_registry,
+        &bot::Roster::demo(),
+        &bindings,
+        &Rules::default(),
+        &mut rng,
+    );
+}
+
+fn back_to_menu(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut state: ResMut<State<AppState>>,
+    host: Option<Res<net::Host>>,
+) {
+    if keyboard_input.clear_just_pressed(KeyCode::Escape) {
+        let menu = if host.is_some() {
+            AppState::Lobby
+        } else {
+            AppState::Menu
+        };
+        // fails only if a transition is already queued
+        let _ = state.set(menu);
+    }
+}
+
+// Show the match is paused
+fn setup_paused(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    locale: Res<locale::Locale>,
+) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(PausedScreen)
+        .with_children(|parent| {
+            let font = asset_server.load(locale.font());
+            parent.spawn_bundle(TextBundle::from_section(
+                locale.text("pause.paused"),
+                TextStyle {
+                    font: font.clone(),
+                    font_size: PAUSED_FONT_SIZE,
+                    color: TEXT_COLOR,
+                },
+            ));
+            parent
+                .spawn_bundle(TextBundle::from_section(
+                    locale.text("pause.save"),
+                    TextStyle {
+                        font,
+                        font_size: HUD_FONT_SIZE,
+                        color: TEXT_COLOR,
+                    },
+                ))
+                .insert(save::SaveLine);
+        });
+}
+
+// Physics steps are skipped while the match is paused, or between stages
+fn unpaused(In(input): In<ShouldRun>, state: Option<Res<State<AppState>>>) -> ShouldRun {
+    match state {
+        Some(state) if matches!(state.current(), AppState::Paused | AppState::Reward) => {
+            ShouldRun::No
+        }
+        _ => input,
+    }
+}
+
+// levels and the editor may have painted the background in colors of their own
+fn reset_background(mut commands: Commands) {
+    commands.insert_resource(ClearColor(theme::Theme::default().palette().background));
+}
+
+fn despawn_with<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// Add players, walls and bricks
+fn spawn_arena(
+    commands: &mut Commands,
+    bot_registry: &bot::BotRegistry,
+    roster: &bot::Roster,
+    bindings: &input::Bindings,
+    rules: &Rules,
+    rng: &mut MatchRng,
+) {
+    let players = if rules.mode.opponents() {
+        roster.players()
+    } else {
+        roster.total_humans()
+    };
+    let (spawn_points, layout) = match &rules.level {
+        Some(level) => level.arena(players),
+        None => {
+            let spawn_points = spawn_points(players, rules.size);
+            let layout =
+                arena::Layout::random(&mut rng.0, rules.brick_density, rules.size, &spawn_points);
+            (spawn_points, layout)
+        }
+    };
+    let palette = rules.theme().palette();
+
+    // humans take the first spawn points, local ones before remote ones, bots the following ones
+    let humans = roster.total_humans();
+    for (index, (col, row)) in spawn_points.iter().copied().enumerate() {
+        let color = if rules.mode == Mode::Teams {
+            TEAM_COLORS[Team::of(index).0]
+        } else if index < humans {
+            roster.color(index)
+        } else {
+            OPPONENT_COLOR
+        };
+        let mut player = spawn_player(commands, cell_position(col, row), color);
+        player.insert(PlayerIndex(index));
+        match rules.mode {
+            Mode::Teams => {
+                player.insert(Team::of(index));
+            }
+            Mode::Crowns => {
+                player.insert(crown::Crowns::default());
+            }
+            Mode::Hill => {
+                player.insert(hill::Hold::default());
+            }
+            _ => {}
+        }
+        if rules.script.is_some() {
+            player.insert(scripting::Points::default());
+        }
+        if index < humans {
+            player
+                .insert(Active(index))
+                .insert(input::BombBuffer::default())
+                .insert(Name::new(format!("player #{}", index + 1)));
+            if index >= roster.humans {
+                player.insert(net::Remote(index - roster.humans));
+            } else if let Some(controls) = bindings.controls(index) {
+                player.insert(controls);
+            } else {
+                player.insert(bindings.pad(index));
+            }
+        } else {
+            let bot = index - humans;
+            player
+                .insert(roster.controller(bot_registry, bot))
+                .insert(Name::new(format!("{} #{}", roster.bot_name(bot), bot + 1)));
+        }
+    }
+
+    // Walls
+    commands
+        .spawn_bundle(WallBundle::new(WallLocation::Left, palette.wall))
+        .insert(Match);
+    commands
+        .spawn_bundle(WallBundle::new(WallLocation::Right, palette.wall))
+        .insert(Match);
+    commands
+        .spawn_bundle(WallBundle::new(WallLocation::Bottom, palette.wall))
+        .insert(Match);
+    commands
+        .spawn_bundle(WallBundle::new(WallLocation::Top, palette.wall))
+        .insert(Match);
+
+    // Floor
+    for ((col, row), _) in layout.cells() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: palette.floor(col, row),
+                    ..default()
+                },
+                transform: Transform {
+                    translation: cell_position(col, row).extend(TILE_Z),
+                    scale: BRICK_SIZE.extend(1.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(Floor)
+            .insert(Match);
+    }
+
+    let mut warps = Vec::new();
+    for ((col, row), tile) in layout.cells() {
+        let position = cell_position(col, row);
+        match tile {
+            arena::Tile::Pillar => spawn_pillar(commands, position, palette.wall),
+            arena::Tile::Breakable => spawn_breakable(commands, position, palette.brick),
+            arena::Tile::Conveyor(direction) => {
+                spawn_conveyor(commands, position, direction, &palette)
+            }
+            arena::Tile::Warp(pair) => warps.push((pair, position)),
+            arena::Tile::Trampoline => spawn_trampoline(commands, position, &palette),
+            arena::Tile::Empty => {}
+        }
+    }
+
+    // the exit hides under a brick, or lies in the open if there are none
+    if rules.mode == Mode::Stages {
+        let mut cells = layout
+            .cells()
+            .filter(|(_, tile)| *tile == arena::Tile::Breakable)
+            .map(|(cell, _)| cell)
+            .collect::<Vec<_>>();
+        if cells.is_empty() {
+            cells = layout
+                .cells()
+                .filter(|(cell, tile)| *tile == arena::Tile::Empty && !spawn_points.contains(cell))
+                .map(|(cell, _)| cell)
+                .collect();
+        }
+        if !cells.is_empty() {
+            let (col, row) = cells[rng.0.gen_range(0..cells.len())];
+            spawn_exit(commands, cell_position(col, row));
+        }
+        mob::spawn_mobs(commands, &layout, &spawn_points, &rules.mobs, rng);
+        if rules.boss {
+            boss::spawn_boss(commands);
+        }
+    }
+
+    if rules.mode == Mode::Hill {
+        hill::spawn_hill(commands, rules.size);
+    }
+
+    // every warp leads to the other one of its pair
+    for &(pair, position) in &warps {
+        if let Some(&(_, destination)) = warps
+            .iter()
+            .find(|(other, other_position)| *other == pair && *other_position != position)
+        {
+            spawn_warp(commands, position, destination, &palette);
+        }
+    }
+}
+
+fn spawn_player<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    position: Vec2,
+    color: Color,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut player = commands.spawn();
+    player
+        .insert(Player::default())
+        .insert(Facing::default())
+        .insert(Velocity::default())
+        .insert(Dash::default())
+        .insert_bundle(SpriteBundle {
+            transform: Transform {
+                translation: position.extend(PLAYER_Z),
+                scale: PLAYER_SIZE.extend(1.0),
+                ..default()
+            },
+            sprite: Sprite { color, ..default() },
+            ..default()
+        })
+        .insert(Match);
+    player
+}
+
+fn spawn_pillar(commands: &mut Commands, position: Vec2, color: Color) {
+    commands
+        .spawn()
+        .insert(Brick)
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite { color, ..default() },
+            transform: Transform {
+                translation: position.extend(BRICK_Z),
+                scale: Vec3::new(BRICK_SIZE.x, BRICK_SIZE.y, 1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match);
+}
+
+fn spawn_breakable(commands: &mut Commands, position: Vec2, color: Color) {
+    commands
+        .spawn()
+        .insert(Brick)
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite { color, ..default() },
+            transform: Transform {
+                translation: position.extend(BRICK_Z),
+                scale: Vec3::new(BRICK_SIZE.x, BRICK_SIZE.y, 1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Breakable)
+        .insert(Match);
+}
+
+fn spawn_conveyor(
+    commands: &mut Commands,
+    position: Vec2,
+    direction: Direction,
+    palette: &theme::Palette,
+) {
+    // below everything else
+    commands
+        .spawn()
+        .insert(Conveyor(direction))
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: palette.conveyor,
+                ..default()
+            },
+            transform: Transform {
+                translation: position.extend(FLOOR_Z),
+                scale: BRICK_SIZE.extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match);
+    // a notch on the side the belt moves towards
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: palette.wall,
+                ..default()
+            },
+            transform: Transform {
+                translation: (position + direction.vector() * BRICK_SIZE / 3.).extend(MARK_Z),
+                scale: (BRICK_SIZE / 5.).extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match);
+}
+
+fn spawn_warp(
+    commands: &mut Commands,
+    position: Vec2,
+    destination: Vec2,
+    palette: &theme::Palette,
+) {
+    commands
+        .spawn()
+        .insert(Warp { destination })
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: palette.warp,
+                ..default()
+            },
+            transform: Transform {
+                translation: position.extend(FLOOR_Z),
+                scale: (BRICK_SIZE * 0.8).extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match);
+}
+
+fn spawn_trampoline(commands: &mut Commands, position: Vec2, palette: &theme::Palette) {
+    commands
+        .spawn()
+        .insert(Trampoline)
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: palette.trampoline,
+                ..default()
+            },
+            transform: Transform {
+                translation: position.extend(FLOOR_Z),
+                scale: (BRICK_SIZE * 0.6).extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match);
+}
+
+fn spawn_bomb(commands: &mut Commands, bomb: Bomb, translation: Vec3) -> Entity {
+    commands
+        .spawn()
+        .insert(bomb)
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: BOMB_COLOR,
+                ..default()
+            },
+            transform: Transform {
+                translation,
+                scale: Vec3::new(BOMB_SIZE.x, BOMB_SIZE.y, 1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match)
+        .id()
+}
+
+fn spawn_fire(commands: &mut Commands, fire: Fire, translation: Vec3, scale: Vec3) {
+    commands.add(LightFire {
+        fire,
+        translation,
+        scale,
+    });
+}
+
+/// Lights a fire, reusing one burnt out if any, so that chains of blasts don't keep spawning
+/// and despawning entities
+struct LightFire {
+    fire: Fire,
+    translation: Vec3,
+    scale: Vec3,
+}
+
+impl Command for LightFire {
+    fn write(self, world: &mut World) {
+        let transform = Transform {
+            // wherever the blast comes from
+            translation: self.translation.truncate().extend(FIRE_Z),
+            scale: self.scale,
+            ..default()
+        };
+        // burnt out fires may be gone already, despawned along with the match they burnt in
+        while let Some(entity) = world.resource_mut::<FirePool>().0.pop() {
+            if let Some(mut fire) = world.get_entity_mut(entity) {
+                fire.insert(self.fire)
+                    .insert(transform)
+                    .insert(Visibility { is_visible: true });
+                return;
+            }
+        }
+        world
+            .spawn()
+            .insert(self.fire)
+            .insert_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: FIRE_COLOR,
+                    ..default()
+                },
+                transform,
+                ..default()
+            })
+            .insert(Match);
+    }
+}
+
+/// Puts a fire out, hiding it away to be lit again
+struct PutOutFire(Entity);
+
+impl Command for PutOutFire {
+    fn write(self, world: &mut World) {
+        if let Some(mut fire) = world.get_entity_mut(self.0) {
+            fire.remove::<Fire>();
+            fire.insert(Visibility { is_visible: false });
+            world.resource_mut::<FirePool>().0.push(self.0);
+        }
+    }
+}
+
+/// Way a fire of the given scale spreads along, and how many cells it reaches on either side of
+/// its middle: fires are a cell wide, and as long as their blast along either rows or columns
+fn fire_reach(scale: Vec3) -> (Vec2, i32) {
+    let cells = scale.truncate() / BRICK_SIZE;
+    let (axis, length) = if cells.x >= cells.y {
+        (Vec2::X, cells.x)
+    } else {
+        (Vec2::Y, cells.y)
+    };
+    (axis, length.round() as i32 / 2)
+}
+
+fn spawn_exit(commands: &mut Commands, position: Vec2) {
+    commands
+        .spawn()
+        .insert(Exit)
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: EXIT_COLOR,
+                ..default()
+            },
+            transform: Transform {
+                // below the brick hiding it
+                translation: position.extend(MARK_Z),
+                scale: (BRICK_SIZE * 0.8).extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match);
+}
+
+fn spawn_corpse(commands: &mut Commands, corpse: Corpse, translation: Vec3, color: Color) {
+    commands
+        .spawn()
+        .insert(corpse)
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite { color, ..default() },
+            transform: Transform {
+                translation,
+                scale: PLAYER_SIZE.extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match);
+}
+
+fn spawn_rubble(commands: &mut Commands, rubble: Rubble, translation: Vec3) {
+    commands
+        .spawn()
+        .insert(rubble)
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: RUBBLE_COLOR,
+                ..default()
+            },
+            transform: Transform {
+                // below players and power-ups
+                translation: translation.truncate().extend(MARK_Z),
+                scale: (BRICK_SIZE * 0.9).extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match);
+}
+
+fn spawn_power_up(
+    commands: &mut Commands,
+    registry: &power_ups::PowerUpRegistry,
+    power_up: PowerUp,
+    translation: Vec3,
+) {
+    commands
+        .spawn()
+        .insert(power_up)
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: registry.color(power_up),
+                ..default()
+            },
+            transform: Transform {
+                translation: translation.truncate().extend(PICKUP_Z),
+                scale: POWER_UP_SIZE.extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match);
+}
+
+/// Center of the given cell, counting columns from the left and rows from the bottom
+fn cell_position(col: usize, row: usize) -> Vec2 {
+    // In Bevy, the `translation` of an entity describes the center point,
+    // not its bottom-left corner
+    Vec2::new(
+        LEFT_WALL + BRICK_SIZE.x / 2. + (col as f32) * BRICK_SIZE.x,
+        BOTTOM_WALL + BRICK_SIZE.y / 2. + (row as f32) * BRICK_SIZE.y,
+    )
+}
+
+/// Starting cells for the given number of players: corners first, then edges' middle points
+fn spawn_points(count: usize, size: (usize, usize)) -> Vec<(usize, usize)> {
+    let (cols, rows) = arena::playable(size);
+    let (left, right) = (cols.start, cols.end - 1);
+    let (bottom, top) = (rows.start, rows.end - 1);
+    // edges lay on even rows and columns, so they never host a pillar
+    [
+        (left, top),
+        (right, top),
+        (right, bottom),
+        (left, bottom),
+        (COLS / 2, top),
+        (COLS / 2, bottom),
+        (left, ROWS / 2),
+        (right, ROWS / 2),
+    ]
+    .into_iter()
+    .take(count)
+    .collect()
+}
+
+#[allow(clippy::type_complexity)]
+fn move_event(
+    rules: Res<Rules>,
+    grid: Res<grid::Grid>,
+    mut event_reader: EventReader<MoveEvent>,
+    collision_query: Query<&Transform, (With<Brick>, Without<Player>)>,
+    rubble_query: Query<&Transform, (With<Rubble>, Without<Player>)>,
+    mut query: Query<
+        (Entity, &mut Facing, &mut Velocity, &Dash, &mut Transform),
+        (With<Player>, Without<Flying>),
+    >,
+) {
+    // directions every player is steered towards during this step, added up, and the last one
+    let mut steering = HashMap::new();
+    for MoveEvent { direction, player } in event_reader.iter() {
+        let (pressed, last) = steering.entry(*player).or_insert((Vec2::ZERO, *direction));
+        *pressed += direction.vector();
+        *last = *direction;
+    }
+    // players block each other from where they stood when the step started
+    let positions = if rules.solid_players {
+        query
+            .iter()
+            .map(|(entity, .., transform)| (entity, *transform))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    for (entity, mut facing, mut velocity, dash, mut player_transform) in &mut query {
+        // dashing players go straight ahead, see `dash`
+        if dash.steps > 0 {
+            continue;
+        }
+        let mut max_speed = Vec2::new(MOVE_SPEED_X, MOVE_SPEED_Y);
+        if rules.mutated(mutator::Mutator::DoubleSpeed) {
+            max_speed *= mutator::SPEED_FACTOR;
+        }
+        let center = cell_center(player_transform.translation);
+        if rubble_query
+            .iter()
+            .any(|rubble| rubble.translation.truncate().distance(center) < 1.)
+        {
+            max_speed *= RUBBLE_SLOWDOWN;
+        }
+        let steer = match steering.get(&entity) {
+            Some(&(pressed, last)) => {
+                let steer = resolve(pressed, velocity.0, rules.diagonals);
+                facing.0 = match (steer.x, steer.y) {
+                    (x, y) if x > 0. && y == 0. => Direction::Right,
+                    (x, y) if x < 0. && y == 0. => Direction::Left,
+                    (x, y) if x == 0. && y > 0. => Direction::Up,
+                    (x, y) if x == 0. && y < 0. => Direction::Down,
+                    _ => last,
+                };
+                steer
+            }
+            None => Vec2::ZERO,
+        };
+        velocity.0 = accelerate(velocity.0, steer, max_speed);
+        if velocity.0 == Vec2::ZERO {
+            continue;
+        }
+
+        // players already overlapping the one moving don't block it, so that they can part
+        let blockers = positions
+            .iter()
+            .filter(|(other, _)| *other != entity)
+            .map(|(_, transform)| *transform)
+            .filter(|other| {
+                collide(
+                    player_transform.translation,
+                    player_transform.scale.truncate(),
+                    other.translation,
+                    other.scale.truncate(),
+                )
+                .is_none()
+            })
+            .collect::<Vec<_>>();
+        // players are smaller than cells, and move less than one at a time
+        let bricks = grid
+            .bricks
+            .around(grid::cell(player_transform.translation))
+            .filter_map(|brick| collision_query.get(brick).ok())
+            .collect::<Vec<_>>();
+        let obstacles = || bricks.iter().copied().chain(&blockers);
+
+        let before = player_transform.translation;
+        let mut offset = velocity.0;
+        if rules.classic_movement {
+            // part of the way goes into lining up with the nearest row or column, so that
+            // corners don't snag players turning a bit early or late
+            let across = Vec2::new(offset.y, offset.x).abs();
+            let misalignment = (center - player_transform.translation.truncate()) * across.signum();
+            let nudge = misalignment.clamp(-across, across);
+            player_transform.translation = step(
+                before,
+                player_transform.scale.truncate(),
+                nudge,
+                obstacles(),
+            );
+            let nudged = player_transform.translation.distance(before);
+            offset *= 1. - nudged / offset.length();
+        }
+        player_transform.translation = step(
+            player_transform.translation,
+            player_transform.scale.truncate(),
+            offset,
+            obstacles(),
+        );
+        // running into something stops the player along that axis
+        if player_transform.translation.x == before.x {
+            velocity.x = 0.;
+        }
+        if player_transform.translation.y == before.y {
+            velocity.y = 0.;
+        }
+    }
+}
+
+// Start the dashes asked for, and carry dashing players along
+#[allow(clippy::type_complexity)]
+fn dash(
+    grid: Res<grid::Grid>,
+    mut event_reader: EventReader<DashEvent>,
+    collision_query: Query<&Transform, (With<Brick>, Without<Player>)>,
+    mut query: Query<
+        (&mut Dash, &mut Velocity, &Facing, &mut Transform),
+        (With<Player>, Without<Flying>),
+    >,
+) {
+    for DashEvent { player } in event_reader.iter() {
+        if let Ok((mut dash, ..)) = query.get_mut(*player) {
+            if dash.cooldown == 0 {
+                *dash = Dash {
+                    steps: DASH_STEPS,
+                    cooldown: DASH_COOLDOWN_STEPS,
+                };
+            }
+        }
+    }
+
+    for (mut dash, mut velocity, facing, mut transform) in &mut query {
+        dash.cooldown = dash.cooldown.saturating_sub(1);
+        if dash.steps == 0 {
+            continue;
+        }
+        dash.steps -= 1;
+        let before = transform.translation;
+        let bricks = grid
+            .bricks
+            .around(grid::cell(before))
+            .filter_map(|brick| collision_query.get(brick).ok());
+        transform.translation = step(
+            before,
+            transform.scale.truncate(),
+            facing.vector() * DASH_SPEED,
+            bricks,
+        );
+        // obstacles cut dashes short, and players come out of them standing still
+        if transform.translation == before {
+            dash.steps = 0;
+        }
+        velocity.0 = Vec2::ZERO;
+    }
+}
+
+/// Steering out of the directions pressed during a step, added up: one of them at a time,
+/// or both of them slowed down to the usual speed when moving diagonally
+fn resolve(pressed: Vec2, velocity: Vec2, diagonals: bool) -> Vec2 {
+    // the same direction pressed twice, as on a keyboard and a pad, counts once
+    let pressed = pressed.clamp(Vec2::NEG_ONE, Vec2::ONE);
+    if pressed.x == 0. || pressed.y == 0. {
+        pressed
+    } else if diagonals {
+        pressed.normalize()
+    } else if velocity.y.abs() > velocity.x.abs() {
+        // keep going along the way the player moves, turning once it's blocked
+        Vec2::new(0., pressed.y)
+    } else {
+        Vec2::new(pressed.x, 0.)
+    }
+}
+
+/// Velocity after a step steered towards the given direction: speeding up along the axes it
+/// points along, up to the given top speed slowed down by how much it points along others,
+/// and slowing down along the others
+fn accelerate(velocity: Vec2, steering: Vec2, max_speed: Vec2) -> Vec2 {
+    let axis = |speed: f32, steering: f32, max_speed: f32| {
+        if steering != 0. {
+            let top = max_speed * steering.abs();
+            (speed + steering.signum() * max_speed * ACCELERATION).clamp(-top, top)
+        } else {
+            speed.signum() * (speed.abs() - max_speed * DECELERATION).max(0.)
+        }
+    };
+    Vec2::new(
+        axis(velocity.x, steering.x, max_speed.x),
+        axis(velocity.y, steering.y, max_speed.y),
+    )
+}
+
+/// Where something of the given size ends up moving by the given offset, stopping at walls
+/// and bricks
+fn step<'a>(
+    translation: Vec3,
+    size: Vec2,
+    offset: Vec2,
+    bricks: impl IntoIterator<Item = &'a Transform>,
+) -> Vec3 {
+    let mut new_translation = translation + offset.extend(0.);
+    new_translation.x = new_translation.x.clamp(
+        LEFT_WALL + BRICK_SIZE.x / 2.,
+        RIGHT_WALL - BRICK_SIZE.x / 2.,
+    );
+    new_translation.y = new_translation.y.clamp(
+        BOTTOM_WALL + BRICK_SIZE.y / 2.,
+        TOP_WALL - BRICK_SIZE.y / 2.,
+    );
+
+    let (mut collide_up, mut collide_down, mut collide_right, mut collide_left) =
+        (false, false, false, false);
+    for brick_transform in bricks {
+        if let Some(collision) = collide(
+            new_translation,
+            size,
+            brick_transform.translation,
+            brick_transform.scale.truncate(),
+        ) {
+            match collision {
+                Collision::Top => collide_down = true,
+                Collision::Bottom => collide_up = true,
+                Collision::Left => collide_right = true,
+                Collision::Right => collide_left = true,
+                _ => {}
+            }
+        }
+    }
+
+    let mut translation = translation;
+    if !collide_up && !collide_down {
+        translation.y = new_translation.y;
+    }
+    if !collide_left && !collide_right {
+        translation.x = new_translation.x;
+    }
+    translation
+}
+
+// Push players and bombs along conveyor belts, once players moved on their own
+#[allow(clippy::type_complexity)]
+fn convey(
+    grid: Res<grid::Grid>,
+    conveyor_query: Query<(&Conveyor, &Transform), (Without<Player>, Without<Bomb>)>,
+    collision_query: Query<&Transform, (With<Brick>, Without<Player>, Without<Bomb>)>,
+    mut player_query: Query<&mut Transform, (With<Player>, Without<Bomb>, Without<Flying>)>,
+    mut bomb_query: Query<&mut Transform, (With<Bomb>, Without<Player>)>,
+) {
+    // the belt under the center of something, if any
+    let belt = |translation: Vec3| {
+        let center = cell_center(translation);
+        conveyor_query
+            .iter()
+            .find(|(_, transform)| transform.translation.truncate().distance(center) < 1.)
+            .map(|(conveyor, _)| conveyor.0)
+    };
+    // belts move things less than a cell at a time
+    let bricks = |translation: Vec3| {
+        grid.bricks
+            .around(grid::cell(translation))
+            .filter_map(|brick| collision_query.get(brick).ok())
+    };
+
+    for mut transform in &mut player_query {
+        if let Some(direction) = belt(transform.translation) {
+            transform.translation = step(
+                transform.translation,
+                transform.scale.truncate(),
+                direction.vector() * CONVEYOR_SPEED,
+                bricks(transform.translation),
+            );
+        }
+    }
+
+    for mut transform in &mut bomb_query {
+        if let Some(direction) = belt(transform.translation) {
+            transform.translation = step(
+                transform.translation,
+                transform.scale.truncate(),
+                direction.vector() * CONVEYOR_SPEED,
+                bricks(transform.translation),
+            );
+            continue;
+        }
+        // bombs pushed off a belt come to rest in the middle of the cell they reached
+        let offset = cell_center(transform.translation) - transform.translation.truncate();
+        if offset != Vec2::ZERO {
+            transform.translation += offset
+                .clamp(Vec2::splat(-CONVEYOR_SPEED), Vec2::splat(CONVEYOR_SPEED))
+                .extend(0.);
+        }
+    }
+}
+
+// Move players and bombs stepping on warps to the other warp of the pair
+#[allow(clippy::type_complexity)]
+fn warp(
+    mut commands: Commands,
+    warp_query: Query<(&Warp, &Transform), (Without<Player>, Without<Bomb>)>,
+    mut player_query: Query<
+        (Entity, &mut Transform, Option<&mut Warped>),
+        (With<Player>, Without<Bomb>, Without<Flying>),
+    >,
+    mut bomb_query: Query<
+        (Entity, &mut Transform, Option<&mut Warped>),
+        (With<Bomb>, Without<Player>),
+    >,
+) {
+    let warps = warp_query
+        .iter()
+        .map(|(warp, transform)| (transform.translation.truncate(), warp.destination))
+        .collect::<Vec<_>>();
+    let entities = player_query.iter_mut().chain(bomb_query.iter_mut());
+    for (entity, mut transform, warped) in entities {
+        let center = cell_center(transform.translation);
+        let destination = warps
+            .iter()
+            .find(|(position, _)| position.distance(center) < 1.)
+            .map(|(_, destination)| *destination);
+        match (warped, destination) {
+            // the cooldown is over only once off the warp, not to bounce back and forth
+            (Some(mut warped), destination) => {
+                warped.0 = warped.0.saturating_sub(1);
+                if warped.0 == 0 && destination.is_none() {
+                    commands.entity(entity).remove::<Warped>();
+                }
+            }
+            (None, Some(destination)) => {
+                transform.translation = destination.extend(transform.translation.z);
+                commands.entity(entity).insert(Warped(WARP_COOLDOWN_STEPS));
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+// Toss players stepping on trampolines, and carry them through the air
+#[allow(clippy::type_complexity)]
+fn bounce(
+    mut commands: Commands,
+    grid: Res<grid::Grid>,
+    trampoline_query: Query<&Transform, (With<Trampoline>, Without<Player>)>,
+    brick_query: Query<&Transform, (With<Brick>, Without<Player>)>,
+    bomb_query: Query<&Transform, (With<Bomb>, Without<Player>)>,
+    mut player_query: Query<
+        (
+            Entity,
+            &Facing,
+            &mut Velocity,
+            &mut Transform,
+            Option<&mut Flying>,
+        ),
+        With<Player>,
+    >,
+) {
+    for (entity, facing, mut velocity, mut transform, flying) in &mut player_query {
+        if let Some(mut flying) = flying {
+            flying.step += 1;
+            let progress = flying.step as f32 / FLIGHT_STEPS as f32;
+            transform.translation = flying
+                .from
+                .lerp(flying.to, progress)
+                .extend(transform.translation.z);
+            if flying.step >= FLIGHT_STEPS {
+                commands.entity(entity).remove::<Flying>();
+            }
+            continue;
+        }
+
+        let center = cell_center(transform.translation);
+        if !trampoline_query
+            .iter()
+            .any(|trampoline| trampoline.translation.truncate().distance(center) < 1.)
+        {
+            continue;
+        }
+        let landing = center + facing.vector() * BRICK_SIZE * TRAMPOLINE_REACH;
+        let inside = landing.x > LEFT_WALL
+            && landing.x < RIGHT_WALL
+            && landing.y > BOTTOM_WALL
+            && landing.y < TOP_WALL;
+        // bombs placed during the step before aren't indexed yet, see `grid::index_bombs`, so
+        // they're all looked at; there's only a handful of them anyway
+        let free = grid
+            .bricks
+            .in_cells([grid::cell(landing.extend(0.))])
+            .filter_map(|brick| brick_query.get(brick).ok())
+            .chain(&bomb_query)
+            .all(|obstacle| obstacle.translation.truncate().distance(landing) >= 1.);
+        // players only land on free cells, otherwise they stay on the trampoline
+        if inside && free {
+            commands.entity(entity).insert(Flying {
+                from: transform.translation.truncate(),
+                to: landing,
+                step: 0,
+            });
+            // players land standing still
+            velocity.0 = Vec2::ZERO;
+        }
+    }
+}
+
+// Push players stuck inside bricks out to the nearest free cell, wherever they got there from
+#[allow(clippy::type_complexity)]
+fn depenetrate(
+    rules: Res<Rules>,
+    grid: Res<grid::Grid>,
+    collision_query: Query<&Transform, (With<Brick>, Without<Player>)>,
+    mut query: Query<(&mut Velocity, &mut Transform), (With<Player>, Without<Flying>)>,
+) {
+    for (mut velocity, mut transform) in &mut query {
+        let stuck = grid
+            .bricks
+            .around(grid::cell(transform.translation))
+            .filter_map(|brick| collision_query.get(brick).ok())
+            .any(|brick| {
+                collide(
+                    transform.translation,
+                    transform.scale.truncate(),
+                    brick.translation,
+                    brick.scale.truncate(),
+                )
+                .is_some()
+            });
+        if !stuck {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        let (cols, rows) = arena::playable(rules.size);
+        let nearest = rows
+            .flat_map(|row| cols.clone().map(move |col| cell_position(col, row)))
+            .filter(|cell| {
+                !grid
+                    .bricks
+                    .in_cells([grid::cell(cell.extend(0.))])
+                    .filter_map(|brick| collision_query.get(brick).ok())
+                    .any(|brick| brick.translation.truncate().distance(*cell) < 1.)
+            })
+            // ties go to the first cell, from the bottom left, so that every step plays out
+            // the same way
+            .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)));
+        // with no free cell at all there's nowhere better to be
+        if let Some(cell) = nearest {
+            transform.translation = cell.extend(transform.translation.z);
+            velocity.0 = Vec2::ZERO;
+        }
+    }
+}
+
+/// Center of the cell the given point lies in
+fn cell_center(translation: Vec3) -> Vec2 {
+    (translation.truncate() / BRICK_SIZE).round() * BRICK_SIZE
+}
+
+#[allow(clippy::type_complexity)]
+fn place_bomb(
+    mut commands: Commands,
+    rules: Res<Rules>,
+    grid: Res<grid::Grid>,
+    mut event_reader: EventReader<BombEvent>,
+    collision_query: Query<&Transform, (With<Brick>, Without<Player>)>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Player,
+            &Transform,
+            Option<&mut input::BombBuffer>,
+        ),
+        (With<Player>, Without<Flying>),
+    >,
+) {
+    let mut players = HashMap::new();
+    for (entity, player, transform, buffer) in &mut query {
+        if player.active_bombs >= player.max_bombs {
+            continue;
+        }
+        players.insert(entity, (player, transform, buffer));
+    }
+
+    for BombEvent {
+        player: player_entity,
+    } in event_reader.iter()
+    {
+        let (player, player_transform, buffer) = if let Some(t) = players.get_mut(player_entity) {
+            t
+        } else {
+            continue;
+        };
+
+        let bricks = grid
+            .bricks
+            .around(grid::cell(player_transform.translation))
+            .filter_map(|brick| collision_query.get(brick).ok())
+            .copied()
+            .collect::<Vec<_>>();
+        let bomb_translation = match bomb_cell(player_transform.translation, &bricks) {
+            Some(cell) => cell.extend(BOMB_Z),
+            None => continue,
+        };
+
+        let fuse = if rules.mutated(mutator::Mutator::InstantFuse) {
+            mutator::INSTANT_FUSE_SECS
+        } else {
+            rules.fuse
+        };
+        let power = player.placed_power(&rules);
+        spawn_bomb(
+            &mut commands,
+            Bomb {
+                player: Some(*player_entity),
+                timer: Timer::from_seconds(fuse, false),
+                power,
+            },
+            bomb_translation,
+        );
+
+        player.active_bombs += 1;
+        if let Some(buffer) = buffer {
+            buffer.0 = 0;
+        }
+    }
+}
+
+/// Walkable cell nearest to the given point among the one it lies in and those around it, where
+/// a bomb can be placed, if any
+fn bomb_cell<'a>(
+    translation: Vec3,
+    bricks: impl IntoIterator<Item = &'a Transform> + Copy,
+) -> Option<Vec2> {
+    let center = cell_center(translation);
+    let position = translation.truncate();
+    (-1..=1)
+        .flat_map(|y| (-1..=1).map(move |x| center + Vec2::new(x as f32, y as f32) * BRICK_SIZE))
+        .filter(|cell| {
+            cell.x > LEFT_WALL && cell.x < RIGHT_WALL && cell.y > BOTTOM_WALL && cell.y < TOP_WALL
+        })
+        .filter(|cell| {
+            !bricks
+                .into_iter()
+                .any(|brick| brick.translation.truncate().distance(*cell) < 1.)
+        })
+        .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)))
+}
+
+fn advance_tick(mut tick: ResMut<Tick>) {
+    tick.0 += 1;
+}
+
+#[allow(clippy::type_complexity)]
+fn check_for_explosions(
+    mut query: Query<(Entity, &mut Bomb), (Without<Brick>, Without<Player>, With<Bomb>)>,
+    mut explosion_events: EventWriter<ExplosionEvent>,
+) {
+    for (bomb_entity, mut bomb) in &mut query {
+        bomb.timer.tick(Duration::from_secs_f32(TIME_STEP));
+        if bomb.timer.finished() {
+            explosion_events.send(ExplosionEvent(bomb_entity));
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn explode(
+    mut commands: Commands,
+    mut rng: ResMut<MatchRng>,
+    rules: Res<Rules>,
+    power_up_registry: Res<power_ups::PowerUpRegistry>,
+    grid: Res<grid::Grid>,
+    bomb_collision_query: Query<
+        (Entity, &Bomb, &Transform),
+        (Without<Brick>, Without<Player>, With<Bomb>),
+    >,
+    brick_collision_query: Query<
+        (Entity, &Transform),
+        (With<Brick>, With<Breakable>, Without<Player>, Without<Bomb>),
+    >,
+    mut player_collision_query: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Player,
+            Option<&Active>,
+            Option<&Team>,
+        ),
+        (Without<Brick>, With<Player>, Without<Bomb>),
+    >,
+    mut event_reader: EventReader<ExplosionEvent>,
+    mut event_writer: EventWriter<Explosion2Event>,
+    mut death_writer: EventWriter<DeathEvent>,
+    mut brick_writer: EventWriter<BrickDestroyedEvent>,
+    mut chain_writer: EventWriter<ChainEvent>,
+    mut game_over_writer: EventWriter<GameOverEvent>,
+) {
+    let rng = &mut rng.0;
+    // players caught by a previous blast of this same step
+    let mut killed = Vec::new();
+    // bombs set off by the blasts of this step, and bricks they destroy: blasts reaching the
+    // same bomb or brick set it off or destroy it only once
+    let mut chained = Vec::<(Entity, Entity)>::new();
+    let mut destroyed = Vec::<(Entity, Vec3, Entity)>::new();
+    let players = player_collision_query.iter().count();
+    let humans = player_collision_query
+        .iter()
+        .filter(|(_, _, _, active, _)| active.is_some())
+        .count();
+    let mut humans_alive = humans;
+
+    // every bomb going off during this step, once even when set off more than once
+    let mut exploded = Vec::new();
+    for ExplosionEvent(bomb_entity) in event_reader.iter() {
+        if !exploded.contains(bomb_entity) {
+            exploded.push(*bomb_entity);
+        }
+    }
+    for &bomb_entity in &exploded {
+        if let Ok((_, bomb, bomb_transform)) = bomb_collision_query.get(bomb_entity) {
+            // only what stands around the cross of the blast can be caught by it
+            let cells = || grid::blast_cells(bomb_transform.translation, bomb.power);
+
+            // bomb
+            for (other_bomb_entity, _other_bomb, other_bomb_transform) in grid
+                .bombs
+                .in_cells(cells())
+                .filter_map(|other| bomb_collision_query.get(other).ok())
+            {
+                // bombs going off during this step already don't go off again at the next one
+                if exploded.contains(&other_bomb_entity)
+                    || chained.iter().any(|(other, _)| *other == other_bomb_entity)
+                {
+                    continue;
+                }
+
+                if in_blast(
+                    bomb_transform.translation,
+                    bomb.power,
+                    other_bomb_transform.translation,
+                    other_bomb_transform.scale.truncate(),
+                ) {
+                    chained.push((other_bomb_entity, bomb_entity));
+                }
+            }
+
+            // brick
+            for (brick_entity, brick_transform) in grid
+                .bricks
+                .in_cells(cells())
+                .filter_map(|brick| brick_collision_query.get(brick).ok())
+            {
+                if !destroyed.iter().any(|(other, ..)| *other == brick_entity)
+                    && in_blast(
+                        bomb_transform.translation,
+                        bomb.power,
+                        brick_transform.translation,
+                        brick_transform.scale.truncate(),
+                    )
+                {
+                    destroyed.push((brick_entity, brick_transform.translation, bomb_entity));
+                }
+            }
+
+            // player
+            // orphaned bombs belong to nobody, so they spare no team
+            let owner_team = bomb
+                .player
+                .and_then(|owner| player_collision_query.get(owner).ok())
+                .and_then(|(.., team)| team.copied());
+            for (player_entity, player_transform, mut player, active, team) in
+                &mut player_collision_query
+            {
+                if Some(player_entity) == bomb.player {
+                    player.active_bombs = player.active_bombs.saturating_sub(1);
+                }
+
+                // players are still caught in their own blasts
+                let spared = !rules.friendly_fire
+                    && Some(player_entity) != bomb.player
+                    && team.is_some()
+                    && team.copied() == owner_team;
+                if !spared
+                    && in_blast(
+                        bomb_transform.translation,
+                        bomb.power,
+                        player_transform.translation,
+                        player_transform.scale.truncate(),
+                    )
+                    && !killed.contains(&player_entity)
+                {
+                    killed.push(player_entity);
+                    if active.is_some() {
+                        humans_alive -= 1;
+                    }
+                    death_writer.send(DeathEvent {
+                        player: player_entity,
+                        killer: bomb.player,
+                    });
+                }
+            }
+
+            let reach = 2. * (bomb.power as f32) + 1.;
+            // horizontal fire
+            spawn_fire(
+                &mut commands,
+                Fire(Timer::from_seconds(1., false)),
+                bomb_transform.translation,
+                Vec3::new(BRICK_SIZE.x * reach, BRICK_SIZE.y, 1.0),
+            );
+            // vertical fire
+            spawn_fire(
+                &mut commands,
+                Fire(Timer::from_seconds(1., false)),
+                bomb_transform.translation,
+                Vec3::new(BRICK_SIZE.x, BRICK_SIZE.y * reach, 1.0),
+            );
+
+            commands.entity(bomb_entity).despawn();
+        }
+    }
+
+    for (bomb, by) in chained {
+        event_writer.send(Explosion2Event(bomb));
+        chain_writer.send(ChainEvent { bomb, by });
+    }
+    for (brick, translation, bomb) in destroyed {
+        brick_writer.send(BrickDestroyedEvent {
+            position: translation.truncate(),
+            bomb,
+        });
+        // the cell is free right away, while the brick crumbles away
+        commands
+            .entity(brick)
+            .remove::<Brick>()
+            .remove::<Breakable>()
+            .insert(animation::Crumble::default());
+
+        if !rules.mutated(mutator::Mutator::NoPickups) && rng.gen_bool(rules.power_up_chance) {
+            if let Some(power_up) = power_up_registry.pick(rng) {
+                spawn_power_up(&mut commands, &power_up_registry, power_up, translation);
+            }
+        }
+        // not even rolled when disabled, to keep the draws of older matches
+        if rules.rubble_chance > 0. && rng.gen_bool(rules.rubble_chance) {
+            spawn_rubble(
+                &mut commands,
+                Rubble(Timer::from_seconds(RUBBLE_SECS, false)),
+                translation,
+            );
+        }
+        if rules.mode == Mode::Crowns && rng.gen_bool(crown::CROWN_CHANCE) {
+            crown::spawn_crown(&mut commands, 1, translation);
+        }
+    }
+
+    // a match with a single player left is already over, unless fighting mobs
+    if humans == 0 || (players <= 1 && rules.mode.last_standing()) || killed.is_empty() {
+        return;
+    }
+    // team matches go on until a single team is left, even with humans watching
+    if rules.mode == Mode::Teams {
+        let mut teams = player_collision_query
+            .iter()
+            .filter(|(entity, ..)| !killed.contains(entity))
+            .filter_map(|(.., team)| team.map(|team| team.0))
+            .collect::<Vec<_>>();
+        teams.sort_unstable();
+        teams.dedup();
+        match teams[..] {
+            [] => game_over_writer.send(GameOverEvent {
+                winner: None,
+                team: None,
+            }),
+            [team] => game_over_writer.send(GameOverEvent {
+                winner: None,
+                team: Some(team),
+            }),
+            _ => {}
+        }
+        return;
+    }
+    // the match goes on as long as a human is left, and somebody to fight against
+    if humans_alive == 0 {
+        game_over_writer.send(GameOverEvent {
+            winner: None,
+            team: None,
+        });
+    } else if players - killed.len() == 1 && rules.mode.last_standing() {
+        // other modes end otherwise
+        // with a human alive, the survivor can only be that human
+        let winner = player_collision_query
+            .iter()
+            .find(|(entity, ..)| !killed.contains(entity))
+            .and_then(|(_, _, _, active, _)| active.map(|active| active.0));
+        game_over_writer.send(GameOverEvent { winner, team: None });
+    }
+}
+
+// Despawn killed players, however they died, leaving their bombs to go off without an owner,
+// and a corpse scoring the opponents killed by humans once gone
+fn bury(
+    mut commands: Commands,
+    mut event_reader: EventReader<DeathEvent>,
+    player_query: Query<(Option<&Active>, &Transform, &Sprite), With<Player>>,
+    mut bomb_query: Query<&mut Bomb>,
+) {
+    // players killed more than once during the step are only despawned once
+    let mut buried = Vec::new();
+    for DeathEvent { player, killer } in event_reader.iter() {
+        if buried.contains(player) {
+            continue;
+        }
+        buried.push(*player);
+        // killed players are still around until the step is over, killers too
+        let human = |entity: Entity| matches!(player_query.get(entity), Ok((Some(_), ..)));
+        let points = if !human(*player) && killer.is_some_and(human) {
+            100
+        } else {
+            0
+        };
+        if let Ok((_, transform, sprite)) = player_query.get(*player) {
+            spawn_corpse(
+                &mut commands,
+                Corpse {
+                    timer: Timer::from_seconds(DEATH_SECS, false),
+                    points,
+                },
+                transform.translation,
+                sprite.color,
+            );
+        }
+        for mut bomb in &mut bomb_query {
+            if bomb.player == Some(*player) {
+                bomb.player = None;
+            }
+        }
+        // bots live on the player they drive, so they stop sending events along with it
+        commands.entity(*player).despawn();
+    }
+}
+
+// Spin, shrink and fade corpses away, scoring their kills once gone
+fn decay(
+    mut commands: Commands,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut query: Query<(
+        Entity,
+        &mut Corpse,
+        &mut Transform,
+        &mut Sprite,
+        Option<&mut TextureAtlasSprite>,
+    )>,
+) {
+    for (entity, mut corpse, mut transform, mut sprite, sheet) in &mut query {
+        corpse.timer.tick(Duration::from_secs_f32(TIME_STEP));
+        if corpse.timer.finished() {
+            scoreboard.score += corpse.points;
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let left = corpse.timer.percent_left();
+        transform.rotation =
+            Quat::from_rotation_z(corpse.timer.percent() * DEATH_TURNS * std::f32::consts::TAU);
+        transform.scale = (PLAYER_SIZE * left).extend(1.0);
+        sprite.color.set_a(left);
+        if let Some(mut sheet) = sheet {
+            sheet.color.set_a(left);
+        }
+    }
+}
+
+// Set off the bombs of players owning a detonator
+fn detonate(
+    mut event_reader: EventReader<DetonateEvent>,
+    mut event_writer: EventWriter<ExplosionEvent>,
+    player_query: Query<&Player>,
+    bomb_query: Query<(Entity, &Bomb)>,
+) {
+    for DetonateEvent { player } in event_reader.iter() {
+        if !player_query
+            .get(*player)
+            .is_ok_and(|player| player.detonator)
+        {
+            continue;
+        }
+        for (bomb_entity, bomb) in &bomb_query {
+            if bomb.player == Some(*player) {
+                event_writer.send(ExplosionEvent(bomb_entity));
+            }
+        }
+    }
+}
+
+/// Checks if the blast of a bomb with the given power, placed at `bomb_translation`,
+/// reaches an object of the given size placed at `translation`
+fn in_blast(bomb_translation: Vec3, power: u8, translation: Vec3, size: Vec2) -> bool {
+    let reach = 2. * (power as f32) + 1.;
+    // horizontal
+    collide(bomb_translation, Vec2::new(BRICK_SIZE.x * reach, BRICK_SIZE.y), translation, size).is_some()
+    // vertical
+    || collide(bomb_translation, Vec2::new(BRICK_SIZE.x, BRICK_SIZE.y * reach), translation, size).is_some()
+}
+
+/// Centers of the cells inside the walls reached by a blast of the given power from the given
+/// cell, its middle first
+fn blast_cells(center: Vec2, power: u8) -> impl Iterator<Item = Vec2> {
+    let arms = Direction::ALL.into_iter().flat_map(move |direction| {
+        (1..=power).map(move |distance| center + direction.vector() * BRICK_SIZE * distance as f32)
+    });
+    std::iter::once(center).chain(arms).filter(|cell| {
+        cell.x > LEFT_WALL && cell.x < RIGHT_WALL && cell.y > BOTTOM_WALL && cell.y < TOP_WALL
+    })
+}
+
+fn fire(mut commands: Commands, mut fire_query: Query<(Entity, &mut Fire), With<Fire>>) {
+    for (fire_entity, mut fire) in &mut fire_query {
+        fire.0.tick(Duration::from_secs_f32(TIME_STEP));
+        if fire.0.finished() {
+            commands.add(PutOutFire(fire_entity));
+        }
+    }
+}
+
+fn clear_rubble(mut commands: Commands, mut rubble_query: Query<(Entity, &mut Rubble)>) {
+    for (rubble_entity, mut rubble) in &mut rubble_query {
+        rubble.0.tick(Duration::from_secs_f32(TIME_STEP));
+        if rubble.0.finished() {
+            commands.entity(rubble_entity).despawn();
+        }
+    }
+}
+
+// Score every brick destroyed
+fn score_bricks(
+    mut scoreboard: ResMut<Scoreboard>,
+    mut event_reader: EventReader<BrickDestroyedEvent>,
+) {
+    // left untouched otherwise, so that the scoreboard isn't redrawn for nothing
+    let destroyed = event_reader.iter().count();
+    if destroyed > 0 {
+        scoreboard.score += destroyed;
+    }
+}
+
+fn pick_up(
+    mut commands: Commands,
+    power_up_query: Query<(Entity, &PowerUp, &Transform)>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
+    mut event_writer: EventWriter<PickUpEvent>,
+) {
+    for (power_up_entity, power_up, power_up_transform) in &power_up_query {
+        for (player, player_transform) in &player_query {
+            if collide(
+                power_up_transform.translation,
+                power_up_transform.scale.truncate(),
+                player_transform.translation,
+                player_transform.scale.truncate(),
+            )
+            .is_some()
+            {
+                commands.entity(power_up_entity).despawn();
+                event_writer.send(PickUpEvent {
+                    player,
+                    power_up: *power_up,
+                    position: power_up_transform.translation.truncate(),
+                });
+                break;
+            }
+        }
+    }
+}
+
+// Give the power-ups picked up to their players
+fn apply_power_ups(
+    registry: Res<power_ups::PowerUpRegistry>,
+    mut event_reader: EventReader<PickUpEvent>,
+    mut player_query: Query<&mut Player>,
+) {
+    for PickUpEvent {
+        player, power_up, ..
+    } in event_reader.iter()
+    {
+        if let Ok(mut player) = player_query.get_mut(*player) {
+            registry.apply(*power_up, &mut player);
+        }
+    }
+}
+
+fn explode2(
+    mut event_reader: EventReader<Explosion2Event>,
+    mut event_writer: EventWriter<ExplosionEvent>,
+) {
+    for event in event_reader.iter() {
+        event_writer.send(ExplosionEvent(event.0));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_scoreboard(
+    rules: Res<Rules>,
+    scoreboard: Res<Scoreboard>,
+    stage: Res<Stage>,
+    waves: Res<survival::Waves>,
+    run: Res<records::Run>,
+    tick: Res<Tick>,
+    rounds: Res<Rounds>,
+    daily: Option<Res<daily::Daily>>,
+    locale: Res<locale::Locale>,
+    mut query: Query<(&mut Text, ChangeTrackers<ScoreText>)>,
+) {
+    let (mut text, tracker) = match query.get_single_mut() {
+        Ok(score) => score,
+        Err(_) => return,
+    };
+    // the time of time attack runs goes by at every step, everything else seldom changes
+    let changed = rules.is_changed()
+        || scoreboard.is_changed()
+        || stage.is_changed()
+        || waves.is_changed()
+        || run.is_changed()
+        || rounds.is_changed()
+        || daily.as_ref().is_some_and(|daily| daily.is_changed())
+        || locale.is_changed()
+        || (rules.mode == Mode::TimeAttack && tick.is_changed());
+    if !changed && !tracker.is_added() {
+        return;
+    }
+    let score = match rules.mode {
+        Mode::Battle | Mode::Teams | Mode::Crowns | Mode::Hill => scoreboard.score.to_string(),
+        Mode::Stages => locale.format(
+            "hud.stage",
+            &[
+                ("score", &scoreboard.score),
+                ("stage", &(stage.cleared + 1)),
+            ],
+        ),
+        Mode::Survival => locale.format(
+            "hud.wave",
+            &[("score", &scoreboard.score), ("wave", &waves.count)],
+        ),
+        Mode::TimeAttack => {
+            // the clock stops once the arena is cleared
+            let time = run
+                .time
+                .unwrap_or((tick.0 - rounds.start) as f32 * TIME_STEP);
+            locale.format(
+                "hud.time",
+                &[
+                    ("score", &scoreboard.score),
+                    ("time", &format!("{time:.1}")),
+                ],
+            )
+        }
+    };
+    text.sections[1].value = match daily {
+        Some(daily) => score + daily.label(&locale).as_str(),
+        None => score,
+    };
+}
+
+/// Meter filling up as the dash of a player gets ready again
+fn dash_meter(dash: &Dash) -> String {
+    let ready = DASH_METER - dash.cooldown * DASH_METER / DASH_COOLDOWN_STEPS;
+    (0..DASH_METER)
+        .map(|index| if index < ready { '#' } else { '.' })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn update_hud(
+    rules: Res<Rules>,
+    rounds: Res<Rounds>,
+    locale: Res<locale::Locale>,
+    player_query: Query<(
+        &Active,
+        &Player,
+        &Dash,
+        Option<&crown::Crowns>,
+        Option<&hill::Hold>,
+        Option<&scripting::Points>,
+    )>,
+    changed_query: Query<
+        (),
+        (
+            With<Active>,
+            Or<(
+                Changed<Player>,
+                Changed<Dash>,
+                Changed<crown::Crowns>,
+                Changed<hill::Hold>,
+                Changed<scripting::Points>,
+            )>,
+        ),
+    >,
+    added_query: Query<(), Added<HumanPanel>>,
+    mut panel_query: Query<(&HumanPanel, &mut Text)>,
+    // humans alive when the panels were last updated, to tell when one is out
+    mut alive: Local<usize>,
+) {
+    let humans = player_query.iter().count();
+    let changed = rules.is_changed()
+        || rounds.is_changed()
+        || locale.is_changed()
+        || humans != *alive
+        || !changed_query.is_empty()
+        || !added_query.is_empty();
+    *alive = humans;
+    if !changed {
+        return;
+    }
+    for (panel, mut text) in &mut panel_query {
+        let player = player_query
+            .iter()
+            .find(|(active, ..)| active.0 == panel.0)
+            .map(|(_, player, dash, crowns, hold, points)| (player, dash, crowns, hold, points));
+        let stats = match player {
+            Some((player, dash, crowns, hold, points)) => {
+                let mut stats = locale.format(
+                    "hud.player",
+                    &[
+                        ("player", &(panel.0 + 1)),
+                        (
+                            "bombs",
+                            &player.max_bombs.saturating_sub(player.active_bombs),
+                        ),
+                        ("max_bombs", &player.max_bombs),
+                        ("power", &player.bomb_power),
+                        ("dash", &dash_meter(dash)),
+                    ],
+                );
+                if player.detonator {
+                    stats.push_str(locale.text("hud.detonator"));
+                }
+                if let Some(crowns) = crowns {
+                    stats.push_str(&locale.format(
+                        "hud.crowns",
+                        &[("crowns", &crowns.0), ("goal", &rules.crowns)],
+                    ));
+                }
+                if let Some(hold) = hold {
+                    stats.push_str(
+                        &locale
+                            .format("hud.hill", &[("secs", &hold.secs()), ("goal", &rules.hill)]),
+                    );
+                }
+                if let Some(points) = points {
+                    stats.push_str(&locale.format("hud.points", &[("points", &points.0)]));
+                }
+                stats
+            }
+            None => locale.format("hud.out", &[("player", &(panel.0 + 1))]),
+        };
+        text.sections[0].value = if rules.rounds > 1 {
+            let wins = rounds.wins.get(panel.0).copied().unwrap_or_default();
+            locale.format("hud.wins", &[("stats", &stats), ("wins", &wins)])
+        } else {
+            stats
+        };
+    }
+}
+
+// End the round when its time is up
+fn time_up(
+    rules: Res<Rules>,
+    rounds: Res<Rounds>,
+    tick: Res<Tick>,
+    mut game_over_writer: EventWriter<GameOverEvent>,
+) {
+    let limit = match rules.time_limit {
+        Some(limit) if !rounds.over => limit,
+        _ => return,
+    };
+    if tick.0 - rounds.start >= (limit as f32 / TIME_STEP) as u64 {
+        game_over_writer.send(GameOverEvent {
+            winner: None,
+            team: None,
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn game_over(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    locale: Res<locale::Locale>,
+    rules: Res<Rules>,
+    roster: Res<bot::Roster>,
+    run: Res<records::Run>,
+    settings: Res<settings::Settings>,
+    tick: Res<Tick>,
+    mut rounds: ResMut<Rounds>,
+    mut event_reader: EventReader<GameOverEvent>,
+    mut round_writer: EventWriter<RoundEndedEvent>,
+    corpse_query: Query<(), With<Corpse>>,
+) {
+    // only the first outcome of a round counts
+    if let Some(event) = event_reader.iter().last() {
+        if !rounds.over && rounds.outcome.is_none() {
+            rounds.outcome = Some((event.winner, event.team));
+        }
+    }
+    // and it's only shown once the dead are done dying
+    if !corpse_query.is_empty() {
+        return;
+    }
+    let (event_winner, event_team) = match rounds.outcome.take() {
+        Some(outcome) => outcome,
+        None => return,
+    };
+    rounds.over = true;
+    rounds.played += 1;
+    if let Some(wins) = event_winner.and_then(|index| rounds.wins.get_mut(index)) {
+        *wins += 1;
+    }
+    if let Some(team) = event_team {
+        rounds.team_wins[team] += 1;
+        // every human in the team shares the win
+        for (index, wins) in rounds.wins.iter_mut().enumerate() {
+            if Team::of(index).0 == team {
+                *wins += 1;
+            }
+        }
+    }
+    let (winner, team) = if rounds.played < rules.rounds {
+        rounds.next = Some(tick.0 + (ROUND_BREAK_SECS / TIME_STEP) as u64);
+        (event_winner, event_team)
+    } else if rules.rounds > 1 {
+        // the last round shows the winner of the whole match
+        if rules.mode == Mode::Teams {
+            (None, rounds.team_leader())
+        } else {
+            (rounds.leader(), None)
+        }
+    } else {
+        (event_winner, event_team)
+    };
+
+    round_writer.send(RoundEndedEvent { winner, team });
+
+    let (value, color) = match (winner, team) {
+        (_, Some(team)) => (
+            locale.format("game_over.team_wins", &[("team", &(team + 1))]),
+            settings.color_vision.adjust(TEAM_COLORS[team]),
+        ),
+        (Some(index), None) => (
+            locale.format("game_over.wins", &[("player", &(index + 1))]),
+            settings.color_vision.adjust(roster.color(index)),
+        ),
+        (None, None) if rules.mode == Mode::TimeAttack && run.time.is_some() => {
+            let record = match run.rank {
+                Some(0) => locale.text("game_over.new_record"),
+                Some(_) => locale.text("game_over.top_time"),
+                None => "",
+            };
+            (
+                locale.text("game_over.cleared").to_string() + record,
+                SCORE_COLOR,
+            )
+        }
+        (None, None) => (locale.text("game_over.over").to_string(), TEXT_COLOR),
+    };
+
+    commands
+        .spawn()
+        .insert_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::FlexEnd,
+                ..Default::default()
+            },
+            // material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .insert_bundle(TextBundle {
+            text: Text {
+                sections: vec![TextSection {
+                    value,
+                    style: TextStyle {
+                        font: asset_server.load(locale.font()),
+                        font_size: GAMEOVER_FONT_SIZE,
+                        color,
+                    },
+                }],
+                alignment: TextAlignment {
+                    vertical: VerticalAlign::Center,
+                    horizontal: HorizontalAlign::Center,
+                },
+            },
+            style: Style {
+                align_self: AlignSelf::Center,
+                align_content: AlignContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..Default::default()
+        })
+        .insert(Match);
+}
+
+// Start over the arena once the break between rounds is over
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn next_round(
+    mut commands: Commands,
+    tick: Res<Tick>,
+    bot_registry: Res<bot::BotRegistry>,
+    roster: Res<bot::Roster>,
+    bindings: Res<input::Bindings>,
+    rules: Res<Rules>,
+    mut rng: ResMut<MatchRng>,
+    mut rounds: ResMut<Rounds>,
+    // the score and the panels carry over to the next round
+    query: Query<Entity, (With<Match>, Without<ScoreText>, Without<HumanPanel>)>,
+) {
+    match rounds.next {
+        Some(next) if tick.0 >= next => {}
+        _ => return,
+    }
+
+    rounds.next = None;
+    rounds.over = false;
+    rounds.start = tick.0;
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_arena(
+        &mut commands,
+        &bot_registry,
+        &roster,
+        &bindings,
+        &rules,
+        &mut rng,
+    );
+}
+
+// Move on to the next stage once every opponent, mob and boss is gone and a human reached the exit
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn exit_stage(
+    mut commands: Commands,
+    bot_registry: Res<bot::BotRegistry>,
+    mut roster: ResMut<bot::Roster>,
+    bindings: Res<input::Bindings>,
+    mut rules: ResMut<Rules>,
+    tick: Res<Tick>,
+    mut rng: ResMut<MatchRng>,
+    mut stage: ResMut<Stage>,
+    mut rounds: ResMut<Rounds>,
+    campaign: Option<ResMut<campaign::Campaign>>,
+    mut state: ResMut<State<AppState>>,
+    exit_query: Query<&Transform, With<Exit>>,
+    brick_query: Query<&Transform, With<Brick>>,
+    player_query: Query<(&Transform, &Player, Option<&Active>)>,
+    mob_query: Query<(), Or<(With<mob::Mob>, With<boss::Boss>)>>,
+    // the score and the panels carry over to the next stage
+    query: Query<Entity, (With<Match>, Without<ScoreText>, Without<HumanPanel>)>,
+) {
+    if rules.mode != Mode::Stages
+        || rounds.over
+        || player_query.iter().any(|(_, _, active)| active.is_none())
+        || !mob_query.is_empty()
+    {
+        return;
+    }
+    let exit = match exit_query.get_single() {
+        Ok(transform) => transform.translation.truncate(),
+        Err(_) => return,
+    };
+    let hidden = brick_query
+        .iter()
+        .any(|brick| brick.translation.truncate().distance(exit) < 1.);
+    let reached = player_query
+        .iter()
+        .any(|(transform, ..)| cell_center(transform.translation).distance(exit) < 1.);
+    if hidden || !reached {
+        return;
+    }
+
+    stage.cleared += 1;
+    stage.carried = player_query
+        .iter()
+        .filter_map(|(_, player, active)| active.map(|active| (active.0, player.clone())))
+        .collect();
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Some(mut campaign) = campaign {
+        let elapsed = ((tick.0 - rounds.start) as f32 * TIME_STEP) as u32;
+        let seconds_left = rules
+            .time_limit
+            .map_or(0, |limit| limit.saturating_sub(elapsed));
+        let next = campaign.advance(seconds_left, &mut rules, &mut roster);
+        // fails only if a transition is already queued
+        let _ = state.push(AppState::Reward);
+        if !next {
+            return;
+        }
+    }
+    rounds.start = tick.0;
+    spawn_arena(
+        &mut commands,
+        &bot_registry,
+        &roster,
+        &bindings,
+        &rules,
+        &mut rng,
+    );
+}
+
+// Give humans starting a new stage the power-ups they left the previous one with
+fn carry_over(stage: Res<Stage>, mut query: Query<(&Active, &mut Player), Added<Active>>) {
+    for (active, mut player) in &mut query {
+        if let Some((_, carried)) = stage.carried.iter().find(|(index, _)| *index == active.0) {
+            *player = Player {
+                active_bombs: 0,
+                ..carried.clone()
+            };
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Night mode, darkening the arena around the light of bombs and fires.
+//!
+//! When turned on from the options screen, matches are played in the dark: a shade is laid over
+//! the arena and everything in it but fires, with light glowing from every cell a fire burns in
+//! and, more faintly, from bombs as their fuse burns, flickering unless flashing is reduced.
+//! Lights are laid anew every frame, following the match without being part of it.
+
+use bevy::prelude::*;
+
+use crate::{
+    fire_reach, settings::Settings, Bomb, Fire, Player, BOTTOM_WALL, BRICK_SIZE, GLOW_Z, LEFT_WALL,
+    NIGHT_Z, RIGHT_WALL, TOP_WALL,
+};
+
+const GLOW_TEXTURE: &str = "textures/glow.png";
+const NIGHT_COLOR: Color = Color::rgba(0.02, 0.02, 0.1, 0.75);
+const FIRE_LIGHT: Color = Color::rgba(1.0, 0.6, 0.2, 0.45);
+const BOMB_LIGHT: Color = Color::rgba(1.0, 0.7, 0.3, 0.25);
+// size of the light cast by every cell of a fire, and by a bomb
+const FIRE_LIGHT_SIZE: Vec2 = Vec2::new(BRICK_SIZE.x * 3., BRICK_SIZE.y * 3.);
+const BOMB_LIGHT_SIZE: Vec2 = Vec2::new(BRICK_SIZE.x * 2., BRICK_SIZE.y * 2.);
+// times a second the light of bombs flickers
+const FLICKER_RATE: f32 = 8.;
+
+/// This resource holds the texture lights are drawn with
+pub struct Glow(Handle<Image>);
+
+/// The shade laid over the arena at night
+#[derive(Component)]
+pub struct Night;
+
+/// Light cast by a bomb or a fire
+#[derive(Component)]
+pub struct Light;
+
+// Load the texture of lights, and lay the shade of night, hidden until needed
+pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Glow(asset_server.load(GLOW_TEXTURE)));
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: NIGHT_COLOR,
+                ..default()
+            },
+            transform: Transform {
+                translation: Vec3::new(0., 0., NIGHT_Z),
+                // well beyond the walls, wherever the camera looks
+                scale: Vec3::new(
+                    (RIGHT_WALL - LEFT_WALL) * 4.,
+                    (TOP_WALL - BOTTOM_WALL) * 4.,
+                    1.0,
+                ),
+                ..default()
+            },
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(Night);
+}
+
+/// Casts a light of the given color and size at the given position
+#[allow(clippy::too_many_arguments)]
+fn spawn_light(commands: &mut Commands, glow: &Glow, position: Vec2, color: Color, size: Vec2) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(size),
+                ..default()
+            },
+            texture: glow.0.clone(),
+            transform: Transform::from_translation(position.extend(GLOW_Z)),
+            ..default()
+        })
+        .insert(Light);
+}
+
+// Darken the arena while matches are played at night, and light it up around bombs and fires
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn shine(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    glow: Res<Glow>,
+    mut night_query: Query<&mut Visibility, With<Night>>,
+    light_query: Query<Entity, With<Light>>,
+    player_query: Query<(), With<Player>>,
+    bomb_query: Query<(&Transform, &Visibility), (With<Bomb>, Without<Night>)>,
+    fire_query: Query<&Transform, With<Fire>>,
+) {
+    for entity in &light_query {
+        commands.entity(entity).despawn();
+    }
+    // only arenas with someone in them are darkened, not the editor
+    let night = settings.night_mode && !player_query.is_empty();
+    for mut visibility in &mut night_query {
+        visibility.is_visible = night;
+    }
+    if !night {
+        return;
+    }
+
+    for transform in &fire_query {
+        let (axis, reach) = fire_reach(transform.scale);
+        for cell in -reach..=reach {
+            let position = transform.translation.truncate() + axis * BRICK_SIZE * cell as f32;
+            spawn_light(&mut commands, &glow, position, FIRE_LIGHT, FIRE_LIGHT_SIZE);
+        }
+    }
+    let flicker = if settings.reduced_flashing {
+        1.
+    } else {
+        1. + 0.1 * (time.seconds_since_startup() as f32 * FLICKER_RATE).sin()
+    };
+    for (transform, visibility) in &bomb_query {
+        // bombs that can't be seen don't give themselves away
+        if visibility.is_visible {
+            let position = transform.translation.truncate();
+            spawn_light(
+                &mut commands,
+                &glow,
+                position,
+                BOMB_LIGHT,
+                BOMB_LIGHT_SIZE * flicker,
+            );
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Lobby screen, shown instead of the menu when hosting an online match.
+
+use bevy::prelude::*;
+
+use crate::{
+    bot::Roster,
+    level::{arena_label, Levels},
+    locale::Locale,
+    net::{chance_label, time_limit_label, Host},
+    AppState, Rules, SCORE_COLOR, TEXT_COLOR,
+};
+
+const LOBBY_FONT_SIZE: f32 = 30.0;
+const MAX_ROUNDS: u32 = 9;
+// round lengths to choose from, in seconds
+const TIME_LIMITS: [Option<u32>; 5] = [None, Some(60), Some(120), Some(180), Some(300)];
+// chance steps of power-ups and bricks, from none to every cell
+const CHANCE_STEP: f64 = 0.1;
+
+/// Marks every entity belonging to the lobby
+#[derive(Component)]
+pub struct Lobby;
+
+/// Something the host can change in the lobby
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Setting {
+    Arena,
+    Opponents,
+    Rounds,
+    TimeLimit,
+    PowerUps,
+    Bricks,
+    Rubble,
+}
+
+impl Setting {
+    const ALL: [Setting; 7] = [
+        Setting::Arena,
+        Setting::Opponents,
+        Setting::Rounds,
+        Setting::TimeLimit,
+        Setting::PowerUps,
+        Setting::Bricks,
+        Setting::Rubble,
+    ];
+
+    fn label(&self, roster: &Roster, rules: &Rules, locale: &Locale) -> String {
+        match self {
+            Setting::Arena => {
+                locale.format("lobby.arena", &[("arena", &arena_label(rules, locale))])
+            }
+            Setting::Opponents => {
+                locale.format("lobby.opponents", &[("opponents", &roster.bots.len())])
+            }
+            Setting::Rounds => locale.format("lobby.rounds", &[("rounds", &rules.rounds)]),
+            Setting::TimeLimit => locale.format(
+                "lobby.time_limit",
+                &[("limit", &time_limit_label(rules.time_limit, locale))],
+            ),
+            Setting::PowerUps => locale.format(
+                "lobby.power_ups",
+                &[("chance", &chance_label(rules.power_up_chance))],
+            ),
+            Setting::Bricks => locale.format(
+                "lobby.bricks",
+                &[("chance", &chance_label(rules.brick_density))],
+            ),
+            Setting::Rubble => locale.format(
+                "lobby.rubble",
+                &[("chance", &chance_label(rules.rubble_chance))],
+            ),
+        }
+    }
+
+    /// Moves the setting one step up, or down
+    fn change(&self, roster: &mut Roster, rules: &mut Rules, levels: &Levels, up: bool) {
+        match self {
+            Setting::Arena => levels.cycle(rules, up),
+            Setting::Opponents => {
+                let count = roster.bots.len();
+                roster.resize(if up {
+                    count + 1
+                } else {
+                    count.saturating_sub(1)
+                });
+            }
+            Setting::Rounds => {
+                rules.rounds = if up {
+                    (rules.rounds + 1).min(MAX_ROUNDS)
+                } else {
+                    rules.rounds.saturating_sub(1).max(1)
+                };
+            }
+            Setting::TimeLimit => {
+                let current = TIME_LIMITS
+                    .iter()
+                    .position(|limit| *limit == rules.time_limit)
+                    .unwrap_or_default();
+                let next = if up {
+                    (current + 1).min(TIME_LIMITS.len() - 1)
+                } else {
+                    current.saturating_sub(1)
+                };
+                rules.time_limit = TIME_LIMITS[next];
+            }
+            Setting::PowerUps => step_chance(&mut rules.power_up_chance, up),
+            Setting::Bricks => step_chance(&mut rules.brick_density, up),
+            Setting::Rubble => step_chance(&mut rules.rubble_chance, up),
+        }
+    }
+}
+
+/// Moves a chance one step up, or down
+fn step_chance(chance: &mut f64, up: bool) {
+    let step = if up { CHANCE_STEP } else { -CHANCE_STEP };
+    // rounded to the step, so that repeated changes don't drift
+    let steps = ((*chance + step) / CHANCE_STEP).round();
+    *chance = (steps * CHANCE_STEP).clamp(0., 1.);
+}
+
+/// A line of the lobby showing one of the settings
+#[derive(Component)]
+pub struct SettingRow(Setting);
+
+/// Marks the text listing the players
+#[derive(Component)]
+pub struct PlayerList;
+
+/// This resource tracks the setting being changed
+#[derive(Default)]
+pub struct Selection(usize);
+
+pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>, locale: Res<Locale>) {
+    commands.insert_resource(Selection::default());
+
+    let style = TextStyle {
+        font: asset_server.load(locale.font()),
+        font_size: LOBBY_FONT_SIZE,
+        color: TEXT_COLOR,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Lobby)
+        .with_children(|parent| {
+            for setting in Setting::ALL {
+                parent
+                    .spawn_bundle(TextBundle::from_section("", style.clone()))
+                    .insert(SettingRow(setting));
+            }
+            parent
+                .spawn_bundle(TextBundle::from_section("", style.clone()))
+                .insert(PlayerList);
+            parent.spawn_bundle(TextBundle::from_section(locale.text("lobby.help"), style));
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn navigate(
+    keyboard_input: Res<Input<KeyCode>>,
+    host: Res<Host>,
+    levels: Res<Levels>,
+    locale: Res<Locale>,
+    mut selection: ResMut<Selection>,
+    mut roster: ResMut<Roster>,
+    mut rules: ResMut<Rules>,
+    mut state: ResMut<State<AppState>>,
+    mut row_query: Query<(&SettingRow, &mut Text), Without<PlayerList>>,
+    mut list_query: Query<&mut Text, With<PlayerList>>,
+) {
+    let rows = Setting::ALL.len();
+    let setting = Setting::ALL[selection.0];
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        selection.0 = (selection.0 + rows - 1) % rows;
+    } else if keyboard_input.just_pressed(KeyCode::Down) {
+        selection.0 = (selection.0 + 1) % rows;
+    } else if keyboard_input.just_pressed(KeyCode::Left) {
+        setting.change(&mut roster, &mut rules, &levels, false);
+    } else if keyboard_input.just_pressed(KeyCode::Right) {
+        setting.change(&mut roster, &mut rules, &levels, true);
+    } else if keyboard_input.just_pressed(KeyCode::Space) && host.ready() {
+        // fails only if a transition is already queued
+        let _ = state.set(AppState::InGame);
+    }
+
+    for (row, mut text) in &mut row_query {
+        let section = &mut text.sections[0];
+        section.value = row.0.label(&roster, &rules, &locale);
+        section.style.color = if row.0 == Setting::ALL[selection.0] {
+            SCORE_COLOR
+        } else {
+            TEXT_COLOR
+        };
+    }
+
+    if let Ok(mut text) = list_query.get_single_mut() {
+        let style = text.sections[0].style.clone();
+        let lobby = host.lobby(&roster, &rules);
+        let mut sections = vec![TextSection::new("\n", style.clone())];
+        for (line, color) in lobby.player_lines(None, &locale) {
+            sections.push(TextSection::new(
+                line + "\n",
+                TextStyle {
+                    color,
+                    ..style.clone()
+                },
+            ));
+        }
+        text.sections = sections;
+    }
+}
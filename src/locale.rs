@@ -0,0 +1,233 @@
+//! Text shown to players, in the language they pick.
+//!
+//! Every string the game shows comes from a locale file in [`LOCALES_DIR`], named after the
+//! code of its language, like `fr.toml`, and looked up by key, like `menu.humans`: keys are
+//! grouped in tables, one for every screen, and strings may hold named arguments, like
+//! `{player}`, filled in when shown. English is built into the game and stands in for whatever
+//! a locale leaves out, so that translations can be partial:
+//!
+//! ```toml
+//! name = "Français"
+//! font = "fonts/FiraSans-Bold.ttf"
+//! mono_font = "fonts/FiraMono-Medium.ttf"
+//!
+//! [menu]
+//! humans = "Humains : "
+//! ```
+//!
+//! Texts are drawn with the fonts of the locale, `font` for words and `mono_font` for numbers
+//! lined up in columns, so that scripts the Fira fonts have no glyphs for can ship fonts of their
+//! own; locales naming a font missing from `assets` fall back to the English one. The language
+//! is picked from the options screen, screens shown afterwards speak it.
+//! Browsers can't list nor read the files of [`LOCALES_DIR`], so WASM builds speak the locales
+//! built into them instead.
+
+use bevy::{prelude::*, utils::HashMap};
+
+use std::fmt::Display;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, path::Path};
+
+use crate::settings::Settings;
+
+/// Where locale files are stored
+pub const LOCALES_DIR: &str = "assets/locales";
+/// Language the game is written in, built into it
+pub const ENGLISH: &str = "en";
+
+// English strings, the reference every other locale translates
+const ENGLISH_STRINGS: &str = include_str!("../assets/locales/en.toml");
+// locales built into browser builds, by code, English aside
+#[cfg(target_arch = "wasm32")]
+const BUILT_IN: [(&str, &str); 1] = [("fr", include_str!("../assets/locales/fr.toml"))];
+// fonts of the English locale, standing in for missing ones
+const DEFAULT_FONT: &str = "fonts/FiraSans-Bold.ttf";
+const DEFAULT_MONO_FONT: &str = "fonts/FiraMono-Medium.ttf";
+
+/// This resource holds the strings of the language being played in
+pub struct Locale {
+    /// Code of the language, naming its file
+    pub code: String,
+    strings: HashMap<String, String>,
+    // English strings, for whatever the language leaves out
+    fallback: HashMap<String, String>,
+    font: String,
+    mono_font: String,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::load(ENGLISH)
+    }
+}
+
+impl Locale {
+    /// Reads the locale of the given language, falling back to English for what it's missing
+    pub fn load(code: &str) -> Self {
+        let fallback = parse(ENGLISH_STRINGS).unwrap_or_else(|e| {
+            warn!("Invalid English locale: {e}");
+            HashMap::default()
+        });
+        let strings = if code == ENGLISH {
+            HashMap::default()
+        } else {
+            read(code)
+                .and_then(|content| parse(&content))
+                .unwrap_or_else(|e| {
+                    warn!("Can't read locale {code}: {e}");
+                    HashMap::default()
+                })
+        };
+        let font = |key: &str, default: &str| match strings.get(key) {
+            Some(font) if has_font(font) => font.clone(),
+            Some(font) => {
+                warn!("Missing font {font} of locale {code}, falling back to {default}");
+                default.to_string()
+            }
+            None => default.to_string(),
+        };
+        Locale {
+            code: code.to_string(),
+            font: font("font", DEFAULT_FONT),
+            mono_font: font("mono_font", DEFAULT_MONO_FONT),
+            strings,
+            fallback,
+        }
+    }
+
+    /// The string of the given key, in English if the language has none, or the key itself
+    pub fn text<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings
+            .get(key)
+            .or_else(|| self.fallback.get(key))
+            .map_or(key, String::as_str)
+    }
+
+    /// The string of the given key, with its arguments filled in
+    pub fn format(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        args.iter()
+            .fold(self.text(key).to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{name}}}"), &value.to_string())
+            })
+    }
+
+    /// Path of the font words are drawn with, within `assets`
+    pub fn font(&self) -> &str {
+        &self.font
+    }
+
+    /// Path of the font numbers lined up in columns are drawn with, within `assets`
+    pub fn mono_font(&self) -> &str {
+        &self.mono_font
+    }
+
+    /// How a setting turned on or off is shown
+    pub fn switch(&self, on: bool) -> &str {
+        self.text(if on { "switch.on" } else { "switch.off" })
+    }
+}
+
+/// The content of the locale file of the given language
+#[cfg(not(target_arch = "wasm32"))]
+fn read(code: &str) -> Result<String, String> {
+    let path = Path::new(LOCALES_DIR).join(format!("{code}.toml"));
+    fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// The content of the locale built in for the given language
+#[cfg(target_arch = "wasm32")]
+fn read(code: &str) -> Result<String, String> {
+    BUILT_IN
+        .iter()
+        .find(|(other, _)| *other == code)
+        .map(|(_, content)| content.to_string())
+        .ok_or_else(|| "not built in".to_string())
+}
+
+/// Whether the font of the given path is among the assets
+#[cfg(not(target_arch = "wasm32"))]
+fn has_font(font: &str) -> bool {
+    Path::new("assets").join(font).exists()
+}
+
+/// Whether the font of the given path is among the assets, which browsers can only tell by
+/// downloading it, so built in locales are trusted to name fonts shipped along with them
+#[cfg(target_arch = "wasm32")]
+fn has_font(_font: &str) -> bool {
+    true
+}
+
+/// Every key of the given locale file, along with its string, tables naming keys with dots
+fn parse(content: &str) -> Result<HashMap<String, String>, String> {
+    let value = content.parse::<toml::Value>().map_err(|e| e.to_string())?;
+    let mut strings = HashMap::default();
+    flatten(String::new(), value, &mut strings);
+    Ok(strings)
+}
+
+fn flatten(prefix: String, value: toml::Value, strings: &mut HashMap<String, String>) {
+    match value {
+        toml::Value::String(text) => {
+            strings.insert(prefix, text);
+        }
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(key, value, strings);
+            }
+        }
+        _ => warn!("Ignoring {prefix} in locale, it's not a string"),
+    }
+}
+
+/// Codes of the languages there are locales for, English first
+#[cfg(not(target_arch = "wasm32"))]
+pub fn languages() -> Vec<String> {
+    let mut codes = fs::read_dir(LOCALES_DIR)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == "toml")
+                })
+                .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+                .filter(|code| code != ENGLISH)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    codes.sort();
+    codes.insert(0, ENGLISH.to_string());
+    codes
+}
+
+/// Codes of the languages built in, English first
+#[cfg(target_arch = "wasm32")]
+pub fn languages() -> Vec<String> {
+    std::iter::once(ENGLISH)
+        .chain(BUILT_IN.iter().map(|(code, _)| *code))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The language after the given one, going back to the first after the last
+pub fn next_language(code: &str) -> String {
+    let codes = languages();
+    let index = codes
+        .iter()
+        .position(|other| other == code)
+        .map_or(0, |index| index + 1);
+    codes[index % codes.len()].clone()
+}
+
+// Switch languages as soon as another one is picked
+pub fn apply(settings: Res<Settings>, mut locale: ResMut<Locale>) {
+    if settings.is_changed() && settings.language != locale.code {
+        *locale = Locale::load(&settings.language);
+    }
+}
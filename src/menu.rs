@@ -0,0 +1,290 @@
+//! Match setup screen.
+
+use bevy::prelude::*;
+
+use crate::{
+    bot::{Roster, MAX_HUMANS},
+    campaign::Campaign,
+    daily::Daily,
+    level::{arena_label, Levels},
+    locale::Locale,
+    mutator::{mutators_label, toggle, Mutator},
+    net::COLORS,
+    save::{SavedMatch, SAVE_PATH},
+    settings::Settings,
+    tutorial::Tutorial,
+    AppState, Rules, Seed, SCORE_COLOR, TEXT_COLOR,
+};
+
+const MENU_FONT_SIZE: f32 = 40.0;
+
+/// Marks every entity belonging to the menu
+#[derive(Component)]
+pub struct Menu;
+
+/// Marks the text showing the number of opponents
+#[derive(Component)]
+pub struct BotCount;
+
+/// Marks the text showing the number of human players
+#[derive(Component)]
+pub struct HumanCount;
+
+/// Marks the text showing the arena played in
+#[derive(Component)]
+pub struct ArenaName;
+
+/// Marks the text showing what matches are about
+#[derive(Component)]
+pub struct Mode;
+
+/// Marks the text showing the mutators matches are played with
+#[derive(Component)]
+pub struct MutatorList;
+
+/// Marks the text showing the colors of local humans
+#[derive(Component)]
+pub struct ColorList;
+
+// keys toggling mutators, in the order of `Mutator::ALL`
+const MUTATOR_KEYS: [KeyCode; 5] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+];
+// keys switching the colors of local humans, one for each of them
+const COLOR_KEYS: [KeyCode; MAX_HUMANS] =
+    [KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9];
+
+#[allow(clippy::too_many_arguments)]
+pub fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+    roster: Res<Roster>,
+    rules: Res<Rules>,
+) {
+    let font = asset_server.load(locale.font());
+    let style = TextStyle {
+        font: font.clone(),
+        font_size: MENU_FONT_SIZE,
+        color: TEXT_COLOR,
+    };
+    let value_style = TextStyle {
+        font,
+        font_size: MENU_FONT_SIZE,
+        color: SCORE_COLOR,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Menu)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle::from_sections([
+                    TextSection::new(locale.text("menu.humans"), style.clone()),
+                    TextSection::new(humans_label(&roster, &locale), value_style.clone()),
+                ]))
+                .insert(HumanCount);
+            parent
+                .spawn_bundle(TextBundle::from_sections(
+                    std::iter::once(TextSection::new(locale.text("menu.colors"), style.clone()))
+                        .chain((0..MAX_HUMANS).map(|_| TextSection::new("", value_style.clone()))),
+                ))
+                .insert(ColorList);
+            parent
+                .spawn_bundle(TextBundle::from_sections([
+                    TextSection::new(locale.text("menu.opponents"), style.clone()),
+                    TextSection::new(roster.bots.len().to_string(), value_style.clone()),
+                ]))
+                .insert(BotCount);
+            parent
+                .spawn_bundle(TextBundle::from_sections([
+                    TextSection::new(locale.text("menu.arena"), style.clone()),
+                    TextSection::new(arena_label(&rules, &locale), value_style.clone()),
+                ]))
+                .insert(ArenaName);
+            parent
+                .spawn_bundle(TextBundle::from_sections([
+                    TextSection::new(locale.text("menu.mode"), style.clone()),
+                    TextSection::new(mode_label(&rules, &locale), value_style.clone()),
+                ]))
+                .insert(Mode);
+            parent
+                .spawn_bundle(TextBundle::from_sections([
+                    TextSection::new(locale.text("menu.mutators"), style.clone()),
+                    TextSection::new(mutators_label(&rules.mutators, &locale), value_style),
+                ]))
+                .insert(MutatorList);
+            parent.spawn_bundle(TextBundle::from_section(locale.text("menu.help"), style));
+        });
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn navigate(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    levels: Res<Levels>,
+    locale: Res<Locale>,
+    mut roster: ResMut<Roster>,
+    mut rules: ResMut<Rules>,
+    mut seed: ResMut<Seed>,
+    mut settings: ResMut<Settings>,
+    mut state: ResMut<State<AppState>>,
+    mut text_query: ParamSet<(
+        Query<&mut Text, With<BotCount>>,
+        Query<&mut Text, With<HumanCount>>,
+        Query<&mut Text, With<ArenaName>>,
+        Query<&mut Text, With<Mode>>,
+        Query<&mut Text, With<MutatorList>>,
+        Query<&mut Text, With<ColorList>>,
+    )>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        roster.humans = roster.humans.saturating_sub(1);
+    }
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        roster.humans = (roster.humans + 1).min(MAX_HUMANS - roster.remotes);
+        // make room for the new human
+        let count = roster.bots.len();
+        roster.resize(count);
+    }
+    if keyboard_input.just_pressed(KeyCode::Left) {
+        let count = roster.bots.len().saturating_sub(1);
+        roster.resize(count);
+    }
+    if keyboard_input.just_pressed(KeyCode::Right) {
+        let count = roster.bots.len() + 1;
+        roster.resize(count);
+    }
+    if keyboard_input.just_pressed(KeyCode::A) {
+        levels.cycle(&mut rules, true);
+    }
+    if keyboard_input.just_pressed(KeyCode::M) {
+        rules.mode = rules.mode.next();
+    }
+    if keyboard_input.just_pressed(KeyCode::F) {
+        rules.friendly_fire = !rules.friendly_fire;
+    }
+    if keyboard_input.just_pressed(KeyCode::G) {
+        rules.classic_movement = !rules.classic_movement;
+    }
+    if keyboard_input.just_pressed(KeyCode::P) {
+        rules.solid_players = !rules.solid_players;
+    }
+    if keyboard_input.just_pressed(KeyCode::D) {
+        rules.diagonals = !rules.diagonals;
+    }
+    for (key, mutator) in MUTATOR_KEYS.into_iter().zip(Mutator::ALL) {
+        if keyboard_input.just_pressed(key) {
+            toggle(&mut rules.mutators, mutator);
+        }
+    }
+    for (human, key) in COLOR_KEYS.into_iter().enumerate() {
+        if keyboard_input.just_pressed(key) && human < roster.humans {
+            // the next color no other human plays with
+            let color = (1..COLORS.len())
+                .map(|offset| (settings.color(human) + offset) % COLORS.len())
+                .find(|color| !roster.colors.iter().any(|other| *other == COLORS[*color]))
+                .unwrap_or_else(|| settings.color(human));
+            roster.colors[human] = COLORS[color];
+            settings.set_color(human, color);
+            settings.save();
+        }
+    }
+    if let Ok(mut text) = text_query.p0().get_single_mut() {
+        text.sections[1].value = roster.bots.len().to_string();
+    }
+    if let Ok(mut text) = text_query.p1().get_single_mut() {
+        text.sections[1].value = humans_label(&roster, &locale);
+    }
+    if let Ok(mut text) = text_query.p2().get_single_mut() {
+        text.sections[1].value = arena_label(&rules, &locale);
+    }
+    if let Ok(mut text) = text_query.p3().get_single_mut() {
+        text.sections[1].value = mode_label(&rules, &locale);
+    }
+    if let Ok(mut text) = text_query.p4().get_single_mut() {
+        text.sections[1].value = mutators_label(&rules.mutators, &locale);
+    }
+    if let Ok(mut text) = text_query.p5().get_single_mut() {
+        for (human, section) in text.sections.iter_mut().skip(1).enumerate() {
+            section.value = if human < roster.humans {
+                locale.format("menu.color", &[("player", &(human + 1))])
+            } else {
+                String::new()
+            };
+            section.style.color = settings.color_vision.adjust(roster.color(human));
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        // fails only if a transition is already queued
+        let _ = state.set(AppState::InGame);
+    } else if keyboard_input.just_pressed(KeyCode::O) {
+        let _ = state.set(AppState::Options);
+    } else if keyboard_input.just_pressed(KeyCode::E) {
+        let _ = state.set(AppState::Editor);
+    } else if keyboard_input.just_pressed(KeyCode::R) {
+        let _ = state.set(AppState::Records);
+    } else if keyboard_input.just_pressed(KeyCode::K) {
+        let _ = state.set(AppState::Mods);
+    } else if keyboard_input.just_pressed(KeyCode::C) && state.set(AppState::InGame).is_ok() {
+        // the menu's own settings are given back once the campaign is left
+        commands.insert_resource(Campaign::start(&mut rules, &mut roster));
+    } else if keyboard_input.just_pressed(KeyCode::T) && state.set(AppState::InGame).is_ok() {
+        commands.insert_resource(Daily::start(&mut rules, &mut roster, &mut seed));
+    } else if keyboard_input.just_pressed(KeyCode::H) && state.set(AppState::InGame).is_ok() {
+        commands.insert_resource(Tutorial::start(&mut rules, &mut roster, &mut seed));
+    } else if keyboard_input.just_pressed(KeyCode::L) {
+        match SavedMatch::load() {
+            Ok(saved) if state.set(AppState::InGame).is_ok() => {
+                saved.prepare(&mut commands, &mut rules, &mut roster);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Can't resume the match saved in {SAVE_PATH}: {e}"),
+        }
+    }
+}
+
+fn humans_label(roster: &Roster, locale: &Locale) -> String {
+    if roster.remotes > 0 {
+        locale.format(
+            "menu.online",
+            &[("humans", &roster.humans), ("remotes", &roster.remotes)],
+        )
+    } else if roster.humans == 0 {
+        locale.text("menu.spectate").to_string()
+    } else {
+        roster.humans.to_string()
+    }
+}
+
+fn mode_label(rules: &Rules, locale: &Locale) -> String {
+    let mut label = locale.text(rules.mode.key()).to_string();
+    if rules.mode == crate::Mode::Teams && !rules.friendly_fire {
+        label.push_str(locale.text("menu.no_friendly_fire"));
+    }
+    if rules.classic_movement {
+        label.push_str(locale.text("menu.classic_movement"));
+    }
+    if rules.solid_players {
+        label.push_str(locale.text("menu.solid_players"));
+    }
+    if rules.diagonals {
+        label.push_str(locale.text("menu.diagonals"));
+    }
+    label
+}
@@ -0,0 +1,283 @@
+//! Roaming enemies of stages, the classic balloons drifting around the arena.
+//!
+//! Mobs don't place bombs: they wander from cell to cell, now and then turning at random and
+//! turning around whenever something blocks their way.
+//! Touching one is deadly for players, while a single blast is enough to pop them, for points.
+//! Every [`MobKind`] moves at its own speed, may chase humans coming close enough and may float
+//! through breakable bricks, as listed in [`MOB_TABLE`].
+//! They're spawned along with stage arenas, as many as the [`Rules`](crate::Rules) ask for,
+//! and a stage can only be left once they're all gone.
+
+use bevy::{prelude::*, sprite::collide_aabb::collide};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::{Layout, Tile},
+    boss::Boss,
+    cell_center, cell_position, Active, Bomb, Breakable, Brick, DeathEvent, Direction, Fire,
+    Flying, GameOverEvent, Match, MatchRng, Player, Scoreboard, BOTTOM_WALL, BRICK_SIZE, LEFT_WALL,
+    PLAYER_Z, RIGHT_WALL, TOP_WALL,
+};
+
+const MOB_SIZE: Vec2 = Vec2::new(36., 36.);
+// mobs never start this close to a spawn point, counting cells along rows and columns
+const MOB_SPAWN_DISTANCE: usize = 3;
+
+/// The kinds of mobs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MobKind {
+    /// Slow and aimless
+    Balloon,
+    /// Floats through breakable bricks, following humans close by
+    Ghost,
+    /// Fast, and chases humans from afar
+    Hound,
+}
+
+/// How a kind of mob behaves
+pub struct MobSpec {
+    color: Color,
+    /// Distance moved every step, which must divide the size of a cell
+    speed: f32,
+    /// Chance to turn at random when reaching the middle of a cell
+    turn_chance: f64,
+    /// Cells within which humans get chased, zero meaning they're never chased
+    pursuit: f32,
+    /// Whether breakable bricks don't stop the mob
+    phasing: bool,
+    /// Points earned by popping the mob
+    points: usize,
+}
+
+/// Behavior of every kind of mob, in the order of [`MobKind`]
+pub static MOB_TABLE: [MobSpec; 3] = [
+    MobSpec {
+        color: Color::rgb(0.95, 0.55, 0.65),
+        speed: BRICK_SIZE.x / 25.,
+        turn_chance: 0.25,
+        pursuit: 0.,
+        phasing: false,
+        points: 100,
+    },
+    MobSpec {
+        color: Color::rgba(0.85, 0.85, 1.0, 0.7),
+        speed: BRICK_SIZE.x / 50.,
+        turn_chance: 0.25,
+        pursuit: 3.,
+        phasing: true,
+        points: 300,
+    },
+    MobSpec {
+        color: Color::rgb(0.55, 0.25, 0.15),
+        speed: BRICK_SIZE.x / 20.,
+        turn_chance: 0.1,
+        pursuit: 5.,
+        phasing: false,
+        points: 500,
+    },
+];
+
+impl MobKind {
+    pub fn spec(&self) -> &'static MobSpec {
+        &MOB_TABLE[*self as usize]
+    }
+}
+
+/// An enemy roaming the arena
+#[derive(Component)]
+pub struct Mob {
+    kind: MobKind,
+    direction: Direction,
+}
+
+/// Places the given mobs on free cells of the arena, away from spawn points
+pub fn spawn_mobs(
+    commands: &mut Commands,
+    layout: &Layout,
+    spawn_points: &[(usize, usize)],
+    mobs: &[MobKind],
+    rng: &mut MatchRng,
+) {
+    let mut cells = layout
+        .cells()
+        .filter(|(_, tile)| *tile == Tile::Empty)
+        .map(|(cell, _)| cell)
+        .filter(|&(col, row)| {
+            spawn_points.iter().all(|&(spawn_col, spawn_row)| {
+                col.abs_diff(spawn_col) + row.abs_diff(spawn_row) >= MOB_SPAWN_DISTANCE
+            })
+        })
+        .collect::<Vec<_>>();
+    for &kind in mobs {
+        if cells.is_empty() {
+            break;
+        }
+        let (col, row) = cells.swap_remove(rng.0.gen_range(0..cells.len()));
+        let direction = Direction::ALL[rng.0.gen_range(0..Direction::ALL.len())];
+        spawn_mob(commands, kind, cell_position(col, row), direction);
+    }
+}
+
+/// Places a mob in the middle of a cell, heading towards the given direction
+pub fn spawn_mob(commands: &mut Commands, kind: MobKind, position: Vec2, direction: Direction) {
+    commands
+        .spawn()
+        .insert(Mob { kind, direction })
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: kind.spec().color,
+                ..default()
+            },
+            transform: Transform {
+                // above bricks, that some mobs float through
+                translation: position.extend(PLAYER_Z),
+                scale: MOB_SIZE.extend(1.0),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Match);
+}
+
+// Move mobs along, picking a new way in the middle of cells
+#[allow(clippy::type_complexity)]
+pub fn roam(
+    mut rng: ResMut<MatchRng>,
+    obstacle_query: Query<
+        (&Transform, Option<&Breakable>),
+        (Or<(With<Brick>, With<Bomb>)>, Without<Mob>),
+    >,
+    human_query: Query<&Transform, (With<Active>, Without<Mob>)>,
+    mut mob_query: Query<(&mut Mob, &mut Transform)>,
+) {
+    let rng = &mut rng.0;
+    let free = |cell: Vec2, phasing: bool| {
+        cell.x > LEFT_WALL
+            && cell.x < RIGHT_WALL
+            && cell.y > BOTTOM_WALL
+            && cell.y < TOP_WALL
+            && !obstacle_query.iter().any(|(obstacle, breakable)| {
+                obstacle.translation.truncate().distance(cell) < 1.
+                    && !(phasing && breakable.is_some())
+            })
+    };
+
+    for (mut mob, mut transform) in &mut mob_query {
+        let spec = mob.kind.spec();
+        let center = cell_center(transform.translation);
+        if transform.translation.truncate().distance(center) >= spec.speed / 2. {
+            // a bomb dropped in the way sends the mob back where it came from
+            let entering = cell_center(
+                transform.translation + (mob.direction.vector() * BRICK_SIZE / 2.).extend(0.),
+            );
+            if !free(entering, spec.phasing) {
+                mob.direction = opposite(mob.direction);
+            }
+        } else {
+            let ways = Direction::ALL
+                .into_iter()
+                .filter(|direction| free(center + direction.vector() * BRICK_SIZE, spec.phasing))
+                .collect::<Vec<_>>();
+            // the closest human in reach, if any
+            let prey = human_query
+                .iter()
+                .map(|human| human.translation.truncate())
+                .filter(|human| human.distance(center) < spec.pursuit * BRICK_SIZE.x)
+                .min_by(|a, b| a.distance(center).total_cmp(&b.distance(center)));
+            if ways.is_empty() {
+                // walled in, waiting for a way out
+                continue;
+            } else if let Some(prey) = prey {
+                mob.direction = ways
+                    .into_iter()
+                    .min_by(|a, b| {
+                        let distance = |direction: &Direction| {
+                            (center + direction.vector() * BRICK_SIZE).distance(prey)
+                        };
+                        distance(a).total_cmp(&distance(b))
+                    })
+                    .unwrap_or(mob.direction);
+            } else if !ways.contains(&mob.direction) || rng.gen_bool(spec.turn_chance) {
+                mob.direction = ways[rng.gen_range(0..ways.len())];
+            }
+        }
+        transform.translation += (mob.direction.vector() * spec.speed).extend(0.);
+    }
+}
+
+// Kill players touching mobs or the boss, ending the match once no human is left
+#[allow(clippy::type_complexity)]
+pub fn touch(
+    mob_query: Query<&Transform, Or<(With<Mob>, With<Boss>)>>,
+    player_query: Query<(Entity, &Transform, Option<&Active>), (With<Player>, Without<Flying>)>,
+    mut death_writer: EventWriter<DeathEvent>,
+    mut game_over_writer: EventWriter<GameOverEvent>,
+) {
+    let mut humans = player_query
+        .iter()
+        .filter(|(_, _, active)| active.is_some())
+        .count();
+    let mut killed = false;
+    for (entity, transform, active) in &player_query {
+        let touched = mob_query.iter().any(|mob| {
+            collide(
+                mob.translation,
+                mob.scale.truncate(),
+                transform.translation,
+                transform.scale.truncate(),
+            )
+            .is_some()
+        });
+        if touched {
+            death_writer.send(DeathEvent {
+                player: entity,
+                killer: None,
+            });
+            if active.is_some() {
+                humans -= 1;
+                killed = true;
+            }
+        }
+    }
+    if killed && humans == 0 {
+        game_over_writer.send(GameOverEvent {
+            winner: None,
+            team: None,
+        });
+    }
+}
+
+// Pop mobs caught in a blast
+pub fn burn(
+    mut commands: Commands,
+    mut scoreboard: ResMut<Scoreboard>,
+    fire_query: Query<&Transform, With<Fire>>,
+    mob_query: Query<(Entity, &Mob, &Transform)>,
+) {
+    for (entity, mob, transform) in &mob_query {
+        let burnt = fire_query.iter().any(|fire| {
+            collide(
+                fire.translation,
+                fire.scale.truncate(),
+                transform.translation,
+                transform.scale.truncate(),
+            )
+            .is_some()
+        });
+        if burnt {
+            scoreboard.score += mob.kind.spec().points;
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::Up => Direction::Down,
+        Direction::Down => Direction::Up,
+        Direction::Left => Direction::Right,
+        Direction::Right => Direction::Left,
+    }
+}
@@ -0,0 +1,329 @@
+//! Content packs, dropped into [`MODS_DIR`] to add to the game or replace parts of it.
+//!
+//! Every folder of [`MODS_DIR`] is a pack, laid out like `assets`, holding any of:
+//!
+//! - `levels/*.toml`: more levels, replacing those of the game with the same name
+//! - `data/power_ups.ron`: power-ups replacing those of the game, see [`power_ups`](crate::power_ups)
+//! - `themes.toml`: colors repainting the themes, like `[ice]` then `brick = [0.6, 0.8, 0.9]`
+//! - `sounds/...`: sounds replacing those of the game with the same path
+//!
+//! along with an optional `pack.toml` naming the pack and giving its priority:
+//!
+//! ```toml
+//! name = "Winter"
+//! priority = 10
+//! ```
+//!
+//! Where packs clash, the one of highest priority wins, then the first by folder name.
+//! Packs found are enabled, unless they're turned off in the packs screen reached from the menu,
+//! which the settings remember. Changes apply when leaving that screen, except for the music,
+//! which is picked at launch. Browsers can't read folders, so they only know of the game itself.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{collections::HashMap, fs, io};
+
+use crate::{
+    announcer::Clips,
+    level::Levels,
+    locale::Locale,
+    power_ups::PowerUpRegistry,
+    settings::Settings,
+    sound::Sounds,
+    theme::{self, Repaint, Theme},
+    AppState, SCORE_COLOR, TEXT_COLOR,
+};
+
+/// Where content packs are looked for
+pub const MODS_DIR: &str = "mods";
+
+const MANIFEST_FILE: &str = "pack.toml";
+const THEMES_FILE: &str = "themes.toml";
+const MODS_FONT_SIZE: f32 = 30.0;
+
+/// What a pack tells about itself in its `pack.toml`
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Manifest {
+    name: Option<String>,
+    priority: i32,
+}
+
+/// A content pack
+#[derive(Clone, Debug)]
+pub struct Pack {
+    /// Name of its folder, which settings know it by
+    pub id: String,
+    /// Name it's shown by, its folder's unless it has one
+    pub name: String,
+    pub priority: i32,
+    pub enabled: bool,
+    path: PathBuf,
+}
+
+/// This resource holds the content packs found in [`MODS_DIR`], from the one of highest priority
+#[derive(Default)]
+pub struct Mods {
+    pub packs: Vec<Pack>,
+}
+
+impl Mods {
+    /// Looks for packs in [`MODS_DIR`], enabling all but the given ones
+    pub fn scan(disabled: &[String]) -> Self {
+        Mods::scan_dir(Path::new(MODS_DIR), disabled)
+    }
+
+    /// Looks for packs in the given folder, enabling all but the given ones
+    pub fn scan_dir(dir: &Path, disabled: &[String]) -> Self {
+        let mut packs = read_packs(dir);
+        for pack in &mut packs {
+            pack.enabled = !disabled.contains(&pack.id);
+        }
+        packs.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.id.cmp(&b.id)));
+        Mods { packs }
+    }
+
+    /// Folders of the packs turned off, as settings keep them
+    pub fn disabled(&self) -> Vec<String> {
+        self.packs
+            .iter()
+            .filter(|pack| !pack.enabled)
+            .map(|pack| pack.id.clone())
+            .collect()
+    }
+
+    /// The file at the given path within `assets`, as replaced by the enabled pack of highest
+    /// priority having one, if any does
+    pub fn file(&self, path: &str) -> Option<PathBuf> {
+        self.packs
+            .iter()
+            .filter(|pack| pack.enabled)
+            .map(|pack| pack.path.join(path))
+            .find(|file| file.is_file())
+    }
+
+    /// Path the asset server loads the given asset from, replaced by enabled packs
+    pub fn asset(&self, path: &str) -> String {
+        // the asset server takes absolute paths as they are, rather than within `assets`
+        self.file(path)
+            .and_then(|file| file.canonicalize().ok())
+            .map_or_else(
+                || path.to_string(),
+                |file| file.to_string_lossy().into_owned(),
+            )
+    }
+
+    /// The given folder within `assets` of every enabled pack having it, from the one of lowest
+    /// priority, so that those coming later win
+    pub fn dirs(&self, dir: &str) -> Vec<PathBuf> {
+        self.packs
+            .iter()
+            .rev()
+            .filter(|pack| pack.enabled)
+            .map(|pack| pack.path.join(dir))
+            .filter(|dir| dir.is_dir())
+            .collect()
+    }
+
+    /// Repaints themes as the enabled packs say, those of higher priority last
+    pub fn paint(&self) {
+        let repaints = self
+            .packs
+            .iter()
+            .rev()
+            .filter(|pack| pack.enabled)
+            .filter_map(|pack| read_themes(&pack.path.join(THEMES_FILE)))
+            .flatten()
+            .collect();
+        theme::repaint(repaints);
+    }
+}
+
+/// Every pack in the given folder, enabled
+#[cfg(not(target_arch = "wasm32"))]
+fn read_packs(dir: &Path) -> Vec<Pack> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            if e.kind() != io::ErrorKind::NotFound {
+                warn!("Can't read content packs from {}: {e}", dir.display());
+            }
+            return Vec::new();
+        }
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .map(|path| {
+            let id = path
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+            let manifest = match fs::read_to_string(path.join(MANIFEST_FILE)) {
+                Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                    warn!("Invalid {MANIFEST_FILE} in content pack {id}: {e}");
+                    Manifest::default()
+                }),
+                Err(_) => Manifest::default(),
+            };
+            Pack {
+                name: manifest.name.unwrap_or_else(|| id.clone()),
+                id,
+                priority: manifest.priority,
+                enabled: true,
+                path,
+            }
+        })
+        .collect()
+}
+
+/// Browsers can't read [`MODS_DIR`], they have no packs
+#[cfg(target_arch = "wasm32")]
+fn read_packs(_dir: &Path) -> Vec<Pack> {
+    Vec::new()
+}
+
+/// The themes repainted by the given file, if it's there and valid
+#[cfg(not(target_arch = "wasm32"))]
+fn read_themes(path: &Path) -> Option<Vec<(Theme, Repaint)>> {
+    let content = fs::read_to_string(path).ok()?;
+    toml::from_str::<HashMap<Theme, Repaint>>(&content)
+        .map(|themes| themes.into_iter().collect())
+        .map_err(|e| warn!("Invalid themes in {}: {e}", path.display()))
+        .ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_themes(_path: &Path) -> Option<Vec<(Theme, Repaint)>> {
+    None
+}
+
+/// Marks every entity belonging to the packs screen
+#[derive(Component)]
+pub struct ModsScreen;
+
+/// A line of the packs screen, showing the pack of the given index
+#[derive(Component)]
+pub struct PackLine(usize);
+
+/// This resource tracks the pack selected in the packs screen
+#[derive(Default)]
+pub struct Selection(usize);
+
+pub fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+    mods: Res<Mods>,
+) {
+    commands.insert_resource(Selection::default());
+
+    let style = TextStyle {
+        font: asset_server.load(locale.font()),
+        font_size: MODS_FONT_SIZE,
+        color: TEXT_COLOR,
+    };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(ModsScreen)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle::from_section(
+                locale.text("mods.title"),
+                TextStyle {
+                    color: SCORE_COLOR,
+                    ..style.clone()
+                },
+            ));
+            if mods.packs.is_empty() {
+                parent.spawn_bundle(TextBundle::from_section(
+                    locale.format("mods.none", &[("dir", &MODS_DIR)]),
+                    style.clone(),
+                ));
+            }
+            for index in 0..mods.packs.len() {
+                parent
+                    .spawn_bundle(TextBundle::from_section("", style.clone()))
+                    .insert(PackLine(index));
+            }
+            parent.spawn_bundle(TextBundle::from_section(locale.text("mods.help"), style));
+        });
+}
+
+pub fn navigate(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    locale: Res<Locale>,
+    mut mods: ResMut<Mods>,
+    mut selection: ResMut<Selection>,
+    mut state: ResMut<State<AppState>>,
+    mut query: Query<(&PackLine, &mut Text)>,
+) {
+    let rows = mods.packs.len().max(1);
+    if keyboard_input.clear_just_pressed(KeyCode::Escape) {
+        // the key press is consumed, or the menu would quit right away
+        let _ = state.set(AppState::Menu);
+    } else if keyboard_input.just_pressed(KeyCode::Up) {
+        selection.0 = (selection.0 + rows - 1) % rows;
+    } else if keyboard_input.just_pressed(KeyCode::Down) {
+        selection.0 = (selection.0 + 1) % rows;
+    } else if keyboard_input.just_pressed(KeyCode::Return) {
+        if let Some(pack) = mods.packs.get_mut(selection.0) {
+            pack.enabled = !pack.enabled;
+        }
+    }
+
+    for (line, mut text) in &mut query {
+        let pack = match mods.packs.get(line.0) {
+            Some(pack) => pack,
+            None => continue,
+        };
+        let section = &mut text.sections[0];
+        section.value = locale.format(
+            "mods.pack",
+            &[
+                ("name", &pack.name),
+                ("priority", &pack.priority),
+                ("switch", &locale.switch(pack.enabled)),
+            ],
+        );
+        section.style.color = if line.0 == selection.0 {
+            SCORE_COLOR
+        } else {
+            TEXT_COLOR
+        };
+    }
+}
+
+// Load the content of the packs enabled, once they've been picked, and remember the choice
+pub fn apply(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mods: Res<Mods>,
+    mut settings: ResMut<Settings>,
+    mut levels: ResMut<Levels>,
+    mut power_up_registry: ResMut<PowerUpRegistry>,
+) {
+    let disabled = mods.disabled();
+    if disabled == settings.disabled_mods {
+        return;
+    }
+    settings.disabled_mods = disabled;
+    settings.save();
+    *levels = Levels::load(&mods);
+    *power_up_registry = PowerUpRegistry::load(&mods);
+    mods.paint();
+    commands.insert_resource(ClearColor(Theme::default().palette().background));
+    commands.insert_resource(Sounds::load(&asset_server, &mods));
+    commands.insert_resource(Clips::load(&asset_server, &mods));
+}
@@ -0,0 +1,96 @@
+//! Mutators, toggles twisting the rules of a match.
+//!
+//! Any number of mutators can be picked in the menu, or by levels, and they're played along
+//! with every mode: systems they affect check the [`Rules`] for them.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{locale::Locale, Bomb, Rules};
+
+/// How much faster players move with [`Mutator::DoubleSpeed`]
+pub const SPEED_FACTOR: f32 = 2.;
+/// Power added to bombs with [`Mutator::GiantBlasts`]
+pub const GIANT_BLAST_POWER: u8 = 3;
+/// Seconds before bombs go off with [`Mutator::InstantFuse`]
+pub const INSTANT_FUSE_SECS: f32 = 0.3;
+/// Seconds bombs can't be seen for once placed, with [`Mutator::InvisibleBombs`]
+const INVISIBLE_BOMB_SECS: f32 = 1.0;
+
+/// The twists a match can be played with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mutator {
+    /// Players move twice as fast
+    DoubleSpeed,
+    /// Blasts reach much farther
+    GiantBlasts,
+    /// Bombs can't be seen for a while once placed
+    InvisibleBombs,
+    /// Destroyed bricks never leave power-ups behind
+    NoPickups,
+    /// Bombs go off almost right away
+    InstantFuse,
+}
+
+impl Mutator {
+    pub const ALL: [Mutator; 5] = [
+        Mutator::DoubleSpeed,
+        Mutator::GiantBlasts,
+        Mutator::InvisibleBombs,
+        Mutator::NoPickups,
+        Mutator::InstantFuse,
+    ];
+
+    /// Name of the mutator, the same whatever the language, on the command line
+    pub fn label(&self) -> &'static str {
+        match self {
+            Mutator::DoubleSpeed => "double speed",
+            Mutator::GiantBlasts => "giant blasts",
+            Mutator::InvisibleBombs => "invisible bombs",
+            Mutator::NoPickups => "no pickups",
+            Mutator::InstantFuse => "instant fuse",
+        }
+    }
+
+    /// Key of the name of the mutator among the strings of locales
+    fn key(&self) -> &'static str {
+        match self {
+            Mutator::DoubleSpeed => "mutator.double_speed",
+            Mutator::GiantBlasts => "mutator.giant_blasts",
+            Mutator::InvisibleBombs => "mutator.invisible_bombs",
+            Mutator::NoPickups => "mutator.no_pickups",
+            Mutator::InstantFuse => "mutator.instant_fuse",
+        }
+    }
+}
+
+/// Picks the given mutator if it isn't already, drops it otherwise
+pub fn toggle(mutators: &mut Vec<Mutator>, mutator: Mutator) {
+    if let Some(index) = mutators.iter().position(|other| *other == mutator) {
+        mutators.remove(index);
+    } else {
+        mutators.push(mutator);
+    }
+}
+
+/// Lists the given mutators
+pub fn mutators_label(mutators: &[Mutator], locale: &Locale) -> String {
+    if mutators.is_empty() {
+        locale.text("mutator.none").to_string()
+    } else {
+        mutators
+            .iter()
+            .map(|mutator| locale.text(mutator.key()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+// Hide bombs placed a moment ago, when asked to
+pub fn hide_bombs(rules: Res<Rules>, mut bomb_query: Query<(&Bomb, &mut Visibility)>) {
+    let invisible = rules.mutated(Mutator::InvisibleBombs);
+    for (bomb, mut visibility) in &mut bomb_query {
+        visibility.is_visible = !invisible || bomb.timer.elapsed_secs() >= INVISIBLE_BOMB_SECS;
+    }
+}
@@ -0,0 +1,1128 @@
+//! Online matches.
+//!
+//! One instance hosts the match and is the only one simulating it, the others join it by
+//! address and just show what the host sends them.
+//! At every physics step a joined instance sends the [`PlayerInput`] of its player, and the
+//! host answers with a [`WorldState`] listing every sprite of the arena, so bombs and fire are
+//! only ever resolved by the host.
+//! Messages travel over a [`Transport`], one per datagram: a lost input is superseded by the
+//! next one, and a lost state by the following step's.
+//!
+//! Between matches the host sits in the lobby, where it sets the [`Rules`] while joined
+//! instances pick a color and declare they're ready; the host starts the match once everybody
+//! is, telling every joined instance at the same time.
+//!
+//! Spectators connect the same way, but only ever receive: they see the lobby and the match
+//! without taking part in them.
+//!
+//! Everybody can [`chat`](crate::chat): lines typed on joined instances go to the host, which
+//! relays them to everybody else.
+//!
+//! Joining instances tell the [`PROTOCOL_VERSION`] they speak, and are turned away by hosts
+//! speaking another one.
+//! A joined instance that goes silent is considered dropped: a bot plays for its player until
+//! it comes back, or somebody else joins in its place. Between matches, its spot is given up
+//! once it's been gone for longer than a grace period.
+
+use bevy::{app::AppExit, prelude::*, time::FixedTimestep};
+use serde::{Deserialize, Serialize};
+
+use std::{io, time::Duration};
+
+use crate::{
+    ai::Brain,
+    bot::{Bot, Roster, MAX_HUMANS},
+    chat::{Chat, ChatPlugin},
+    input::{Bindings, BombBuffer, Controls, BOMB_BUFFER_STEPS},
+    level::arena_label,
+    locale::Locale,
+    mutator::mutators_label,
+    settings::Settings,
+    setup_camera,
+    theme::Theme,
+    transport::{Address, Transport},
+    AppState, BombEvent, DashEvent, DetonateEvent, Direction, Floor, Match, MoveEvent, Rules,
+    Scoreboard, Tick, PLAYER_COLORS, SCOREBOARD_FONT_SIZE, SCOREBOARD_TEXT_PADDING, SCORE_COLOR,
+    TEXT_COLOR, TIME_STEP,
+};
+
+/// Port suggested for hosting
+pub const DEFAULT_PORT: u16 = 7777;
+/// Version of the messages exchanged, instances only play with others speaking the same one
+pub const PROTOCOL_VERSION: u32 = 26;
+
+// how many physics steps a joining instance waits before asking again to join
+const JOIN_RETRY_STEPS: u32 = 60;
+// how many instances can watch a match, on top of the ones playing it
+const MAX_SPECTATORS: usize = 8;
+// how long an instance can stay silent before it's considered dropped
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+// how long the spot of a dropped player is kept between matches, waiting for it to come back
+const RECONNECT_GRACE: Duration = Duration::from_secs(30);
+const LOBBY_FONT_SIZE: f32 = 30.0;
+
+/// Colors human players can pick from, the first ones being the defaults of local humans.
+/// None of them is the color of opponents, so that humans are never mistaken for bots
+pub const COLORS: [Color; 8] = [
+    PLAYER_COLORS[0],
+    PLAYER_COLORS[1],
+    PLAYER_COLORS[2],
+    PLAYER_COLORS[3],
+    Color::rgb(0.7, 0.3, 0.5),
+    Color::rgb(0.5, 0.3, 0.1),
+    Color::rgb(0.1, 0.1, 0.4),
+    Color::rgb(0.4, 0.7, 0.5),
+];
+
+/// What a human wants to do at a given step, as sent over the network
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerInput {
+    /// Directions being held
+    pub moves: Vec<Direction>,
+    /// Number of bomb key presses so far, so that presses carried by lost datagrams still count
+    pub bombs: u32,
+    /// Whether the detonate key is being held
+    pub detonate: bool,
+    /// Whether the dash key is being held
+    pub dash: bool,
+}
+
+impl PlayerInput {
+    /// Reads the keys being held, given the bomb key presses counted so far
+    pub fn read(keyboard_input: &Input<KeyCode>, controls: &Controls, bombs: u32) -> Self {
+        PlayerInput {
+            moves: [
+                (controls.up, Direction::Up),
+                (controls.down, Direction::Down),
+                (controls.left, Direction::Left),
+                (controls.right, Direction::Right),
+            ]
+            .into_iter()
+            .filter(|(key, _)| keyboard_input.pressed(*key))
+            .map(|(_, direction)| direction)
+            .collect(),
+            bombs,
+            detonate: keyboard_input.pressed(controls.detonate),
+            dash: keyboard_input.pressed(controls.dash),
+        }
+    }
+
+    /// Sends the events the input stands for, `bombs` being the presses already honored
+    #[allow(clippy::too_many_arguments)]
+    pub fn send(
+        &self,
+        player: Entity,
+        bombs: &mut u32,
+        buffer: &mut BombBuffer,
+        move_writer: &mut EventWriter<MoveEvent>,
+        bomb_writer: &mut EventWriter<BombEvent>,
+        detonate_writer: &mut EventWriter<DetonateEvent>,
+        dash_writer: &mut EventWriter<DashEvent>,
+    ) {
+        for direction in &self.moves {
+            move_writer.send(MoveEvent {
+                direction: *direction,
+                player,
+            });
+        }
+        if self.bombs != *bombs {
+            *bombs = self.bombs;
+            buffer.0 = BOMB_BUFFER_STEPS;
+        }
+        if buffer.0 > 0 {
+            buffer.0 -= 1;
+            bomb_writer.send(BombEvent { player });
+        }
+        if self.detonate {
+            detonate_writer.send(DetonateEvent { player });
+        }
+        if self.dash {
+            dash_writer.send(DashEvent { player });
+        }
+    }
+}
+
+/// A sprite as shown by the host
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpriteState {
+    pub position: Vec2,
+    pub size: Vec2,
+    pub color: Color,
+}
+
+/// Everything a joined instance needs to show the arena at a given step
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorldState {
+    pub tick: u64,
+    pub score: usize,
+    pub sprites: Vec<SpriteState>,
+}
+
+/// A human player waiting in the lobby
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LobbyPlayer {
+    /// Index among [`COLORS`]
+    pub color: usize,
+    pub ready: bool,
+    /// Whether the player joined from another instance
+    pub remote: bool,
+    /// Whether the player's instance is still there
+    pub connected: bool,
+}
+
+/// What the lobby of the host looks like
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LobbyState {
+    pub rules: Rules,
+    /// Every human player, in spawn order
+    pub players: Vec<LobbyPlayer>,
+}
+
+impl LobbyState {
+    /// Lines describing the rules, in the language of the instance showing them
+    pub fn rule_lines(&self, locale: &Locale) -> Vec<String> {
+        let rules = &self.rules;
+        let movement = if rules.classic_movement {
+            locale.text("lobby.classic")
+        } else {
+            locale.text("lobby.free")
+        };
+        vec![
+            locale.format("lobby.arena", &[("arena", &arena_label(rules, locale))]),
+            locale.format("lobby.rounds", &[("rounds", &rules.rounds)]),
+            locale.format(
+                "lobby.time_limit",
+                &[("limit", &time_limit_label(rules.time_limit, locale))],
+            ),
+            locale.format(
+                "lobby.power_ups",
+                &[("chance", &chance_label(rules.power_up_chance))],
+            ),
+            locale.format(
+                "lobby.bricks",
+                &[("chance", &chance_label(rules.brick_density))],
+            ),
+            locale.format(
+                "lobby.rubble",
+                &[("chance", &chance_label(rules.rubble_chance))],
+            ),
+            locale.format(
+                "lobby.mutators",
+                &[("mutators", &mutators_label(&rules.mutators, locale))],
+            ),
+            locale.format("lobby.movement", &[("movement", &movement)]),
+            locale.format(
+                "lobby.solid_players",
+                &[("switch", &locale.switch(rules.solid_players))],
+            ),
+            locale.format(
+                "lobby.diagonals",
+                &[("switch", &locale.switch(rules.diagonals))],
+            ),
+        ]
+    }
+
+    /// Lines describing the players in their colors, `me` being the one of the instance
+    /// showing them
+    pub fn player_lines(&self, me: Option<usize>, locale: &Locale) -> Vec<(String, Color)> {
+        // what's only shown when true
+        let flag = |on: bool, key: &'static str| if on { locale.text(key) } else { "" };
+        self.players
+            .iter()
+            .enumerate()
+            .map(|(index, player)| {
+                let seat = if player.remote {
+                    "lobby.online"
+                } else {
+                    "lobby.local"
+                };
+                let line = locale.format(
+                    "lobby.player",
+                    &[
+                        ("player", &(index + 1)),
+                        ("seat", &locale.text(seat)),
+                        ("ready", &flag(player.ready, "lobby.ready")),
+                        ("dropped", &flag(!player.connected, "lobby.dropped")),
+                        ("you", &flag(me == Some(index), "lobby.you")),
+                    ],
+                );
+                (line, COLORS[player.color])
+            })
+            .collect()
+    }
+}
+
+/// How a time limit is shown in the lobby
+pub fn time_limit_label(limit: Option<u32>, locale: &Locale) -> String {
+    match limit {
+        Some(seconds) => format!("{}:{:02}", seconds / 60, seconds % 60),
+        None => locale.text("lobby.no_time_limit").to_string(),
+    }
+}
+
+/// How a chance is shown in the lobby
+pub fn chance_label(chance: f64) -> String {
+    format!("{}%", (chance * 100.).round())
+}
+
+/// Messages exchanged between the host and joined instances.
+/// The first two variants must never change, so that instances speaking different versions
+/// can still tell each other apart.
+#[derive(Debug, Serialize, Deserialize)]
+enum Message {
+    /// A joining instance asks for a spot in the match, or just to watch it
+    Join { version: u32, spectator: bool },
+    /// The host speaks another version
+    Incompatible { version: u32 },
+    /// The host accepted a joining instance, which plays as the given human
+    Welcome { player: usize },
+    /// The host accepted a watching instance
+    Watching,
+    /// The host has no spot left
+    Full,
+    /// A watching instance is still there
+    Alive,
+    /// Input of a joined instance's player
+    Input(PlayerInput),
+    /// What the host is showing
+    State(WorldState),
+    /// What the lobby of the host looks like, sent while waiting for a match to start
+    Lobby(Box<LobbyState>),
+    /// A joined instance picks the color of its player, as an index among [`COLORS`]
+    Color(usize),
+    /// A joined instance is ready to play, or not anymore
+    Ready(bool),
+    /// The host started the match
+    Start,
+    /// A joined instance typed a chat line
+    Say(String),
+    /// A chat line relayed by the host, with its author
+    Chat { from: Author, text: String },
+}
+
+/// Who wrote a chat line, named by every instance in its own language
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Author {
+    Host,
+    Spectator,
+    /// A joined player, numbered among all players from 1
+    Player(usize),
+}
+
+impl Author {
+    /// How the author is shown in the chat
+    fn name(&self, locale: &Locale) -> String {
+        match self {
+            Author::Host => locale.text("chat.host").to_string(),
+            Author::Spectator => locale.text("chat.spectator").to_string(),
+            Author::Player(player) => locale.format("chat.player", &[("player", player)]),
+        }
+    }
+}
+
+impl Message {
+    fn send(&self, transport: &Transport, address: &Address) {
+        let result = bincode::serialize(self)
+            .map_err(|e| e.to_string())
+            .and_then(|datagram| {
+                transport
+                    .send(&datagram, address)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            warn!("Can't send message to {address}: {e}");
+        }
+    }
+
+    /// Reads every message waiting on the transport
+    fn receive_all(transport: &Transport) -> Vec<(Message, Address)> {
+        let datagrams = transport.receive().unwrap_or_else(|e| {
+            warn!("Can't receive messages: {e}");
+            Vec::new()
+        });
+        datagrams
+            .into_iter()
+            .filter_map(
+                |(datagram, address)| match bincode::deserialize(&datagram) {
+                    Ok(message) => Some((message, address)),
+                    Err(e) => {
+                        warn!("Invalid message from {address}: {e}");
+                        None
+                    }
+                },
+            )
+            .collect()
+    }
+}
+
+/// A joined instance, as seen by the host
+struct Peer {
+    address: Address,
+    input: PlayerInput,
+    // bomb key presses already turned into bombs
+    bombs: u32,
+    // index among the colors
+    color: usize,
+    ready: bool,
+    // time since the latest message
+    silence: Duration,
+    connected: bool,
+}
+
+/// An instance watching the match, as seen by the host
+struct Spectator {
+    address: Address,
+    // time since the latest message
+    silence: Duration,
+}
+
+/// This resource exists when hosting an online match
+pub struct Host {
+    transport: Transport,
+    peers: Vec<Peer>,
+    spectators: Vec<Spectator>,
+}
+
+impl Host {
+    pub fn bind(port: u16) -> io::Result<Self> {
+        Ok(Host {
+            transport: Transport::bind(port)?,
+            peers: Vec::new(),
+            spectators: Vec::new(),
+        })
+    }
+
+    /// Addresses of every joined instance, playing or watching
+    fn audience(&self) -> impl Iterator<Item = &Address> {
+        self.peers
+            .iter()
+            .map(|peer| &peer.address)
+            .chain(self.spectators.iter().map(|spectator| &spectator.address))
+    }
+
+    /// What the lobby looks like
+    pub fn lobby(&self, roster: &Roster, rules: &Rules) -> LobbyState {
+        // local humans play with the colors picked in the menu, and are always ready
+        let locals = (0..roster.humans).map(|index| LobbyPlayer {
+            color: local_color(roster, index),
+            ready: true,
+            remote: false,
+            connected: true,
+        });
+        let remotes = self.peers.iter().map(|peer| LobbyPlayer {
+            color: peer.color,
+            ready: peer.ready,
+            remote: true,
+            connected: peer.connected,
+        });
+        LobbyState {
+            rules: rules.clone(),
+            players: locals.chain(remotes).collect(),
+        }
+    }
+
+    /// Whether every joined instance is ready for the match to start, dropped ones are
+    /// replaced by bots
+    pub fn ready(&self) -> bool {
+        self.peers.iter().all(|peer| peer.ready || !peer.connected)
+    }
+
+    /// Makes the roster match the joined instances
+    fn sync_roster(&self, roster: &mut Roster) {
+        roster.remotes = self.peers.len();
+        for (index, peer) in self.peers.iter().enumerate() {
+            roster.colors[roster.humans + index] = COLORS[peer.color];
+        }
+        // make room for new humans
+        let count = roster.bots.len();
+        roster.resize(count);
+    }
+
+    /// Whether the given color is picked by a human other than the given remote one
+    fn color_taken(&self, roster: &Roster, color: usize, remote: Option<usize>) -> bool {
+        (0..roster.humans).any(|index| local_color(roster, index) == color)
+            || self
+                .peers
+                .iter()
+                .enumerate()
+                .any(|(index, peer)| peer.color == color && Some(index) != remote)
+    }
+}
+
+/// Index among the colors of the one the given local human plays with
+fn local_color(roster: &Roster, index: usize) -> usize {
+    COLORS
+        .iter()
+        .position(|color| *color == roster.color(index))
+        .unwrap_or(index)
+}
+
+/// Marks a player driven by inputs coming from the network, with the index of their source
+#[derive(Component)]
+pub struct Remote(pub usize);
+
+/// Accepts joining instances and collects inputs, on every frame
+// addresses are copied on desktops, but peers of browsers have to be cloned
+#[allow(clippy::clone_on_copy)]
+pub fn host_receive(
+    locale: Res<Locale>,
+    mut host: ResMut<Host>,
+    mut roster: ResMut<Roster>,
+    mut chat: ResMut<Chat>,
+) {
+    for (message, address) in Message::receive_all(&host.transport) {
+        let peer = host.peers.iter().position(|peer| peer.address == address);
+        // any message shows the instance is still there
+        if let Some(index) = peer {
+            let peer = &mut host.peers[index];
+            peer.silence = Duration::ZERO;
+            if !peer.connected {
+                info!("{address} is back");
+                peer.connected = true;
+            }
+        }
+        if let Some(spectator) = host
+            .spectators
+            .iter_mut()
+            .find(|spectator| spectator.address == address)
+        {
+            spectator.silence = Duration::ZERO;
+        }
+
+        match (message, peer) {
+            (Message::Join { version, .. }, _) if version != PROTOCOL_VERSION => {
+                warn!("{address} speaks version {version} instead of {PROTOCOL_VERSION}");
+                Message::Incompatible {
+                    version: PROTOCOL_VERSION,
+                }
+                .send(&host.transport, &address);
+            }
+            (
+                Message::Join {
+                    spectator: false, ..
+                },
+                Some(index),
+            ) => {
+                // the welcome got lost
+                Message::Welcome {
+                    player: roster.humans + index,
+                }
+                .send(&host.transport, &address);
+            }
+            (
+                Message::Join {
+                    spectator: false, ..
+                },
+                None,
+            ) => {
+                // somebody coming back gets a new address, so the spot of any dropped player
+                // is as good as theirs
+                if let Some(index) = host.peers.iter().position(|peer| !peer.connected) {
+                    info!(
+                        "{address} joined in place of player #{}",
+                        roster.humans + index + 1
+                    );
+                    let peer = &mut host.peers[index];
+                    peer.address = address.clone();
+                    peer.input = PlayerInput::default();
+                    peer.bombs = 0;
+                    peer.silence = Duration::ZERO;
+                    peer.connected = true;
+                    Message::Welcome {
+                        player: roster.humans + index,
+                    }
+                    .send(&host.transport, &address);
+                    continue;
+                }
+
+                if roster.total_humans() >= MAX_HUMANS {
+                    Message::Full.send(&host.transport, &address);
+                    continue;
+                }
+                info!("{address} joined, it'll play from the next match");
+                let color = (0..COLORS.len())
+                    .find(|color| !host.color_taken(&roster, *color, None))
+                    .unwrap_or_default();
+                host.peers.push(Peer {
+                    address: address.clone(),
+                    input: PlayerInput::default(),
+                    bombs: 0,
+                    color,
+                    ready: false,
+                    silence: Duration::ZERO,
+                    connected: true,
+                });
+                host.sync_roster(&mut roster);
+                Message::Welcome {
+                    player: roster.humans + host.peers.len() - 1,
+                }
+                .send(&host.transport, &address);
+            }
+            (
+                Message::Join {
+                    spectator: true, ..
+                },
+                None,
+            ) => {
+                if !host
+                    .spectators
+                    .iter()
+                    .any(|spectator| spectator.address == address)
+                {
+                    if host.spectators.len() >= MAX_SPECTATORS {
+                        Message::Full.send(&host.transport, &address);
+                        continue;
+                    }
+                    info!("{address} is watching");
+                    host.spectators.push(Spectator {
+                        address: address.clone(),
+                        silence: Duration::ZERO,
+                    });
+                }
+                // answered again if the first answer got lost
+                Message::Watching.send(&host.transport, &address);
+            }
+            (Message::Alive, _) => {}
+            (Message::Input(input), Some(index)) => host.peers[index].input = input,
+            (Message::Color(color), Some(index)) => {
+                // colors are unique, a taken one is just ignored
+                if color < COLORS.len() && !host.color_taken(&roster, color, Some(index)) {
+                    host.peers[index].color = color;
+                    host.sync_roster(&mut roster);
+                }
+            }
+            (Message::Ready(ready), Some(index)) => host.peers[index].ready = ready,
+            (Message::Say(text), peer) => {
+                let from = match peer {
+                    Some(index) => Author::Player(roster.humans + index + 1),
+                    None if host
+                        .spectators
+                        .iter()
+                        .any(|spectator| spectator.address == address) =>
+                    {
+                        Author::Spectator
+                    }
+                    None => continue,
+                };
+                chat.push(&from.name(&locale), &text);
+                let line = Message::Chat { from, text };
+                // the author already shows its own line
+                for other in host.audience().filter(|other| **other != address) {
+                    line.send(&host.transport, other);
+                }
+            }
+            (message, _) => warn!("Unexpected message from {address}: {message:?}"),
+        }
+    }
+}
+
+/// Notices joined instances going silent, on every frame.
+/// A bot plays for dropped players until they come back, their spots are given up between
+/// matches once the grace period is over.
+pub fn host_watchdog(
+    mut commands: Commands,
+    time: Res<Time>,
+    state: Res<State<AppState>>,
+    mut host: ResMut<Host>,
+    mut roster: ResMut<Roster>,
+    query: Query<(Entity, &Remote, Option<&Bot>)>,
+) {
+    for peer in &mut host.peers {
+        peer.silence += time.delta();
+        if peer.connected && peer.silence > DISCONNECT_TIMEOUT {
+            warn!("{} dropped", peer.address);
+            peer.connected = false;
+        }
+    }
+    for spectator in &mut host.spectators {
+        spectator.silence += time.delta();
+    }
+    host.spectators
+        .retain(|spectator| spectator.silence <= DISCONNECT_TIMEOUT);
+
+    // no player is bound to a spot between matches
+    if *state.current() == AppState::Lobby {
+        let count = host.peers.len();
+        host.peers
+            .retain(|peer| peer.connected || peer.silence <= RECONNECT_GRACE);
+        if host.peers.len() != count {
+            host.sync_roster(&mut roster);
+            // the players after the ones gone moved up
+            for (index, peer) in host.peers.iter().enumerate() {
+                Message::Welcome {
+                    player: roster.humans + index,
+                }
+                .send(&host.transport, &peer.address);
+            }
+        }
+    }
+
+    for (entity, remote, bot) in &query {
+        let connected = host.peers.get(remote.0).is_some_and(|peer| peer.connected);
+        match (connected, bot) {
+            (false, None) => {
+                commands
+                    .entity(entity)
+                    .insert(Bot(Box::new(Brain::default())));
+            }
+            (true, Some(_)) => {
+                commands.entity(entity).remove::<Bot>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Shows the lobby to every joined instance, on every frame so that lost datagrams are soon
+/// made up for
+pub fn host_lobby(host: Res<Host>, roster: Res<Roster>, rules: Res<Rules>) {
+    let lobby = Message::Lobby(Box::new(host.lobby(&roster, &rules)));
+    for address in host.audience() {
+        lobby.send(&host.transport, address);
+    }
+}
+
+/// Sends the chat lines typed on the host
+pub fn host_chat(host: Res<Host>, locale: Res<Locale>, mut chat: ResMut<Chat>) {
+    for text in std::mem::take(&mut chat.outbox) {
+        chat.push(&Author::Host.name(&locale), &text);
+        let line = Message::Chat {
+            from: Author::Host,
+            text,
+        };
+        for address in host.audience() {
+            line.send(&host.transport, address);
+        }
+    }
+}
+
+/// Tells every joined instance the match started
+pub fn host_start(mut host: ResMut<Host>) {
+    for address in host.audience() {
+        Message::Start.send(&host.transport, address);
+    }
+    // everybody has to be ready again for the next match
+    for peer in &mut host.peers {
+        peer.ready = false;
+    }
+}
+
+/// Turns the inputs of joined instances into the same events local humans send
+pub fn drive_remote(
+    host: Option<ResMut<Host>>,
+    mut move_writer: EventWriter<MoveEvent>,
+    mut bomb_writer: EventWriter<BombEvent>,
+    mut detonate_writer: EventWriter<DetonateEvent>,
+    mut dash_writer: EventWriter<DashEvent>,
+    mut query: Query<(Entity, &Remote, &mut BombBuffer)>,
+) {
+    let mut host = match host {
+        Some(host) => host,
+        None => return,
+    };
+
+    for (player, remote, mut buffer) in &mut query {
+        // dropped players are driven by bots meanwhile
+        let peer = match host.peers.get_mut(remote.0) {
+            Some(peer) if peer.connected => peer,
+            _ => continue,
+        };
+        peer.input.send(
+            player,
+            &mut peer.bombs,
+            &mut buffer,
+            &mut move_writer,
+            &mut bomb_writer,
+            &mut detonate_writer,
+            &mut dash_writer,
+        );
+    }
+}
+
+/// Sends what the host is showing to every joined instance
+#[allow(clippy::type_complexity)]
+pub fn host_broadcast(
+    host: Option<Res<Host>>,
+    state: Option<Res<State<AppState>>>,
+    tick: Res<Tick>,
+    scoreboard: Res<Scoreboard>,
+    // tiles of the floor are left out, not to send one for every cell
+    query: Query<(&Transform, &Sprite), (With<Match>, Without<Floor>)>,
+) {
+    let host = match host {
+        Some(host) if host.audience().next().is_some() => host,
+        _ => return,
+    };
+    // joined instances take states as the sign that the match started
+    if matches!(state, Some(state) if *state.current() == AppState::Lobby) {
+        return;
+    }
+
+    let state = Message::State(WorldState {
+        tick: tick.0,
+        score: scoreboard.score,
+        sprites: query
+            .iter()
+            .map(|(transform, sprite)| SpriteState {
+                position: transform.translation.truncate(),
+                size: transform.scale.truncate(),
+                color: sprite.color,
+            })
+            .collect(),
+    });
+    for address in host.audience() {
+        state.send(&host.transport, address);
+    }
+}
+
+/// This resource exists when playing a match hosted elsewhere
+pub struct Client {
+    transport: Transport,
+    host: Address,
+    // whether we only watch the match
+    spectator: bool,
+    // whether the host accepted us
+    accepted: bool,
+    // human we're playing as, once accepted
+    player: Option<usize>,
+    // bomb key presses so far
+    bombs: u32,
+    // steps since the last request to join
+    join_steps: u32,
+    // step of the latest state shown
+    tick: Option<u64>,
+    // time since the latest message from the host
+    silence: Duration,
+    // lobby of the host, while waiting for a match to start
+    lobby: Option<LobbyState>,
+}
+
+/// Marks sprites mirroring the host's ones
+#[derive(Component)]
+struct Mirror;
+
+/// Marks the score text of a joined instance
+#[derive(Component)]
+struct RemoteScore;
+
+/// Marks the text showing the lobby of the host on a joined instance
+#[derive(Component)]
+struct RemoteLobby;
+
+/// Plays the match hosted at the given address, or just watches it, until the window is closed
+pub fn join(host: Address, spectator: bool) -> io::Result<()> {
+    let transport = Transport::bind(0)?;
+
+    let settings = Settings::load();
+    App::new()
+        .insert_resource(settings.window.descriptor())
+        .add_plugins(DefaultPlugins)
+        .add_plugin(ChatPlugin)
+        .insert_resource(ClearColor(Theme::default().palette().background))
+        .insert_resource(Locale::load(&settings.language))
+        .insert_resource(settings.bindings)
+        .insert_resource(Client {
+            transport,
+            host,
+            spectator,
+            accepted: false,
+            player: None,
+            bombs: 0,
+            join_steps: JOIN_RETRY_STEPS,
+            tick: None,
+            silence: Duration::ZERO,
+            lobby: None,
+        })
+        .add_startup_system(setup_camera)
+        .add_startup_system(setup_client)
+        .add_system(count_bombs)
+        .add_system(client_receive)
+        .add_system(client_lobby.after(client_receive))
+        .add_system(client_chat)
+        .add_system(bevy::window::close_on_esc)
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(FixedTimestep::step(TIME_STEP as f64))
+                .with_system(client_send),
+        )
+        .run();
+    Ok(())
+}
+
+fn setup_client(mut commands: Commands, asset_server: Res<AssetServer>, locale: Res<Locale>) {
+    commands
+        .spawn_bundle(
+            TextBundle::from_sections([
+                TextSection::new(
+                    locale.text("hud.score"),
+                    TextStyle {
+                        font: asset_server.load(locale.font()),
+                        font_size: SCOREBOARD_FONT_SIZE,
+                        color: TEXT_COLOR,
+                    },
+                ),
+                TextSection::from_style(TextStyle {
+                    font: asset_server.load(locale.mono_font()),
+                    font_size: SCOREBOARD_FONT_SIZE,
+                    color: SCORE_COLOR,
+                }),
+            ])
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: SCOREBOARD_TEXT_PADDING,
+                    left: SCOREBOARD_TEXT_PADDING,
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(RemoteScore);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load(locale.font()),
+                        font_size: LOBBY_FONT_SIZE,
+                        color: TEXT_COLOR,
+                    },
+                ))
+                .insert(RemoteLobby);
+        });
+}
+
+/// Counts bomb key presses, on every frame so that short taps between physics steps aren't lost
+fn count_bombs(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut client: ResMut<Client>,
+) {
+    if keyboard_input.just_pressed(bindings.players[0].bomb) {
+        client.bombs = client.bombs.wrapping_add(1);
+    }
+}
+
+fn client_send(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut client: ResMut<Client>,
+) {
+    client.join_steps += 1;
+    let retry = client.join_steps >= JOIN_RETRY_STEPS;
+    if retry {
+        client.join_steps = 0;
+    }
+    if !client.accepted {
+        if retry {
+            Message::Join {
+                version: PROTOCOL_VERSION,
+                spectator: client.spectator,
+            }
+            .send(&client.transport, &client.host);
+        }
+        return;
+    }
+    // spectators have nothing to send, besides showing they're still there
+    if client.spectator {
+        if retry {
+            Message::Alive.send(&client.transport, &client.host);
+        }
+        return;
+    }
+
+    // joined instances play with the first keyboard layout
+    let input = PlayerInput::read(&keyboard_input, &bindings.players[0], client.bombs);
+    Message::Input(input).send(&client.transport, &client.host);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn client_receive(
+    mut commands: Commands,
+    time: Res<Time>,
+    locale: Res<Locale>,
+    mut client: ResMut<Client>,
+    mut chat: ResMut<Chat>,
+    mut exit: EventWriter<AppExit>,
+    mirror_query: Query<Entity, With<Mirror>>,
+    mut score_query: Query<&mut Text, With<RemoteScore>>,
+) {
+    let mut latest = None;
+    client.silence += time.delta();
+    for (message, address) in Message::receive_all(&client.transport) {
+        if address != client.host {
+            continue;
+        }
+        client.silence = Duration::ZERO;
+        match message {
+            Message::Incompatible { version } => {
+                error!("{address} speaks version {version} instead of {PROTOCOL_VERSION}");
+                exit.send(AppExit);
+            }
+            Message::Welcome { player } => {
+                if client.player.is_none() {
+                    info!("Joined {address} as player #{}", player + 1);
+                }
+                client.accepted = true;
+                client.player = Some(player);
+            }
+            Message::Watching => {
+                if !client.accepted {
+                    info!("Watching {address}");
+                }
+                client.accepted = true;
+            }
+            Message::Full => {
+                error!("{address} has no room left");
+                exit.send(AppExit);
+            }
+            Message::Lobby(lobby) => {
+                if client.lobby.is_none() {
+                    // the arena of the last match isn't shown anymore
+                    for entity in &mirror_query {
+                        commands.entity(entity).despawn();
+                    }
+                }
+                client.lobby = Some(*lobby);
+                latest = None;
+            }
+            Message::Chat { from, text } => chat.push(&from.name(&locale), &text),
+            // states only come once the match started, in case the start message got lost
+            Message::Start => client.lobby = None,
+            // datagrams may arrive out of order, older states are dropped
+            Message::State(state) if client.tick.is_none_or(|tick| state.tick > tick) => {
+                client.tick = Some(state.tick);
+                client.lobby = None;
+                latest = Some(state);
+            }
+            Message::State(_) => {}
+            message => warn!("Unexpected message from {address}: {message:?}"),
+        }
+    }
+
+    if client.accepted && client.silence > DISCONNECT_TIMEOUT {
+        warn!("Lost {}, trying to join again", client.host);
+        client.accepted = false;
+        client.join_steps = JOIN_RETRY_STEPS;
+    }
+
+    let state = match latest {
+        Some(state) => state,
+        None => return,
+    };
+    for entity in &mirror_query {
+        commands.entity(entity).despawn();
+    }
+    for sprite in state.sprites {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: sprite.color,
+                    ..default()
+                },
+                transform: Transform {
+                    translation: sprite.position.extend(0.0),
+                    scale: sprite.size.extend(1.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(Mirror);
+    }
+    if let Ok(mut text) = score_query.get_single_mut() {
+        text.sections[1].value = state.score.to_string();
+    }
+}
+
+/// Shows the lobby of the host, letting our player pick a color and get ready
+fn client_lobby(
+    keyboard_input: Res<Input<KeyCode>>,
+    client: Res<Client>,
+    locale: Res<Locale>,
+    mut query: Query<&mut Text, With<RemoteLobby>>,
+) {
+    let mut text = match query.get_single_mut() {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    let lobby = match &client.lobby {
+        Some(lobby) => lobby,
+        None => {
+            text.sections.truncate(1);
+            text.sections[0].value.clear();
+            return;
+        }
+    };
+
+    if let Some(me) = client.player.and_then(|player| lobby.players.get(player)) {
+        let step = if keyboard_input.just_pressed(KeyCode::Left) {
+            Some(COLORS.len() - 1)
+        } else if keyboard_input.just_pressed(KeyCode::Right) {
+            Some(1)
+        } else {
+            None
+        };
+        // the first color nobody else picked, going the chosen way
+        let color = step.and_then(|step| {
+            (1..COLORS.len())
+                .map(|offset| (me.color + offset * step) % COLORS.len())
+                .find(|color| !lobby.players.iter().any(|player| player.color == *color))
+        });
+        if let Some(color) = color {
+            Message::Color(color).send(&client.transport, &client.host);
+        }
+        if keyboard_input.just_pressed(KeyCode::Space) {
+            Message::Ready(!me.ready).send(&client.transport, &client.host);
+        }
+    }
+
+    let style = text.sections[0].style.clone();
+    let mut sections = lobby
+        .rule_lines(&locale)
+        .into_iter()
+        .map(|line| TextSection::new(line + "\n", style.clone()))
+        .collect::<Vec<_>>();
+    sections.push(TextSection::new("\n", style.clone()));
+    for (line, color) in lobby.player_lines(client.player, &locale) {
+        sections.push(TextSection::new(
+            line + "\n",
+            TextStyle {
+                color,
+                ..style.clone()
+            },
+        ));
+    }
+    let help = if client.spectator {
+        locale.text("lobby.watching")
+    } else {
+        locale.text("lobby.joined")
+    };
+    sections.push(TextSection::new(help, style));
+    text.sections = sections;
+}
+
+/// Sends the chat lines typed on a joined instance
+fn client_chat(client: Res<Client>, locale: Res<Locale>, mut chat: ResMut<Chat>) {
+    // lines typed before being accepted have nobody to go to
+    if !client.accepted {
+        chat.outbox.clear();
+        return;
+    }
+    for text in std::mem::take(&mut chat.outbox) {
+        chat.push(locale.text("chat.me"), &text);
+        Message::Say(text).send(&client.transport, &client.host);
+    }
+}
@@ -0,0 +1,338 @@
+//! Options screen, where key bindings, settings, accessibility aids and the language can be
+//! changed.
+
+use bevy::prelude::*;
+
+use crate::{
+    display::{next_resolution, step_ui_scale, RESOLUTIONS},
+    input::{Action, Bindings},
+    locale::{next_language, Locale},
+    settings::Settings,
+    AppState, SCORE_COLOR, TEXT_COLOR,
+};
+
+const OPTIONS_FONT_SIZE: f32 = 30.0;
+
+/// Marks every entity belonging to the options screen
+#[derive(Component)]
+pub struct Options;
+
+/// A line of the options screen, showing the key bound to an action of a player
+#[derive(Component)]
+pub struct BindingRow {
+    player: usize,
+    action: Action,
+}
+
+/// The line of the options screen showing the settings
+#[derive(Component)]
+pub struct SettingsLine;
+
+/// The line of the options screen showing how loud things are
+#[derive(Component)]
+pub struct VolumeLine;
+
+/// The line of the options screen showing how the window is shown
+#[derive(Component)]
+pub struct WindowLine;
+
+// how much volumes change with every key press
+const VOLUME_STEP: f32 = 0.1;
+// keys turning down and up the master volume, the one of the music and the one of effects
+const VOLUME_KEYS: [KeyCode; 6] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+];
+
+/// Turns the given volume up or down by a step, keeping it between zero and one
+fn step(volume: &mut f32, up: bool) {
+    let step = if up { VOLUME_STEP } else { -VOLUME_STEP };
+    *volume = (*volume + step).clamp(0., 1.);
+}
+
+/// The line of the options screen showing the aids to accessibility
+#[derive(Component)]
+pub struct AccessibilityLine;
+
+/// This resource tracks the line being edited
+#[derive(Default)]
+pub struct Selection {
+    row: usize,
+    // whether the next key pressed gets bound to the selected action
+    capturing: bool,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+    bindings: Res<Bindings>,
+) {
+    commands.insert_resource(Selection::default());
+
+    let style = TextStyle {
+        font: asset_server.load(locale.font()),
+        font_size: OPTIONS_FONT_SIZE,
+        color: TEXT_COLOR,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Options)
+        .with_children(|parent| {
+            for player in 0..bindings.players.len() {
+                for action in Action::ALL {
+                    parent
+                        .spawn_bundle(TextBundle::from_section("", style.clone()))
+                        .insert(BindingRow { player, action });
+                }
+            }
+            parent
+                .spawn_bundle(TextBundle::from_section("", style.clone()))
+                .insert(SettingsLine);
+            parent
+                .spawn_bundle(TextBundle::from_section("", style.clone()))
+                .insert(VolumeLine);
+            parent
+                .spawn_bundle(TextBundle::from_section("", style.clone()))
+                .insert(WindowLine);
+            parent
+                .spawn_bundle(TextBundle::from_section("", style.clone()))
+                .insert(AccessibilityLine);
+            parent.spawn_bundle(TextBundle::from_section(locale.text("options.help"), style));
+        });
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn navigate(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut selection: ResMut<Selection>,
+    mut bindings: ResMut<Bindings>,
+    mut settings: ResMut<Settings>,
+    locale: Res<Locale>,
+    mut state: ResMut<State<AppState>>,
+    mut query: Query<(&BindingRow, &mut Text)>,
+    mut settings_query: Query<&mut Text, (With<SettingsLine>, Without<BindingRow>)>,
+    mut volume_query: Query<
+        &mut Text,
+        (With<VolumeLine>, Without<SettingsLine>, Without<BindingRow>),
+    >,
+    mut window_query: Query<
+        &mut Text,
+        (
+            With<WindowLine>,
+            Without<VolumeLine>,
+            Without<SettingsLine>,
+            Without<BindingRow>,
+        ),
+    >,
+    mut accessibility_query: Query<
+        &mut Text,
+        (
+            With<AccessibilityLine>,
+            Without<WindowLine>,
+            Without<VolumeLine>,
+            Without<SettingsLine>,
+            Without<BindingRow>,
+        ),
+    >,
+) {
+    let rows = bindings.players.len() * Action::ALL.len();
+
+    if selection.capturing {
+        let pressed = keyboard_input.get_just_pressed().next().copied();
+        // Escape can't be bound, it always cancels
+        if keyboard_input.clear_just_pressed(KeyCode::Escape) {
+            selection.capturing = false;
+        } else if let Some(key) = pressed {
+            let (player, action) = (
+                selection.row / Action::ALL.len(),
+                Action::ALL[selection.row % Action::ALL.len()],
+            );
+            bindings.players[player].bind(action, key);
+            settings.bindings = bindings.clone();
+            settings.save();
+            selection.capturing = false;
+            // don't let the same key press start a new capture
+            keyboard_input.clear_just_pressed(key);
+        }
+    } else if keyboard_input.clear_just_pressed(KeyCode::Escape) {
+        // the key press is consumed, or the menu would quit right away
+        let _ = state.set(AppState::Menu);
+    } else if keyboard_input.just_pressed(KeyCode::Up) {
+        selection.row = (selection.row + rows - 1) % rows;
+    } else if keyboard_input.just_pressed(KeyCode::Down) {
+        selection.row = (selection.row + 1) % rows;
+    } else if keyboard_input.just_pressed(KeyCode::Return) {
+        selection.capturing = true;
+    } else if keyboard_input.just_pressed(KeyCode::P) {
+        settings.particles = !settings.particles;
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::S) {
+        settings.screen_shake = !settings.screen_shake;
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::C) {
+        settings.follow_camera = !settings.follow_camera;
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::B) {
+        settings.blast_preview = !settings.blast_preview;
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::N) {
+        settings.night_mode = !settings.night_mode;
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::V) {
+        settings.announcer_voice = !settings.announcer_voice;
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::M) {
+        settings.muted = !settings.muted;
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::F) {
+        settings.window.fullscreen = !settings.window.fullscreen;
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::Y) {
+        settings.window.vsync = !settings.window.vsync;
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::L) {
+        settings.language = next_language(&settings.language);
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::A) {
+        settings.reduced_flashing = !settings.reduced_flashing;
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::G) {
+        settings.gentle_particles = !settings.gentle_particles;
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::D) {
+        settings.color_vision = settings.color_vision.next();
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::T) {
+        settings.patterns = !settings.patterns;
+        settings.save();
+    } else if keyboard_input.any_just_pressed([KeyCode::Minus, KeyCode::Equals]) {
+        // texts on this very screen are scaled right away, to see how it looks
+        settings.ui_scale = step_ui_scale(
+            settings.ui_scale,
+            keyboard_input.just_pressed(KeyCode::Equals),
+        );
+        settings.save();
+    } else if keyboard_input.just_pressed(KeyCode::R) {
+        let (width, height) =
+            RESOLUTIONS[next_resolution(settings.window.width, settings.window.height)];
+        settings.window.width = width;
+        settings.window.height = height;
+        settings.save();
+    } else if let Some(key) = VOLUME_KEYS
+        .iter()
+        .position(|key| keyboard_input.just_pressed(*key))
+    {
+        let volume = match key / 2 {
+            0 => &mut settings.master_volume,
+            1 => &mut settings.music_volume,
+            _ => &mut settings.effects_volume,
+        };
+        step(volume, key % 2 == 1);
+        settings.save();
+    }
+
+    for mut text in &mut settings_query {
+        text.sections[0].value = locale.format(
+            "options.settings",
+            &[
+                ("particles", &locale.switch(settings.particles)),
+                ("follow_camera", &locale.switch(settings.follow_camera)),
+                ("blast_preview", &locale.switch(settings.blast_preview)),
+                ("night_mode", &locale.switch(settings.night_mode)),
+                ("announcer_voice", &locale.switch(settings.announcer_voice)),
+            ],
+        );
+    }
+
+    for mut text in &mut volume_query {
+        text.sections[0].value = locale.format(
+            "options.volume",
+            &[
+                ("muted", &locale.switch(settings.muted)),
+                ("master", &format!("{:.0}", settings.master_volume * 100.)),
+                ("music", &format!("{:.0}", settings.music_volume * 100.)),
+                ("effects", &format!("{:.0}", settings.effects_volume * 100.)),
+            ],
+        );
+    }
+
+    for mut text in &mut window_query {
+        text.sections[0].value = locale.format(
+            "options.window",
+            &[
+                ("width", &settings.window.width),
+                ("height", &settings.window.height),
+                ("fullscreen", &locale.switch(settings.window.fullscreen)),
+                ("vsync", &locale.switch(settings.window.vsync)),
+                ("language", &locale.text("name")),
+                ("ui_scale", &format!("{:.0}", settings.ui_scale * 100.)),
+            ],
+        );
+    }
+
+    for mut text in &mut accessibility_query {
+        text.sections[0].value = locale.format(
+            "options.accessibility",
+            &[
+                ("screen_shake", &locale.switch(settings.screen_shake)),
+                (
+                    "reduced_flashing",
+                    &locale.switch(settings.reduced_flashing),
+                ),
+                (
+                    "gentle_particles",
+                    &locale.switch(settings.gentle_particles),
+                ),
+                ("color_vision", &locale.text(settings.color_vision.key())),
+                ("patterns", &locale.switch(settings.patterns)),
+            ],
+        );
+    }
+
+    for (row, mut text) in &mut query {
+        let index = row.player * Action::ALL.len()
+            + Action::ALL
+                .iter()
+                .position(|action| *action == row.action)
+                .unwrap_or_default();
+        let selected = index == selection.row;
+        let section = &mut text.sections[0];
+        let (player, action) = (row.player + 1, locale.text(row.action.key()));
+        section.value = if selected && selection.capturing {
+            locale.format(
+                "options.capturing",
+                &[("player", &player), ("action", &action)],
+            )
+        } else {
+            locale.format(
+                "options.binding",
+                &[
+                    ("player", &player),
+                    ("action", &action),
+                    (
+                        "key",
+                        &format!("{:?}", bindings.players[row.player].key(row.action)),
+                    ),
+                ],
+            )
+        };
+        section.style.color = if selected { SCORE_COLOR } else { TEXT_COLOR };
+    }
+}
@@ -0,0 +1,177 @@
+//! Particles thrown around by explosions and pickups, only there to be seen.
+//!
+//! Every cell reached by a blast throws sparks, and its middle a puff of smoke, while power-ups
+//! burst into sparkles of their color once picked up.
+//! Particles are left out of the match altogether: they draw from their own random generator,
+//! not to change how matches play out, and can be turned off from the options screen, or made
+//! gentle: blasts only puff smoke, and fewer sparkles drift slowly away from pickups.
+//! Faded out particles are hidden away in the [`ParticlePool`], to be thrown again by later
+//! blasts instead of spawning new ones.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    fire_reach, power_ups::PowerUpRegistry, settings::Settings, Fire, PickUpEvent, BRICK_SIZE,
+};
+
+const PARTICLE_SIZE: Vec2 = Vec2::new(6., 6.);
+const SMOKE_SIZE: Vec2 = Vec2::new(18., 18.);
+const SPARK_COLOR: Color = Color::rgb(1.0, 0.8, 0.3);
+const SMOKE_COLOR: Color = Color::rgb(0.45, 0.45, 0.45);
+// particles thrown by every cell of a blast, by the middle of a blast, and by a pickup
+const SPARKS: usize = 3;
+const SMOKE_PUFFS: usize = 4;
+const SPARKLES: usize = 12;
+// sparkles thrown by a pickup when particles are gentle
+const GENTLE_SPARKLES: usize = 4;
+// top speed of particles, in pixels per second
+const SPARK_SPEED: f32 = 120.;
+const SMOKE_SPEED: f32 = 20.;
+const SPARKLE_SPEED: f32 = 80.;
+// how much slower particles are when gentle
+const GENTLE_SLOWDOWN: f32 = 0.25;
+// seconds particles last for, at most
+const PARTICLE_SECS: f32 = 0.6;
+const SMOKE_SECS: f32 = 1.2;
+
+/// A particle flying away, fading out as it goes
+#[derive(Component)]
+pub struct Particle {
+    velocity: Vec2,
+    timer: Timer,
+}
+
+/// This resource holds particles faded out, hidden away to be thrown again
+#[derive(Default)]
+pub struct ParticlePool(Vec<Entity>);
+
+/// Throws the given number of particles from `position`, in random directions
+fn spawn_particles(
+    commands: &mut Commands,
+    pool: &mut ParticlePool,
+    count: usize,
+    position: Vec3,
+    (color, size): (Color, Vec2),
+    (speed, secs): (f32, f32),
+) {
+    let rng = &mut rand::thread_rng();
+    for _ in 0..count {
+        let angle = rng.gen_range(0. ..std::f32::consts::TAU);
+        let velocity = Vec2::new(angle.cos(), angle.sin()) * speed * rng.gen_range(0.3..1.);
+        // overwriting the components of a pooled particle keeps it where it is in memory
+        let mut particle = match pool.0.pop() {
+            Some(entity) => commands.entity(entity),
+            None => commands.spawn(),
+        };
+        particle
+            .insert_bundle(SpriteBundle {
+                sprite: Sprite { color, ..default() },
+                transform: Transform {
+                    // above whatever they're thrown from
+                    translation: position + Vec3::Z,
+                    scale: size.extend(1.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(Particle {
+                velocity,
+                timer: Timer::from_seconds(secs * rng.gen_range(0.5..1.), false),
+            });
+    }
+}
+
+// Throw sparks from every cell of new fires, and smoke from their middle
+pub fn blast(
+    mut commands: Commands,
+    mut pool: ResMut<ParticlePool>,
+    settings: Res<Settings>,
+    query: Query<&Transform, Added<Fire>>,
+) {
+    if !settings.particles {
+        return;
+    }
+    for transform in &query {
+        let (axis, reach) = fire_reach(transform.scale);
+        // gentle blasts throw no sparks at all
+        for cell in (-reach..=reach).filter(|_| !settings.gentle_particles) {
+            let position = transform.translation + (axis * BRICK_SIZE * cell as f32).extend(0.);
+            spawn_particles(
+                &mut commands,
+                &mut pool,
+                SPARKS,
+                position,
+                (SPARK_COLOR, PARTICLE_SIZE),
+                (SPARK_SPEED, PARTICLE_SECS),
+            );
+        }
+        spawn_particles(
+            &mut commands,
+            &mut pool,
+            SMOKE_PUFFS,
+            transform.translation,
+            (SMOKE_COLOR, SMOKE_SIZE),
+            (SMOKE_SPEED, SMOKE_SECS),
+        );
+    }
+}
+
+// Burst power-ups into sparkles once picked up
+pub fn sparkle(
+    mut commands: Commands,
+    mut pool: ResMut<ParticlePool>,
+    settings: Res<Settings>,
+    power_up_registry: Res<PowerUpRegistry>,
+    mut event_reader: EventReader<PickUpEvent>,
+) {
+    for PickUpEvent {
+        power_up, position, ..
+    } in event_reader.iter()
+    {
+        if !settings.particles {
+            continue;
+        }
+        let (count, speed) = if settings.gentle_particles {
+            (GENTLE_SPARKLES, SPARKLE_SPEED * GENTLE_SLOWDOWN)
+        } else {
+            (SPARKLES, SPARKLE_SPEED)
+        };
+        spawn_particles(
+            &mut commands,
+            &mut pool,
+            count,
+            position.extend(0.),
+            (power_up_registry.color(*power_up), PARTICLE_SIZE),
+            (speed, PARTICLE_SECS),
+        );
+    }
+}
+
+// Move particles along, fading them out until they're hidden away in the pool
+pub fn drift(
+    time: Res<Time>,
+    mut pool: ResMut<ParticlePool>,
+    mut query: Query<(
+        Entity,
+        &mut Particle,
+        &mut Transform,
+        &mut Sprite,
+        &mut Visibility,
+    )>,
+) {
+    for (entity, mut particle, mut transform, mut sprite, mut visibility) in &mut query {
+        if particle.timer.finished() {
+            // already in the pool
+            continue;
+        }
+        particle.timer.tick(time.delta());
+        if particle.timer.finished() {
+            visibility.is_visible = false;
+            pool.0.push(entity);
+            continue;
+        }
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.);
+        sprite.color.set_a(particle.timer.percent_left());
+    }
+}
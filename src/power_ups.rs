@@ -0,0 +1,297 @@
+//! Kinds of power-ups, defined as data rather than code.
+//!
+//! Every kind of power-up destroyed bricks may leave behind is described in [`POWER_UPS_PATH`],
+//! a RON list read at startup into the [`PowerUpRegistry`], so that power-ups can be rebalanced
+//! or added without rebuilding the game:
+//!
+//! ```ron
+//! (
+//!     id: "fire_up",
+//!     color: (1.0, 0.6, 0.0),
+//!     sprite: Some("textures/fire_up.png"),
+//!     rarity: 0.45,
+//!     effect: Power(1),
+//!     duration: Some(10.0),
+//! )
+//! ```
+//!
+//! `rarity` weighs how often a kind drops against the others, `sprite` is drawn instead of a
+//! square of `color` when given, and effects given for a `duration`, in seconds, wear off
+//! afterwards. Content packs may replace the file with their own `data/power_ups.ron`.
+//! Power-ups in the arena only hold the index of their kind, so instances playing together need
+//! the same definitions. The definitions built into the game stand in for a missing or invalid
+//! file, and are the only ones browsers know of.
+
+use bevy::prelude::*;
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{mods::Mods, Player, PowerUp, MAX_BOMBS, MAX_POWER, TIME_STEP};
+
+/// Where power-ups are defined
+pub const POWER_UPS_PATH: &str = "assets/data/power_ups.ron";
+// the same within `assets`, where content packs replace it
+const POWER_UPS_ASSET: &str = "data/power_ups.ron";
+
+// definitions shipped with the game
+const BUILT_IN: &str = include_str!("../assets/data/power_ups.ron");
+
+/// What picking up a power-up does to a player
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Effect {
+    /// More bombs at a time, or fewer
+    Bombs(i8),
+    /// More tiles of blast, or fewer
+    Power(i8),
+    /// Bombs can be set off at will
+    Detonator,
+}
+
+impl Effect {
+    /// Gives the effect to the given player, returning the effect actually given, as stats stop
+    /// at their bounds, or `None` if the player already had it for good
+    fn apply(&self, player: &mut Player) -> Option<Effect> {
+        match *self {
+            Effect::Bombs(bombs) => {
+                let before = player.max_bombs;
+                player.max_bombs = shift(before, bombs, MAX_BOMBS);
+                Some(Effect::Bombs(player.max_bombs as i8 - before as i8))
+            }
+            Effect::Power(power) => {
+                let before = player.bomb_power;
+                player.bomb_power = shift(before, power, MAX_POWER);
+                Some(Effect::Power(player.bomb_power as i8 - before as i8))
+            }
+            // detonators given for good are the ones not among the boosts
+            Effect::Detonator if player.detonator && !has_timed_detonator(player) => None,
+            Effect::Detonator => {
+                player.detonator = true;
+                Some(Effect::Detonator)
+            }
+        }
+    }
+
+    /// Takes the effect back from the given player, once worn off
+    fn revert(&self, player: &mut Player) {
+        match *self {
+            Effect::Bombs(bombs) => player.max_bombs = shift(player.max_bombs, -bombs, MAX_BOMBS),
+            Effect::Power(power) => {
+                player.bomb_power = shift(player.bomb_power, -power, MAX_POWER);
+            }
+            // unless another detonator is still on
+            Effect::Detonator => player.detonator = has_timed_detonator(player),
+        }
+    }
+}
+
+/// Whether the given player has a detonator that will wear off
+fn has_timed_detonator(player: &Player) -> bool {
+    player
+        .boosts
+        .iter()
+        .any(|boost| boost.effect == Effect::Detonator)
+}
+
+/// The given stat moved by the given amount, kept between 1 and the given maximum
+fn shift(stat: u8, by: i8, max: u8) -> u8 {
+    (stat as i16 + by as i16).clamp(1, max as i16) as u8
+}
+
+/// An effect wearing off after a while
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Boost {
+    /// The effect as it was given, which stats at their bounds may have cut short
+    pub effect: Effect,
+    /// Physics steps left before it wears off
+    pub steps_left: u64,
+}
+
+/// A kind of power-up, as defined in [`POWER_UPS_PATH`]
+#[derive(Clone, Debug, Deserialize)]
+pub struct PowerUpKind {
+    /// Name of the kind, as typed in the console and in mode scripts
+    pub id: String,
+    /// Color of the square drawn for the power-up, without a sprite
+    pub color: (f32, f32, f32),
+    /// Image drawn for the power-up, within `assets`
+    #[serde(default)]
+    pub sprite: Option<String>,
+    /// How often the kind drops, against the others
+    pub rarity: f64,
+    pub effect: Effect,
+    /// Seconds the effect lasts for, forever if `None`
+    #[serde(default)]
+    pub duration: Option<f32>,
+}
+
+/// This resource holds every kind of power-up, in the order they're defined
+pub struct PowerUpRegistry {
+    kinds: Vec<PowerUpKind>,
+}
+
+impl Default for PowerUpRegistry {
+    fn default() -> Self {
+        PowerUpRegistry::load(&Mods::default())
+    }
+}
+
+impl PowerUpRegistry {
+    /// Reads the definitions of [`POWER_UPS_PATH`], as replaced by content packs, falling back
+    /// to the built-in ones
+    pub fn load(mods: &Mods) -> Self {
+        let path = mods
+            .file(POWER_UPS_ASSET)
+            .unwrap_or_else(|| PathBuf::from(POWER_UPS_PATH));
+        let kinds = read(&path)
+            .and_then(|content| parse(&content))
+            .unwrap_or_else(|e| {
+                warn!(
+                    "Can't read power-ups from {}, using the built-in ones: {e}",
+                    path.display()
+                );
+                parse(BUILT_IN).unwrap_or_else(|e| {
+                    warn!("Invalid built-in power-ups: {e}");
+                    Vec::new()
+                })
+            });
+        PowerUpRegistry { kinds }
+    }
+
+    /// The kind of the given power-up
+    pub fn get(&self, power_up: PowerUp) -> Option<&PowerUpKind> {
+        self.kinds.get(power_up.0 as usize)
+    }
+
+    /// The power-up of the given kind
+    pub fn find(&self, id: &str) -> Option<PowerUp> {
+        self.kinds
+            .iter()
+            .position(|kind| kind.id == id)
+            .map(|index| PowerUp(index as u8))
+    }
+
+    /// Names of every kind, in the order they're defined
+    #[cfg(feature = "debug-tools")]
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.kinds.iter().map(|kind| kind.id.as_str())
+    }
+
+    /// A power-up to drop, drawn by rarity, `None` if there are none at all
+    pub fn pick(&self, rng: &mut impl Rng) -> Option<PowerUp> {
+        let weights = WeightedIndex::new(self.kinds.iter().map(|kind| kind.rarity)).ok()?;
+        Some(PowerUp(weights.sample(rng) as u8))
+    }
+
+    /// Color of the square drawn for the given power-up
+    pub fn color(&self, power_up: PowerUp) -> Color {
+        self.get(power_up).map_or(Color::WHITE, |kind| {
+            Color::rgb(kind.color.0, kind.color.1, kind.color.2)
+        })
+    }
+
+    /// Gives the effect of the given power-up to the given player, for as long as it lasts
+    pub fn apply(&self, power_up: PowerUp, player: &mut Player) {
+        let kind = match self.get(power_up) {
+            Some(kind) => kind,
+            None => return,
+        };
+        let effect = match kind.effect.apply(player) {
+            Some(effect) => effect,
+            None => return,
+        };
+        match kind.duration {
+            Some(secs) => player.boosts.push(Boost {
+                effect,
+                steps_left: (secs / TIME_STEP).round() as u64,
+            }),
+            // timed detonators wearing off would take this one back along with them
+            None if effect == Effect::Detonator => {
+                player.boosts.retain(|boost| boost.effect != Effect::Detonator);
+            }
+            None => {}
+        }
+    }
+}
+
+/// The content of the given definitions
+#[cfg(not(target_arch = "wasm32"))]
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| e.to_string())
+}
+
+/// Browsers can't read [`POWER_UPS_PATH`], they only know of the definitions built in
+#[cfg(target_arch = "wasm32")]
+fn read(_path: &Path) -> Result<String, String> {
+    Ok(BUILT_IN.to_string())
+}
+
+/// The kinds of the given definitions, checking they can be told apart and dropped
+fn parse(content: &str) -> Result<Vec<PowerUpKind>, String> {
+    let kinds = ron::from_str::<Vec<PowerUpKind>>(content).map_err(|e| e.to_string())?;
+    // power-ups hold the index of their kind in a byte
+    if kinds.len() > u8::MAX as usize + 1 {
+        return Err(format!("{} kinds, at most 256 are allowed", kinds.len()));
+    }
+    for (index, kind) in kinds.iter().enumerate() {
+        if kinds[..index].iter().any(|other| other.id == kind.id) {
+            return Err(format!("{:?} is defined twice", kind.id));
+        }
+        if !kind.rarity.is_finite() || kind.rarity < 0. {
+            return Err(format!("{:?} has an invalid rarity", kind.id));
+        }
+        if kind
+            .duration
+            .is_some_and(|secs| !secs.is_finite() || secs < 0.)
+        {
+            return Err(format!("{:?} has an invalid duration", kind.id));
+        }
+    }
+    Ok(kinds)
+}
+
+// Take back the effects of power-ups once they wear off
+pub fn wear_off(mut query: Query<&mut Player>) {
+    for mut player in &mut query {
+        // left untouched otherwise, so that panels aren't redrawn for nothing
+        if player.boosts.is_empty() {
+            continue;
+        }
+        for boost in &mut player.boosts {
+            boost.steps_left = boost.steps_left.saturating_sub(1);
+        }
+        let (worn, kept) = player
+            .boosts
+            .drain(..)
+            .partition::<Vec<_>, _>(|boost| boost.steps_left == 0);
+        player.boosts = kept;
+        for boost in worn {
+            boost.effect.revert(&mut player);
+        }
+    }
+}
+
+// Draw power-ups with the sprite of their kind, if it has one
+pub fn dress(
+    asset_server: Res<AssetServer>,
+    registry: Res<PowerUpRegistry>,
+    mut query: Query<(&PowerUp, &mut Sprite, &mut Handle<Image>), Added<PowerUp>>,
+) {
+    for (power_up, mut sprite, mut image) in &mut query {
+        if let Some(path) = registry
+            .get(*power_up)
+            .and_then(|kind| kind.sprite.as_ref())
+        {
+            *image = asset_server.load(path.as_str());
+            // the sprite is drawn as it is, rather than tinted
+            sprite.color = Color::WHITE;
+            sprite.custom_size = Some(Vec2::ONE);
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! Time attack mode, and the best times it keeps on disk.
+//!
+//! In time attack humans play alone, with no opponents: the run is over once every breakable
+//! brick of the arena is gone, and the time it took is kept among the best ones of the arena
+//! in [`RECORDS_PATH`].
+//! Arenas are told apart by level, or for random ones by size and seed, since only arenas
+//! generated from the same seed are the same.
+//! The records screen lists the best times of every arena.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::{collections::BTreeMap, io};
+
+use crate::{
+    locale::Locale, storage, AppState, Breakable, GameOverEvent, Mode, Rounds, Rules, Seed, Tick,
+    SCORE_COLOR, TEXT_COLOR, TIME_STEP,
+};
+
+/// Where the best times are stored
+pub const RECORDS_PATH: &str = "records.toml";
+
+const RECORDS_FONT_SIZE: f32 = 30.0;
+// best times kept for every arena
+const RECORDS_KEPT: usize = 5;
+
+/// Best times of every arena, in seconds from the fastest, kept on disk between runs
+#[derive(Default, Serialize, Deserialize)]
+struct Records {
+    times: BTreeMap<String, Vec<f32>>,
+}
+
+impl Records {
+    /// Reads the records from disk, starting with none if there are none
+    fn load() -> Self {
+        match storage::read(RECORDS_PATH) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                warn!("Invalid records in {RECORDS_PATH}: {e}");
+                Records::default()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Records::default(),
+            Err(e) => {
+                warn!("Can't read records from {RECORDS_PATH}: {e}");
+                Records::default()
+            }
+        }
+    }
+
+    /// Writes the records to disk
+    fn save(&self) {
+        let result = toml::to_string(self)
+            .map_err(|e| e.to_string())
+            .and_then(|content| storage::write(RECORDS_PATH, &content).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Can't save records to {RECORDS_PATH}: {e}");
+        }
+    }
+
+    /// Adds a time to those of the given arena, returning its rank, counting from zero,
+    /// if it's among the best ones
+    fn add(&mut self, arena: String, time: f32) -> Option<usize> {
+        let times = self.times.entry(arena).or_default();
+        let rank = times.iter().take_while(|best| **best <= time).count();
+        if rank >= RECORDS_KEPT {
+            return None;
+        }
+        times.insert(rank, time);
+        times.truncate(RECORDS_KEPT);
+        Some(rank)
+    }
+}
+
+/// Name the records of the arena being played are kept under, the same whatever the language
+fn arena_key(rules: &Rules, seed: &Seed) -> String {
+    match (&rules.level, seed.0) {
+        (Some(level), _) => level.name.clone(),
+        (None, Some(seed)) => format!("Random {}x{} #{seed}", rules.size.0, rules.size.1),
+        (None, None) => format!("Random {}x{}", rules.size.0, rules.size.1),
+    }
+}
+
+/// This resource tracks the time attack run being played
+#[derive(Default)]
+pub struct Run {
+    /// Seconds it took to clear the arena, once cleared
+    pub time: Option<f32>,
+    /// Rank of the time among the best ones of the arena, counting from zero, if it's one of them
+    pub rank: Option<usize>,
+    // physics step the round being played started at, to start over with the next one
+    start: u64,
+}
+
+// End the run once every breakable brick is gone, keeping its time if it's among the best ones
+pub fn clear(
+    rules: Res<Rules>,
+    seed: Res<Seed>,
+    tick: Res<Tick>,
+    rounds: Res<Rounds>,
+    mut run: ResMut<Run>,
+    brick_query: Query<(), With<Breakable>>,
+    mut game_over_writer: EventWriter<GameOverEvent>,
+) {
+    if rules.mode != Mode::TimeAttack {
+        return;
+    }
+    if run.start != rounds.start {
+        *run = Run {
+            start: rounds.start,
+            ..default()
+        };
+    }
+    // the outcome of a run may be shown a bit later than it's known
+    if rounds.over || run.time.is_some() || !brick_query.is_empty() {
+        return;
+    }
+    let time = (tick.0 - rounds.start) as f32 * TIME_STEP;
+    let mut records = Records::load();
+    run.time = Some(time);
+    run.rank = records.add(arena_key(&rules, &seed), time);
+    if run.rank.is_some() {
+        records.save();
+    }
+    game_over_writer.send(GameOverEvent {
+        winner: None,
+        team: None,
+    });
+}
+
+/// Marks every entity belonging to the records screen
+#[derive(Component)]
+pub struct RecordsScreen;
+
+pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>, locale: Res<Locale>) {
+    let style = TextStyle {
+        font: asset_server.load(locale.font()),
+        font_size: RECORDS_FONT_SIZE,
+        color: TEXT_COLOR,
+    };
+    let records = Records::load();
+    let mut lines = vec![(locale.text("records.title").to_string(), SCORE_COLOR)];
+    if records.times.is_empty() {
+        lines.push((locale.text("records.none").to_string(), TEXT_COLOR));
+    }
+    for (arena, times) in &records.times {
+        let times = times
+            .iter()
+            .map(|time| format!("{time:.2}s"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        lines.push((format!("{arena}: {times}"), TEXT_COLOR));
+    }
+    lines.push((locale.text("records.back").to_string(), TEXT_COLOR));
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(RecordsScreen)
+        .with_children(|parent| {
+            for (line, color) in lines {
+                parent.spawn_bundle(TextBundle::from_section(
+                    line,
+                    TextStyle {
+                        color,
+                        ..style.clone()
+                    },
+                ));
+            }
+        });
+}
+
+pub fn navigate(mut keyboard_input: ResMut<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if keyboard_input.clear_just_pressed(KeyCode::Escape) {
+        // the key press is consumed, or the menu would quit right away
+        let _ = state.set(AppState::Menu);
+    }
+}
@@ -0,0 +1,182 @@
+//! Levels and rules reloaded as they're changed on disk, for designers to try them out.
+//!
+//! With `--hot-reload`, the levels of [`LEVELS_DIR`] and of the content packs, the level given
+//! with `--level` and the rules given with `--rules` are looked at a couple of times a second.
+//! Once a level changes the menu lists it as it is, and if it's the one played in the round
+//! starts over in it right away. Once the rules change they're played by from then on: fuses,
+//! drops and the like at once, the size and the bricks of random arenas from the next round.
+//! Rules given one by one on the command line still win over those of the files reloaded.
+//! Files left invalid while being edited are warned about, and the game goes on as it was.
+
+use bevy::prelude::*;
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{
+    cli::Overrides,
+    level::{Level, Levels, LEVELS_DIR},
+    mods::Mods,
+    AppState, Rounds, Rules, Tick,
+};
+
+// seconds between two looks at the files watched
+const POLL_SECS: f32 = 0.5;
+
+/// Reloads levels and rules as they're changed on disk
+pub struct HotReloadPlugin {
+    /// Level played from outside the levels of the game, if any
+    pub level: Option<PathBuf>,
+    /// Rules played by, if any
+    pub rules: Option<PathBuf>,
+    /// Rules given one by one, played by whatever the files say
+    pub overrides: Overrides,
+}
+
+impl Plugin for HotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HotReload {
+            timer: Timer::from_seconds(POLL_SECS, true),
+            level: self.level.clone(),
+            rules: self.rules.clone(),
+            overrides: self.overrides.clone(),
+            level_stamps: Stamps::default(),
+            rules_stamps: Stamps::default(),
+        })
+        .add_system(watch);
+    }
+}
+
+/// When files were last modified, as of the last look at them
+#[derive(Default)]
+pub(crate) struct Stamps(Option<HashMap<PathBuf, SystemTime>>);
+
+impl Stamps {
+    /// Takes note of when the given files were last modified, telling whether any of them
+    /// changed, showed up or went away since the last time
+    pub(crate) fn update(&mut self, paths: impl Iterator<Item = PathBuf>) -> bool {
+        let stamps = paths
+            .filter_map(|path| {
+                let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+                Some((path, modified))
+            })
+            .collect::<HashMap<_, _>>();
+        // the first look only takes note
+        let changed = self.0.as_ref().is_some_and(|old| *old != stamps);
+        self.0 = Some(stamps);
+        changed
+    }
+}
+
+/// This resource tracks the files watched
+struct HotReload {
+    timer: Timer,
+    level: Option<PathBuf>,
+    rules: Option<PathBuf>,
+    overrides: Overrides,
+    level_stamps: Stamps,
+    rules_stamps: Stamps,
+}
+
+/// The level files in the given folder
+fn level_files(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Plays by the given rules reloaded, keeping the arena and the script played unless they pick
+/// their own, and the rules given one by one
+pub(crate) fn merge(rules: &mut Rules, mut reloaded: Rules, overrides: &Overrides) {
+    if reloaded.level.is_none() {
+        if let Some(level) = rules.level.take() {
+            level.apply(&mut reloaded);
+        }
+    }
+    if reloaded.script.is_none() {
+        reloaded.script = rules.script.take();
+    }
+    overrides.apply(&mut reloaded);
+    *rules = reloaded;
+}
+
+// Reload levels and rules changed on disk, starting the round over if its arena changed
+#[allow(clippy::too_many_arguments)]
+fn watch(
+    mut commands: Commands,
+    time: Res<Time>,
+    mods: Res<Mods>,
+    state: Res<State<AppState>>,
+    tick: Res<Tick>,
+    mut hot_reload: ResMut<HotReload>,
+    mut levels: ResMut<Levels>,
+    mut rules: ResMut<Rules>,
+    mut rounds: ResMut<Rounds>,
+) {
+    if !hot_reload.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let hot_reload = &mut *hot_reload;
+
+    let mut relaid = false;
+    let files = std::iter::once(PathBuf::from(LEVELS_DIR))
+        .chain(mods.dirs("levels"))
+        .flat_map(|dir| level_files(&dir))
+        .chain(hot_reload.level.clone());
+    if hot_reload.level_stamps.update(files) {
+        *levels = Levels::load(&mods);
+        // the level given on the command line comes first, as it did at launch
+        let given = hot_reload.level.as_ref().and_then(|path| {
+            Level::load(path)
+                .map_err(|e| warn!("Invalid level {}: {e}", path.display()))
+                .ok()
+        });
+        let reloaded = rules.level.as_ref().and_then(|current| {
+            given
+                .into_iter()
+                .chain(levels.0.iter().cloned())
+                .find(|level| level.name == current.name)
+                .filter(|level| level != current)
+        });
+        if let Some(level) = reloaded {
+            info!("Level {} reloaded", level.name);
+            level.apply(&mut rules);
+            hot_reload.overrides.apply(&mut rules);
+            relaid = true;
+        }
+    }
+
+    let rules_changed = hot_reload.rules.as_ref().filter(|path| {
+        hot_reload
+            .rules_stamps
+            .update(std::iter::once(path.to_path_buf()))
+    });
+    if let Some(path) = rules_changed {
+        match Rules::load(path) {
+            Ok(reloaded) => {
+                info!("Rules reloaded from {}", path.display());
+                merge(&mut rules, reloaded, &hot_reload.overrides);
+            }
+            Err(e) => warn!("Can't reload rules from {}: {e}", path.display()),
+        }
+    }
+
+    let playing =
+        state.current() == &AppState::InGame || state.inactives().contains(&AppState::InGame);
+    if relaid && playing {
+        commands.insert_resource(ClearColor(rules.theme().palette().background));
+        // the round starts over at once, with nobody winning it
+        rounds.over = true;
+        rounds.outcome = None;
+        rounds.next = Some(tick.0);
+    }
+}
@@ -0,0 +1,35 @@
+//! Replays of matches.
+//!
+//! Matches between bots are fully determined by their seed, their rules and the bots playing
+//! them, which is all a replay holds: played again from it, they go exactly as they did.
+//! Headless runs print the seed they number their matches from, to write replays of them.
+//! Humans can take part too, following the [`InputScript`] they're given in the replay
+//! instead of anybody's controls.
+
+use serde::{Deserialize, Serialize};
+
+use std::{fs, path::Path};
+
+use crate::{script::InputScript, Rules};
+
+/// A match to play again, as written in a replay file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    /// Scripts driving the humans of the match, one each, none for bots-only matches
+    #[serde(default)]
+    pub humans: Vec<InputScript>,
+    /// Controllers of the bots, one per opponent
+    pub bots: Vec<String>,
+    /// Rules missing from the file are the default ones
+    #[serde(default)]
+    pub rules: Rules,
+}
+
+impl Replay {
+    /// Reads a replay from disk
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        toml::from_str(&content).map_err(|e| e.to_string())
+    }
+}
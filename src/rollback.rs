@@ -0,0 +1,786 @@
+//! Peer-to-peer matches with rollback.
+//!
+//! Both instances simulate the whole match, each one driving one of the two players.
+//! Local inputs are applied a few steps later than they're read, giving them time to reach
+//! the other instance; remote inputs that are late are predicted by repeating the latest one.
+//! When a late input turns out to differ from its prediction, the match is restored to the
+//! [`MatchState`] saved before that step and simulated again up to the present.
+//! This only works because physics steps are deterministic: they're run by hand, with every
+//! event producer ordered before its readers, and every random outcome comes from [`MatchRng`].
+
+use bevy::{
+    ecs::{
+        event::{Events, ManualEventReader},
+        system::CommandQueue,
+    },
+    input::InputSystem,
+    prelude::*,
+    render::texture::ImageSettings,
+};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
+use crate::{
+    accessibility, animation, announcer,
+    bot::{BotRegistry, Roster},
+    camera, clock, display, game_over, hints,
+    input::{Bindings, BombBuffer},
+    lighting,
+    locale::Locale,
+    net::{PlayerInput, Remote, PROTOCOL_VERSION},
+    particles, physics,
+    power_ups::{self, Boost, PowerUpRegistry},
+    settings::Settings,
+    setup, setup_camera, sound, spawn_arena, spawn_bomb, spawn_breakable, spawn_corpse, spawn_fire,
+    spawn_player, spawn_power_up, spawn_rubble,
+    theme::Theme,
+    transport::{Address, Transport},
+    update_hud, update_scoreboard, Active, Bomb, BombEvent, Breakable, Corpse, Dash, DashEvent,
+    DetonateEvent, Direction, Explosion2Event, ExplosionEvent, Facing, Fire, GamePlugin,
+    HumanPanel, Match, MatchRng, MoveEvent, Phase, Player, PowerUp, Rubble, Rules, ScoreText,
+    Scoreboard, Stepping, Tick, Velocity, DEATH_SECS, PLAYER_COLORS, RUBBLE_SECS,
+};
+
+/// Steps a local input is delayed by, unless configured otherwise
+pub const DEFAULT_INPUT_DELAY: u64 = 2;
+
+// a match is played by two instances
+const SEATS: usize = 2;
+// how many steps can be rolled back, and predicted before waiting for the other instance
+const MAX_ROLLBACK: u64 = 16;
+// how many of the latest local inputs every datagram carries, so that a lost one doesn't matter
+const INPUT_WINDOW: u64 = 8;
+
+/// Options of a peer-to-peer match
+pub struct Config {
+    /// Where inputs are exchanged
+    pub transport: Transport,
+    /// Address of the other instance, the first one to show up if unknown
+    pub peer: Option<Address>,
+    /// Which player the local instance drives, the first one also picks the seed.
+    /// If unknown, the instance with the lowest address takes the first seat.
+    pub seat: Option<usize>,
+    /// Steps local inputs are delayed by
+    pub input_delay: u64,
+}
+
+/// Inputs of an instance, starting at the given step
+#[derive(Debug, Serialize, Deserialize)]
+struct Packet {
+    /// Version of the protocol, always first so that any version can read it
+    version: u32,
+    /// Seed of the match, only meaningful when coming from the first seat
+    seed: u64,
+    first: u64,
+    inputs: Vec<PlayerInput>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct PlayerState {
+    seat: usize,
+    position: Vec2,
+    max_bombs: u8,
+    active_bombs: u8,
+    bomb_power: u8,
+    detonator: bool,
+    facing: Direction,
+    velocity: Vec2,
+    dash: Dash,
+    buffer: u8,
+    boosts: Vec<Boost>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct BombState {
+    // `None` once the owner is dead
+    seat: Option<usize>,
+    translation: Vec3,
+    power: u8,
+    // fuses last as long as the rules said when the bomb was placed
+    fuse: Duration,
+    elapsed: Duration,
+    // whether another blast set it off, to explode at the next step
+    chained: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct FireState {
+    translation: Vec3,
+    scale: Vec3,
+    elapsed: Duration,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CorpseState {
+    translation: Vec3,
+    color: Color,
+    elapsed: Duration,
+    points: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct RubbleState {
+    translation: Vec3,
+    elapsed: Duration,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct PowerUpState {
+    translation: Vec3,
+    kind: PowerUp,
+}
+
+/// Everything that changes during a match, as it was before a given step
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MatchState {
+    tick: u64,
+    score: usize,
+    rng: ChaCha8Rng,
+    // bomb key presses already honored, per seat
+    honored: Vec<u32>,
+    players: Vec<PlayerState>,
+    bombs: Vec<BombState>,
+    fires: Vec<FireState>,
+    rubble: Vec<RubbleState>,
+    // breakable ones, the others never change
+    bricks: Vec<Vec2>,
+    power_ups: Vec<PowerUpState>,
+    // the round isn't over until they're gone
+    corpses: Vec<CorpseState>,
+}
+
+impl MatchState {
+    /// Takes a snapshot of the match, given the bombs set off by a blast of the previous step
+    fn save(world: &mut World, chained: &[Entity]) -> Self {
+        let mut players = world.query::<(
+            Entity,
+            &Remote,
+            &Transform,
+            &Player,
+            &Facing,
+            &Velocity,
+            &Dash,
+            &BombBuffer,
+        )>();
+        let seats = players
+            .iter(world)
+            .map(|(entity, remote, ..)| (entity, remote.0))
+            .collect::<Vec<_>>();
+        let players = players
+            .iter(world)
+            .map(
+                |(_, remote, transform, player, facing, velocity, dash, buffer)| PlayerState {
+                    seat: remote.0,
+                    position: transform.translation.truncate(),
+                    max_bombs: player.max_bombs,
+                    active_bombs: player.active_bombs,
+                    bomb_power: player.bomb_power,
+                    detonator: player.detonator,
+                    facing: facing.0,
+                    velocity: velocity.0,
+                    dash: *dash,
+                    buffer: buffer.0,
+                    boosts: player.boosts.clone(),
+                },
+            )
+            .collect();
+        let bombs = world
+            .query::<(Entity, &Bomb, &Transform)>()
+            .iter(world)
+            .map(|(entity, bomb, transform)| BombState {
+                seat: seats
+                    .iter()
+                    .find(|(player, _)| Some(*player) == bomb.player)
+                    .map(|(_, seat)| *seat),
+                translation: transform.translation,
+                power: bomb.power,
+                fuse: bomb.timer.duration(),
+                elapsed: bomb.timer.elapsed(),
+                chained: chained.contains(&entity),
+            })
+            .collect();
+        let fires = world
+            .query::<(&Fire, &Transform)>()
+            .iter(world)
+            .map(|(fire, transform)| FireState {
+                translation: transform.translation,
+                scale: transform.scale,
+                elapsed: fire.0.elapsed(),
+            })
+            .collect();
+        let rubble = world
+            .query::<(&Rubble, &Transform)>()
+            .iter(world)
+            .map(|(rubble, transform)| RubbleState {
+                translation: transform.translation,
+                elapsed: rubble.0.elapsed(),
+            })
+            .collect();
+        let bricks = world
+            .query_filtered::<&Transform, With<Breakable>>()
+            .iter(world)
+            .map(|transform| transform.translation.truncate())
+            .collect();
+        let power_ups = world
+            .query::<(&PowerUp, &Transform)>()
+            .iter(world)
+            .map(|(power_up, transform)| PowerUpState {
+                translation: transform.translation,
+                kind: *power_up,
+            })
+            .collect();
+        let corpses = world
+            .query::<(&Corpse, &Transform, &Sprite)>()
+            .iter(world)
+            .map(|(corpse, transform, sprite)| CorpseState {
+                translation: transform.translation,
+                color: sprite.color,
+                elapsed: corpse.timer.elapsed(),
+                points: corpse.points,
+            })
+            .collect();
+
+        MatchState {
+            tick: world.resource::<Tick>().0,
+            score: world.resource::<Scoreboard>().score,
+            rng: world.resource::<MatchRng>().0.clone(),
+            honored: world.resource::<SeatInputs>().honored.clone(),
+            players,
+            bombs,
+            fires,
+            rubble,
+            bricks,
+            power_ups,
+            corpses,
+        }
+    }
+
+    /// Brings the match back to the snapshot
+    fn restore(&self, world: &mut World) {
+        let mut leftovers = world.query_filtered::<Entity, Or<(
+            With<Player>,
+            With<Bomb>,
+            With<Fire>,
+            With<Rubble>,
+            With<Breakable>,
+            With<PowerUp>,
+            With<Corpse>,
+        )>>();
+        for entity in leftovers.iter(world).collect::<Vec<_>>() {
+            world.despawn(entity);
+        }
+        world.resource_mut::<Events<ExplosionEvent>>().clear();
+        world.resource_mut::<Events<Explosion2Event>>().clear();
+        world.resource_mut::<Events<MoveEvent>>().clear();
+        world.resource_mut::<Events<BombEvent>>().clear();
+        world.resource_mut::<Events<DetonateEvent>>().clear();
+        world.resource_mut::<Events<DashEvent>>().clear();
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, world);
+        let mut seats = Vec::new();
+        for state in &self.players {
+            let player = spawn_player(&mut commands, state.position, PLAYER_COLORS[state.seat])
+                .insert(Player {
+                    max_bombs: state.max_bombs,
+                    active_bombs: state.active_bombs,
+                    bomb_power: state.bomb_power,
+                    detonator: state.detonator,
+                    boosts: state.boosts.clone(),
+                })
+                .insert(Facing(state.facing))
+                .insert(Velocity(state.velocity))
+                .insert(state.dash)
+                .insert(Active(state.seat))
+                .insert(BombBuffer(state.buffer))
+                .insert(Name::new(format!("player #{}", state.seat + 1)))
+                .insert(Remote(state.seat))
+                .id();
+            seats.push((state.seat, player));
+        }
+        for position in &self.bricks {
+            spawn_breakable(&mut commands, *position, Theme::default().palette().brick);
+        }
+        let mut chained = Vec::new();
+        for state in &self.bombs {
+            let mut timer = Timer::new(state.fuse, false);
+            timer.set_elapsed(state.elapsed);
+            let owner = state
+                .seat
+                .and_then(|seat| seats.iter().find(|(other, _)| *other == seat))
+                .map(|(_, player)| *player);
+            let bomb = spawn_bomb(
+                &mut commands,
+                Bomb {
+                    player: owner,
+                    timer,
+                    power: state.power,
+                },
+                state.translation,
+            );
+            if state.chained {
+                chained.push(bomb);
+            }
+        }
+        for state in &self.fires {
+            let mut timer = Timer::from_seconds(1., false);
+            timer.set_elapsed(state.elapsed);
+            spawn_fire(&mut commands, Fire(timer), state.translation, state.scale);
+        }
+        for state in &self.rubble {
+            let mut timer = Timer::from_seconds(RUBBLE_SECS, false);
+            timer.set_elapsed(state.elapsed);
+            spawn_rubble(&mut commands, Rubble(timer), state.translation);
+        }
+        let power_up_registry = world.resource::<PowerUpRegistry>();
+        for state in &self.power_ups {
+            spawn_power_up(
+                &mut commands,
+                power_up_registry,
+                state.kind,
+                state.translation,
+            );
+        }
+        for state in &self.corpses {
+            let mut timer = Timer::from_seconds(DEATH_SECS, false);
+            timer.set_elapsed(state.elapsed);
+            spawn_corpse(
+                &mut commands,
+                Corpse {
+                    timer,
+                    points: state.points,
+                },
+                state.translation,
+                state.color,
+            );
+        }
+        queue.apply(world);
+
+        let mut explosions = world.resource_mut::<Events<ExplosionEvent>>();
+        for bomb in chained {
+            explosions.send(ExplosionEvent(bomb));
+        }
+        world.resource_mut::<Tick>().0 = self.tick;
+        world.resource_mut::<Scoreboard>().score = self.score;
+        world.resource_mut::<MatchRng>().0 = self.rng.clone();
+        world.resource_mut::<SeatInputs>().honored = self.honored.clone();
+    }
+}
+
+/// This resource holds the inputs of the step being simulated
+struct SeatInputs {
+    current: Vec<PlayerInput>,
+    // bomb key presses already honored, per seat
+    honored: Vec<u32>,
+}
+
+impl Default for SeatInputs {
+    fn default() -> Self {
+        SeatInputs {
+            current: vec![PlayerInput::default(); SEATS],
+            honored: vec![0; SEATS],
+        }
+    }
+}
+
+/// This resource tracks the exchange of inputs and the history of the match
+struct Session {
+    transport: Transport,
+    peer: Option<Address>,
+    // known once the other instance is
+    seat: Option<usize>,
+    input_delay: u64,
+    // known from the start on the first seat, from the other instance on the second one
+    seed: Option<u64>,
+    // whether the other instance ever answered
+    connected: bool,
+    // a single physics step, run by hand
+    stage: SystemStage,
+    // next step to simulate
+    tick: u64,
+    // bomb key presses so far
+    bombs: u32,
+    local: BTreeMap<u64, PlayerInput>,
+    remote: BTreeMap<u64, PlayerInput>,
+    // first step whose remote input is still unknown
+    remote_known: u64,
+    // remote inputs assumed for steps already simulated
+    predictions: BTreeMap<u64, PlayerInput>,
+    snapshots: VecDeque<MatchState>,
+    chain_reader: ManualEventReader<Explosion2Event>,
+}
+
+impl Session {
+    fn started(&self) -> bool {
+        self.connected && self.seat.is_some() && self.seed.is_some()
+    }
+
+    fn send(&self) {
+        let peer = match &self.peer {
+            Some(peer) => peer,
+            None => return,
+        };
+        let first = self
+            .local
+            .keys()
+            .next_back()
+            .map_or(0, |last| (last + 1).saturating_sub(INPUT_WINDOW));
+        let packet = Packet {
+            version: PROTOCOL_VERSION,
+            seed: self.seed.unwrap_or_default(),
+            first,
+            inputs: self
+                .local
+                .range(first..)
+                .map(|(_, input)| input.clone())
+                .collect(),
+        };
+        let result = bincode::serialize(&packet)
+            .map_err(|e| e.to_string())
+            .and_then(|datagram| {
+                self.transport
+                    .send(&datagram, peer)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            warn!("Can't send inputs to {peer}: {e}");
+        }
+    }
+
+    /// Collects the other instance's inputs, returning the first step that was mispredicted
+    fn receive(&mut self) -> Option<u64> {
+        for address in self.transport.connected() {
+            if self.peer.is_none() {
+                info!("{address} showed up");
+                self.peer = Some(address);
+            }
+        }
+        if self.seat.is_none() {
+            if let (Some(peer), Some(local)) = (&self.peer, self.transport.local()) {
+                let seat = if local < *peer { 0 } else { 1 };
+                info!("Playing as player #{}", seat + 1);
+                self.seat = Some(seat);
+                if seat == 0 {
+                    self.seed = Some(rand::random());
+                }
+            }
+        }
+
+        let datagrams = self.transport.receive().unwrap_or_else(|e| {
+            warn!("Can't receive inputs: {e}");
+            Vec::new()
+        });
+        let mut mispredicted = None;
+        for (datagram, address) in datagrams {
+            if self.peer.as_ref() != Some(&address) {
+                continue;
+            }
+            let packet = match bincode::deserialize::<Packet>(&datagram) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    warn!("Invalid inputs from {address}: {e}");
+                    continue;
+                }
+            };
+            if packet.version != PROTOCOL_VERSION {
+                warn!(
+                    "{address} speaks version {} instead of {PROTOCOL_VERSION}",
+                    packet.version
+                );
+                continue;
+            }
+
+            if !self.connected {
+                info!("Connected to {address}");
+                self.connected = true;
+            }
+            if self.seed.is_none() {
+                self.seed = Some(packet.seed);
+            }
+            for (tick, input) in (packet.first..).zip(packet.inputs) {
+                if self.remote.contains_key(&tick) {
+                    continue;
+                }
+                if let Some(predicted) = self.predictions.remove(&tick) {
+                    if predicted != input {
+                        mispredicted =
+                            Some(mispredicted.map_or(tick, |other: u64| other.min(tick)));
+                    }
+                }
+                self.remote.insert(tick, input);
+            }
+            while self.remote.contains_key(&self.remote_known) {
+                self.remote_known += 1;
+            }
+        }
+        mispredicted
+    }
+
+    /// Runs the next step, with the inputs known or predicted for it
+    fn simulate(&mut self, world: &mut World) {
+        let remote = match self.remote.get(&self.tick) {
+            Some(input) => input.clone(),
+            None => {
+                let predicted = self
+                    .remote
+                    .range(..self.tick)
+                    .next_back()
+                    .map(|(_, input)| input.clone())
+                    .unwrap_or_default();
+                self.predictions.insert(self.tick, predicted.clone());
+                predicted
+            }
+        };
+        let local = self.local.get(&self.tick).cloned().unwrap_or_default();
+
+        {
+            // steps are only simulated once the seat is known
+            let seat = self.seat.unwrap_or_default();
+            let mut seats = world.resource_mut::<SeatInputs>();
+            seats.current[seat] = local;
+            seats.current[1 - seat] = remote;
+        }
+        self.stage.run(world);
+        self.tick += 1;
+    }
+
+    /// Saves the match, then runs the next step
+    fn advance(&mut self, world: &mut World) {
+        let chained = self
+            .chain_reader
+            .iter(world.resource::<Events<Explosion2Event>>())
+            .map(|event| event.0)
+            .collect::<Vec<_>>();
+        self.snapshots.push_back(MatchState::save(world, &chained));
+        if self.snapshots.len() as u64 > MAX_ROLLBACK {
+            self.snapshots.pop_front();
+        }
+        self.simulate(world);
+    }
+
+    /// Goes back to the given step and simulates again up to the present
+    fn roll_back(&mut self, world: &mut World, tick: u64) {
+        let index = match self.snapshots.iter().position(|state| state.tick == tick) {
+            Some(index) => index,
+            None => {
+                error!("Can't roll back to step {tick}, the match is out of sync");
+                return;
+            }
+        };
+        self.snapshots.truncate(index + 1);
+        let state = self.snapshots[index].clone();
+        state.restore(world);
+        // the restored snapshot stays the one of its step, chained bombs included
+        self.chain_reader = ManualEventReader::default();
+        let now = self.tick;
+        self.tick = tick;
+        self.simulate(world);
+        while self.tick < now {
+            self.advance(world);
+        }
+    }
+}
+
+/// Plays a match against another instance, until the window is closed
+pub fn run(config: Config) {
+    let mut stage = SystemStage::parallel();
+    for set in physics() {
+        stage.add_system_set(set);
+    }
+    stage.add_system(drive_seats.label(Phase::Input));
+
+    let settings = Settings::load();
+    let mut app = App::new();
+    app.insert_resource(settings.window.descriptor())
+        .insert_resource(ImageSettings::default_nearest())
+        .add_plugins(DefaultPlugins)
+        .add_plugin(GamePlugin {
+            stepping: Stepping::Manual,
+        })
+        .insert_resource(ClearColor(Theme::default().palette().background))
+        .insert_resource(settings.bindings.clone())
+        .insert_resource(Locale::load(&settings.language))
+        .insert_resource(settings)
+        .insert_resource(Roster {
+            humans: 0,
+            remotes: SEATS,
+            bots: Vec::new(),
+            ..default()
+        })
+        .init_resource::<SeatInputs>()
+        .insert_resource(Session {
+            transport: config.transport,
+            peer: config.peer,
+            seat: config.seat,
+            input_delay: config.input_delay,
+            seed: (config.seat == Some(0)).then(rand::random),
+            connected: false,
+            stage,
+            tick: 0,
+            bombs: 0,
+            // the first steps happen before any input could be applied
+            local: (0..config.input_delay)
+                .map(|tick| (tick, PlayerInput::default()))
+                .collect(),
+            remote: BTreeMap::new(),
+            remote_known: 0,
+            predictions: BTreeMap::new(),
+            snapshots: VecDeque::new(),
+            chain_reader: ManualEventReader::default(),
+        })
+        .add_startup_system(setup_camera)
+        .add_startup_system(animation::load)
+        .add_startup_system(setup)
+        .add_system(animation::dress)
+        .add_system(power_ups::dress)
+        .add_system(animation::walk)
+        .add_system(animation::fuse)
+        .add_system(animation::ignite)
+        .add_system(animation::burn)
+        .add_system(animation::crumble)
+        .add_system(accessibility::mark)
+        .init_resource::<particles::ParticlePool>()
+        .add_system(particles::blast)
+        .add_system(particles::sparkle)
+        .add_system(particles::drift)
+        .add_system(camera::shake)
+        .add_system(camera::follow.after(camera::shake))
+        .add_system(hints::preview)
+        .add_system(hints::telegraph)
+        .add_startup_system(lighting::setup)
+        .add_system(lighting::shine)
+        .add_startup_system(sound::load)
+        .add_system(sound::play)
+        .add_system(sound::tune)
+        .add_startup_system(announcer::load)
+        .add_system(announcer::announce)
+        .add_system_to_stage(CoreStage::PreUpdate, display::fullscreen.after(InputSystem))
+        .add_system(display::apply)
+        .add_system(display::scale_text)
+        .add_system(announcer::first_blood)
+        .add_system(announcer::hurry_up)
+        .add_system(count_bombs)
+        .add_system(update_scoreboard)
+        .add_system(update_hud)
+        .add_system(game_over)
+        .add_system(announcer::winners.after(game_over))
+        .add_system(bevy::window::close_on_esc)
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(clock::catch_up)
+                .with_system(step.exclusive_system()),
+        );
+    #[cfg(not(target_arch = "wasm32"))]
+    app.add_startup_system(display::set_icon);
+    #[cfg(feature = "debug-tools")]
+    app.add_plugin(crate::debug::DebugPlugin);
+    app.run();
+}
+
+/// Counts bomb key presses, on every frame so that short taps between physics steps aren't lost
+fn count_bombs(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut session: ResMut<Session>,
+) {
+    if keyboard_input.just_pressed(bindings.players[0].bomb) {
+        session.bombs = session.bombs.wrapping_add(1);
+    }
+}
+
+/// Turns the inputs of the step being simulated into the same events local humans send
+fn drive_seats(
+    mut seats: ResMut<SeatInputs>,
+    mut move_writer: EventWriter<MoveEvent>,
+    mut bomb_writer: EventWriter<BombEvent>,
+    mut detonate_writer: EventWriter<DetonateEvent>,
+    mut dash_writer: EventWriter<DashEvent>,
+    mut query: Query<(Entity, &Remote, &mut BombBuffer)>,
+) {
+    let seats = &mut *seats;
+    for (player, remote, mut buffer) in &mut query {
+        if let (Some(input), Some(honored)) =
+            (seats.current.get(remote.0), seats.honored.get_mut(remote.0))
+        {
+            input.send(
+                player,
+                honored,
+                &mut buffer,
+                &mut move_writer,
+                &mut bomb_writer,
+                &mut detonate_writer,
+                &mut dash_writer,
+            );
+        }
+    }
+}
+
+/// Exchanges inputs, fixes mispredictions and runs the next step
+fn step(world: &mut World) {
+    world.resource_scope(|world, mut session: Mut<Session>| {
+        let session = &mut *session;
+        let mispredicted = session.receive();
+
+        if !session.started() {
+            // tell the other instance we're here
+            session.send();
+            return;
+        }
+        if session.tick == 0 {
+            let seed = session.seed.unwrap_or_default();
+            world.insert_resource(MatchRng::seeded(seed));
+            respawn_arena(world);
+        }
+
+        if let Some(tick) = mispredicted {
+            session.roll_back(world, tick);
+        }
+
+        // wait for the other instance instead of predicting too far ahead
+        if session.tick >= session.remote_known + MAX_ROLLBACK {
+            session.send();
+            return;
+        }
+
+        let bindings = world.resource::<Bindings>();
+        let input = PlayerInput::read(
+            world.resource::<Input<KeyCode>>(),
+            &bindings.players[0],
+            session.bombs,
+        );
+        session
+            .local
+            .insert(session.tick + session.input_delay, input);
+        session.send();
+        session.advance(world);
+    });
+}
+
+/// Lays out the arena again, since it's drawn from the seed that wasn't known at startup
+fn respawn_arena(world: &mut World) {
+    let mut leftovers =
+        world.query_filtered::<Entity, (With<Match>, Without<ScoreText>, Without<HumanPanel>)>();
+    for entity in leftovers.iter(world).collect::<Vec<_>>() {
+        world.despawn(entity);
+    }
+
+    // the generator is taken out of the world while commands borrow it
+    let mut rng = world.remove_resource::<MatchRng>().unwrap_or_default();
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, world);
+    spawn_arena(
+        &mut commands,
+        world.resource::<BotRegistry>(),
+        world.resource::<Roster>(),
+        world.resource::<Bindings>(),
+        world.resource::<Rules>(),
+        &mut rng,
+    );
+    queue.apply(world);
+    world.insert_resource(rng);
+}
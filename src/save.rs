@@ -0,0 +1,409 @@
+//! Matches saved while paused, to be resumed later.
+//!
+//! S on the pause screen writes the match to [`SAVE_PATH`], and L in the menu picks it up from
+//! there. The arena is laid out anew by the saved rules, then what changes during a match is
+//! brought back as it was: players and their power-ups, bombs and their fuses, fires, bricks and
+//! pillars, power-ups lying around, the exit, the score, rounds and the random generator.
+//! Mobs, the boss, crowns on the ground and the hill start over. Campaigns keep their own
+//! progress instead, and online matches can't be saved at all.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use std::{fs, time::Duration};
+
+use crate::{
+    bot::{Roster, MAX_HUMANS},
+    campaign::Campaign,
+    crown::Crowns,
+    hill::Hold,
+    locale::Locale,
+    power_ups::{Boost, PowerUpRegistry},
+    spawn_bomb, spawn_breakable, spawn_exit, spawn_fire, spawn_pillar, spawn_power_up, Bomb,
+    Breakable, Brick, Dash, Direction, Exit, Facing, Fire, MatchRng, Player, PlayerIndex, PowerUp,
+    Rounds, Rules, Scoreboard, Tick, Velocity, TEAMS,
+};
+
+/// Where the saved match is stored
+pub const SAVE_PATH: &str = "savegame.bin";
+
+#[derive(Serialize, Deserialize)]
+struct SavedPlayer {
+    // among the players of the match, in spawn order
+    index: usize,
+    position: Vec2,
+    max_bombs: u8,
+    active_bombs: u8,
+    bomb_power: u8,
+    detonator: bool,
+    facing: Direction,
+    velocity: Vec2,
+    dash: Dash,
+    crowns: Option<u32>,
+    hold: Option<u64>,
+    boosts: Vec<Boost>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedBomb {
+    // index of the player who placed it, `None` once that player is dead
+    owner: Option<usize>,
+    translation: Vec3,
+    power: u8,
+    fuse: Duration,
+    elapsed: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedFire {
+    translation: Vec3,
+    scale: Vec3,
+    elapsed: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPowerUp {
+    translation: Vec3,
+    kind: PowerUp,
+}
+
+/// A match as it was when saved
+#[derive(Serialize, Deserialize)]
+pub struct SavedMatch {
+    rules: Rules,
+    humans: usize,
+    bots: Vec<String>,
+    colors: [Color; MAX_HUMANS],
+    tick: u64,
+    score: usize,
+    rng: ChaCha8Rng,
+    played: u32,
+    wins: Vec<u32>,
+    team_wins: [u32; TEAMS],
+    // physics step the current round started at
+    start: u64,
+    // only the ones still alive
+    players: Vec<SavedPlayer>,
+    bombs: Vec<SavedBomb>,
+    fires: Vec<SavedFire>,
+    bricks: Vec<Vec2>,
+    // random arenas turn some of them into bricks, the generator may not do it the same again
+    pillars: Vec<Vec2>,
+    power_ups: Vec<SavedPowerUp>,
+    exit: Option<Vec3>,
+}
+
+impl SavedMatch {
+    /// Reads the saved match from disk
+    pub fn load() -> Result<Self, String> {
+        let content = fs::read(SAVE_PATH).map_err(|e| e.to_string())?;
+        bincode::deserialize(&content).map_err(|e| e.to_string())
+    }
+
+    /// Writes the match to disk
+    fn store(&self) -> Result<(), String> {
+        let content = bincode::serialize(self).map_err(|e| e.to_string())?;
+        fs::write(SAVE_PATH, content).map_err(|e| e.to_string())
+    }
+
+    /// Sets the rules and players up for the saved match, to be brought back by [`resume`] once
+    /// its arena is laid out
+    pub fn prepare(self, commands: &mut Commands, rules: &mut Rules, roster: &mut Roster) {
+        *rules = self.rules.clone();
+        roster.humans = self.humans;
+        roster.remotes = 0;
+        roster.bots = self.bots.clone();
+        roster.colors = self.colors;
+        commands.insert_resource(Resume(self));
+    }
+}
+
+/// This resource holds a saved match waiting for its arena to be laid out
+pub struct Resume(SavedMatch);
+
+/// Marks the line of the pause screen telling about saving
+#[derive(Component)]
+pub struct SaveLine;
+
+/// The match being played, as it's saved
+#[derive(SystemParam)]
+#[allow(clippy::type_complexity)]
+pub(crate) struct Snapshot<'w, 's> {
+    rules: Res<'w, Rules>,
+    roster: Res<'w, Roster>,
+    tick: Res<'w, Tick>,
+    scoreboard: Res<'w, Scoreboard>,
+    rng: Res<'w, MatchRng>,
+    rounds: Res<'w, Rounds>,
+    player_query: Query<
+        'w,
+        's,
+        (
+            &'static PlayerIndex,
+            &'static Player,
+            &'static Transform,
+            &'static Facing,
+            &'static Velocity,
+            &'static Dash,
+            Option<&'static Crowns>,
+            Option<&'static Hold>,
+        ),
+    >,
+    bomb_query: Query<'w, 's, (&'static Bomb, &'static Transform)>,
+    fire_query: Query<'w, 's, (&'static Fire, &'static Transform)>,
+    brick_query: Query<'w, 's, (&'static Transform, Option<&'static Breakable>), With<Brick>>,
+    power_up_query: Query<'w, 's, (&'static PowerUp, &'static Transform)>,
+    exit_query: Query<'w, 's, &'static Transform, With<Exit>>,
+}
+
+impl<'w, 's> Snapshot<'w, 's> {
+    /// The match as it is now
+    pub fn take(&self) -> SavedMatch {
+        let index_of = |entity: Entity| {
+            self.player_query
+                .get(entity)
+                .ok()
+                .map(|(index, ..)| index.0)
+        };
+        let bricks = |breakable: bool| {
+            self.brick_query
+                .iter()
+                .filter(|(_, kind)| kind.is_some() == breakable)
+                .map(|(transform, _)| transform.translation.truncate())
+                .collect()
+        };
+        SavedMatch {
+            rules: self.rules.clone(),
+            humans: self.roster.humans,
+            bots: self.roster.bots.clone(),
+            colors: self.roster.colors,
+            tick: self.tick.0,
+            score: self.scoreboard.score,
+            rng: self.rng.0.clone(),
+            played: self.rounds.played,
+            wins: self.rounds.wins.clone(),
+            team_wins: self.rounds.team_wins,
+            start: self.rounds.start,
+            players: self
+                .player_query
+                .iter()
+                .map(
+                    |(index, player, transform, facing, velocity, dash, crowns, hold)| {
+                        SavedPlayer {
+                            index: index.0,
+                            position: transform.translation.truncate(),
+                            max_bombs: player.max_bombs,
+                            active_bombs: player.active_bombs,
+                            bomb_power: player.bomb_power,
+                            detonator: player.detonator,
+                            facing: facing.0,
+                            velocity: velocity.0,
+                            dash: *dash,
+                            crowns: crowns.map(|crowns| crowns.0),
+                            hold: hold.map(|hold| hold.0),
+                            boosts: player.boosts.clone(),
+                        }
+                    },
+                )
+                .collect(),
+            bombs: self
+                .bomb_query
+                .iter()
+                .map(|(bomb, transform)| SavedBomb {
+                    owner: bomb.player.and_then(index_of),
+                    translation: transform.translation,
+                    power: bomb.power,
+                    fuse: bomb.timer.duration(),
+                    elapsed: bomb.timer.elapsed(),
+                })
+                .collect(),
+            fires: self
+                .fire_query
+                .iter()
+                .map(|(fire, transform)| SavedFire {
+                    translation: transform.translation,
+                    scale: transform.scale,
+                    elapsed: fire.0.elapsed(),
+                })
+                .collect(),
+            bricks: bricks(true),
+            pillars: bricks(false),
+            power_ups: self
+                .power_up_query
+                .iter()
+                .map(|(power_up, transform)| SavedPowerUp {
+                    translation: transform.translation,
+                    kind: *power_up,
+                })
+                .collect(),
+            exit: self
+                .exit_query
+                .iter()
+                .next()
+                .map(|transform| transform.translation),
+        }
+    }
+}
+
+// Save the match when asked to, from the pause screen
+pub fn save(
+    keyboard_input: Res<Input<KeyCode>>,
+    locale: Res<Locale>,
+    campaign: Option<Res<Campaign>>,
+    snapshot: Snapshot,
+    mut line_query: Query<&mut Text, With<SaveLine>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::S) {
+        return;
+    }
+    let result = if campaign.is_some() || snapshot.roster.remotes > 0 {
+        Err(locale.text("save.forbidden").to_string())
+    } else {
+        snapshot.take().store()
+    };
+    let message = match result {
+        Ok(()) => locale.text("save.saved").to_string(),
+        Err(e) => {
+            warn!("Can't save the match to {SAVE_PATH}: {e}");
+            locale.format("save.failed", &[("error", &e)])
+        }
+    };
+    for mut text in &mut line_query {
+        text.sections[0].value = message.clone();
+    }
+}
+
+// Bring a saved match back, as soon as its arena is laid out
+#[allow(clippy::type_complexity)]
+pub fn resume(
+    mut commands: Commands,
+    resume: Option<Res<Resume>>,
+    power_up_registry: Res<PowerUpRegistry>,
+    (mut tick, mut scoreboard, mut rng, mut rounds): (
+        ResMut<Tick>,
+        ResMut<Scoreboard>,
+        ResMut<MatchRng>,
+        ResMut<Rounds>,
+    ),
+    mut player_query: Query<(
+        Entity,
+        &PlayerIndex,
+        &mut Player,
+        &mut Transform,
+        &mut Facing,
+        &mut Velocity,
+        &mut Dash,
+        Option<&mut Crowns>,
+        Option<&mut Hold>,
+    )>,
+    leftover_query: Query<
+        Entity,
+        Or<(
+            With<Bomb>,
+            With<Fire>,
+            With<Brick>,
+            With<PowerUp>,
+            With<Exit>,
+        )>,
+    >,
+) {
+    let saved = match &resume {
+        Some(resume) if !player_query.is_empty() => &resume.0,
+        _ => return,
+    };
+
+    let mut players = Vec::new();
+    for (
+        entity,
+        index,
+        mut player,
+        mut transform,
+        mut facing,
+        mut velocity,
+        mut dash,
+        crowns,
+        hold,
+    ) in &mut player_query
+    {
+        let state = match saved.players.iter().find(|state| state.index == index.0) {
+            Some(state) => state,
+            None => {
+                // dead by the time the match was saved
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        *player = Player {
+            max_bombs: state.max_bombs,
+            active_bombs: state.active_bombs,
+            bomb_power: state.bomb_power,
+            detonator: state.detonator,
+            boosts: state.boosts.clone(),
+        };
+        transform.translation = state.position.extend(transform.translation.z);
+        facing.0 = state.facing;
+        velocity.0 = state.velocity;
+        *dash = state.dash;
+        if let (Some(mut crowns), Some(count)) = (crowns, state.crowns) {
+            crowns.0 = count;
+        }
+        if let (Some(mut hold), Some(steps)) = (hold, state.hold) {
+            hold.0 = steps;
+        }
+        players.push((index.0, entity));
+    }
+
+    for entity in &leftover_query {
+        commands.entity(entity).despawn();
+    }
+    let palette = saved.rules.theme().palette();
+    for position in &saved.bricks {
+        spawn_breakable(&mut commands, *position, palette.brick);
+    }
+    for position in &saved.pillars {
+        spawn_pillar(&mut commands, *position, palette.wall);
+    }
+    for state in &saved.bombs {
+        let mut timer = Timer::new(state.fuse, false);
+        timer.set_elapsed(state.elapsed);
+        let owner = state
+            .owner
+            .and_then(|owner| players.iter().find(|(index, _)| *index == owner))
+            .map(|(_, player)| *player);
+        spawn_bomb(
+            &mut commands,
+            Bomb {
+                player: owner,
+                timer,
+                power: state.power,
+            },
+            state.translation,
+        );
+    }
+    for state in &saved.fires {
+        let mut timer = Timer::from_seconds(1., false);
+        timer.set_elapsed(state.elapsed);
+        spawn_fire(&mut commands, Fire(timer), state.translation, state.scale);
+    }
+    for state in &saved.power_ups {
+        spawn_power_up(
+            &mut commands,
+            &power_up_registry,
+            state.kind,
+            state.translation,
+        );
+    }
+    if let Some(translation) = saved.exit {
+        spawn_exit(&mut commands, translation.truncate());
+    }
+
+    tick.0 = saved.tick;
+    scoreboard.score = saved.score;
+    rng.0 = saved.rng.clone();
+    rounds.played = saved.played;
+    rounds.wins = saved.wins.clone();
+    rounds.team_wins = saved.team_wins;
+    rounds.start = saved.start;
+    commands.remove_resource::<Resume>();
+}
@@ -0,0 +1,123 @@
+//! Players driven by scripts, instead of a keyboard or a gamepad.
+//!
+//! An [`InputScript`] lists what its player does, physics step after physics step, sending the
+//! same events keys would. Scripts can drive any player directly, as tests do, or be handed to
+//! local humans through the [`Scripts`] resource, as replays do: those humans then follow their
+//! script instead of their controls. Players stand still once their script is over.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input::{Controls, Pad},
+    Active, BombEvent, DashEvent, DetonateEvent, Direction, MoveEvent,
+};
+
+/// What a scripted player does for a while
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScriptStep {
+    /// Directions held
+    pub moves: Vec<Direction>,
+    /// Whether a bomb is placed, at the first physics step only
+    pub bomb: bool,
+    /// Whether bombs are set off
+    pub detonate: bool,
+    /// Whether the player dashes
+    pub dash: bool,
+    /// Physics steps this lasts for
+    pub steps: u32,
+}
+
+impl Default for ScriptStep {
+    fn default() -> Self {
+        ScriptStep {
+            moves: Vec::new(),
+            bomb: false,
+            detonate: false,
+            dash: false,
+            steps: 1,
+        }
+    }
+}
+
+/// What a player does, physics step after physics step
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputScript {
+    pub steps: Vec<ScriptStep>,
+    // index of the step being played, and physics steps it's been played for
+    #[serde(skip)]
+    current: usize,
+    #[serde(skip)]
+    elapsed: u32,
+}
+
+impl InputScript {
+    #[cfg(test)]
+    pub fn new(steps: Vec<ScriptStep>) -> Self {
+        InputScript { steps, ..default() }
+    }
+
+    /// What to do at this physics step, if the script isn't over, moving on to the next one
+    fn advance(&mut self) -> Option<(&ScriptStep, bool)> {
+        while self.elapsed >= self.steps.get(self.current)?.steps {
+            self.current += 1;
+            self.elapsed = 0;
+        }
+        let first = self.elapsed == 0;
+        self.elapsed += 1;
+        Some((&self.steps[self.current], first))
+    }
+}
+
+/// This resource holds the scripts local humans follow, by index, instead of their controls
+#[derive(Default)]
+pub struct Scripts(pub Vec<InputScript>);
+
+// Hand scripts over to the local humans they're meant for
+pub fn attach(
+    mut commands: Commands,
+    scripts: Res<Scripts>,
+    query: Query<(Entity, &Active), Added<Active>>,
+) {
+    for (entity, active) in &query {
+        if let Some(script) = scripts.0.get(active.0) {
+            commands
+                .entity(entity)
+                .remove::<Controls>()
+                .remove::<Pad>()
+                .insert(script.clone());
+        }
+    }
+}
+
+// Send the events of what scripted players do at this physics step
+pub fn drive(
+    mut move_writer: EventWriter<MoveEvent>,
+    mut bomb_writer: EventWriter<BombEvent>,
+    mut detonate_writer: EventWriter<DetonateEvent>,
+    mut dash_writer: EventWriter<DashEvent>,
+    mut query: Query<(Entity, &mut InputScript)>,
+) {
+    for (player, mut script) in &mut query {
+        let (step, first) = match script.advance() {
+            Some(step) => step,
+            None => continue,
+        };
+        for direction in &step.moves {
+            move_writer.send(MoveEvent {
+                direction: *direction,
+                player,
+            });
+        }
+        if step.bomb && first {
+            bomb_writer.send(BombEvent { player });
+        }
+        if step.detonate {
+            detonate_writer.send(DetonateEvent { player });
+        }
+        if step.dash {
+            dash_writer.send(DashEvent { player });
+        }
+    }
+}
@@ -0,0 +1,497 @@
+//! Custom game modes, scripted in [Rhai](https://rhai.rs) without rebuilding the game.
+//!
+//! A mode script is played on top of the mode picked in the [`Rules`], and is sent along with
+//! them to the other instances of online matches. It defines hooks, functions called as the
+//! match goes, all of them optional:
+//!
+//! ```rhai
+//! fn on_round_start(round) {}           // a round starts, counting from 0
+//! fn on_tick(step) {}                   // a physics step is over, 60 every second of the round
+//! fn on_brick_destroyed(col, row, by) {} // `by` is the player whose bomb it was, or -1
+//! fn on_player_killed(player, killer) {} // `killer` is -1 when nobody is to blame
+//! ```
+//!
+//! Players are numbered as in the arena, humans first. Hooks can't see the variables of the
+//! script: what they want to keep from one call to the next goes in `this`, a map shared by
+//! every hook throughout the match. They act on the match through these functions:
+//!
+//! - `players()`: every player still around, as maps of `index`, `col` and `row`
+//! - `arena()`: the playable cells, as a map of `first_col`, `last_col`, `first_row`, `last_row`
+//! - `random(n)`: a number from 0 up to `n` excluded, the same given the seed of the match
+//! - `award(player, points)`: points for the player, shown in its panel
+//! - `points(player)`: the points of the player
+//! - `win(player)`, `end_round()`: ends the round, with or without a winner
+//! - `spawn_brick(col, row)`, `spawn_power_up(col, row, kind)`: `kind` is the id of a power-up,
+//!   like `"bomb_up"`, see [`power_ups`](crate::power_ups)
+//! - `mark(col, row)`, `clear_marks()`: highlights cells, like a flag or a goal
+//! - `set_rule(name, value)`: changes a rule among `fuse`, `power_up_chance`, `rubble_chance`,
+//!   `time_limit`, `friendly_fire`, `solid_players`, `classic_movement` and `diagonals`
+//!
+//! Scripts are cut short after running for too long, and stopped for the rest of the match
+//! after their first error, which is logged. Rollback can't take back what hooks keep in `this`,
+//! so scripted modes are meant for local and hosted matches.
+
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST, INT};
+use serde::{Deserialize, Serialize};
+
+use std::sync::{Arc, Mutex, MutexGuard};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, path::Path};
+
+use crate::{
+    arena::playable, cell_position, power_ups::PowerUpRegistry, spawn_breakable, spawn_power_up,
+    Active, Bomb, BrickDestroyedEvent, DeathEvent, GameOverEvent, Match, MatchRng, PlayerIndex,
+    Rounds, Rules, Tick, BOTTOM_WALL, BRICK_SIZE, LEFT_WALL, MARK_Z, PICKUP_Z,
+};
+
+// operations a hook may run before being cut short, so that endless loops can't hang the game
+const MAX_OPERATIONS: u64 = 100_000;
+const MARK_COLOR: Color = Color::rgba(0.4, 0.8, 1.0, 0.4);
+
+/// A mode script, along with the name it's shown by
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModeScript {
+    pub name: String,
+    pub source: String,
+}
+
+impl ModeScript {
+    /// Reads a mode script from disk, checking that it compiles
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Engine::new().compile(&source).map_err(|e| e.to_string())?;
+        Ok(ModeScript {
+            name: path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned()),
+            source,
+        })
+    }
+}
+
+/// Points awarded to a player by the mode script
+#[derive(Component, Default)]
+pub struct Points(pub i64);
+
+/// Marks a cell highlighted by the mode script
+#[derive(Component)]
+pub struct Mark;
+
+/// What hooks asked for, done once they're over
+enum Request {
+    Award(usize, i64),
+    Win(usize),
+    EndRound,
+    SpawnBrick(usize, usize),
+    SpawnPowerUp(usize, usize, String),
+    Mark(usize, usize),
+    ClearMarks,
+    SetRule(String, Dynamic),
+}
+
+/// A player, as seen by hooks
+#[derive(Clone, Copy)]
+struct PlayerView {
+    index: usize,
+    col: INT,
+    row: INT,
+    points: i64,
+}
+
+/// What functions called by hooks share with the game
+struct Shared {
+    requests: Vec<Request>,
+    players: Vec<PlayerView>,
+    arena: Map,
+    rng: ChaCha8Rng,
+}
+
+/// A mode script ready to run
+pub struct Compiled {
+    script: ModeScript,
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    /// State kept by hooks from one call to the next
+    this: Dynamic,
+    shared: Arc<Mutex<Shared>>,
+    /// Step the last round hooks were told about started at
+    round_start: Option<u64>,
+    /// Whether the script failed already, and is left alone for the rest of the match
+    failed: bool,
+}
+
+impl Compiled {
+    fn new(script: &ModeScript) -> Result<Self, String> {
+        let shared = Arc::new(Mutex::new(Shared {
+            requests: Vec::new(),
+            players: Vec::new(),
+            arena: Map::new(),
+            rng: ChaCha8Rng::seed_from_u64(0),
+        }));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        register(&mut engine, &shared);
+        let ast = engine.compile(&script.source).map_err(|e| e.to_string())?;
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| e.to_string())?;
+        Ok(Compiled {
+            script: script.clone(),
+            engine,
+            ast,
+            scope,
+            this: Dynamic::from(Map::new()),
+            shared,
+            round_start: None,
+            failed: false,
+        })
+    }
+
+    /// Calls the given hook, if the script defines it, with the given arguments
+    fn call(&mut self, hook: &str, args: Vec<Dynamic>) {
+        if self.failed
+            || !self
+                .ast
+                .iter_functions()
+                .any(|function| function.name == hook && function.params.len() == args.len())
+        {
+            return;
+        }
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.this);
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut self.scope,
+            &self.ast,
+            hook,
+            args,
+        );
+        if let Err(e) = result {
+            warn!(
+                "Mode script {} failed in {hook}, stopping it: {e}",
+                self.script.name
+            );
+            self.failed = true;
+        }
+    }
+}
+
+/// Lock on what hooks share with the game, which stays usable even if a hook panicked
+fn lock(shared: &Mutex<Shared>) -> MutexGuard<'_, Shared> {
+    shared
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Makes the functions of the module documentation available to scripts
+fn register(engine: &mut Engine, shared: &Arc<Mutex<Shared>>) {
+    let state = shared.clone();
+    engine.register_fn("players", move || -> Array {
+        lock(&state)
+            .players
+            .iter()
+            .map(|player| {
+                let mut map = Map::new();
+                map.insert("index".into(), Dynamic::from(player.index as INT));
+                map.insert("col".into(), Dynamic::from(player.col));
+                map.insert("row".into(), Dynamic::from(player.row));
+                Dynamic::from(map)
+            })
+            .collect()
+    });
+    let state = shared.clone();
+    engine.register_fn("arena", move || -> Map { lock(&state).arena.clone() });
+    let state = shared.clone();
+    engine.register_fn("random", move |n: INT| -> INT {
+        lock(&state).rng.gen_range(0..n.max(1))
+    });
+    let state = shared.clone();
+    engine.register_fn("points", move |player: INT| -> INT {
+        lock(&state)
+            .players
+            .iter()
+            .find(|view| view.index as INT == player)
+            .map_or(0, |view| view.points)
+    });
+
+    let request = |shared: &Arc<Mutex<Shared>>| {
+        let state = shared.clone();
+        move |request: Request| lock(&state).requests.push(request)
+    };
+    let send = request(shared);
+    engine.register_fn("award", move |player: INT, points: INT| {
+        if let Ok(player) = usize::try_from(player) {
+            send(Request::Award(player, points));
+        }
+    });
+    let send = request(shared);
+    engine.register_fn("win", move |player: INT| {
+        if let Ok(player) = usize::try_from(player) {
+            send(Request::Win(player));
+        }
+    });
+    let send = request(shared);
+    engine.register_fn("end_round", move || send(Request::EndRound));
+    let send = request(shared);
+    engine.register_fn("spawn_brick", move |col: INT, row: INT| {
+        if let Some((col, row)) = cell(col, row) {
+            send(Request::SpawnBrick(col, row));
+        }
+    });
+    let send = request(shared);
+    // kinds are only known to the game, unknown ones are warned about there
+    engine.register_fn("spawn_power_up", move |col: INT, row: INT, kind: &str| {
+        if let Some((col, row)) = cell(col, row) {
+            send(Request::SpawnPowerUp(col, row, kind.to_string()));
+        }
+    });
+    let send = request(shared);
+    engine.register_fn("mark", move |col: INT, row: INT| {
+        if let Some((col, row)) = cell(col, row) {
+            send(Request::Mark(col, row));
+        }
+    });
+    let send = request(shared);
+    engine.register_fn("clear_marks", move || send(Request::ClearMarks));
+    let send = request(shared);
+    engine.register_fn(
+        "set_rule",
+        move |name: &str, value: Dynamic| -> Result<(), Box<EvalAltResult>> {
+            // checked right away, so that mistakes show up where they're made
+            apply_rule(&mut Rules::default(), name, &value)?;
+            send(Request::SetRule(name.to_string(), value));
+            Ok(())
+        },
+    );
+}
+
+/// The cell of the given coordinates, if they're not negative
+fn cell(col: INT, row: INT) -> Option<(usize, usize)> {
+    Some((usize::try_from(col).ok()?, usize::try_from(row).ok()?))
+}
+
+/// The column and row of the given position
+fn cell_of(position: Vec2) -> (INT, INT) {
+    let offset = (position - Vec2::new(LEFT_WALL, BOTTOM_WALL)) / BRICK_SIZE - 0.5;
+    (offset.x.round() as INT, offset.y.round() as INT)
+}
+
+/// Changes the named rule to the given value, or tells what's wrong
+fn apply_rule(rules: &mut Rules, name: &str, value: &Dynamic) -> Result<(), String> {
+    let float = || {
+        value
+            .as_float()
+            .or_else(|_| value.as_int().map(|value| value as f64))
+            .map_err(|_| format!("expected a number for {name}"))
+    };
+    let boolean = || {
+        value
+            .as_bool()
+            .map_err(|_| format!("expected true or false for {name}"))
+    };
+    match name {
+        "fuse" => rules.fuse = float()?.max(0.) as f32,
+        "power_up_chance" => rules.power_up_chance = float()?.clamp(0., 1.),
+        "rubble_chance" => rules.rubble_chance = float()?.clamp(0., 1.),
+        "time_limit" => {
+            let secs = float()?;
+            // nothing but positive limits limit anything
+            rules.time_limit = (secs > 0.).then_some(secs as u32);
+        }
+        "friendly_fire" => rules.friendly_fire = boolean()?,
+        "solid_players" => rules.solid_players = boolean()?,
+        "classic_movement" => rules.classic_movement = boolean()?,
+        "diagonals" => rules.diagonals = boolean()?,
+        _ => return Err(format!("unknown rule {name:?}")),
+    }
+    Ok(())
+}
+
+// Run the hooks of the mode script, and do what they ask for
+#[allow(clippy::too_many_arguments)]
+pub fn run_hooks(
+    mut commands: Commands,
+    mut rules: ResMut<Rules>,
+    power_up_registry: Res<PowerUpRegistry>,
+    tick: Res<Tick>,
+    rounds: Res<Rounds>,
+    mut rng: ResMut<MatchRng>,
+    mut death_reader: EventReader<DeathEvent>,
+    mut brick_reader: EventReader<BrickDestroyedEvent>,
+    mut game_over_writer: EventWriter<GameOverEvent>,
+    mut player_query: Query<(
+        &PlayerIndex,
+        &Transform,
+        Option<&Active>,
+        Option<&mut Points>,
+    )>,
+    bomb_query: Query<&Bomb>,
+    mark_query: Query<Entity, With<Mark>>,
+    mut compiled: Local<Option<Compiled>>,
+) {
+    // the script is compiled again whenever the rules pick another one
+    let script = match &rules.script {
+        Some(script) => script,
+        None => {
+            *compiled = None;
+            return;
+        }
+    };
+    if compiled.as_ref().map(|compiled| &compiled.script) != Some(script) {
+        *compiled = match Compiled::new(script) {
+            Ok(compiled) => Some(compiled),
+            Err(e) => {
+                warn!("Invalid mode script {}: {e}", script.name);
+                None
+            }
+        };
+    }
+    let compiled = match compiled.as_mut() {
+        Some(compiled) if !rounds.over => compiled,
+        _ => return,
+    };
+
+    {
+        let (cols, rows) = playable(rules.size);
+        let mut shared = lock(&compiled.shared);
+        shared.requests.clear();
+        // killed players and bombs are still around until the step is over
+        shared.players = player_query
+            .iter()
+            .map(|(index, transform, _, points)| {
+                let (col, row) = cell_of(transform.translation.truncate());
+                PlayerView {
+                    index: index.0,
+                    col,
+                    row,
+                    points: points.map_or(0, |points| points.0),
+                }
+            })
+            .collect();
+        shared.arena = [
+            ("first_col", cols.start),
+            ("last_col", cols.end - 1),
+            ("first_row", rows.start),
+            ("last_row", rows.end - 1),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.into(), Dynamic::from(value as INT)))
+        .collect();
+        shared.rng = ChaCha8Rng::seed_from_u64(rng.0.gen());
+    }
+
+    let index_of = |entity: Option<Entity>| {
+        entity
+            .and_then(|entity| player_query.get(entity).ok())
+            .map_or(-1, |(index, ..)| index.0 as INT)
+    };
+    if compiled.round_start != Some(rounds.start) {
+        compiled.round_start = Some(rounds.start);
+        compiled.call("on_round_start", vec![Dynamic::from(rounds.played as INT)]);
+    }
+    for BrickDestroyedEvent { position, bomb } in brick_reader.iter() {
+        let owner = bomb_query.get(*bomb).ok().and_then(|bomb| bomb.player);
+        let (col, row) = cell_of(*position);
+        compiled.call(
+            "on_brick_destroyed",
+            vec![
+                Dynamic::from(col),
+                Dynamic::from(row),
+                Dynamic::from(index_of(owner)),
+            ],
+        );
+    }
+    for DeathEvent { player, killer } in death_reader.iter() {
+        compiled.call(
+            "on_player_killed",
+            vec![
+                Dynamic::from(index_of(Some(*player))),
+                Dynamic::from(index_of(*killer)),
+            ],
+        );
+    }
+    compiled.call(
+        "on_tick",
+        vec![Dynamic::from((tick.0 - rounds.start) as INT)],
+    );
+
+    let requests = std::mem::take(&mut lock(&compiled.shared).requests);
+    for request in requests {
+        match request {
+            Request::Award(player, points) => {
+                let players = player_query
+                    .iter_mut()
+                    .filter(|(index, ..)| index.0 == player);
+                for (_, _, _, player_points) in players {
+                    if let Some(mut player_points) = player_points {
+                        player_points.0 += points;
+                    }
+                }
+            }
+            Request::Win(player) => {
+                let winner = player_query
+                    .iter()
+                    .find(|(index, ..)| index.0 == player)
+                    .and_then(|(_, _, active, _)| active.map(|active| active.0));
+                game_over_writer.send(GameOverEvent { winner, team: None });
+            }
+            Request::EndRound => game_over_writer.send(GameOverEvent {
+                winner: None,
+                team: None,
+            }),
+            Request::SpawnBrick(col, row) => {
+                spawn_breakable(
+                    &mut commands,
+                    cell_position(col, row),
+                    rules.theme().palette().brick,
+                );
+            }
+            Request::SpawnPowerUp(col, row, kind) => match power_up_registry.find(&kind) {
+                Some(power_up) => spawn_power_up(
+                    &mut commands,
+                    &power_up_registry,
+                    power_up,
+                    cell_position(col, row).extend(PICKUP_Z),
+                ),
+                None => warn!(
+                    "Mode script {} can't spawn unknown power-up {kind:?}",
+                    compiled.script.name
+                ),
+            },
+            Request::Mark(col, row) => {
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: MARK_COLOR,
+                            ..default()
+                        },
+                        transform: Transform {
+                            translation: cell_position(col, row).extend(MARK_Z),
+                            scale: BRICK_SIZE.extend(1.0),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .insert(Mark)
+                    .insert(Match);
+            }
+            Request::ClearMarks => {
+                for entity in &mark_query {
+                    commands.entity(entity).despawn();
+                }
+            }
+            Request::SetRule(name, value) => {
+                if let Err(e) = apply_rule(&mut rules, &name, &value) {
+                    warn!("Mode script {} can't set a rule: {e}", compiled.script.name);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Settings of the game itself, as opposed to the rules of a match.
+//!
+//! Settings are loaded at startup from [`SETTINGS_PATH`], before the window is even opened, and
+//! saved back there whenever they're changed from the options screen. The first run writes the
+//! default ones out, to be edited by hand. Settings missing from the file keep their default
+//! value, and so do the rules matches start from in the menu.
+
+use bevy::{prelude::*, window::PresentMode, window::WindowMode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accessibility::ColorVision, bot::MAX_HUMANS, display::TITLE, input::Bindings, locale,
+    net::COLORS, storage, Rules,
+};
+
+use std::io;
+
+/// Where settings are stored
+pub const SETTINGS_PATH: &str = "settings.toml";
+
+/// This resource holds the settings of the game
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Whether explosions and pickups throw particles around
+    pub particles: bool,
+    /// Whether explosions shake the screen
+    pub screen_shake: bool,
+    /// Whether flames, fuses and lights are kept from flashing, fading and burning down instead
+    pub reduced_flashing: bool,
+    /// Whether particles are thrown fewer and slower, drifting instead of bursting
+    pub gentle_particles: bool,
+    /// Whether the camera follows the first local human around arenas larger than the window
+    pub follow_camera: bool,
+    /// Whether the cells a bomb placed right now would blast are marked under local humans
+    pub blast_preview: bool,
+    /// Whether matches are played in the dark, lit by bombs and fires
+    pub night_mode: bool,
+    /// Index among the colors humans pick from of the one every local human plays with
+    pub colors: Vec<usize>,
+    /// Volume of everything heard, from zero to one
+    pub master_volume: f32,
+    /// Volume of the music, from zero to one, scaled by the master one
+    pub music_volume: f32,
+    /// Volume of sound effects, from zero to one, scaled by the master one
+    pub effects_volume: f32,
+    /// Whether nothing is heard at all, whatever the volumes
+    pub muted: bool,
+    /// Whether the announcer calls out what happens, on top of showing it
+    pub announcer_voice: bool,
+    /// Color vision deficiency the colors players are told apart by are adjusted for
+    pub color_vision: ColorVision,
+    /// Whether players, their bombs and fires are marked with patterns, on top of colors
+    pub patterns: bool,
+    /// Scale of texts, from three quarters of their size to twice as large
+    pub ui_scale: f32,
+    /// Code of the language texts are shown in, naming its file among the locales
+    pub language: String,
+    /// Folders of the content packs turned off, see [`mods`](crate::mods)
+    pub disabled_mods: Vec<String>,
+    // tables come last, or they can't be written out
+    /// How the window is opened
+    pub window: WindowSettings,
+    /// Controls of every human player
+    pub bindings: Bindings,
+    /// Rules matches start from in the menu
+    pub rules: Rules,
+}
+
+/// How the window is opened
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    /// Width of the window, in logical pixels
+    pub width: f32,
+    /// Height of the window, in logical pixels
+    pub height: f32,
+    /// Whether frames wait for the display to be ready for them
+    pub vsync: bool,
+    /// Whether the window takes the whole screen
+    pub fullscreen: bool,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            width: 1280.,
+            height: 720.,
+            vsync: true,
+            fullscreen: false,
+        }
+    }
+}
+
+impl WindowSettings {
+    /// The window described by these settings
+    pub fn descriptor(&self) -> WindowDescriptor {
+        WindowDescriptor {
+            title: TITLE.to_string(),
+            width: self.width,
+            height: self.height,
+            present_mode: self.present_mode(),
+            mode: self.mode(),
+            ..default()
+        }
+    }
+
+    /// How frames are shown, waiting for the display or not
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::Fifo
+        } else {
+            PresentMode::Immediate
+        }
+    }
+
+    /// Whether the window is windowed or takes the whole screen
+    pub fn mode(&self) -> WindowMode {
+        if self.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            particles: true,
+            screen_shake: true,
+            reduced_flashing: false,
+            gentle_particles: false,
+            follow_camera: true,
+            blast_preview: false,
+            night_mode: false,
+            colors: (0..MAX_HUMANS).collect(),
+            master_volume: 1.0,
+            music_volume: 0.5,
+            effects_volume: 1.0,
+            muted: false,
+            announcer_voice: true,
+            color_vision: ColorVision::default(),
+            patterns: false,
+            ui_scale: 1.0,
+            language: locale::ENGLISH.to_string(),
+            disabled_mods: Vec::new(),
+            window: WindowSettings::default(),
+            bindings: Bindings::default(),
+            rules: Rules::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings from disk, falling back to the default ones, which are written out
+    /// if there were none at all
+    pub fn load() -> Self {
+        let mut settings = match storage::read(SETTINGS_PATH) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                warn!("Invalid settings in {SETTINGS_PATH}: {e}");
+                Settings::default()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let settings = Settings::default();
+                settings.save();
+                settings
+            }
+            Err(e) => {
+                warn!("Can't read settings from {SETTINGS_PATH}: {e}");
+                Settings::default()
+            }
+        };
+        // bindings written for fewer players still leave everybody with some controls
+        settings.bindings.complete();
+        settings
+    }
+
+    /// Index among the colors humans pick from of the one the given local human plays with,
+    /// its default one unless a valid one was picked
+    pub fn color(&self, human: usize) -> usize {
+        self.colors
+            .get(human)
+            .copied()
+            .filter(|color| *color < COLORS.len())
+            .unwrap_or(human)
+    }
+
+    /// Picks the given color for the given local human
+    pub fn set_color(&mut self, human: usize, color: usize) {
+        if self.colors.len() <= human {
+            self.colors = (0..MAX_HUMANS).map(|human| self.color(human)).collect();
+        }
+        self.colors[human] = color;
+    }
+
+    /// Writes the settings to disk
+    pub fn save(&self) {
+        let result = toml::to_string(self)
+            .map_err(|e| e.to_string())
+            .and_then(|content| storage::write(SETTINGS_PATH, &content).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Can't save settings to {SETTINGS_PATH}: {e}");
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Music, and sound effects played as the match goes on and while going through menus.
+//!
+//! Sounds follow what's seen rather than the match itself: new bombs and blasts are heard once
+//! they show up, destroyed bricks, pickups and deaths as soon as they're announced.
+//! Blasts and pickups are heard from where they happen: quieter the farther they are from where
+//! the camera looks, and from the side they're on. Each of them comes as a pair of sounds, one
+//! for each ear, played together as loud as needed to pan them.
+//! The music plays on a loop from startup. Both are as loud as the settings say, and the music
+//! follows them as soon as they're changed.
+
+use bevy::{audio::AudioSink, prelude::*};
+
+use crate::{
+    camera::Shake, mods::Mods, settings::Settings, Bomb, BrickDestroyedEvent, DeathEvent, Fire,
+    PickUpEvent, BRICK_SIZE, RIGHT_WALL,
+};
+
+const MUSIC: &str = "sounds/Windless Slopes.ogg";
+const PLACE_SOUND: &str = "sounds/place.wav";
+const FUSE_SOUND: &str = "sounds/fuse.wav";
+const EXPLOSION_SOUNDS: [&str; 2] = ["sounds/explosion_left.wav", "sounds/explosion_right.wav"];
+const BRICK_SOUND: &str = "sounds/brick.wav";
+const PICKUP_SOUNDS: [&str; 2] = ["sounds/pickup_left.wav", "sounds/pickup_right.wav"];
+const DEATH_SOUND: &str = "sounds/death.wav";
+const MENU_SOUND: &str = "sounds/menu.wav";
+// keys moving through menus
+const MENU_KEYS: [KeyCode; 5] = [
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Return,
+];
+// distance from where the camera looks within which sounds are heard as loud as they get, and
+// beyond which they're heard as quiet as they get, in pixels
+const HEARING_NEAR: f32 = BRICK_SIZE.x * 4.;
+const HEARING_FAR: f32 = BRICK_SIZE.x * 16.;
+// how loud the farthest sounds are heard, out of the nearest ones
+const QUIETEST: f32 = 0.25;
+// distance from where the camera looks at which sounds are heard from one side only, in pixels
+const PAN_REACH: f32 = RIGHT_WALL;
+
+/// This resource holds the sound effects of the game
+pub struct Sounds {
+    place: Handle<AudioSource>,
+    fuse: Handle<AudioSource>,
+    explosion: Stereo,
+    brick: Handle<AudioSource>,
+    pickup: Stereo,
+    death: Handle<AudioSource>,
+    menu: Handle<AudioSource>,
+}
+
+/// A sound heard from somewhere, as heard by the left ear and by the right one
+struct Stereo([Handle<AudioSource>; 2]);
+
+impl Stereo {
+    fn load(asset_server: &AssetServer, mods: &Mods, paths: [&str; 2]) -> Self {
+        Stereo(paths.map(|path| asset_server.load(mods.asset(path).as_str())))
+    }
+}
+
+impl Sounds {
+    /// The sound effects of the game, as replaced by content packs
+    pub fn load(asset_server: &AssetServer, mods: &Mods) -> Self {
+        let load = |path: &str| asset_server.load(mods.asset(path).as_str());
+        Sounds {
+            place: load(PLACE_SOUND),
+            fuse: load(FUSE_SOUND),
+            explosion: Stereo::load(asset_server, mods, EXPLOSION_SOUNDS),
+            brick: load(BRICK_SOUND),
+            pickup: Stereo::load(asset_server, mods, PICKUP_SOUNDS),
+            death: load(DEATH_SOUND),
+            menu: load(MENU_SOUND),
+        }
+    }
+}
+
+/// This resource holds the music being played
+pub struct Music(Handle<AudioSink>);
+
+/// How loud something is heard at the given volume of its own, given the settings
+fn loudness(settings: &Settings, volume: f32) -> f32 {
+    if settings.muted {
+        0.
+    } else {
+        settings.master_volume * volume
+    }
+}
+
+/// Plays the given sound effect as loud as the settings say
+pub fn play_effect(audio: &Audio, settings: &Settings, sound: &Handle<AudioSource>) {
+    audio.play_with_settings(
+        sound.clone(),
+        PlaybackSettings::ONCE.with_volume(loudness(settings, settings.effects_volume)),
+    );
+}
+
+/// Plays the given sound effect as heard from `listener`, when it happens at `position`
+fn play_effect_at(
+    audio: &Audio,
+    settings: &Settings,
+    sound: &Stereo,
+    position: Vec2,
+    listener: Vec2,
+) {
+    let distance = position.distance(listener);
+    let nearness = 1. - ((distance - HEARING_NEAR) / (HEARING_FAR - HEARING_NEAR)).clamp(0., 1.);
+    let volume =
+        loudness(settings, settings.effects_volume) * (QUIETEST + (1. - QUIETEST) * nearness);
+    // from a quarter turn to the left to one to the right, as loud in the middle as unpanned sounds
+    let pan = ((position.x - listener.x) / PAN_REACH).clamp(-1., 1.);
+    let angle = (pan + 1.) * std::f32::consts::FRAC_PI_4;
+    let ears = [angle.cos(), angle.sin()].map(|ear| ear * std::f32::consts::SQRT_2);
+    for (sound, ear) in sound.0.iter().zip(ears) {
+        audio.play_with_settings(
+            sound.clone(),
+            PlaybackSettings::ONCE.with_volume(volume * ear),
+        );
+    }
+}
+
+// Load the sound effects, and start the music
+pub fn load(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    settings: Res<Settings>,
+    mods: Res<Mods>,
+) {
+    let music = audio.play_with_settings(
+        asset_server.load(mods.asset(MUSIC).as_str()),
+        PlaybackSettings::LOOP.with_volume(loudness(&settings, settings.music_volume)),
+    );
+    commands.insert_resource(Music(audio_sinks.get_handle(music)));
+    commands.insert_resource(Sounds::load(&asset_server, &mods));
+}
+
+// Keep the music as loud as the settings say
+pub fn tune(settings: Res<Settings>, music: Res<Music>, audio_sinks: Res<Assets<AudioSink>>) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(sink) = audio_sinks.get(&music.0) {
+        sink.set_volume(loudness(&settings, settings.music_volume));
+    }
+}
+
+// Play the sounds of the match
+#[allow(clippy::too_many_arguments)]
+pub fn play(
+    audio: Res<Audio>,
+    settings: Res<Settings>,
+    sounds: Res<Sounds>,
+    mut pick_up_reader: EventReader<PickUpEvent>,
+    mut death_reader: EventReader<DeathEvent>,
+    mut brick_reader: EventReader<BrickDestroyedEvent>,
+    camera_query: Query<&Transform, With<Shake>>,
+    bomb_query: Query<(), Added<Bomb>>,
+    fire_query: Query<&Transform, Added<Fire>>,
+) {
+    let listener = camera_query
+        .get_single()
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+    // the fuse hisses as long as bombs tick
+    for _ in &bomb_query {
+        play_effect(&audio, &settings, &sounds.place);
+        play_effect(&audio, &settings, &sounds.fuse);
+    }
+    // a single sound for many bricks destroyed at once
+    if brick_reader.iter().count() > 0 {
+        play_effect(&audio, &settings, &sounds.brick);
+    }
+    // and a single one for chains of blasts, from the nearest of them
+    let nearest = fire_query
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .min_by(|a, b| a.distance(listener).total_cmp(&b.distance(listener)));
+    if let Some(position) = nearest {
+        play_effect_at(&audio, &settings, &sounds.explosion, position, listener);
+    }
+    for PickUpEvent { position, .. } in pick_up_reader.iter() {
+        play_effect_at(&audio, &settings, &sounds.pickup, *position, listener);
+    }
+    for _ in death_reader.iter() {
+        play_effect(&audio, &settings, &sounds.death);
+    }
+}
+
+// Click through menus
+pub fn click(
+    audio: Res<Audio>,
+    settings: Res<Settings>,
+    sounds: Res<Sounds>,
+    keyboard_input: Res<Input<KeyCode>>,
+) {
+    if keyboard_input.any_just_pressed(MENU_KEYS) {
+        play_effect(&audio, &settings, &sounds.menu);
+    }
+}
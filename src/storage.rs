@@ -0,0 +1,51 @@
+//! Where what the game remembers between launches is kept.
+//!
+//! Native builds keep settings, records and progress in files next to the game, named by their
+//! path. Browsers can't write files, so WASM builds keep them in the local storage of the page
+//! instead, under the same names. Either way missing ones are reported as
+//! [`io::ErrorKind::NotFound`], so that callers can tell them apart from unreadable ones.
+
+use std::io;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+
+/// Reads what's stored under the given path
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(path: &str) -> io::Result<String> {
+    fs::read_to_string(path)
+}
+
+/// Stores the given content under the given path, replacing whatever was there
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(path: &str, content: &str) -> io::Result<()> {
+    fs::write(path, content)
+}
+
+/// Reads what's stored under the given path
+#[cfg(target_arch = "wasm32")]
+pub fn read(path: &str) -> io::Result<String> {
+    local_storage()?
+        .get_item(path)
+        .map_err(js_error)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("nothing stored in {path}")))
+}
+
+/// Stores the given content under the given path, replacing whatever was there
+#[cfg(target_arch = "wasm32")]
+pub fn write(path: &str, content: &str) -> io::Result<()> {
+    local_storage()?.set_item(path, content).map_err(js_error)
+}
+
+/// The local storage of the page, which may be turned off by the browser
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> io::Result<web_sys::Storage> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "no local storage"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn js_error(e: wasm_bindgen::JsValue) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("{e:?}"))
+}
@@ -0,0 +1,153 @@
+//! Survival mode, lasting as long as possible against endless waves of mobs.
+//!
+//! Every now and then a new wave of [`mob`](crate::mob)s shows up along the edges of the arena,
+//! each one larger and nastier than the previous, while bricks grow back on free cells.
+//! Humans score points for every second they survive, on top of those for popping mobs, and the
+//! match is over once they're all gone.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    arena::playable,
+    cell_position,
+    mob::{spawn_mob, Mob, MobKind},
+    spawn_breakable, Active, Bomb, Brick, Conveyor, Direction, MatchRng, Mode, Player, PowerUp,
+    Rounds, Rules, Scoreboard, Tick, Trampoline, Warp, BRICK_SIZE, TIME_STEP,
+};
+
+// seconds before the first wave, and between the following ones
+const FIRST_WAVE_SECS: f32 = 3.0;
+const WAVE_SECS: f32 = 20.0;
+// seconds between bricks growing back
+const REGROWTH_SECS: f32 = 8.0;
+// points scored for every second survived
+const SECOND_POINTS: usize = 10;
+const MAX_WAVE_MOBS: usize = 8;
+// mobs never show up this close to a human, nor bricks grow back, in cells
+const WAVE_DISTANCE: f32 = 4.;
+const REGROWTH_DISTANCE: f32 = 2.;
+
+/// This resource tracks the waves of a survival match
+#[derive(Default)]
+pub struct Waves {
+    /// Waves spawned so far
+    pub count: u32,
+    // physics step the round being played started at, to start over with the next one
+    start: u64,
+}
+
+/// Mobs making up the given wave, counting from one
+#[allow(clippy::too_many_arguments)]
+fn wave(number: u32) -> Vec<MobKind> {
+    let count = (number as usize + 1).min(MAX_WAVE_MOBS);
+    (0..count)
+        .map(|index| {
+            if number >= 5 && index % 3 == 0 {
+                MobKind::Hound
+            } else if number >= 3 && index % 2 == 0 {
+                MobKind::Ghost
+            } else {
+                MobKind::Balloon
+            }
+        })
+        .collect()
+}
+
+// Send waves of mobs, grow bricks back and score the seconds survived
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn survive(
+    mut commands: Commands,
+    rules: Res<Rules>,
+    tick: Res<Tick>,
+    rounds: Res<Rounds>,
+    mut rng: ResMut<MatchRng>,
+    mut waves: ResMut<Waves>,
+    mut scoreboard: ResMut<Scoreboard>,
+    occupied_query: Query<
+        &Transform,
+        Or<(
+            With<Brick>,
+            With<Bomb>,
+            With<Player>,
+            With<Mob>,
+            With<PowerUp>,
+            With<Conveyor>,
+            With<Warp>,
+            With<Trampoline>,
+        )>,
+    >,
+    human_query: Query<&Transform, With<Active>>,
+) {
+    if rules.mode != Mode::Survival || rounds.over {
+        return;
+    }
+    if waves.start != rounds.start {
+        *waves = Waves {
+            start: rounds.start,
+            ..default()
+        };
+    }
+    let rng = &mut rng.0;
+    let elapsed = tick.0 - rounds.start;
+    let every = |secs: f32| elapsed > 0 && elapsed.is_multiple_of((secs / TIME_STEP) as u64);
+
+    if every(1.0) {
+        scoreboard.score += SECOND_POINTS;
+    }
+
+    // free cells of the playable area, far enough from humans
+    let (cols, rows) = playable(rules.size);
+    let free = |distance: f32| {
+        let mut cells = Vec::new();
+        for row in rows.clone() {
+            for col in cols.clone() {
+                let cell = cell_position(col, row);
+                let taken = occupied_query
+                    .iter()
+                    .any(|other| other.translation.truncate().distance(cell) < BRICK_SIZE.x);
+                let close = human_query.iter().any(|human| {
+                    human.translation.truncate().distance(cell) < distance * BRICK_SIZE.x
+                });
+                if !taken && !close {
+                    cells.push((col, row));
+                }
+            }
+        }
+        cells
+    };
+
+    let first = (FIRST_WAVE_SECS / TIME_STEP) as u64;
+    if elapsed >= first && (elapsed - first).is_multiple_of((WAVE_SECS / TIME_STEP) as u64) {
+        waves.count += 1;
+        let mut cells = free(WAVE_DISTANCE)
+            .into_iter()
+            .filter(|(col, row)| {
+                *col == cols.start
+                    || *col == cols.end - 1
+                    || *row == rows.start
+                    || *row == rows.end - 1
+            })
+            .collect::<Vec<_>>();
+        for kind in wave(waves.count) {
+            if cells.is_empty() {
+                break;
+            }
+            let (col, row) = cells.swap_remove(rng.gen_range(0..cells.len()));
+            let direction = Direction::ALL[rng.gen_range(0..Direction::ALL.len())];
+            spawn_mob(&mut commands, kind, cell_position(col, row), direction);
+        }
+    }
+
+    if every(REGROWTH_SECS) {
+        let cells = free(REGROWTH_DISTANCE);
+        if !cells.is_empty() {
+            let (col, row) = cells[rng.gen_range(0..cells.len())];
+            spawn_breakable(
+                &mut commands,
+                cell_position(col, row),
+                rules.theme().palette().brick,
+            );
+        }
+    }
+}
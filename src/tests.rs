@@ -0,0 +1,901 @@
+//! Matches played step by step with no window, checking how blasts and pickups play out.
+
+use bevy::{
+    ecs::{
+        event::Events,
+        system::{CommandQueue, SystemState},
+    },
+    prelude::*,
+};
+
+use crate::{
+    accessibility::ColorVision,
+    achievements::{Achievement, AchievementEvent},
+    blast_cells,
+    bot::{BotRegistry, Roster},
+    bury, cell_position,
+    daily::{date, seed_of},
+    display::{self, step_ui_scale, MAX_UI_SCALE},
+    explode,
+    headless::{build_test_app, tally, Tally},
+    input::Bindings,
+    level::{Level, Levels},
+    locale::Locale,
+    mods::Mods,
+    net::COLORS,
+    power_ups::{Boost, Effect, PowerUpRegistry},
+    save::{resume, Snapshot},
+    script::{InputScript, ScriptStep},
+    scripting::{ModeScript, Points},
+    settings::Settings,
+    spawn_arena, spawn_bomb, spawn_breakable, spawn_player, spawn_power_up,
+    tutorial::{self, Tutorial},
+    Active, Bomb, BombEvent, Breakable, Brick, BrickDestroyedEvent, Direction, Fire, FirePool,
+    GameOverEvent, Match, MatchRng, Player, PlayerIndex, PowerUp, Rules, Scoreboard, Seed, BOMB_Z,
+    BOTTOM_WALL, FUSE_SECS, LEFT_WALL, MAX_POWER, OPPONENT_COLOR, RIGHT_WALL, TEAM_COLORS,
+    TEXT_COLOR, TIME_STEP, TOP_WALL,
+};
+
+/// Physics steps a bomb with a full fuse ticks for
+fn fuse_steps() -> usize {
+    (FUSE_SECS / TIME_STEP).round() as usize
+}
+
+/// A test app whose destroyed bricks never leave anything behind
+fn app() -> App {
+    let mut app = build_test_app();
+    app.insert_resource(Rules {
+        power_up_chance: 0.,
+        ..default()
+    });
+    app
+}
+
+/// Spawns entities through commands, the way systems do
+fn spawn<R>(app: &mut App, spawner: impl FnOnce(&mut Commands) -> R) -> R {
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, &app.world);
+    let spawned = spawner(&mut commands);
+    queue.apply(&mut app.world);
+    spawned
+}
+
+/// Places a bomb owned by nobody in the given cell, going off after the given physics steps
+fn spawn_ticking_bomb(
+    app: &mut App,
+    (col, row): (usize, usize),
+    power: u8,
+    steps: usize,
+) -> Entity {
+    spawn(app, |commands| {
+        spawn_bomb(
+            commands,
+            Bomb {
+                player: None,
+                timer: Timer::from_seconds(steps as f32 * TIME_STEP, false),
+                power,
+            },
+            cell_position(col, row).extend(BOMB_Z),
+        )
+    })
+}
+
+fn count<T: Component>(app: &mut App) -> usize {
+    app.world.query::<&T>().iter(&app.world).count()
+}
+
+#[test]
+fn bomb_goes_off_when_its_fuse_burns_out() {
+    let mut app = app();
+    let player = spawn(&mut app, |commands| {
+        spawn_player(commands, cell_position(0, 0), Color::WHITE).id()
+    });
+    app.world
+        .resource_mut::<Events<BombEvent>>()
+        .send(BombEvent { player });
+    app.update();
+    assert_eq!(count::<Bomb>(&mut app), 1);
+    assert_eq!(app.world.get::<Player>(player).unwrap().active_bombs, 1);
+
+    for _ in 1..fuse_steps() {
+        app.update();
+        assert_eq!(count::<Bomb>(&mut app), 1);
+    }
+    app.update();
+    assert_eq!(count::<Bomb>(&mut app), 0);
+    // one fire along each axis
+    assert_eq!(count::<Fire>(&mut app), 2);
+}
+
+#[test]
+fn blast_reaches_as_far_as_its_power() {
+    let mut app = app();
+    spawn(&mut app, |commands| {
+        spawn_breakable(commands, cell_position(3, 0), Color::WHITE);
+        spawn_breakable(commands, cell_position(4, 0), Color::WHITE);
+    });
+    spawn_ticking_bomb(&mut app, (2, 0), 1, 1);
+    app.update();
+
+    let standing = app
+        .world
+        .query_filtered::<&Transform, With<Breakable>>()
+        .iter(&app.world)
+        .map(|transform| transform.translation.truncate())
+        .collect::<Vec<_>>();
+    assert_eq!(standing, vec![cell_position(4, 0)]);
+}
+
+#[test]
+fn blasts_stop_at_the_walls() {
+    let corner = cell_position(0, 0);
+    let cells = blast_cells(corner, 3).collect::<Vec<_>>();
+    // only up and right of the corner, the other ways are walled off
+    assert_eq!(cells.len(), 7);
+    assert!(cells.iter().all(|cell| cell.x > LEFT_WALL
+        && cell.x < RIGHT_WALL
+        && cell.y > BOTTOM_WALL
+        && cell.y < TOP_WALL));
+}
+
+#[test]
+fn blasts_set_off_the_bombs_they_reach() {
+    let mut app = app();
+    spawn_ticking_bomb(&mut app, (0, 0), 2, 1);
+    let chained = spawn_ticking_bomb(&mut app, (2, 0), 1, fuse_steps());
+    let untouched = spawn_ticking_bomb(&mut app, (4, 0), 1, fuse_steps());
+
+    app.update();
+    assert_eq!(count::<Bomb>(&mut app), 2);
+    // the blast sets off the next bomb at the following step
+    app.update();
+    assert!(app.world.get::<Bomb>(chained).is_none());
+    assert!(app.world.get::<Bomb>(untouched).is_some());
+}
+
+#[test]
+fn power_ups_are_picked_up_by_players_touching_them() {
+    let mut app = app();
+    let registry = PowerUpRegistry::default();
+    let (fire_up, bomb_up) = (registry.find("fire_up"), registry.find("bomb_up"));
+    let player = spawn(&mut app, |commands| {
+        let position = |col| cell_position(col, 0).extend(0.);
+        spawn_power_up(commands, &registry, fire_up.unwrap(), position(1));
+        spawn_power_up(commands, &registry, bomb_up.unwrap(), position(3));
+        spawn_player(commands, cell_position(1, 0), Color::WHITE).id()
+    });
+    app.update();
+
+    let stats = app.world.get::<Player>(player).unwrap();
+    assert_eq!((stats.bomb_power, stats.max_bombs), (2, 1));
+    assert_eq!(count::<PowerUp>(&mut app), 1);
+}
+
+#[test]
+fn timed_power_ups_wear_off() {
+    let mut app = app();
+    let player = spawn(&mut app, |commands| {
+        spawn_player(commands, cell_position(1, 0), Color::WHITE).id()
+    });
+    {
+        let mut stats = app.world.get_mut::<Player>(player).unwrap();
+        stats.bomb_power += 1;
+        stats.boosts.push(Boost {
+            effect: Effect::Power(1),
+            steps_left: 2,
+        });
+    }
+
+    app.update();
+    assert_eq!(app.world.get::<Player>(player).unwrap().bomb_power, 2);
+    app.update();
+    let stats = app.world.get::<Player>(player).unwrap();
+    assert_eq!(stats.bomb_power, 1);
+    assert!(stats.boosts.is_empty());
+}
+
+#[test]
+fn scripted_players_follow_their_script() {
+    let mut app = app();
+    let start = cell_position(0, 0);
+    let player = spawn(&mut app, |commands| {
+        spawn_player(commands, start, Color::WHITE)
+            .insert(InputScript::new(vec![
+                ScriptStep {
+                    moves: vec![Direction::Right],
+                    steps: 10,
+                    ..default()
+                },
+                ScriptStep {
+                    bomb: true,
+                    steps: 5,
+                    ..default()
+                },
+            ]))
+            .id()
+    });
+    for _ in 0..15 {
+        app.update();
+    }
+
+    let position = app.world.get::<Transform>(player).unwrap().translation;
+    assert!(position.x > start.x);
+    assert_eq!(position.y, start.y);
+    // held for a few steps, the bomb is still placed only once
+    assert_eq!(count::<Bomb>(&mut app), 1);
+}
+
+#[test]
+fn burnt_out_fires_are_lit_again() {
+    let mut app = app();
+    spawn_ticking_bomb(&mut app, (0, 0), 1, 1);
+    app.update();
+    let lit = app
+        .world
+        .query_filtered::<Entity, With<Fire>>()
+        .iter(&app.world)
+        .collect::<Vec<_>>();
+    assert_eq!(lit.len(), 2);
+
+    // fires burn for a second
+    for _ in 0..fuse_steps() {
+        app.update();
+    }
+    assert_eq!(count::<Fire>(&mut app), 0);
+    assert_eq!(app.world.resource::<FirePool>().0.len(), 2);
+
+    spawn_ticking_bomb(&mut app, (4, 0), 1, 1);
+    app.update();
+    let relit = app
+        .world
+        .query_filtered::<Entity, With<Fire>>()
+        .iter(&app.world)
+        .collect::<Vec<_>>();
+    assert!(relit.iter().all(|fire| lit.contains(fire)));
+    assert!(app.world.resource::<FirePool>().0.is_empty());
+}
+
+#[test]
+fn bricks_reached_by_several_blasts_are_destroyed_once() {
+    let mut app = app();
+    spawn(&mut app, |commands| {
+        spawn_breakable(commands, cell_position(1, 0), Color::WHITE);
+    });
+    spawn_ticking_bomb(&mut app, (0, 0), 1, 1);
+    spawn_ticking_bomb(&mut app, (2, 0), 1, 1);
+    app.update();
+
+    assert_eq!(count::<Breakable>(&mut app), 0);
+    assert_eq!(app.world.resource::<Scoreboard>().score, 1);
+}
+
+#[test]
+fn destroyed_bricks_are_announced_where_they_stood() {
+    let mut app = app();
+    spawn(&mut app, |commands| {
+        spawn_breakable(commands, cell_position(1, 0), Color::WHITE);
+        spawn_breakable(commands, cell_position(0, 1), Color::WHITE);
+    });
+    spawn_ticking_bomb(&mut app, (0, 0), 1, 1);
+    app.update();
+
+    let events = app.world.resource::<Events<BrickDestroyedEvent>>();
+    let mut positions = events
+        .iter_current_update_events()
+        .map(|event| event.position)
+        .collect::<Vec<_>>();
+    positions.sort_by(|a, b| a.x.total_cmp(&b.x));
+    assert_eq!(positions, vec![cell_position(0, 1), cell_position(1, 0)]);
+}
+
+#[test]
+fn chains_of_bombs_set_off_by_humans_unlock_an_achievement() {
+    let mut app = app();
+    let player = spawn(&mut app, |commands| {
+        spawn_player(commands, cell_position(0, 4), Color::WHITE)
+            .insert(Active(0))
+            .id()
+    });
+    spawn(&mut app, |commands| {
+        spawn_bomb(
+            commands,
+            Bomb {
+                player: Some(player),
+                timer: Timer::from_seconds(TIME_STEP, false),
+                power: 2,
+            },
+            cell_position(0, 0).extend(BOMB_Z),
+        )
+    });
+    for col in [2, 4, 6, 8] {
+        spawn_ticking_bomb(&mut app, (col, 0), 2, fuse_steps());
+    }
+    // every link of the chain goes off at the step after the previous one
+    for _ in 0..5 {
+        app.update();
+    }
+
+    assert_eq!(count::<Bomb>(&mut app), 0);
+    let events = app.world.resource::<Events<AchievementEvent>>();
+    assert!(events
+        .get_reader()
+        .iter(events)
+        .any(|AchievementEvent(achievement)| *achievement == Achievement::ChainReaction));
+}
+
+#[test]
+fn daily_challenges_are_seeded_by_their_date() {
+    assert_eq!(date(0), (1970, 1, 1));
+    assert_eq!(date(11_016), (2000, 2, 29));
+    assert_eq!(date(20_742), (2026, 10, 16));
+    assert_eq!(seed_of(date(20_742)), 20_261_016);
+}
+
+#[test]
+fn tutorial_lessons_are_over_once_done() {
+    let mut app = app();
+    let tutorial = Tutorial::start(
+        &mut Rules::default(),
+        &mut Roster::default(),
+        &mut Seed::default(),
+    );
+    app.insert_resource(tutorial).add_system(tutorial::advance);
+    spawn(&mut app, |commands| {
+        spawn_player(commands, cell_position(0, 0), Color::WHITE)
+            .insert(Active(0))
+            .insert(InputScript::new(vec![
+                ScriptStep {
+                    steps: 5,
+                    ..default()
+                },
+                ScriptStep {
+                    moves: vec![Direction::Right],
+                    steps: 5,
+                    ..default()
+                },
+                ScriptStep {
+                    bomb: true,
+                    ..default()
+                },
+            ]));
+    });
+
+    // standing still teaches nothing
+    for _ in 0..5 {
+        app.update();
+    }
+    assert_eq!(app.world.resource::<Tutorial>().lesson, 0);
+    // the bomb is only seen once placed, at the step after it's asked for
+    for _ in 0..7 {
+        app.update();
+    }
+    assert_eq!(app.world.resource::<Tutorial>().lesson, 2);
+}
+
+#[test]
+fn locales_fall_back_to_english_for_what_they_leave_out() {
+    let french = Locale::load("fr");
+    assert_eq!(french.text("menu.humans"), "Humains : ");
+    assert_eq!(
+        french.format("announcer.wins", &[("player", &2)]),
+        "Le joueur 2 gagne !"
+    );
+    // unknown languages speak English, and unknown keys are shown as they are
+    let unknown = Locale::load("xx");
+    assert_eq!(unknown.text("menu.humans"), "Humans: ");
+    assert_eq!(unknown.font(), "fonts/FiraSans-Bold.ttf");
+    assert_eq!(unknown.text("menu.nothing"), "menu.nothing");
+}
+
+#[test]
+fn colorblind_palettes_keep_players_apart() {
+    for vision in ColorVision::ALL {
+        // humans and bots play against each other, teams only against the other team
+        let colors = COLORS
+            .iter()
+            .chain([&OPPONENT_COLOR])
+            .map(|color| vision.adjust(*color))
+            .collect::<Vec<_>>();
+        for (index, color) in colors.iter().enumerate() {
+            assert!(
+                !colors[index + 1..].contains(color),
+                "{vision:?} mixes up players"
+            );
+        }
+        assert_ne!(
+            vision.adjust(TEAM_COLORS[0]),
+            vision.adjust(TEAM_COLORS[1]),
+            "{vision:?} mixes up teams"
+        );
+        // colors players aren't told apart by are left alone
+        assert_eq!(vision.adjust(TEXT_COLOR), TEXT_COLOR);
+    }
+    assert_eq!(ColorVision::Normal.adjust(COLORS[0]), COLORS[0]);
+    assert_ne!(ColorVision::RedGreen.adjust(COLORS[1]), COLORS[1]);
+}
+
+#[test]
+fn texts_follow_the_interface_scale() {
+    let mut app = App::new();
+    app.insert_resource(Settings {
+        ui_scale: 1.5,
+        ..default()
+    })
+    .add_system(display::scale_text);
+    let text = app
+        .world
+        .spawn()
+        .insert(Text::from_section(
+            "",
+            TextStyle {
+                font_size: 20.,
+                ..default()
+            },
+        ))
+        .id();
+    let font_size = |app: &App| {
+        app.world.get::<Text>(text).unwrap().sections[0]
+            .style
+            .font_size
+    };
+
+    // scaled once as it shows up, however many frames go by
+    app.update();
+    app.update();
+    assert_eq!(font_size(&app), 30.);
+    // and again from its own size whenever the scale changes
+    app.world.resource_mut::<Settings>().ui_scale = 0.75;
+    app.update();
+    assert_eq!(font_size(&app), 15.);
+
+    assert_eq!(step_ui_scale(MAX_UI_SCALE, true), MAX_UI_SCALE);
+    assert_eq!(step_ui_scale(1.0, false), 0.75);
+}
+
+#[test]
+fn headless_statistics_credit_bomb_owners() {
+    let mut app = app();
+    app.init_resource::<Tally>()
+        .add_system(tally.after(explode).before(bury));
+    let player = spawn(&mut app, |commands| {
+        spawn_breakable(commands, cell_position(1, 0), Color::WHITE);
+        spawn_player(commands, cell_position(0, 1), Color::WHITE).insert(Name::new("victim"));
+        spawn_player(commands, cell_position(0, 0), Color::WHITE)
+            .insert(Name::new("bomber"))
+            .id()
+    });
+    app.world
+        .resource_mut::<Events<BombEvent>>()
+        .send(BombEvent { player });
+    for _ in 0..=fuse_steps() {
+        app.update();
+    }
+
+    let tally = app.world.resource::<Tally>();
+    assert_eq!(tally.bricks.get("bomber"), Some(&1));
+    // the bomber blew itself up too, which doesn't count
+    assert_eq!(tally.kills.get("bomber"), Some(&1));
+    assert_eq!(tally.kills.get("victim"), None);
+}
+
+#[test]
+#[cfg(not(target_arch = "wasm32"))]
+fn external_bots_are_named_and_answer_with_actions() {
+    use crate::{
+        bot::BotAction,
+        external::{is_external, parse_answer},
+    };
+
+    assert!(is_external("exec:python3 bot.py"));
+    assert!(is_external("ws:9001"));
+    assert!(!is_external("exec: "));
+    assert!(!is_external("ws:port"));
+    assert!(!is_external("brain"));
+
+    assert_eq!(
+        parse_answer(r#"{"action":"bomb"}"#).unwrap(),
+        BotAction::Bomb
+    );
+    assert_eq!(
+        parse_answer(r#"{"action":"left"}"#).unwrap(),
+        BotAction::Move(Direction::Left)
+    );
+    assert_eq!(
+        parse_answer(r#"{"action":"idle"}"#).unwrap(),
+        BotAction::Idle
+    );
+    assert!(parse_answer(r#"{"action":"jump"}"#).is_err());
+    assert!(parse_answer("left").is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn external_bots_never_hold_up_the_step() {
+    use crate::{
+        bot::{BotAction, PlayerInfo, Snapshot},
+        external::connect,
+    };
+    use std::time::{Duration, Instant};
+
+    let snapshot = Snapshot {
+        me: PlayerInfo {
+            entity: Entity::from_raw(0),
+            position: Vec2::ZERO,
+            size: Vec2::splat(30.),
+            human: false,
+            bombs_left: 1,
+            bomb_power: 1,
+        },
+        others: Vec::new(),
+        bombs: Vec::new(),
+        bricks: Vec::new(),
+        power_ups: Vec::new(),
+    };
+    let mut rng = MatchRng::seeded(0);
+
+    // a bot that never answers stands still, without the step waiting for it
+    let mut silent = connect("exec:sleep 10").unwrap();
+    let start = Instant::now();
+    for _ in 0..10 {
+        assert_eq!(silent.decide(&snapshot, &mut rng.0), BotAction::Idle);
+    }
+    assert!(start.elapsed() < Duration::from_millis(100));
+
+    // answers are taken at the steps after they arrive
+    let mut eager = connect(r#"exec:yes {"action":"left"}"#).unwrap();
+    let start = Instant::now();
+    let mut action = BotAction::Idle;
+    while action == BotAction::Idle && start.elapsed() < Duration::from_secs(5) {
+        action = eager.decide(&snapshot, &mut rng.0);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(action, BotAction::Move(Direction::Left));
+}
+
+#[test]
+fn mode_scripts_award_points_and_end_rounds() {
+    let mut app = app();
+    app.world.resource_mut::<Rules>().script = Some(ModeScript {
+        name: "test".to_string(),
+        source: r#"
+            fn on_tick(step) {
+                award(0, 2);
+                if points(0) >= 4 {
+                    win(0);
+                }
+            }
+        "#
+        .to_string(),
+    });
+    let player = spawn(&mut app, |commands| {
+        spawn_player(commands, cell_position(0, 0), Color::WHITE)
+            .insert(PlayerIndex(0))
+            .insert(Active(0))
+            .insert(Points::default())
+            .id()
+    });
+
+    app.update();
+    app.update();
+    assert_eq!(app.world.get::<Points>(player).unwrap().0, 4);
+    assert!(app.world.resource::<Events<GameOverEvent>>().is_empty());
+    app.update();
+    let mut reader = app.world.resource::<Events<GameOverEvent>>().get_reader();
+    let events = app.world.resource::<Events<GameOverEvent>>();
+    assert_eq!(
+        reader.iter(events).next().and_then(|event| event.winner),
+        Some(0)
+    );
+}
+
+#[test]
+#[cfg(not(target_arch = "wasm32"))]
+fn content_packs_replace_assets_by_priority() {
+    let dir = std::env::temp_dir().join(format!("bomberman-mods-{}", std::process::id()));
+    let write = |path: &str, content: &str| {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    };
+    write("low/sounds/brick.wav", "");
+    write(
+        "low/levels/pack.toml",
+        &toml::to_string(&Level::blank("Pack level")).unwrap(),
+    );
+    write("high/pack.toml", "name = \"High\"\npriority = 5");
+    write("high/sounds/brick.wav", "");
+
+    let mods = Mods::scan_dir(&dir, &[]);
+    let names = mods.packs.iter().map(|pack| pack.name.as_str());
+    assert_eq!(names.collect::<Vec<_>>(), ["High", "low"]);
+    assert_eq!(
+        mods.file("sounds/brick.wav"),
+        Some(dir.join("high/sounds/brick.wav"))
+    );
+    assert_eq!(mods.file("sounds/death.wav"), None);
+    assert_eq!(mods.asset("sounds/death.wav"), "sounds/death.wav");
+    let levels = Levels::load(&mods);
+    assert!(levels.0.iter().any(|level| level.name == "Pack level"));
+
+    let mods = Mods::scan_dir(&dir, &["high".to_string()]);
+    assert_eq!(mods.disabled(), ["high"]);
+    assert_eq!(
+        mods.file("sounds/brick.wav"),
+        Some(dir.join("low/sounds/brick.wav"))
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn resumed_matches_keep_their_bricks() {
+    let mut app = app();
+    app.insert_resource(Roster::default());
+    // lays the arena out anew, the way starting a round does
+    let lay_out = |app: &mut App, seed: u64| {
+        let leftovers = app
+            .world
+            .query_filtered::<Entity, With<Match>>()
+            .iter(&app.world)
+            .collect::<Vec<_>>();
+        for entity in leftovers {
+            app.world.despawn(entity);
+        }
+        let mut rng = MatchRng::seeded(seed);
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &app.world);
+        spawn_arena(
+            &mut commands,
+            app.world.resource::<BotRegistry>(),
+            app.world.resource::<Roster>(),
+            app.world.resource::<Bindings>(),
+            app.world.resource::<Rules>(),
+            &mut rng,
+        );
+        queue.apply(&mut app.world);
+        app.insert_resource(rng);
+    };
+    let bricks = |app: &mut App| {
+        let mut bricks = app
+            .world
+            .query_filtered::<(&Transform, Option<&Breakable>), With<Brick>>()
+            .iter(&app.world)
+            .map(|(transform, breakable)| {
+                let position = transform.translation.round();
+                (position.x as i32, position.y as i32, breakable.is_some())
+            })
+            .collect::<Vec<_>>();
+        bricks.sort_unstable();
+        bricks
+    };
+
+    lay_out(&mut app, 1);
+    let before = bricks(&mut app);
+    let saved = SystemState::<Snapshot>::new(&mut app.world)
+        .get(&app.world)
+        .take();
+    // the generator doesn't turn the same pillars into bricks again
+    lay_out(&mut app, 2);
+    assert_ne!(bricks(&mut app), before);
+
+    let (mut rules, mut roster) = (Rules::default(), Roster::default());
+    spawn(&mut app, |commands| {
+        saved.prepare(commands, &mut rules, &mut roster)
+    });
+    let mut resume = IntoSystem::into_system(resume);
+    resume.initialize(&mut app.world);
+    resume.run((), &mut app.world);
+    resume.apply_buffers(&mut app.world);
+    assert_eq!(bricks(&mut app), before);
+}
+
+#[test]
+fn levels_leave_their_rules_behind() {
+    let overriding = toml::from_str::<Level>(&format!(
+        "name = \"Overriding\"\nmap = {:?}\n[rules]\nrounds = 5\nmutators = [\"no_pickups\"]",
+        Level::blank("").map
+    ))
+    .unwrap();
+    let levels = Levels(vec![overriding, Level::blank("Plain")]);
+    let mut rules = Rules::default();
+
+    levels.cycle(&mut rules, true);
+    assert_eq!(rules.rounds, 5);
+    assert_eq!(rules.mutators.len(), 1);
+    levels.cycle(&mut rules, true);
+    assert_eq!(
+        rules.level.as_ref().map(|level| level.name.as_str()),
+        Some("Plain")
+    );
+    assert_eq!(rules.rounds, Rules::default().rounds);
+    assert!(rules.mutators.is_empty());
+
+    levels.cycle(&mut rules, false);
+    levels.cycle(&mut rules, false);
+    assert!(rules.level.is_none());
+    assert_eq!(rules.rounds, Rules::default().rounds);
+    assert!(rules.mutators.is_empty());
+}
+
+#[test]
+#[cfg(not(target_arch = "wasm32"))]
+fn timed_power_ups_picked_up_at_the_cap_take_nothing_back() {
+    let dir = std::env::temp_dir().join(format!("bomberman-boosts-{}", std::process::id()));
+    let path = dir.join("timed/data/power_ups.ron");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(
+        path,
+        r#"[(id: "fire_up", color: (1.0, 0.6, 0.0), rarity: 1.0, effect: Power(1), duration: Some(0.05))]"#,
+    )
+    .unwrap();
+    let registry = PowerUpRegistry::load(&Mods::scan_dir(&dir, &[]));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let mut app = app();
+    let player = spawn(&mut app, |commands| {
+        spawn_player(commands, cell_position(1, 0), Color::WHITE).id()
+    });
+    {
+        let mut stats = app.world.get_mut::<Player>(player).unwrap();
+        stats.bomb_power = MAX_POWER;
+        registry.apply(registry.find("fire_up").unwrap(), &mut stats);
+        assert_eq!(stats.bomb_power, MAX_POWER);
+    }
+    for _ in 0..3 {
+        app.update();
+    }
+    let stats = app.world.get::<Player>(player).unwrap();
+    assert!(stats.boosts.is_empty());
+    assert_eq!(stats.bomb_power, MAX_POWER);
+}
+
+#[test]
+#[cfg(not(target_arch = "wasm32"))]
+fn hot_reload_notices_changes_and_keeps_the_command_line() {
+    use crate::{
+        cli::Overrides,
+        mutator::Mutator,
+        reload::{merge, Stamps},
+    };
+    use std::{
+        fs::{self, File},
+        time::{Duration, SystemTime},
+    };
+
+    let dir = std::env::temp_dir().join(format!("bomberman-reload-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let first = dir.join("first.toml");
+    let second = dir.join("second.toml");
+    fs::write(&first, "").unwrap();
+    let files = || [first.clone(), second.clone()].into_iter();
+    let mut stamps = Stamps::default();
+    // the first look only takes note
+    assert!(!stamps.update(files()));
+    assert!(!stamps.update(files()));
+    File::options()
+        .write(true)
+        .open(&first)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(60))
+        .unwrap();
+    assert!(stamps.update(files()));
+    assert!(!stamps.update(files()));
+    fs::write(&second, "").unwrap();
+    assert!(stamps.update(files()));
+    fs::remove_file(&first).unwrap();
+    assert!(stamps.update(files()));
+    fs::remove_dir_all(&dir).unwrap();
+
+    let overrides = Overrides {
+        cols: Some(5),
+        rows: None,
+        mutators: vec![Mutator::NoPickups],
+    };
+    let mut rules = Rules::default();
+    Level::blank("Arena").apply(&mut rules);
+    overrides.apply(&mut rules);
+    let reloaded = Rules {
+        rounds: 7,
+        size: (9, 9),
+        mutators: vec![Mutator::GiantBlasts],
+        ..default()
+    };
+    merge(&mut rules, reloaded, &overrides);
+    assert_eq!(rules.rounds, 7);
+    assert_eq!(rules.size, (5, 9));
+    assert_eq!(rules.mutators, [Mutator::NoPickups]);
+    assert_eq!(rules.level.unwrap().name, "Arena");
+}
+
+#[test]
+fn grid_forgets_bricks_as_they_go() {
+    use crate::grid::{self, Grid};
+
+    let mut app = app();
+    spawn(&mut app, |commands| {
+        spawn_breakable(commands, cell_position(3, 0), Color::WHITE);
+        spawn_breakable(commands, cell_position(4, 0), Color::WHITE);
+        spawn_breakable(commands, cell_position(5, 2), Color::WHITE);
+    });
+    app.update();
+
+    let cell = |col, row| grid::cell(cell_position(col, row).extend(0.));
+    let in_cell = |app: &App, col, row| {
+        let grid = app.world.resource::<Grid>();
+        grid.bricks.in_cells([cell(col, row)]).collect::<Vec<_>>()
+    };
+    let around = |app: &App, col, row| {
+        let grid = app.world.resource::<Grid>();
+        grid.bricks.around(cell(col, row)).collect::<Vec<_>>()
+    };
+    let (first, second) = match (&in_cell(&app, 3, 0)[..], &in_cell(&app, 4, 0)[..]) {
+        ([first], [second]) => (*first, *second),
+        other => panic!("bricks indexed as {other:?}"),
+    };
+    assert_eq!(in_cell(&app, 5, 2).len(), 1);
+    assert_eq!(around(&app, 4, 0), [first, second]);
+
+    app.world.despawn(second);
+    app.update();
+    assert!(in_cell(&app, 4, 0).is_empty());
+    assert_eq!(around(&app, 4, 0), [first]);
+
+    // bricks blown up leave the grid as soon as they crumble, before they're gone
+    spawn_ticking_bomb(&mut app, (2, 0), 1, 1);
+    app.update();
+    app.update();
+    assert!(app.world.get::<Brick>(first).is_none());
+    assert!(in_cell(&app, 3, 0).is_empty());
+    assert!(around(&app, 3, 0).is_empty());
+    assert_eq!(in_cell(&app, 5, 2).len(), 1);
+
+    spawn(&mut app, |commands| {
+        spawn_breakable(commands, cell_position(4, 0), Color::WHITE);
+    });
+    app.update();
+    assert_eq!(in_cell(&app, 4, 0).len(), 1);
+}
+
+#[test]
+#[cfg(not(target_arch = "wasm32"))]
+fn timed_detonators_leave_lasting_ones_alone() {
+    let dir = std::env::temp_dir().join(format!("bomberman-detonators-{}", std::process::id()));
+    let path = dir.join("timed/data/power_ups.ron");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(
+        path,
+        r#"[
+            (id: "detonator", color: (1.0, 0.0, 0.0), rarity: 1.0, effect: Detonator),
+            (id: "timed", color: (1.0, 0.0, 0.0), rarity: 1.0, effect: Detonator, duration: Some(0.05)),
+        ]"#,
+    )
+    .unwrap();
+    let registry = PowerUpRegistry::load(&Mods::scan_dir(&dir, &[]));
+    std::fs::remove_dir_all(&dir).unwrap();
+    let lasting = registry.find("detonator").unwrap();
+    let timed = registry.find("timed").unwrap();
+
+    let mut app = app();
+    let orders = [vec![lasting, timed], vec![timed, lasting], vec![timed]];
+    let players = orders
+        .into_iter()
+        .enumerate()
+        .map(|(index, order)| {
+            let player = spawn(&mut app, |commands| {
+                spawn_player(commands, cell_position(index * 2, 0), Color::WHITE).id()
+            });
+            let mut stats = app.world.get_mut::<Player>(player).unwrap();
+            for power_up in order {
+                registry.apply(power_up, &mut stats);
+            }
+            player
+        })
+        .collect::<Vec<_>>();
+    for _ in 0..5 {
+        app.update();
+    }
+    // only the detonator that was never given for good wore off
+    let detonators = players
+        .iter()
+        .map(|player| app.world.get::<Player>(*player).unwrap().detonator);
+    assert_eq!(detonators.collect::<Vec<_>>(), [true, true, false]);
+}
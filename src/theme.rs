@@ -0,0 +1,170 @@
+//! Colors arenas are drawn with.
+//!
+//! Every level picks one of the themes by name, like `theme = "ice"`, while random arenas are
+//! drawn with the classic one. Content packs may repaint them, see [`mods`](crate::mods).
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::sync::RwLock;
+
+// how much darker every other tile of the floor is
+const FLOOR_SHADE: f32 = 0.92;
+
+// themes as repainted by content packs, looked up from everywhere arenas are drawn
+static REPAINTS: RwLock<Vec<(Theme, Repaint)>> = RwLock::new(Vec::new());
+
+/// A set of colors to draw arenas with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Classic,
+    Ice,
+    Desert,
+    Factory,
+}
+
+/// Colors of every kind of tile
+#[derive(Clone, Copy, Debug)]
+pub struct Palette {
+    /// Color of empty cells
+    pub background: Color,
+    /// Color of pillars and of the walls around the arena
+    pub wall: Color,
+    /// Color of breakable bricks
+    pub brick: Color,
+    /// Color of conveyor belts, whose arrows take the color of walls
+    pub conveyor: Color,
+    /// Color of warps
+    pub warp: Color,
+    /// Color of trampolines
+    pub trampoline: Color,
+}
+
+/// Colors of a theme replaced by a content pack, as red, green and blue, the others being kept
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Repaint {
+    background: Option<(f32, f32, f32)>,
+    wall: Option<(f32, f32, f32)>,
+    brick: Option<(f32, f32, f32)>,
+    conveyor: Option<(f32, f32, f32)>,
+    warp: Option<(f32, f32, f32)>,
+    trampoline: Option<(f32, f32, f32)>,
+}
+
+impl Repaint {
+    /// The given palette with the colors of this one
+    fn over(&self, palette: Palette) -> Palette {
+        let color = |repainted: Option<(f32, f32, f32)>, color| {
+            repainted.map_or(color, |(r, g, b)| Color::rgb(r, g, b))
+        };
+        Palette {
+            background: color(self.background, palette.background),
+            wall: color(self.wall, palette.wall),
+            brick: color(self.brick, palette.brick),
+            conveyor: color(self.conveyor, palette.conveyor),
+            warp: color(self.warp, palette.warp),
+            trampoline: color(self.trampoline, palette.trampoline),
+        }
+    }
+}
+
+/// Repaints themes with the given colors, in order, forgetting previous ones
+pub fn repaint(repaints: Vec<(Theme, Repaint)>) {
+    // a panic while holding the lock left nothing half written
+    match REPAINTS.write() {
+        Ok(mut current) => *current = repaints,
+        Err(poisoned) => *poisoned.into_inner() = repaints,
+    }
+}
+
+impl Palette {
+    /// Color of the floor of the given cell, in a checkerboard of the background and a shade of it
+    pub fn floor(&self, col: usize, row: usize) -> Color {
+        if (col + row).is_multiple_of(2) {
+            self.background
+        } else {
+            Color::rgb(
+                self.background.r() * FLOOR_SHADE,
+                self.background.g() * FLOOR_SHADE,
+                self.background.b() * FLOOR_SHADE,
+            )
+        }
+    }
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [Theme::Classic, Theme::Ice, Theme::Desert, Theme::Factory];
+
+    /// Key of the name of the theme among the strings of locales
+    pub fn key(&self) -> &'static str {
+        match self {
+            Theme::Classic => "theme.classic",
+            Theme::Ice => "theme.ice",
+            Theme::Desert => "theme.desert",
+            Theme::Factory => "theme.factory",
+        }
+    }
+
+    /// Colors of the theme, as repainted by content packs
+    pub fn palette(&self) -> Palette {
+        let repaints = REPAINTS
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        repaints
+            .iter()
+            .filter(|(theme, _)| theme == self)
+            .fold(self.built_in(), |palette, (_, repaint)| {
+                repaint.over(palette)
+            })
+    }
+
+    /// Colors the theme comes with
+    fn built_in(&self) -> Palette {
+        match self {
+            Theme::Classic => Palette {
+                background: Color::rgb(0.7, 0.85, 0.65),
+                wall: Color::rgb(0.55, 0.55, 0.55),
+                brick: Color::rgb(0.4, 0.0, 0.0),
+                conveyor: Color::rgb(0.5, 0.7, 0.45),
+                warp: Color::rgb(0.2, 0.5, 0.8),
+                trampoline: Color::rgb(0.9, 0.5, 0.2),
+            },
+            Theme::Ice => Palette {
+                background: Color::rgb(0.85, 0.92, 1.0),
+                wall: Color::rgb(0.45, 0.6, 0.75),
+                brick: Color::rgb(0.65, 0.8, 0.9),
+                conveyor: Color::rgb(0.7, 0.85, 0.95),
+                warp: Color::rgb(0.5, 0.3, 0.8),
+                trampoline: Color::rgb(0.3, 0.4, 0.6),
+            },
+            Theme::Desert => Palette {
+                background: Color::rgb(0.9, 0.82, 0.6),
+                wall: Color::rgb(0.6, 0.45, 0.3),
+                brick: Color::rgb(0.75, 0.55, 0.3),
+                conveyor: Color::rgb(0.8, 0.7, 0.5),
+                warp: Color::rgb(0.2, 0.6, 0.6),
+                trampoline: Color::rgb(0.7, 0.3, 0.2),
+            },
+            Theme::Factory => Palette {
+                background: Color::rgb(0.35, 0.35, 0.38),
+                wall: Color::rgb(0.15, 0.15, 0.2),
+                brick: Color::rgb(0.55, 0.4, 0.1),
+                conveyor: Color::rgb(0.45, 0.45, 0.5),
+                warp: Color::rgb(0.3, 0.8, 0.9),
+                trampoline: Color::rgb(0.9, 0.7, 0.1),
+            },
+        }
+    }
+
+    /// The theme after this one, going back to the first after the last
+    pub fn next(&self) -> Theme {
+        let index = Theme::ALL
+            .iter()
+            .position(|theme| theme == self)
+            .unwrap_or_default();
+        Theme::ALL[(index + 1) % Theme::ALL.len()]
+    }
+}
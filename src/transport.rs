@@ -0,0 +1,160 @@
+//! Datagrams exchanged with other instances.
+//!
+//! Native builds send UDP datagrams, addressed by socket address.
+//! Browsers can't open sockets, so WASM builds meet the other instances in a room of a
+//! [matchbox](https://github.com/johanhelsing/matchbox) signaling server instead, and exchange
+//! datagrams over unreliable WebRTC data channels, addressed by peer id. The room is the
+//! URL-encoded `room` parameter of the page's address, or else the one `BOMBERMAN_ROOM` held
+//! when building, or else a local server.
+//! Either way datagrams may get lost or arrive out of order, and the rest of the game only
+//! ever sees a [`Transport`] and its [`Address`]es.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use udp::{resolve, Address, Transport};
+#[cfg(target_arch = "wasm32")]
+pub use webrtc::{resolve, room, Address, Transport};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod udp {
+    use std::{
+        io,
+        net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    };
+
+    // largest datagram we expect to receive
+    const MAX_DATAGRAM: usize = 64 * 1024;
+
+    /// Where datagrams are sent to
+    pub type Address = SocketAddr;
+
+    /// Reads an address given on the command line, like `127.0.0.1:7777`
+    pub fn resolve(address: &str) -> Option<Address> {
+        address.to_socket_addrs().ok()?.next()
+    }
+
+    pub struct Transport {
+        socket: UdpSocket,
+    }
+
+    impl Transport {
+        /// Listens on the given port, any free one if 0
+        pub fn bind(port: u16) -> io::Result<Self> {
+            let socket = UdpSocket::bind(("0.0.0.0", port))?;
+            socket.set_nonblocking(true)?;
+            Ok(Transport { socket })
+        }
+
+        pub fn send(&self, datagram: &[u8], address: &Address) -> io::Result<()> {
+            self.socket.send_to(datagram, address).map(|_| ())
+        }
+
+        /// Reads every datagram waiting, along with its sender
+        pub fn receive(&self) -> io::Result<Vec<(Vec<u8>, Address)>> {
+            let mut buffer = vec![0; MAX_DATAGRAM];
+            let mut datagrams = Vec::new();
+            loop {
+                match self.socket.recv_from(&mut buffer) {
+                    Ok((size, address)) => datagrams.push((buffer[..size].to_vec(), address)),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(datagrams),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        /// Instances that showed up since the last call, before sending anything.
+        /// Over UDP nobody is known until its first datagram arrives.
+        pub fn connected(&self) -> Vec<Address> {
+            Vec::new()
+        }
+
+        /// Address other instances know us by, if we can tell
+        pub fn local(&self) -> Option<Address> {
+            self.socket.local_addr().ok()
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod webrtc {
+    use matchbox_socket::{PeerId, WebRtcSocket};
+    use web_sys::UrlSearchParams;
+
+    use std::{io, sync::Mutex};
+
+    // room instances meet in when the page doesn't tell, pairing them two by two
+    const DEFAULT_ROOM: &str = match option_env!("BOMBERMAN_ROOM") {
+        Some(room) => room,
+        None => "ws://127.0.0.1:3536/bomberman?next=2",
+    };
+
+    /// Where datagrams are sent to
+    pub type Address = PeerId;
+
+    /// The room to meet other instances in, as the page's URL tells
+    pub fn room() -> String {
+        web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .and_then(|search| UrlSearchParams::new_with_str(&search).ok())
+            .and_then(|params| params.get("room"))
+            .unwrap_or_else(|| DEFAULT_ROOM.to_string())
+    }
+
+    /// Reads an address, which is just the peer id
+    pub fn resolve(address: &str) -> Option<Address> {
+        Some(address.to_string())
+    }
+
+    pub struct Transport {
+        // the socket needs to be mutable even just to send, the lock is never contended
+        socket: Mutex<WebRtcSocket>,
+    }
+
+    impl Transport {
+        /// Sockets aren't available in browsers, see [`Transport::open`]
+        pub fn bind(_port: u16) -> io::Result<Self> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "browsers can only play through a signaling server",
+            ))
+        }
+
+        /// Joins a room of a matchbox signaling server, like `wss://example.com/room?next=2`
+        pub fn open(room: &str) -> Self {
+            let (socket, message_loop) = WebRtcSocket::new_unreliable(room);
+            wasm_bindgen_futures::spawn_local(message_loop);
+            Transport {
+                socket: Mutex::new(socket),
+            }
+        }
+
+        fn socket(&self) -> std::sync::MutexGuard<'_, WebRtcSocket> {
+            // nothing can panic while holding the lock
+            self.socket.lock().unwrap()
+        }
+
+        pub fn send(&self, datagram: &[u8], address: &Address) -> io::Result<()> {
+            self.socket().send(datagram.into(), address.clone());
+            Ok(())
+        }
+
+        /// Reads every datagram waiting, along with its sender
+        pub fn receive(&self) -> io::Result<Vec<(Vec<u8>, Address)>> {
+            Ok(self
+                .socket()
+                .receive()
+                .into_iter()
+                .map(|(address, datagram)| (datagram.into_vec(), address))
+                .collect())
+        }
+
+        /// Instances that showed up since the last call, before sending anything
+        pub fn connected(&self) -> Vec<Address> {
+            self.socket().accept_new_connections()
+        }
+
+        /// Address other instances know us by
+        pub fn local(&self) -> Option<Address> {
+            Some(self.socket().id().clone())
+        }
+    }
+}
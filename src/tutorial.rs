@@ -0,0 +1,203 @@
+//! Tutorial, teaching how to play one lesson at a time.
+//!
+//! The tutorial is played alone in a small arena, always the same one, whose bricks all leave a
+//! power-up behind. Every lesson shows a prompt at the bottom of the screen, telling what to do,
+//! and is over only once the human does it: its goal is checked against what the match tells,
+//! the events of moves, bombs, blasts and pickups, whatever sends them, so that scripted players
+//! go through the tutorial just as humans do. Humans caught in a blast try again in a new arena,
+//! picking up from the lesson they were at.
+
+use bevy::prelude::*;
+
+use crate::{
+    bot::Roster, locale::Locale, Active, Bomb, BrickDestroyedEvent, Corpse, Match, MoveEvent,
+    PickUpEvent, Rounds, Rules, Seed, Tick, SCORE_COLOR, TEXT_COLOR,
+};
+
+/// Seed of the tutorial arena, the same for every run
+const TUTORIAL_SEED: u64 = 1;
+/// Columns and rows of the tutorial arena
+const TUTORIAL_SIZE: (usize, usize) = (7, 5);
+
+const PROMPT_FONT_SIZE: f32 = 30.0;
+
+/// What the human has to do to be done with a lesson
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Goal {
+    Move,
+    PlaceBomb,
+    DestroyBrick,
+    PickUp,
+    /// Nothing left to do, the tutorial is over
+    None,
+}
+
+/// Lessons of the tutorial, in order, with the key of their prompt among the strings of locales
+const LESSONS: [(&str, Goal); 5] = [
+    ("tutorial.move", Goal::Move),
+    ("tutorial.place_bomb", Goal::PlaceBomb),
+    ("tutorial.destroy_brick", Goal::DestroyBrick),
+    ("tutorial.pick_up", Goal::PickUp),
+    ("tutorial.done", Goal::None),
+];
+
+/// This resource tracks the tutorial being played, if any
+pub struct Tutorial {
+    /// Lesson being played, counting from zero
+    pub lesson: usize,
+    // settings of the menu, given back once the tutorial is left
+    rules: Rules,
+    roster: Roster,
+    seed: Option<u64>,
+}
+
+impl Tutorial {
+    /// Sets up the tutorial from its first lesson, its rules, player and seed
+    pub fn start(rules: &mut Rules, roster: &mut Roster, seed: &mut Seed) -> Self {
+        let tutorial = Tutorial {
+            lesson: 0,
+            rules: rules.clone(),
+            roster: roster.clone(),
+            seed: seed.0,
+        };
+        *rules = Rules {
+            size: TUTORIAL_SIZE,
+            power_up_chance: 1.,
+            rubble_chance: 0.,
+            ..default()
+        };
+        roster.humans = 1;
+        roster.remotes = 0;
+        roster.bots = Vec::new();
+        seed.0 = Some(TUTORIAL_SEED);
+        tutorial
+    }
+
+    fn goal(&self) -> Goal {
+        LESSONS[self.lesson].1
+    }
+}
+
+/// Marks the prompt of the lesson being played
+#[derive(Component)]
+pub struct Prompt;
+
+// Move on to the next lesson once the human did what the current one asks
+pub fn advance(
+    tutorial: Option<ResMut<Tutorial>>,
+    mut move_reader: EventReader<MoveEvent>,
+    mut brick_reader: EventReader<BrickDestroyedEvent>,
+    mut pick_up_reader: EventReader<PickUpEvent>,
+    human_query: Query<(), With<Active>>,
+    bomb_query: Query<&Bomb, Added<Bomb>>,
+) {
+    let mut tutorial = match tutorial {
+        Some(tutorial) => tutorial,
+        None => return,
+    };
+    let human = |player: Entity| human_query.get(player).is_ok();
+    // every event is read, so that a lesson isn't done by what happened during the previous one
+    let moved = move_reader.iter().any(|event| human(event.player));
+    let destroyed = brick_reader.iter().count() > 0;
+    let picked_up = pick_up_reader.iter().any(|event| human(event.player));
+    let done = match tutorial.goal() {
+        Goal::Move => moved,
+        Goal::PlaceBomb => bomb_query.iter().any(|bomb| bomb.player.is_some_and(human)),
+        Goal::DestroyBrick => destroyed,
+        Goal::PickUp => picked_up,
+        Goal::None => false,
+    };
+    if done {
+        tutorial.lesson += 1;
+    }
+}
+
+// Lay out a new arena once the human is done dying, to try again
+pub fn retry(
+    tutorial: Option<Res<Tutorial>>,
+    tick: Res<Tick>,
+    mut rounds: ResMut<Rounds>,
+    human_query: Query<(), With<Active>>,
+    corpse_query: Query<(), With<Corpse>>,
+) {
+    if tutorial.is_some()
+        && human_query.is_empty()
+        && corpse_query.is_empty()
+        && rounds.next.is_none()
+    {
+        rounds.next = Some(tick.0);
+    }
+}
+
+// Show the prompt of the lesson being played, again after every new arena
+pub fn prompt(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+    tutorial: Option<Res<Tutorial>>,
+    mut query: Query<&mut Text, With<Prompt>>,
+) {
+    let tutorial = match tutorial {
+        Some(tutorial) => tutorial,
+        None => return,
+    };
+    let value = locale.format(
+        "tutorial.prompt",
+        &[
+            ("lesson", &(tutorial.lesson + 1)),
+            ("lessons", &LESSONS.len()),
+            ("prompt", &locale.text(LESSONS[tutorial.lesson].0)),
+        ],
+    );
+    // the last prompt stands out
+    let color = if tutorial.goal() == Goal::None {
+        SCORE_COLOR
+    } else {
+        TEXT_COLOR
+    };
+    if let Ok(mut text) = query.get_single_mut() {
+        if tutorial.is_changed() || locale.is_changed() {
+            text.sections[0].value = value;
+            text.sections[0].style.color = color;
+        }
+        return;
+    }
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                value,
+                TextStyle {
+                    font: asset_server.load(locale.font()),
+                    font_size: PROMPT_FONT_SIZE,
+                    color,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(Prompt)
+        .insert(Match);
+}
+
+/// Gives back the settings of the menu when leaving the tutorial
+pub fn leave(
+    mut commands: Commands,
+    tutorial: Option<Res<Tutorial>>,
+    mut rules: ResMut<Rules>,
+    mut roster: ResMut<Roster>,
+    mut seed: ResMut<Seed>,
+) {
+    if let Some(tutorial) = tutorial {
+        *rules = tutorial.rules.clone();
+        *roster = tutorial.roster.clone();
+        seed.0 = tutorial.seed;
+        commands.remove_resource::<Tutorial>();
+    }
+}